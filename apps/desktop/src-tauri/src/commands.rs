@@ -14,8 +14,17 @@ use serde::{Deserialize, Serialize};
 use tauri::State;
 
 use keyforge_crypto::kdf::KdfParams;
+use keyforge_crypto::otp::{OtpContext, OtpGenerator, TokenSpec, TYPE_TOTP};
+use keyforge_crypto::password::PasswordPolicy;
 use keyforge_crypto::random::generate_salt;
+use keyforge_crypto::selftest::SelfTestReport;
+use keyforge_vault::constants::EXPORT_MAX_SIZE;
 use keyforge_vault::db::Vault;
+use keyforge_vault::formats::google::MigrationExport;
+use keyforge_vault::formats::pskc::PskcExport;
+use keyforge_vault::import::{
+    FileImportReport, ImportMode, ImportPreview, ImportReport, ImportSecret, PendingImport,
+};
 use keyforge_vault::token::{NewToken, Token};
 
 // ── Managed state ────────────────────────────────────────────────────
@@ -30,6 +39,13 @@ pub struct AppState {
     pub salts: Mutex<Option<VaultSalts>>,
     /// Cached token list (invalidated on mutation).
     pub token_cache: Mutex<Option<Vec<Token>>>,
+    /// Result of the crypto power-on self-test (set once during setup).
+    pub self_test: Mutex<Option<SelfTestReport>>,
+    /// Import previewed but not yet committed; holds decoded secrets.
+    pub pending_import: Mutex<Option<PendingImport>>,
+    /// Minimum master-password strength enforced on vault creation,
+    /// loaded from `keyforge.policy` when present.
+    pub password_policy: Mutex<PasswordPolicy>,
 }
 
 impl AppState {
@@ -39,9 +55,64 @@ impl AppState {
             vault_path: Mutex::new(None),
             salts: Mutex::new(None),
             token_cache: Mutex::new(None),
+            self_test: Mutex::new(None),
+            pending_import: Mutex::new(None),
+            password_policy: Mutex::new(PasswordPolicy::default()),
         }
     }
 
+    /// Record the outcome of `keyforge_crypto::self_test()`.
+    pub fn set_self_test(&self, report: SelfTestReport) {
+        if let Ok(mut slot) = self.self_test.lock() {
+            *slot = Some(report);
+        }
+    }
+
+    /// Replace the default password policy with the persisted one, if any.
+    pub fn load_password_policy(&self) -> Result<(), String> {
+        let policy_path = vault_dir()?.join(POLICY_FILE);
+        if !policy_path.exists() {
+            return Ok(());
+        }
+        let policy_json =
+            std::fs::read(&policy_path).map_err(|e| format!("Failed to read policy: {e}"))?;
+        let settings: PasswordPolicySettings = serde_json::from_slice(&policy_json)
+            .map_err(|e| format!("Failed to parse policy: {e}"))?;
+        *self.password_policy.lock().map_err(|e| e.to_string())? = settings.into_policy()?;
+        Ok(())
+    }
+
+    fn password_policy(&self) -> Result<PasswordPolicy, String> {
+        Ok(self
+            .password_policy
+            .lock()
+            .map_err(|e| e.to_string())?
+            .clone())
+    }
+
+    /// Refuse to derive or use vault keys unless the self-test passed.
+    fn ensure_self_test_passed(&self) -> Result<(), String> {
+        let guard = self.self_test.lock().map_err(|e| e.to_string())?;
+        match guard.as_ref() {
+            Some(report) => report.clone().into_result(),
+            None => Err("Crypto self-test has not run".into()),
+        }
+    }
+
+    /// Run an import against the unlocked vault, then invalidate the
+    /// cached token list.
+    fn import_with<T>(
+        &self,
+        import: impl FnOnce(&Vault) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let guard = self.vault.lock().map_err(|e| e.to_string())?;
+        let vault = guard.as_ref().ok_or("Vault is locked")?;
+        let imported = import(vault)?;
+        drop(guard);
+        self.invalidate_cache();
+        Ok(imported)
+    }
+
     /// Invalidate the cached token list.
     fn invalidate_cache(&self) {
         if let Ok(mut cache) = self.token_cache.lock() {
@@ -71,14 +142,18 @@ fn kdf_params() -> KdfParams {
 /// Create a brand-new encrypted vault.
 ///
 /// Derives two independent keys (SQLCipher + secret encryption) from the
-/// master password via Argon2id, creates the SQLCipher database, and
-/// leaves the vault **unlocked**.
+/// master password via Argon2id into locked memory, creates the SQLCipher
+/// database, and leaves the vault **unlocked**. Passwords below the
+/// configured [`PasswordPolicy`] are rejected.
 #[tauri::command]
 pub fn vault_create(password: String, state: State<'_, AppState>) -> Result<String, String> {
+    state.ensure_self_test_passed()?;
+    state.password_policy()?.check(&password, &kdf_params())?;
+
     let sqlcipher_salt = generate_salt();
     let secret_salt = generate_salt();
 
-    let (sqlcipher_key, secret_key) = keyforge_crypto::kdf::derive_key_pair(
+    let (sqlcipher_key, secret_key) = keyforge_crypto::kdf::derive_key_pair_locked(
         password.as_bytes(),
         &sqlcipher_salt,
         &secret_salt,
@@ -92,7 +167,7 @@ pub fn vault_create(password: String, state: State<'_, AppState>) -> Result<Stri
     let vault_path = vault_dir.join("keyforge.vault");
     let vault_path_str = vault_path.to_string_lossy().to_string();
 
-    let vault = Vault::create(&vault_path_str, &sqlcipher_key, secret_key)?;
+    let vault = Vault::create(&vault_path_str, sqlcipher_key.expose(), secret_key)?;
 
     // Persist the salts next to the vault so we can re-derive on unlock.
     let salts = VaultSalts {
@@ -115,9 +190,11 @@ pub fn vault_create(password: String, state: State<'_, AppState>) -> Result<Stri
 /// Unlock the vault with the master password.
 ///
 /// Re-derives keys from the stored salts and opens the existing SQLCipher
-/// database.
+/// database. Refused if the crypto self-test failed at startup.
 #[tauri::command]
 pub fn vault_unlock(password: String, state: State<'_, AppState>) -> Result<bool, String> {
+    state.ensure_self_test_passed()?;
+
     let vault_dir = vault_dir()?;
 
     let vault_path = vault_dir.join("keyforge.vault");
@@ -133,14 +210,14 @@ pub fn vault_unlock(password: String, state: State<'_, AppState>) -> Result<bool
     let salts: VaultSalts =
         serde_json::from_slice(&salts_json).map_err(|e| format!("Failed to parse salts: {e}"))?;
 
-    let (sqlcipher_key, secret_key) = keyforge_crypto::kdf::derive_key_pair(
+    let (sqlcipher_key, secret_key) = keyforge_crypto::kdf::derive_key_pair_locked(
         password.as_bytes(),
         &salts.sqlcipher_salt,
         &salts.secret_salt,
         &kdf_params(),
     )?;
 
-    let vault = Vault::open(&vault_path_str, &sqlcipher_key, secret_key)?;
+    let vault = Vault::open(&vault_path_str, sqlcipher_key.expose(), secret_key)?;
 
     *state.vault.lock().map_err(|e| e.to_string())? = Some(vault);
     *state.vault_path.lock().map_err(|e| e.to_string())? = Some(vault_path_str);
@@ -155,6 +232,7 @@ pub fn vault_unlock(password: String, state: State<'_, AppState>) -> Result<bool
 pub fn vault_lock(state: State<'_, AppState>) -> Result<(), String> {
     // Dropping the Vault runs its Drop impl which zeroizes the secret key.
     *state.vault.lock().map_err(|e| e.to_string())? = None;
+    *state.pending_import.lock().map_err(|e| e.to_string())? = None;
     state.invalidate_cache();
     Ok(())
 }
@@ -166,6 +244,100 @@ pub fn vault_is_locked(state: State<'_, AppState>) -> Result<bool, String> {
     Ok(guard.is_none())
 }
 
+/// Strength of a candidate master password, as shown by the meter.
+#[derive(Debug, Clone, Serialize)]
+pub struct PasswordStrength {
+    pub entropy_bits: f64,
+    pub crack_seconds: f64,
+    /// 0 (terrible) to 4 (strong).
+    pub score: u8,
+    /// Whether `vault_create` would accept the password.
+    pub acceptable: bool,
+    pub warnings: Vec<PasswordWarning>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PasswordWarning {
+    /// Stable identifier, e.g. `too_short`.
+    pub code: &'static str,
+    pub message: String,
+}
+
+/// Estimate master-password strength for the creation screen's meter,
+/// against the same policy `vault_create` enforces.
+#[tauri::command]
+pub fn password_strength(
+    password: String,
+    state: State<'_, AppState>,
+) -> Result<PasswordStrength, String> {
+    let policy = state.password_policy()?;
+    let estimate = policy.estimate(&password, &kdf_params());
+    Ok(PasswordStrength {
+        entropy_bits: estimate.entropy_bits,
+        crack_seconds: estimate.crack_seconds,
+        score: estimate.score,
+        acceptable: policy.accepts(&password, &estimate),
+        warnings: estimate
+            .warnings
+            .iter()
+            .map(|w| PasswordWarning {
+                code: w.code(),
+                message: w.to_string(),
+            })
+            .collect(),
+    })
+}
+
+/// Master-password policy as stored in `keyforge.policy`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasswordPolicySettings {
+    pub min_length: usize,
+    pub min_entropy_bits: f64,
+}
+
+impl PasswordPolicySettings {
+    fn into_policy(self) -> Result<PasswordPolicy, String> {
+        if self.min_length == 0 || !self.min_entropy_bits.is_finite() || self.min_entropy_bits < 0.0
+        {
+            return Err("Invalid password policy".into());
+        }
+        Ok(PasswordPolicy {
+            min_length: self.min_length,
+            min_entropy_bits: self.min_entropy_bits,
+        })
+    }
+}
+
+/// Return the policy `vault_create` enforces.
+#[tauri::command]
+pub fn password_policy_get(state: State<'_, AppState>) -> Result<PasswordPolicySettings, String> {
+    let policy = state.password_policy()?;
+    Ok(PasswordPolicySettings {
+        min_length: policy.min_length,
+        min_entropy_bits: policy.min_entropy_bits,
+    })
+}
+
+/// Persist a new master-password policy and enforce it from now on.
+#[tauri::command]
+pub fn password_policy_set(
+    policy: PasswordPolicySettings,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let policy_json =
+        serde_json::to_vec(&policy).map_err(|e| format!("Failed to serialize policy: {e}"))?;
+    let policy = policy.into_policy()?;
+
+    let vault_dir = vault_dir()?;
+    std::fs::create_dir_all(&vault_dir)
+        .map_err(|e| format!("Failed to create vault directory: {e}"))?;
+    std::fs::write(vault_dir.join(POLICY_FILE), &policy_json)
+        .map_err(|e| format!("Failed to write policy: {e}"))?;
+
+    *state.password_policy.lock().map_err(|e| e.to_string())? = policy;
+    Ok(())
+}
+
 /// Check whether a vault file exists on disk.
 #[tauri::command]
 pub fn vault_exists() -> Result<bool, String> {
@@ -289,25 +461,37 @@ pub fn token_increment_counter(id: String, state: State<'_, AppState>) -> Result
 
 // ── OTP generation ───────────────────────────────────────────────────
 
-/// Generate a TOTP code for a stored token (secret retrieved from vault).
+/// A generated code plus its validity window, for any token type.
+#[derive(Debug, Serialize)]
+pub struct GeneratedCode {
+    pub code: String,
+    /// Unix seconds at which the code stops being valid (`None` for HOTP).
+    pub valid_until: Option<u64>,
+    /// Seconds remaining in the current window (`None` for HOTP).
+    pub remaining: Option<u64>,
+}
+
+/// Generate the current code for a stored token of any type.
 #[tauri::command]
-pub fn otp_generate_totp(token_id: String, state: State<'_, AppState>) -> Result<String, String> {
+pub fn otp_generate(token_id: String, state: State<'_, AppState>) -> Result<GeneratedCode, String> {
     let guard = state.vault.lock().map_err(|e| e.to_string())?;
     let vault = guard.as_ref().ok_or("Vault is locked")?;
 
-    let token = vault.get_token(&token_id)?.ok_or("Token not found")?;
-    let secret = vault.get_token_secret(&token_id)?;
-
-    let algo = parse_algorithm(&token.algorithm)?;
-
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map_err(|e| e.to_string())?
-        .as_secs();
+    let ctx = OtpContext::now();
+    let code = vault.generate_code(&token_id, &ctx)?;
+    Ok(GeneratedCode {
+        remaining: code.remaining(ctx.time),
+        valid_until: code.valid_until,
+        code: code.code,
+    })
+}
 
-    let code =
-        keyforge_crypto::totp::generate(&secret, now, token.period as u64, token.digits, algo);
-    Ok(code)
+/// Generate a TOTP code for a stored token (secret retrieved from vault).
+#[tauri::command]
+pub fn otp_generate_totp(token_id: String, state: State<'_, AppState>) -> Result<String, String> {
+    let guard = state.vault.lock().map_err(|e| e.to_string())?;
+    let vault = guard.as_ref().ok_or("Vault is locked")?;
+    Ok(vault.generate_code(&token_id, &OtpContext::now())?.code)
 }
 
 /// Generate a TOTP code from a raw Base32 secret (for preview / manual entry).
@@ -319,15 +503,8 @@ pub fn otp_generate_totp_raw(
     period: u64,
 ) -> Result<String, String> {
     let secret_bytes = base32_decode(&secret)?;
-    let algo = parse_algorithm(&algorithm)?;
-
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map_err(|e| e.to_string())?
-        .as_secs();
-
-    let code = keyforge_crypto::totp::generate(&secret_bytes, now, period, digits, algo);
-    Ok(code)
+    let spec = TokenSpec::from_parts(TYPE_TOTP, &algorithm, digits, period, 0)?;
+    Ok(spec.generate(&secret_bytes, &OtpContext::now())?.code)
 }
 
 /// Generate a HOTP code for a stored token.
@@ -335,27 +512,31 @@ pub fn otp_generate_totp_raw(
 pub fn otp_generate_hotp(token_id: String, state: State<'_, AppState>) -> Result<String, String> {
     let guard = state.vault.lock().map_err(|e| e.to_string())?;
     let vault = guard.as_ref().ok_or("Vault is locked")?;
-
-    let token = vault.get_token(&token_id)?.ok_or("Token not found")?;
-    let secret = vault.get_token_secret(&token_id)?;
-
-    let algo = parse_algorithm(&token.algorithm)?;
-
-    let code = keyforge_crypto::hotp::generate(&secret, token.counter, token.digits, algo);
-    Ok(code)
+    Ok(vault.generate_code(&token_id, &OtpContext::now())?.code)
 }
 
 // ── Import / Export ──────────────────────────────────────────────────
 
-/// Import tokens from `otpauth://` URIs.
+/// Import tokens from `otpauth://` URIs, all or nothing unless `mode` is
+/// `best_effort`. Tokens already in the vault are skipped.
 #[tauri::command]
-pub fn vault_import_uris(uris: Vec<String>, state: State<'_, AppState>) -> Result<usize, String> {
-    let guard = state.vault.lock().map_err(|e| e.to_string())?;
-    let vault = guard.as_ref().ok_or("Vault is locked")?;
-    let count = vault.import_uris(&uris)?;
-    drop(guard);
-    state.invalidate_cache();
-    Ok(count)
+pub fn vault_import_uris(
+    uris: Vec<String>,
+    mode: Option<ImportMode>,
+    state: State<'_, AppState>,
+) -> Result<ImportReport, String> {
+    state.import_with(|vault| vault.import_uris(&uris, mode.unwrap_or_default()))
+}
+
+/// Import a text file of `otpauth://` URIs, one per line. Blank lines and
+/// `#` comments are ignored.
+#[tauri::command]
+pub fn vault_import_uri_text(
+    text: String,
+    mode: Option<ImportMode>,
+    state: State<'_, AppState>,
+) -> Result<ImportReport, String> {
+    state.import_with(|vault| vault.import_uri_text(&text, mode.unwrap_or_default()))
 }
 
 /// Export all tokens as `otpauth://` URIs.
@@ -383,13 +564,181 @@ pub fn vault_import_encrypted(
     data: Vec<u8>,
     password: String,
     state: State<'_, AppState>,
-) -> Result<usize, String> {
+) -> Result<ImportReport, String> {
+    state.import_with(|vault| vault.import_encrypted(&data, password.as_bytes()))
+}
+
+/// Stream an encrypted backup straight to `path`.
+#[tauri::command]
+pub fn vault_backup_to_file(
+    path: String,
+    export_password: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let guard = state.vault.lock().map_err(|e| e.to_string())?;
+    let vault = guard.as_ref().ok_or("Vault is locked")?;
+    let file = std::fs::File::create(&path).map_err(|e| e.to_string())?;
+    let mut writer = std::io::BufWriter::new(file);
+    vault.export_encrypted_to(export_password.as_bytes(), &mut writer)?;
+    writer
+        .into_inner()
+        .map_err(|e| e.to_string())?
+        .sync_all()
+        .map_err(|e| e.to_string())
+}
+
+/// Restore an encrypted backup from `path`, decrypting it into a buffer
+/// sized from the file so no plaintext copies are left behind.
+#[tauri::command]
+pub fn vault_restore_from_file(
+    path: String,
+    password: String,
+    state: State<'_, AppState>,
+) -> Result<ImportReport, String> {
+    let file = std::fs::File::open(&path).map_err(|e| e.to_string())?;
+    let len = file.metadata().map_err(|e| e.to_string())?.len();
+    if len > EXPORT_MAX_SIZE {
+        return Err(format!(
+            "The backup is larger than {} MiB",
+            EXPORT_MAX_SIZE >> 20
+        ));
+    }
+    let len = usize::try_from(len).map_err(|e| e.to_string())?;
+    state.import_with(|vault| {
+        vault.import_encrypted_from(std::io::BufReader::new(file), password.as_bytes(), len)
+    })
+}
+
+/// Export the vault as a browser extension vault (PBKDF2 + AES-GCM JSON).
+#[tauri::command]
+pub fn vault_export_extension(
+    export_password: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<u8>, String> {
+    let guard = state.vault.lock().map_err(|e| e.to_string())?;
+    let vault = guard.as_ref().ok_or("Vault is locked")?;
+    vault.export_extension_vault(export_password.as_bytes())
+}
+
+/// Import every token from a browser extension vault.
+#[tauri::command]
+pub fn vault_import_extension(
+    data: Vec<u8>,
+    password: String,
+    mode: Option<ImportMode>,
+    state: State<'_, AppState>,
+) -> Result<ImportReport, String> {
+    state.import_with(|vault| {
+        vault.import_extension_vault(&data, password.as_bytes(), mode.unwrap_or_default())
+    })
+}
+
+/// Export the vault as an Aegis export, encrypted when `password` is set.
+#[tauri::command]
+pub fn vault_export_aegis(
+    password: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<u8>, String> {
+    let guard = state.vault.lock().map_err(|e| e.to_string())?;
+    let vault = guard.as_ref().ok_or("Vault is locked")?;
+    vault.export_aegis(password.as_deref().map(str::as_bytes))
+}
+
+/// The secret an import file was sealed with, from the optional command
+/// arguments: a KeePass `keyfile` (with any `password`), a raw `key`, or a
+/// password alone.
+fn import_secret<'a>(
+    password: Option<&'a str>,
+    keyfile: Option<&'a [u8]>,
+    key: Option<&'a [u8]>,
+) -> Option<ImportSecret<'a>> {
+    let password = password.map(str::as_bytes);
+    match (keyfile, key) {
+        (Some(keyfile), _) => Some(ImportSecret::Keyfile { keyfile, password }),
+        (None, Some(key)) => Some(ImportSecret::Key(key)),
+        (None, None) => password.map(ImportSecret::Password),
+    }
+}
+
+/// Import a file of any supported format, detected from its contents.
+/// Encrypted formats need `password`, a KeePass `keyfile` or a PSKC
+/// pre-shared `key`; the reply names the detected format. Google
+/// Authenticator transfer codes are read as text, one
+/// `otpauth-migration://` URI per line.
+#[tauri::command]
+pub fn vault_import_file(
+    data: Vec<u8>,
+    password: Option<String>,
+    keyfile: Option<Vec<u8>>,
+    key: Option<Vec<u8>>,
+    mode: Option<ImportMode>,
+    state: State<'_, AppState>,
+) -> Result<FileImportReport, String> {
+    let secret = import_secret(password.as_deref(), keyfile.as_deref(), key.as_deref());
+    state.import_with(|vault| vault.import_file(&data, secret, mode.unwrap_or_default()))
+}
+
+/// Decode an import file of any supported format without importing it.
+/// The preview replaces any earlier one until `vault_commit_import`.
+#[tauri::command]
+pub fn vault_preview_import(
+    data: Vec<u8>,
+    password: Option<String>,
+    keyfile: Option<Vec<u8>>,
+    key: Option<Vec<u8>>,
+    state: State<'_, AppState>,
+) -> Result<ImportPreview, String> {
+    let secret = import_secret(password.as_deref(), keyfile.as_deref(), key.as_deref());
+    let guard = state.vault.lock().map_err(|e| e.to_string())?;
+    let vault = guard.as_ref().ok_or("Vault is locked")?;
+    let pending = vault.preview_import(&data, secret)?;
+    let preview = pending.preview().clone();
+    *state.pending_import.lock().map_err(|e| e.to_string())? = Some(pending);
+    Ok(preview)
+}
+
+/// Import the previewed candidates named in `ids`. The preview is kept
+/// whenever the commit fails, so the user can retry.
+#[tauri::command]
+pub fn vault_commit_import(
+    ids: Vec<usize>,
+    state: State<'_, AppState>,
+) -> Result<ImportReport, String> {
     let guard = state.vault.lock().map_err(|e| e.to_string())?;
     let vault = guard.as_ref().ok_or("Vault is locked")?;
-    let count = vault.import_encrypted(&data, password.as_bytes())?;
+    let mut slot = state.pending_import.lock().map_err(|e| e.to_string())?;
+    let pending = slot.as_ref().ok_or("No import is pending")?;
+    let summary = vault.commit_import(pending, &ids)?;
+    *slot = None;
+    drop(slot);
     drop(guard);
     state.invalidate_cache();
-    Ok(count)
+    Ok(summary)
+}
+
+/// Export tokens as a PSKC key container, encrypted when `password` is
+/// set. `ids` selects a subset; `None` exports the whole vault.
+#[tauri::command]
+pub fn vault_export_pskc(
+    ids: Option<Vec<String>>,
+    password: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<PskcExport, String> {
+    let guard = state.vault.lock().map_err(|e| e.to_string())?;
+    let vault = guard.as_ref().ok_or("Vault is locked")?;
+    vault.export_pskc(ids.as_deref(), password.as_deref().map(str::as_bytes))
+}
+
+/// Encode tokens as Google Authenticator transfer QR payloads. `ids`
+/// selects a subset; `None` exports the whole vault.
+#[tauri::command]
+pub fn vault_export_google_migration(
+    ids: Option<Vec<String>>,
+    state: State<'_, AppState>,
+) -> Result<MigrationExport, String> {
+    let guard = state.vault.lock().map_err(|e| e.to_string())?;
+    let vault = guard.as_ref().ok_or("Vault is locked")?;
+    vault.export_google_migration(ids.as_deref())
 }
 
 // ── Platform info ────────────────────────────────────────────────────
@@ -405,6 +754,9 @@ pub fn platform_info() -> Result<serde_json::Value, String> {
 
 // ── Helpers ──────────────────────────────────────────────────────────
 
+/// Password policy file, next to the vault.
+const POLICY_FILE: &str = "keyforge.policy";
+
 /// Resolve the vault data directory (platform-specific).
 fn vault_dir() -> Result<std::path::PathBuf, String> {
     let base = dirs_next::data_local_dir()
@@ -419,12 +771,3 @@ fn base32_decode(input: &str) -> Result<Vec<u8>, String> {
     )
     .ok_or_else(|| "Invalid Base32 secret: expected uppercase A-Z and 2-7 characters".to_string())
 }
-
-fn parse_algorithm(s: &str) -> Result<keyforge_crypto::hotp::Algorithm, String> {
-    match s {
-        "SHA1" => Ok(keyforge_crypto::hotp::Algorithm::SHA1),
-        "SHA256" => Ok(keyforge_crypto::hotp::Algorithm::SHA256),
-        "SHA512" => Ok(keyforge_crypto::hotp::Algorithm::SHA512),
-        other => Err(format!("Unsupported algorithm: {other}")),
-    }
-}
@@ -1,10 +1,17 @@
 mod commands;
 
+use keyforge_crypto::hardening::HardeningConfig;
+use tauri::Manager;
+
 use commands::{
-    otp_generate_hotp, otp_generate_totp, otp_generate_totp_raw, platform_info, token_add,
-    token_delete, token_increment_counter, token_list, token_reorder, token_update, vault_create,
-    vault_exists, vault_export_encrypted, vault_export_uris, vault_import_encrypted,
-    vault_import_uris, vault_is_locked, vault_lock, vault_unlock, AppState,
+    otp_generate, otp_generate_hotp, otp_generate_totp, otp_generate_totp_raw, password_policy_get,
+    password_policy_set, password_strength, platform_info, token_add, token_delete,
+    token_increment_counter, token_list, token_reorder, token_update, vault_backup_to_file,
+    vault_commit_import, vault_create, vault_exists, vault_export_aegis, vault_export_encrypted,
+    vault_export_extension, vault_export_google_migration, vault_export_pskc, vault_export_uris,
+    vault_import_encrypted, vault_import_extension, vault_import_file, vault_import_uri_text,
+    vault_import_uris, vault_is_locked, vault_lock, vault_preview_import, vault_restore_from_file,
+    vault_unlock, AppState,
 };
 
 /// Build and configure the Tauri application.
@@ -14,9 +21,38 @@ use commands::{
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
+        .plugin(tauri_plugin_log::Builder::new().build())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_clipboard_manager::init())
         .manage(AppState::new())
+        .setup(|app| {
+            // Keep key material out of core dumps and away from debuggers.
+            // Set KEYFORGE_ALLOW_DEBUGGER=1 to attach gdb/lldb locally.
+            let config = HardeningConfig::from_env();
+            let hardening = keyforge_crypto::hardening::apply(&config);
+            if cfg!(target_os = "linux")
+                && (config.disable_core_dumps != hardening.core_dumps_disabled
+                    || config.deny_ptrace != hardening.ptrace_denied)
+            {
+                log::warn!("Process hardening incomplete: {hardening:?}");
+            }
+
+            // Power-on known-answer tests; a failure keeps the vault locked.
+            let report = keyforge_crypto::self_test();
+            for failure in report.failures() {
+                log::error!(
+                    "Crypto self-test failed: {} ({})",
+                    failure.name,
+                    failure.detail.as_deref().unwrap_or("no detail")
+                );
+            }
+            app.state::<AppState>().set_self_test(report);
+
+            if let Err(e) = app.state::<AppState>().load_password_policy() {
+                log::warn!("Using the default password policy: {e}");
+            }
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             // Vault lifecycle
             vault_create,
@@ -24,6 +60,9 @@ pub fn run() {
             vault_lock,
             vault_is_locked,
             vault_exists,
+            password_strength,
+            password_policy_get,
+            password_policy_set,
             // Token CRUD
             token_list,
             token_add,
@@ -32,14 +71,26 @@ pub fn run() {
             token_reorder,
             token_increment_counter,
             // OTP generation
+            otp_generate,
             otp_generate_totp,
             otp_generate_totp_raw,
             otp_generate_hotp,
             // Import / Export
             vault_import_uris,
+            vault_import_uri_text,
             vault_export_uris,
             vault_export_encrypted,
             vault_import_encrypted,
+            vault_backup_to_file,
+            vault_restore_from_file,
+            vault_export_extension,
+            vault_import_extension,
+            vault_export_google_migration,
+            vault_export_aegis,
+            vault_export_pskc,
+            vault_import_file,
+            vault_preview_import,
+            vault_commit_import,
             // Platform
             platform_info,
         ])
@@ -5,6 +5,7 @@
 //! SQLCipher database on disk. No mocks, no stubs.
 
 use keyforge_vault::db::Vault;
+use keyforge_vault::import::ImportMode;
 use keyforge_vault::token::NewToken;
 use tempfile::TempDir;
 
@@ -24,7 +25,7 @@ fn create_vault_with_password(dir: &TempDir, password: &str) -> (Vault, [u8; 16]
     let sqlcipher_salt = keyforge_crypto::random::generate_salt();
     let secret_salt = keyforge_crypto::random::generate_salt();
 
-    let (sqlcipher_key, secret_key) = keyforge_crypto::kdf::derive_key_pair(
+    let (sqlcipher_key, mut secret_key) = keyforge_crypto::kdf::derive_key_pair(
         password.as_bytes(),
         &sqlcipher_salt,
         &secret_salt,
@@ -33,7 +34,7 @@ fn create_vault_with_password(dir: &TempDir, password: &str) -> (Vault, [u8; 16]
     .unwrap();
 
     let path = dir.path().join("e2e.vault");
-    let vault = Vault::create(path.to_str().unwrap(), &sqlcipher_key, secret_key).unwrap();
+    let vault = Vault::create(path.to_str().unwrap(), &sqlcipher_key, &mut secret_key).unwrap();
     (vault, sqlcipher_salt, secret_salt)
 }
 
@@ -44,7 +45,7 @@ fn reopen_vault(
     sqlcipher_salt: &[u8; 16],
     secret_salt: &[u8; 16],
 ) -> Vault {
-    let (sqlcipher_key, secret_key) = keyforge_crypto::kdf::derive_key_pair(
+    let (sqlcipher_key, mut secret_key) = keyforge_crypto::kdf::derive_key_pair(
         password.as_bytes(),
         sqlcipher_salt,
         secret_salt,
@@ -53,7 +54,7 @@ fn reopen_vault(
     .unwrap();
 
     let path = dir.path().join("e2e.vault");
-    Vault::open(path.to_str().unwrap(), &sqlcipher_key, secret_key).unwrap()
+    Vault::open(path.to_str().unwrap(), &sqlcipher_key, &mut secret_key).unwrap()
 }
 
 fn github_token() -> NewToken {
@@ -100,7 +101,7 @@ fn e2e_wrong_password_fails() {
     let (_, sqlcipher_salt, secret_salt) = create_vault_with_password(&dir, "right-password");
 
     // Wrong password → derive different keys → SQLCipher rejects
-    let (sqlcipher_key, secret_key) = keyforge_crypto::kdf::derive_key_pair(
+    let (sqlcipher_key, mut secret_key) = keyforge_crypto::kdf::derive_key_pair(
         b"wrong-password",
         &sqlcipher_salt,
         &secret_salt,
@@ -109,7 +110,7 @@ fn e2e_wrong_password_fails() {
     .unwrap();
 
     let path = dir.path().join("e2e.vault");
-    let result = Vault::open(path.to_str().unwrap(), &sqlcipher_key, secret_key);
+    let result = Vault::open(path.to_str().unwrap(), &sqlcipher_key, &mut secret_key);
     assert!(result.is_err(), "Wrong password should fail to open vault");
 }
 
@@ -268,7 +269,11 @@ fn e2e_export_import_uris() {
     // Import into a fresh vault
     let dir2 = TempDir::new().unwrap();
     let (vault2, _, _) = create_vault_with_password(&dir2, "other-password");
-    let count = vault2.import_uris(&uris).unwrap();
+    let count = vault2
+        .import_uris(&uris, ImportMode::Atomic)
+        .unwrap()
+        .imported
+        .len();
     assert_eq!(count, 2);
 
     let tokens = vault2.list_tokens().unwrap();
@@ -291,7 +296,9 @@ fn e2e_export_import_encrypted() {
     let (vault2, _, _) = create_vault_with_password(&dir2, "other-vault");
     let count = vault2
         .import_encrypted(&export_data, b"export-secret")
-        .unwrap();
+        .unwrap()
+        .imported
+        .len();
     assert_eq!(count, 1);
 
     let tokens = vault2.list_tokens().unwrap();
@@ -315,7 +322,11 @@ fn e2e_import_otpauth_uris() {
         "otpauth://hotp/AWS:admin?secret=JBSWY3DPEHPK3PXP&counter=42".to_string(),
     ];
 
-    let count = vault.import_uris(&uris).unwrap();
+    let count = vault
+        .import_uris(&uris, ImportMode::Atomic)
+        .unwrap()
+        .imported
+        .len();
     assert_eq!(count, 2);
 
     let tokens = vault.list_tokens().unwrap();
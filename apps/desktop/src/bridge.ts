@@ -41,6 +41,56 @@ export interface AddTokenInput {
   icon: string | null;
 }
 
+export interface PasswordPolicy {
+  min_length: number;
+  min_entropy_bits: number;
+}
+
+export type ImportMode = 'atomic' | 'best_effort';
+
+export interface ImportFailure {
+  /** `decode`: an entry of a foreign export that could not be read. */
+  kind: 'invalid_uri' | 'unsupported' | 'storage' | 'decode';
+  detail: string;
+}
+
+/** Entries are numbered from 1: by line in text files, by place otherwise. */
+export interface DuplicateEntry {
+  number: number;
+  label: string;
+  token_id: string;
+  kind: 'secret' | 'label';
+}
+
+export interface ImportReport {
+  imported: Token[];
+  /** Left out: the vault already holds their secret. */
+  duplicates: DuplicateEntry[];
+  /** Imported, but sharing the issuer and account of a vault token. */
+  label_matches: DuplicateEntry[];
+  failed: FailedEntry[];
+}
+
+export interface FailedEntry {
+  number: number;
+  label: string;
+  reason: ImportFailure;
+}
+
+const FAILURE_TITLES: Record<ImportFailure['kind'], string> = {
+  invalid_uri: 'Not a valid otpauth:// URI',
+  unsupported: 'Not supported',
+  storage: 'Could not be saved',
+  decode: 'Could not be read from the file',
+};
+
+/** One line of the import result view for an entry that failed. */
+export function describeFailedEntry(entry: FailedEntry): string {
+  const label = entry.label ? ` (${entry.label})` : '';
+  const title = FAILURE_TITLES[entry.reason.kind];
+  return `Entry ${entry.number}${label}: ${title}: ${entry.reason.detail}`;
+}
+
 // ── Vault lifecycle ─────────────────────────────────────────────────
 
 export function vaultCreate(password: string): Promise<string> {
@@ -63,6 +113,14 @@ export function vaultExists(): Promise<boolean> {
   return invoke<boolean>('vault_exists');
 }
 
+export function passwordPolicyGet(): Promise<PasswordPolicy> {
+  return invoke<PasswordPolicy>('password_policy_get');
+}
+
+export function passwordPolicySet(policy: PasswordPolicy): Promise<void> {
+  return invoke<void>('password_policy_set', { policy });
+}
+
 // ── Token CRUD ──────────────────────────────────────────────────────
 
 export function tokenList(): Promise<Token[]> {
@@ -101,8 +159,12 @@ export function otpGenerateHotp(tokenId: string): Promise<string> {
 
 // ── Import / Export ─────────────────────────────────────────────────
 
-export function vaultImportUris(uris: string[]): Promise<number> {
-  return invoke<number>('vault_import_uris', { uris });
+export function vaultImportUris(uris: string[], mode?: ImportMode): Promise<ImportReport> {
+  return invoke<ImportReport>('vault_import_uris', { uris, mode });
+}
+
+export function vaultImportUriText(text: string, mode?: ImportMode): Promise<ImportReport> {
+  return invoke<ImportReport>('vault_import_uri_text', { text, mode });
 }
 
 export function vaultExportUris(): Promise<string[]> {
@@ -1,11 +1,12 @@
 //! AES-256-GCM authenticated encryption
 
 use aes_gcm::{
-    aead::{Aead, KeyInit},
-    Aes256Gcm, Nonce,
+    aead::{Aead, AeadInPlace, KeyInit},
+    Aes256Gcm, Nonce, Tag,
 };
 
 use crate::error::CryptoError;
+use crate::secure_mem::LockedBuffer;
 
 pub const NONCE_SIZE: usize = 12;
 pub const TAG_SIZE: usize = 16;
@@ -65,6 +66,32 @@ pub fn decrypt(encrypted: &[u8], key: &[u8; 32]) -> Result<Vec<u8>, String> {
     Ok(plaintext)
 }
 
+/// Like [`decrypt`], but the plaintext only ever exists in locked memory.
+pub fn decrypt_locked(encrypted: &[u8], key: &[u8; 32]) -> Result<LockedBuffer, String> {
+    if encrypted.len() < NONCE_SIZE + TAG_SIZE {
+        return Err(CryptoError::CiphertextTooShort.into());
+    }
+
+    let (nonce_bytes, rest) = encrypted.split_at(NONCE_SIZE);
+    let (ciphertext, tag) = rest.split_at(rest.len() - TAG_SIZE);
+
+    let cipher =
+        Aes256Gcm::new_from_slice(key).map_err(|e| CryptoError::CipherInit(e.to_string()))?;
+
+    // On failure the buffer is dropped, which zeroizes any partial output.
+    let mut buffer = LockedBuffer::from_slice(ciphertext);
+    cipher
+        .decrypt_in_place_detached(
+            Nonce::from_slice(nonce_bytes),
+            b"",
+            buffer.as_mut_slice(),
+            Tag::from_slice(tag),
+        )
+        .map_err(|_| CryptoError::DecryptionAuth)?;
+
+    Ok(buffer)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -143,6 +170,19 @@ mod tests {
         assert_eq!(decrypted, plaintext);
     }
 
+    #[test]
+    fn test_decrypt_locked_roundtrip() {
+        let key = test_key();
+        let encrypted = encrypt(b"locked secret", &key).unwrap();
+
+        let decrypted = decrypt_locked(&encrypted, &key).unwrap();
+        assert_eq!(decrypted.as_slice(), b"locked secret");
+
+        let mut tampered = encrypted.clone();
+        tampered[NONCE_SIZE] ^= 0xff;
+        assert!(decrypt_locked(&tampered, &key).is_err());
+    }
+
     #[test]
     fn test_ciphertext_too_short() {
         let key = test_key();
@@ -0,0 +1,281 @@
+//! AES-256-CBC with HMAC-SHA256 (encrypt-then-MAC)
+//!
+//! Bitwarden seals its password-protected exports this way (its "type 2"
+//! encrypted strings). The MAC covers `iv ‖ ciphertext` and is checked in
+//! constant time before anything is decrypted. Legacy Authenticator Pro
+//! backups and PSKC key containers use bare AES-CBC with no MAC (PSKC
+//! carries its own), which is all [`decrypt_unauthenticated_locked`] and
+//! [`encrypt_unauthenticated`] are for. Only used for foreign files; the
+//! vault itself uses AES-256-GCM.
+
+use aes::{Aes128, Aes192, Aes256};
+use cbc::cipher::block_padding::Pkcs7;
+use cbc::cipher::{BlockCipher, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::error::CryptoError;
+use crate::secure_mem::{LockedBuffer, LockedKey};
+
+pub const IV_SIZE: usize = 16;
+pub const MAC_SIZE: usize = 32;
+
+/// An encryption key and a MAC key.
+pub struct CbcHmacKeys {
+    pub enc: LockedKey,
+    pub mac: LockedKey,
+}
+
+impl CbcHmacKeys {
+    /// Split one 256-bit key into encryption and MAC keys with
+    /// HKDF-Expand-SHA256 (info `"enc"` and `"mac"`), as Bitwarden
+    /// stretches a password-derived key.
+    pub fn stretch(key: &[u8; 32]) -> Result<Self, String> {
+        let hkdf =
+            Hkdf::<Sha256>::from_prk(key).map_err(|e| CryptoError::KdfDerivation(e.to_string()))?;
+        let mut keys = Self {
+            enc: LockedKey::zeroed(),
+            mac: LockedKey::zeroed(),
+        };
+        hkdf.expand(b"enc", keys.enc.expose_mut())
+            .and_then(|()| hkdf.expand(b"mac", keys.mac.expose_mut()))
+            .map_err(|e| CryptoError::KdfDerivation(e.to_string()))?;
+        Ok(keys)
+    }
+}
+
+/// The three parts of a sealed message.
+pub struct CbcHmacSealed {
+    pub iv: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+    pub mac: Vec<u8>,
+}
+
+/// Encrypt with a random IV. Only foreign files are ever decrypted, so
+/// this exists to test [`decrypt_locked`].
+#[cfg(test)]
+fn encrypt(plaintext: &[u8], keys: &CbcHmacKeys) -> Result<CbcHmacSealed, String> {
+    let iv = crate::random::generate_bytes(IV_SIZE);
+    let mut ciphertext = plaintext.to_vec();
+    ciphertext.resize(plaintext.len() + IV_SIZE - plaintext.len() % IV_SIZE, 0);
+    cbc::Encryptor::<Aes256>::new(keys.enc.expose().into(), iv[..].into())
+        .encrypt_padded_mut::<Pkcs7>(&mut ciphertext, plaintext.len())
+        .map_err(|e| CryptoError::Encryption(e.to_string()))?;
+    let mac = mac(keys, &iv, &ciphertext)?
+        .finalize()
+        .into_bytes()
+        .to_vec();
+    Ok(CbcHmacSealed {
+        iv,
+        ciphertext,
+        mac,
+    })
+}
+
+/// Verify the MAC, then decrypt into locked memory.
+pub fn decrypt_locked(sealed: &CbcHmacSealed, keys: &CbcHmacKeys) -> Result<LockedBuffer, String> {
+    if sealed.iv.len() != IV_SIZE {
+        return Err(CryptoError::InvalidNonceSize {
+            expected: IV_SIZE,
+            got: sealed.iv.len(),
+        }
+        .into());
+    }
+    mac(keys, &sealed.iv, &sealed.ciphertext)?
+        .verify_slice(&sealed.mac)
+        .map_err(|_| CryptoError::DecryptionAuth)?;
+
+    let mut buffer = LockedBuffer::from_slice(&sealed.ciphertext);
+    let len = cbc::Decryptor::<Aes256>::new(keys.enc.expose().into(), sealed.iv[..].into())
+        .decrypt_padded_mut::<Pkcs7>(buffer.as_mut_slice())
+        .map_err(|_| CryptoError::DecryptionAuth)?
+        .len();
+    Ok(LockedBuffer::from_slice(&buffer.as_slice()[..len]))
+}
+
+/// Decrypt AES-CBC with PKCS#7 padding and no MAC into locked memory.
+///
+/// The key picks AES-128, AES-192 or AES-256. Nothing authenticates the
+/// ciphertext: a wrong key usually shows up as bad padding, but not
+/// always, so callers must validate the plaintext.
+pub fn decrypt_unauthenticated_locked(
+    ciphertext: &[u8],
+    key: &[u8],
+    iv: &[u8],
+) -> Result<LockedBuffer, String> {
+    if iv.len() != IV_SIZE {
+        return Err(CryptoError::InvalidNonceSize {
+            expected: IV_SIZE,
+            got: iv.len(),
+        }
+        .into());
+    }
+    let mut buffer = LockedBuffer::from_slice(ciphertext);
+    let len = match key.len() {
+        16 => decrypt_padded::<Aes128>(buffer.as_mut_slice(), key, iv)?,
+        24 => decrypt_padded::<Aes192>(buffer.as_mut_slice(), key, iv)?,
+        32 => decrypt_padded::<Aes256>(buffer.as_mut_slice(), key, iv)?,
+        got => return Err(CryptoError::InvalidKeySize { expected: 32, got }.into()),
+    };
+    Ok(LockedBuffer::from_slice(&buffer.as_slice()[..len]))
+}
+
+/// Encrypt with AES-CBC, PKCS#7 padding, a random IV and no MAC.
+///
+/// Returns `iv ‖ ciphertext`, the layout XML Encryption expects in a
+/// `CipherValue`. The key picks AES-128, AES-192 or AES-256.
+pub fn encrypt_unauthenticated(plaintext: &[u8], key: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = crate::random::generate_bytes(IV_SIZE);
+    out.extend_from_slice(plaintext);
+    out.resize(
+        IV_SIZE + plaintext.len() + IV_SIZE - plaintext.len() % IV_SIZE,
+        0,
+    );
+    let (iv, buffer) = out.split_at_mut(IV_SIZE);
+    match key.len() {
+        16 => encrypt_padded::<Aes128>(buffer, plaintext.len(), key, iv)?,
+        24 => encrypt_padded::<Aes192>(buffer, plaintext.len(), key, iv)?,
+        32 => encrypt_padded::<Aes256>(buffer, plaintext.len(), key, iv)?,
+        got => return Err(CryptoError::InvalidKeySize { expected: 32, got }.into()),
+    }
+    Ok(out)
+}
+
+fn decrypt_padded<C>(buffer: &mut [u8], key: &[u8], iv: &[u8]) -> Result<usize, String>
+where
+    C: BlockCipher + BlockDecryptMut,
+    cbc::Decryptor<C>: KeyIvInit,
+{
+    let len = cbc::Decryptor::<C>::new_from_slices(key, iv)
+        .map_err(|e| CryptoError::CipherInit(e.to_string()))?
+        .decrypt_padded_mut::<Pkcs7>(buffer)
+        .map_err(|_| CryptoError::DecryptionAuth)?
+        .len();
+    Ok(len)
+}
+
+fn encrypt_padded<C>(buffer: &mut [u8], len: usize, key: &[u8], iv: &[u8]) -> Result<(), String>
+where
+    C: BlockCipher + BlockEncryptMut,
+    cbc::Encryptor<C>: KeyIvInit,
+{
+    cbc::Encryptor::<C>::new_from_slices(key, iv)
+        .map_err(|e| CryptoError::CipherInit(e.to_string()))?
+        .encrypt_padded_mut::<Pkcs7>(buffer, len)
+        .map_err(|e| CryptoError::Encryption(e.to_string()))?;
+    Ok(())
+}
+
+fn mac(keys: &CbcHmacKeys, iv: &[u8], ciphertext: &[u8]) -> Result<Hmac<Sha256>, String> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(keys.mac.expose())
+        .map_err(|e| CryptoError::CipherInit(e.to_string()))?;
+    mac.update(iv);
+    mac.update(ciphertext);
+    Ok(mac)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn keys() -> CbcHmacKeys {
+        CbcHmacKeys::stretch(&[0x42; 32]).unwrap()
+    }
+
+    #[test]
+    fn test_stretch_matches_hkdf_expand() {
+        // HKDF-Expand-SHA256(PRK = 0x42 * 32, info, 32), computed independently.
+        let keys = keys();
+        assert_eq!(
+            keys.enc.expose().to_vec(),
+            hex("24b60a01bfaa348927d350b9a03fc305b94996a95bf33076c0e185fdeb1b7720")
+        );
+        assert_eq!(
+            keys.mac.expose().to_vec(),
+            hex("1f9bd091b6b758f92536b528bf457889b3f3cd57f81a9f3fb51f91ac9e4558f2")
+        );
+    }
+
+    #[test]
+    fn test_round_trip_and_tampering() {
+        let keys = keys();
+        let mut sealed = encrypt(b"otpauth://totp/x?secret=AAAA", &keys).unwrap();
+        assert_eq!(sealed.ciphertext.len() % 16, 0);
+        assert_eq!(
+            decrypt_locked(&sealed, &keys).unwrap().as_slice(),
+            b"otpauth://totp/x?secret=AAAA"
+        );
+
+        sealed.ciphertext[0] ^= 1;
+        assert!(decrypt_locked(&sealed, &keys).is_err());
+        sealed.ciphertext[0] ^= 1;
+        sealed.iv[0] ^= 1;
+        assert!(decrypt_locked(&sealed, &keys).is_err());
+        sealed.iv.pop();
+        assert!(decrypt_locked(&sealed, &keys).is_err());
+
+        let other = CbcHmacKeys::stretch(&[0x43; 32]).unwrap();
+        let sealed = encrypt(b"", &keys).unwrap();
+        assert!(decrypt_locked(&sealed, &other).is_err());
+        assert!(decrypt_locked(&sealed, &keys).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_unauthenticated_decrypt() {
+        let keys = keys();
+        let sealed = encrypt(b"{\"Authenticators\":[]}", &keys).unwrap();
+        let plain =
+            decrypt_unauthenticated_locked(&sealed.ciphertext, keys.enc.expose(), &sealed.iv)
+                .unwrap();
+        assert_eq!(plain.as_slice(), b"{\"Authenticators\":[]}");
+
+        // Without a MAC, tampering goes unnoticed unless it breaks the padding.
+        let mut tampered = sealed.ciphertext.clone();
+        tampered[0] ^= 1;
+        let plain =
+            decrypt_unauthenticated_locked(&tampered, keys.enc.expose(), &sealed.iv).unwrap();
+        assert_ne!(plain.as_slice(), b"{\"Authenticators\":[]}");
+
+        assert!(decrypt_unauthenticated_locked(&sealed.ciphertext, &[0; 32], &[0; 8]).is_err());
+        assert!(
+            decrypt_unauthenticated_locked(&sealed.ciphertext[1..], &[0; 32], &sealed.iv).is_err()
+        );
+        assert!(decrypt_unauthenticated_locked(&sealed.ciphertext, &[0; 20], &sealed.iv).is_err());
+    }
+
+    #[test]
+    fn test_unauthenticated_aes128_vector() {
+        // NIST SP 800-38A F.2.1, first block, plus one block of PKCS#7 padding.
+        let key = hex("2b7e151628aed2a6abf7158809cf4f3c");
+        let iv = hex("000102030405060708090a0b0c0d0e0f");
+        let plaintext = hex("6bc1bee22e409f96e93d7e117393172a");
+        let ciphertext = hex("7649abac8119b246cee98e9b12e9197d8964e0b149c10b7b682e6e39aaeb731c");
+        let plain = decrypt_unauthenticated_locked(&ciphertext, &key, &iv).unwrap();
+        assert_eq!(plain.as_slice(), &plaintext[..]);
+    }
+
+    #[test]
+    fn test_unauthenticated_round_trip() {
+        for size in [16, 24, 32] {
+            let key = vec![7u8; size];
+            for plaintext in [&b""[..], b"0123456789abcdef", b"a PSKC secret"] {
+                let sealed = encrypt_unauthenticated(plaintext, &key).unwrap();
+                assert_eq!(sealed.len() % 16, 0);
+                assert!(sealed.len() > IV_SIZE + plaintext.len());
+                let plain =
+                    decrypt_unauthenticated_locked(&sealed[IV_SIZE..], &key, &sealed[..IV_SIZE])
+                        .unwrap();
+                assert_eq!(plain.as_slice(), plaintext);
+            }
+        }
+        assert!(encrypt_unauthenticated(b"x", &[0; 8]).is_err());
+    }
+}
@@ -13,12 +13,42 @@ pub enum CryptoError {
     CiphertextTooShort,
     /// AES-256-GCM decryption/authentication failed.
     DecryptionAuth,
-    /// Argon2id parameter validation failed.
+    /// KDF parameter validation failed (Argon2id cost, PBKDF2 iterations).
     InvalidKdfParams(String),
-    /// Argon2id key derivation failed.
+    /// Key derivation failed.
     KdfDerivation(String),
     /// The nonce passed to encrypt_with_nonce has the wrong length.
     InvalidNonceSize { expected: usize, got: usize },
+    /// A raw key passed in from outside Rust has the wrong length.
+    InvalidKeySize { expected: usize, got: usize },
+    /// An HMAC algorithm name was not recognised.
+    UnsupportedAlgorithm(String),
+    /// A token type name was not recognised.
+    UnsupportedTokenType(String),
+    /// The digit count is not valid for the token's code encoder.
+    UnsupportedDigits(u32),
+    /// A TOTP period of zero seconds.
+    InvalidPeriod,
+    /// The period is not valid for the token's code encoder.
+    UnsupportedPeriod(u64),
+    /// A master password fell below the configured strength policy.
+    WeakPassword {
+        entropy_bits: u32,
+        required_bits: u32,
+        min_length: usize,
+    },
+    /// One or more power-on known-answer tests failed.
+    SelfTestFailed(String),
+    /// A streaming AEAD header was missing or malformed.
+    StreamHeader(String),
+    /// A streaming AEAD input ended before its final chunk.
+    StreamTruncated,
+    /// A streaming AEAD input exceeded the chunk counter range.
+    StreamTooLong,
+    /// A streaming AEAD input continued past its final chunk.
+    StreamTrailingData,
+    /// Reading or writing the underlying stream failed.
+    Io(String),
 }
 
 impl fmt::Display for CryptoError {
@@ -28,11 +58,36 @@ impl fmt::Display for CryptoError {
             Self::Encryption(e) => write!(f, "Encryption failed: {}", e),
             Self::CiphertextTooShort => write!(f, "Ciphertext too short"),
             Self::DecryptionAuth => write!(f, "Decryption failed: authentication error"),
-            Self::InvalidKdfParams(e) => write!(f, "Invalid Argon2id params: {}", e),
-            Self::KdfDerivation(e) => write!(f, "Argon2id derivation failed: {}", e),
+            Self::InvalidKdfParams(e) => write!(f, "Invalid KDF params: {}", e),
+            Self::KdfDerivation(e) => write!(f, "Key derivation failed: {}", e),
             Self::InvalidNonceSize { expected, got } => {
                 write!(f, "Invalid nonce size: expected {}, got {}", expected, got)
             }
+            Self::InvalidKeySize { expected, got } => {
+                write!(f, "Invalid key size: expected {}, got {}", expected, got)
+            }
+            Self::UnsupportedAlgorithm(a) => write!(f, "Unsupported algorithm: {}", a),
+            Self::UnsupportedTokenType(t) => write!(f, "Unsupported token type: {}", t),
+            Self::UnsupportedDigits(d) => write!(f, "Unsupported digit count: {}", d),
+            Self::InvalidPeriod => write!(f, "TOTP period must be > 0"),
+            Self::UnsupportedPeriod(p) => write!(f, "Unsupported period: {}s", p),
+            Self::WeakPassword {
+                entropy_bits,
+                required_bits,
+                min_length,
+            } => write!(
+                f,
+                "Password too weak: ~{} bits of entropy, need {} bits and at least {} characters",
+                entropy_bits, required_bits, min_length
+            ),
+            Self::SelfTestFailed(names) => write!(f, "Crypto self-test failed: {}", names),
+            Self::StreamHeader(e) => write!(f, "Invalid stream header: {}", e),
+            Self::StreamTruncated => write!(f, "Encrypted stream is truncated"),
+            Self::StreamTooLong => write!(f, "Encrypted stream is too long"),
+            Self::StreamTrailingData => {
+                write!(f, "Encrypted stream has data after its final chunk")
+            }
+            Self::Io(e) => write!(f, "I/O error: {}", e),
         }
     }
 }
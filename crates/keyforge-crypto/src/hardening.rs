@@ -0,0 +1,110 @@
+//! Process hardening
+//!
+//! Keeps key material out of core dumps and away from same-user debuggers
+//! while the vault may be unlocked. On Linux this clears the dumpable flag
+//! (`PR_SET_DUMPABLE`), which also blocks `ptrace` attach and
+//! `/proc/<pid>/mem` reads by other processes of the same user, and sets
+//! `RLIMIT_CORE` to zero. Other Unix targets only get the core limit; on
+//! remaining targets [`apply`] is a no-op.
+
+/// Environment variable that relaxes hardening for local debugging.
+pub const ALLOW_DEBUGGER_ENV: &str = "KEYFORGE_ALLOW_DEBUGGER";
+
+/// Which protections [`apply`] should enable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HardeningConfig {
+    /// Set `RLIMIT_CORE` to 0 so a crash never writes a core file.
+    pub disable_core_dumps: bool,
+    /// Mark the process non-dumpable so debuggers cannot attach.
+    pub deny_ptrace: bool,
+}
+
+impl Default for HardeningConfig {
+    fn default() -> Self {
+        Self {
+            disable_core_dumps: true,
+            deny_ptrace: true,
+        }
+    }
+}
+
+impl HardeningConfig {
+    /// No protections at all — for attaching `gdb`/`lldb` during development.
+    pub fn permissive() -> Self {
+        Self {
+            disable_core_dumps: false,
+            deny_ptrace: false,
+        }
+    }
+
+    /// Default protections unless `KEYFORGE_ALLOW_DEBUGGER` is set to a
+    /// non-empty value other than `0`.
+    pub fn from_env() -> Self {
+        match std::env::var(ALLOW_DEBUGGER_ENV) {
+            Ok(v) if !v.is_empty() && v != "0" => Self::permissive(),
+            _ => Self::default(),
+        }
+    }
+}
+
+/// Which protections were actually put in place.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HardeningReport {
+    pub core_dumps_disabled: bool,
+    pub ptrace_denied: bool,
+}
+
+/// Apply the requested protections to the current process.
+///
+/// Individual failures are reported as `false` in the returned report
+/// rather than aborting, so the app still starts on restricted kernels.
+pub fn apply(config: &HardeningConfig) -> HardeningReport {
+    HardeningReport {
+        core_dumps_disabled: config.disable_core_dumps && disable_core_dumps(),
+        ptrace_denied: config.deny_ptrace && deny_ptrace(),
+    }
+}
+
+#[cfg(unix)]
+fn disable_core_dumps() -> bool {
+    let limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: setrlimit only reads the struct we pass.
+    unsafe { libc::setrlimit(libc::RLIMIT_CORE, &limit) == 0 }
+}
+
+#[cfg(not(unix))]
+fn disable_core_dumps() -> bool {
+    false
+}
+
+#[cfg(target_os = "linux")]
+fn deny_ptrace() -> bool {
+    // SAFETY: PR_SET_DUMPABLE takes a single integer argument.
+    unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0) == 0 }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn deny_ptrace() -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_enables_everything() {
+        let config = HardeningConfig::default();
+        assert!(config.disable_core_dumps);
+        assert!(config.deny_ptrace);
+    }
+
+    #[test]
+    fn test_permissive_applies_nothing() {
+        let report = apply(&HardeningConfig::permissive());
+        assert_eq!(report, HardeningReport::default());
+    }
+}
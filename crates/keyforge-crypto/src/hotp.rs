@@ -5,6 +5,8 @@ use sha1::Sha1;
 use sha2::{Sha256, Sha512};
 use zeroize::Zeroize;
 
+use crate::error::CryptoError;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Algorithm {
     SHA1,
@@ -12,8 +14,33 @@ pub enum Algorithm {
     SHA512,
 }
 
+impl Algorithm {
+    /// Canonical upper-case name as used in `otpauth://` URIs.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::SHA1 => "SHA1",
+            Self::SHA256 => "SHA256",
+            Self::SHA512 => "SHA512",
+        }
+    }
+}
+
+impl std::str::FromStr for Algorithm {
+    type Err = String;
+
+    /// Parse an algorithm name, ignoring case and an optional dash (`SHA-256`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().replace('-', "").as_str() {
+            "SHA1" => Ok(Self::SHA1),
+            "SHA256" => Ok(Self::SHA256),
+            "SHA512" => Ok(Self::SHA512),
+            _ => Err(CryptoError::UnsupportedAlgorithm(s.to_string()).into()),
+        }
+    }
+}
+
 /// Supported digit counts for OTP codes.
-const SUPPORTED_DIGITS: [u32; 2] = [6, 8];
+pub const SUPPORTED_DIGITS: [u32; 2] = [6, 8];
 
 /// Generate an HOTP code per RFC 4226.
 ///
@@ -26,6 +53,14 @@ pub fn generate(secret: &[u8], counter: u64, digits: u32, algorithm: Algorithm)
         "unsupported digit count {digits}: must be 6 or 8"
     );
 
+    let otp = truncate(secret, counter, algorithm) % 10u32.pow(digits);
+
+    format!("{:0>width$}", otp, width = digits as usize)
+}
+
+/// HMAC the counter and apply dynamic truncation (RFC 4226 §5.3), returning
+/// the 31-bit value that code encoders reduce to digits or characters.
+pub fn truncate(secret: &[u8], counter: u64, algorithm: Algorithm) -> u32 {
     let counter_bytes = counter.to_be_bytes();
 
     let mut hmac_result = match algorithm {
@@ -56,12 +91,10 @@ pub fn generate(secret: &[u8], counter: u64, digits: u32, algorithm: Algorithm)
         | ((hmac_result[offset + 2] as u32) << 8)
         | (hmac_result[offset + 3] as u32);
 
-    let otp = binary % 10u32.pow(digits);
-
     // Zeroize sensitive data
     hmac_result.zeroize();
 
-    format!("{:0>width$}", otp, width = digits as usize)
+    binary
 }
 
 #[cfg(test)]
@@ -107,6 +140,15 @@ mod tests {
         assert_ne!(sha1, sha512);
     }
 
+    #[test]
+    fn test_algorithm_from_str() {
+        assert_eq!("sha1".parse::<Algorithm>().unwrap(), Algorithm::SHA1);
+        assert_eq!("SHA-256".parse::<Algorithm>().unwrap(), Algorithm::SHA256);
+        assert_eq!("SHA512".parse::<Algorithm>().unwrap(), Algorithm::SHA512);
+        assert!("MD5".parse::<Algorithm>().is_err());
+        assert_eq!(Algorithm::SHA256.as_str(), "SHA256");
+    }
+
     #[test]
     fn test_deterministic() {
         let secret = b"test-secret-key";
@@ -1,13 +1,29 @@
-//! Argon2id key derivation
+//! Password-based key derivation
+//!
+//! Argon2id protects every native vault. PBKDF2-HMAC-SHA256 exists only for
+//! interop: with the browser extension, where Web Crypto offers nothing
+//! stronger, and with 2FAS, Bitwarden, andOTP and legacy Authenticator Pro
+//! (both SHA-1) files. scrypt exists only to open Aegis backups, and Argon2d
+//! and AES-KDF only to open KeePass databases. Never use any of these for a
+//! vault this crate creates on its own.
 
+use aes::cipher::{BlockEncrypt, KeyInit};
+use aes::Aes256;
 use argon2::{Algorithm, Argon2, Params, Version};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use zeroize::Zeroize;
 
 use crate::error::CryptoError;
+use crate::secure_mem::LockedKey;
 
 pub const DEFAULT_MEMORY_KIB: u32 = 65536; // 64 MiB
 pub const DEFAULT_TIME_COST: u32 = 3;
 pub const DEFAULT_PARALLELISM: u32 = 4;
 pub const KEY_LENGTH: usize = 32;
+/// PBKDF2 iteration count used by the browser extension (OWASP 2023).
+pub const PBKDF2_DEFAULT_ITERATIONS: u32 = 600_000;
 
 #[derive(Debug, Clone)]
 pub struct KdfParams {
@@ -32,6 +48,49 @@ pub fn derive_key(
     salt: &[u8; 16],
     params: &KdfParams,
 ) -> Result<[u8; KEY_LENGTH], String> {
+    let mut output = [0u8; KEY_LENGTH];
+    derive_into(password, salt, params, &mut output)?;
+    Ok(output)
+}
+
+/// Derive a 256-bit key directly into locked memory.
+pub fn derive_key_locked(
+    password: &[u8],
+    salt: &[u8; 16],
+    params: &KdfParams,
+) -> Result<LockedKey, String> {
+    derive_key_argon2id_locked(password, salt, params)
+}
+
+/// Like [`derive_key_locked`], but with a salt of any length, for foreign
+/// formats that do not use 16-byte salts (Bitwarden hashes its salt to 32
+/// bytes). Callers reading parameters from a file must bound them first.
+pub fn derive_key_argon2id_locked(
+    password: &[u8],
+    salt: &[u8],
+    params: &KdfParams,
+) -> Result<LockedKey, String> {
+    let mut key = LockedKey::zeroed();
+    derive_into(password, salt, params, key.expose_mut())?;
+    Ok(key)
+}
+
+fn derive_into(
+    password: &[u8],
+    salt: &[u8],
+    params: &KdfParams,
+    output: &mut [u8; KEY_LENGTH],
+) -> Result<(), String> {
+    argon2_into(Algorithm::Argon2id, password, salt, params, output)
+}
+
+fn argon2_into(
+    algorithm: Algorithm,
+    password: &[u8],
+    salt: &[u8],
+    params: &KdfParams,
+    output: &mut [u8; KEY_LENGTH],
+) -> Result<(), String> {
     let argon2_params = Params::new(
         params.memory_kib,
         params.time_cost,
@@ -40,14 +99,49 @@ pub fn derive_key(
     )
     .map_err(|e| CryptoError::InvalidKdfParams(e.to_string()))?;
 
-    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, argon2_params);
+    let argon2 = Argon2::new(algorithm, Version::V0x13, argon2_params);
 
-    let mut output = [0u8; KEY_LENGTH];
     argon2
-        .hash_password_into(password, salt, &mut output)
+        .hash_password_into(password, salt, output)
         .map_err(|e| CryptoError::KdfDerivation(e.to_string()))?;
 
-    Ok(output)
+    Ok(())
+}
+
+/// Derive a 256-bit key with Argon2d (v1.3), the default of KeePass
+/// databases, into locked memory. Callers reading parameters from a file
+/// must bound them first.
+pub fn derive_key_argon2d_locked(
+    password: &[u8],
+    salt: &[u8],
+    params: &KdfParams,
+) -> Result<LockedKey, String> {
+    let mut key = LockedKey::zeroed();
+    argon2_into(Algorithm::Argon2d, password, salt, params, key.expose_mut())?;
+    Ok(key)
+}
+
+/// KeePass's AES-KDF: encrypt `key` with AES-256-ECB under `seed`
+/// `rounds` times, then hash the result with SHA-256. Callers reading
+/// `rounds` from a file must bound it first.
+pub fn derive_key_aes_kdf_locked(
+    key: &[u8; KEY_LENGTH],
+    seed: &[u8; 32],
+    rounds: u64,
+) -> LockedKey {
+    let cipher = Aes256::new(seed.into());
+    let mut transformed = LockedKey::zeroed();
+    transformed.expose_mut().copy_from_slice(key);
+    for _ in 0..rounds {
+        for block in transformed.expose_mut().chunks_exact_mut(16) {
+            cipher.encrypt_block(block.into());
+        }
+    }
+    let mut output = LockedKey::zeroed();
+    let mut hash = Sha256::digest(transformed.expose());
+    output.expose_mut().copy_from_slice(&hash);
+    hash.zeroize();
+    output
 }
 
 /// Derive two independent keys (SQLCipher + secret encryption) from a single password.
@@ -62,6 +156,115 @@ pub fn derive_key_pair(
     Ok((sqlcipher_key, secret_key))
 }
 
+/// Like [`derive_key_pair`], but both keys are derived into locked memory.
+pub fn derive_key_pair_locked(
+    password: &[u8],
+    sqlcipher_salt: &[u8; 16],
+    secret_salt: &[u8; 16],
+    params: &KdfParams,
+) -> Result<(LockedKey, LockedKey), String> {
+    let sqlcipher_key = derive_key_locked(password, sqlcipher_salt, params)?;
+    let secret_key = derive_key_locked(password, secret_salt, params)?;
+    Ok((sqlcipher_key, secret_key))
+}
+
+/// Derive a 256-bit key with PBKDF2-HMAC-SHA256 (extension and import interop).
+pub fn derive_key_pbkdf2(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+) -> Result<[u8; KEY_LENGTH], String> {
+    let mut output = [0u8; KEY_LENGTH];
+    pbkdf2_into(password, salt, iterations, &mut output)?;
+    Ok(output)
+}
+
+/// Like [`derive_key_pbkdf2`], but the key is derived into locked memory.
+pub fn derive_key_pbkdf2_locked(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+) -> Result<LockedKey, String> {
+    let mut key = LockedKey::zeroed();
+    pbkdf2_into(password, salt, iterations, key.expose_mut())?;
+    Ok(key)
+}
+
+/// Derive a 256-bit key with PBKDF2-HMAC-SHA1 into locked memory (andOTP,
+/// legacy Authenticator Pro and PSKC interop only).
+pub fn derive_key_pbkdf2_sha1_locked(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+) -> Result<LockedKey, String> {
+    check_pbkdf2_iterations(iterations)?;
+    let mut key = LockedKey::zeroed();
+    pbkdf2::pbkdf2_hmac::<Sha1>(password, salt, iterations, key.expose_mut());
+    Ok(key)
+}
+
+fn pbkdf2_into(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    output: &mut [u8; KEY_LENGTH],
+) -> Result<(), String> {
+    check_pbkdf2_iterations(iterations)?;
+    pbkdf2::pbkdf2_hmac::<Sha256>(password, salt, iterations, output);
+    Ok(())
+}
+
+fn check_pbkdf2_iterations(iterations: u32) -> Result<(), String> {
+    if iterations == 0 {
+        return Err(CryptoError::InvalidKdfParams("PBKDF2 iterations must be > 0".into()).into());
+    }
+    Ok(())
+}
+
+/// Derive a 256-bit key with scrypt (Aegis backup interop).
+///
+/// `log_n` is the base-2 logarithm of the CPU/memory cost `N`. Callers
+/// reading parameters from a file must bound them first: memory use is
+/// `128 · r · N` bytes.
+pub fn derive_key_scrypt(
+    password: &[u8],
+    salt: &[u8],
+    log_n: u8,
+    r: u32,
+    p: u32,
+) -> Result<[u8; KEY_LENGTH], String> {
+    let mut output = [0u8; KEY_LENGTH];
+    scrypt_into(password, salt, log_n, r, p, &mut output)?;
+    Ok(output)
+}
+
+/// Like [`derive_key_scrypt`], but the key is derived into locked memory.
+pub fn derive_key_scrypt_locked(
+    password: &[u8],
+    salt: &[u8],
+    log_n: u8,
+    r: u32,
+    p: u32,
+) -> Result<LockedKey, String> {
+    let mut key = LockedKey::zeroed();
+    scrypt_into(password, salt, log_n, r, p, key.expose_mut())?;
+    Ok(key)
+}
+
+fn scrypt_into(
+    password: &[u8],
+    salt: &[u8],
+    log_n: u8,
+    r: u32,
+    p: u32,
+    output: &mut [u8; KEY_LENGTH],
+) -> Result<(), String> {
+    let params = scrypt::Params::new(log_n, r, p, KEY_LENGTH)
+        .map_err(|e| CryptoError::InvalidKdfParams(e.to_string()))?;
+    scrypt::scrypt(password, salt, &params, output)
+        .map_err(|e| CryptoError::KdfDerivation(e.to_string()).into())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -75,6 +278,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_argon2id_any_salt_length() {
+        let salt = [7u8; 16];
+        let fixed = derive_key(b"password", &salt, &test_params()).unwrap();
+        let any = derive_key_argon2id_locked(b"password", &salt, &test_params()).unwrap();
+        assert_eq!(any.expose(), &fixed);
+
+        let long = derive_key_argon2id_locked(b"password", &[7u8; 32], &test_params()).unwrap();
+        assert_ne!(long.expose(), &fixed);
+        assert!(derive_key_argon2id_locked(b"password", &[7u8; 4], &test_params()).is_err());
+    }
+
     #[test]
     fn test_derive_key_deterministic() {
         let password = b"test-password";
@@ -135,6 +350,20 @@ mod tests {
         assert_ne!(key_a, key_b);
     }
 
+    #[test]
+    fn test_locked_matches_unlocked() {
+        let salt = [1u8; 16];
+        let params = test_params();
+
+        let plain = derive_key(b"test-password", &salt, &params).unwrap();
+        let locked = derive_key_locked(b"test-password", &salt, &params).unwrap();
+        assert_eq!(locked.expose(), &plain);
+
+        let (a, b) = derive_key_pair_locked(b"test-password", &salt, &[2u8; 16], &params).unwrap();
+        assert_eq!(a.expose(), &plain);
+        assert_ne!(a.expose(), b.expose());
+    }
+
     #[test]
     fn test_empty_password() {
         let salt = [1u8; 16];
@@ -154,4 +383,97 @@ mod tests {
         assert!(result.is_ok());
         assert_eq!(result.unwrap().len(), 32);
     }
+
+    fn to_hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    #[test]
+    fn test_pbkdf2_rfc7914_vectors() {
+        // RFC 7914 §11, first 32 bytes of each PBKDF2-HMAC-SHA256 output.
+        let key = derive_key_pbkdf2(b"passwd", b"salt", 1).unwrap();
+        assert_eq!(
+            to_hex(&key),
+            "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc"
+        );
+        let key = derive_key_pbkdf2(b"Password", b"NaCl", 80_000).unwrap();
+        assert_eq!(
+            to_hex(&key),
+            "4ddcd8f60b98be21830cee5ef22701f9641a4418d04c0414aeff08876b34ab56"
+        );
+    }
+
+    #[test]
+    fn test_scrypt_rfc7914_vectors() {
+        // RFC 7914 §12, first 32 bytes of each scrypt output.
+        let key = derive_key_scrypt(b"", b"", 4, 1, 1).unwrap();
+        assert_eq!(
+            to_hex(&key),
+            "77d6576238657b203b19ca42c18a0497f16b4844e3074ae8dfdffa3fede21442"
+        );
+        let key = derive_key_scrypt(b"password", b"NaCl", 10, 8, 16).unwrap();
+        assert_eq!(
+            to_hex(&key),
+            "fdbabe1c9d3472007856e7190d01e9fe7c6ad7cbc8237830e77376634b373162"
+        );
+        let locked = derive_key_scrypt_locked(b"", b"", 4, 1, 1).unwrap();
+        assert_eq!(
+            to_hex(locked.expose()),
+            to_hex(&derive_key_scrypt(b"", b"", 4, 1, 1).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_scrypt_rejects_invalid_params() {
+        assert!(derive_key_scrypt(b"pw", b"salt", 64, 8, 1).is_err());
+        assert!(derive_key_scrypt(b"pw", b"salt", 4, 0, 1).is_err());
+        assert!(derive_key_scrypt(b"pw", b"salt", 4, 8, 0).is_err());
+    }
+
+    #[test]
+    fn test_pbkdf2_locked_matches_unlocked() {
+        let plain = derive_key_pbkdf2(b"test-password", &[1u8; 16], 10).unwrap();
+        let locked = derive_key_pbkdf2_locked(b"test-password", &[1u8; 16], 10).unwrap();
+        assert_eq!(locked.expose(), &plain);
+    }
+
+    #[test]
+    fn test_pbkdf2_rejects_zero_iterations() {
+        assert!(derive_key_pbkdf2(b"pw", b"salt", 0).is_err());
+        assert!(derive_key_pbkdf2_sha1_locked(b"pw", b"salt", 0).is_err());
+    }
+
+    #[test]
+    fn test_pbkdf2_sha1_rfc6070_vector() {
+        // RFC 6070 (c = 4096), extended to 32 bytes.
+        let key = derive_key_pbkdf2_sha1_locked(b"password", b"salt", 4096).unwrap();
+        assert_eq!(
+            to_hex(key.expose()),
+            "4b007901b765489abead49d926f721d065a429c12e463f6c4cd79401085b03db"
+        );
+    }
+
+    #[test]
+    fn test_argon2d_differs_from_argon2id() {
+        let salt = [7u8; 16];
+        let id = derive_key_argon2id_locked(b"password", &salt, &test_params()).unwrap();
+        let d = derive_key_argon2d_locked(b"password", &salt, &test_params()).unwrap();
+        assert_ne!(id.expose(), d.expose());
+        assert!(derive_key_argon2d_locked(b"password", &[7u8; 4], &test_params()).is_err());
+    }
+
+    #[test]
+    fn test_aes_kdf_vector() {
+        // AES-256-ECB under the seed, 10 rounds, then SHA-256; computed independently.
+        let key = derive_key_aes_kdf_locked(&[0x11; 32], &[0x22; 32], 10);
+        assert_eq!(
+            to_hex(key.expose()),
+            "1de77ceb2625877a6a5fa428a3ebd94ae43524e76bbb7ea2fc58c52c3295ef60"
+        );
+        let once = derive_key_aes_kdf_locked(&[0x11; 32], &[0x22; 32], 0);
+        assert_eq!(
+            to_hex(once.expose()),
+            "02d449a31fbb267c8f352e9968a79e3e5fc95c1bbeaa502fd6454ebde5a4bedc"
+        );
+    }
 }
@@ -0,0 +1,56 @@
+//! Raw ChaCha20 keystream (RFC 8439, 96-bit nonce), without authentication
+//!
+//! KeePass databases use it twice: as the payload cipher, authenticated by
+//! the file's own HMAC blocks, and as the inner stream that masks protected
+//! fields inside the decrypted XML. Only used to read foreign files.
+
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use chacha20::ChaCha20;
+
+/// A ChaCha20 keystream starting at block 0, consumed in order.
+pub struct ChaCha20Keystream {
+    cipher: ChaCha20,
+}
+
+impl ChaCha20Keystream {
+    pub fn new(key: &[u8; 32], nonce: &[u8; 12]) -> Self {
+        Self {
+            cipher: ChaCha20::new(key.into(), nonce.into()),
+        }
+    }
+
+    /// XOR the next `data.len()` keystream bytes into `data`.
+    pub fn apply(&mut self, data: &mut [u8]) {
+        self.cipher.apply_keystream(data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc8439_keystream_block() {
+        // RFC 8439 A.1, test vector #1: all-zero key and nonce, block 0.
+        let mut block = [0u8; 64];
+        ChaCha20Keystream::new(&[0; 32], &[0; 12]).apply(&mut block);
+        let hex: String = block.iter().map(|b| format!("{b:02x}")).collect();
+        assert_eq!(
+            hex,
+            "76b8e0ada0f13d90405d6ae55386bd28bdd219b8a08ded1aa836efcc8b770dc7\
+             da41597c5157488d7724e03fb8d84a376a43b8f41518a11cc387b669b2ee6586"
+        );
+    }
+
+    #[test]
+    fn test_split_application_continues_the_stream() {
+        let mut whole = [0u8; 100];
+        ChaCha20Keystream::new(&[3; 32], &[4; 12]).apply(&mut whole);
+        let mut parts = [0u8; 100];
+        let mut stream = ChaCha20Keystream::new(&[3; 32], &[4; 12]);
+        for chunk in parts.chunks_mut(7) {
+            stream.apply(chunk);
+        }
+        assert_eq!(whole, parts);
+    }
+}
@@ -1,6 +1,17 @@
 pub mod aead;
+pub mod cbc_hmac;
 pub mod error;
+pub mod hardening;
 pub mod hotp;
 pub mod kdf;
+pub mod keystream;
+pub mod otp;
+pub mod password;
 pub mod random;
+pub mod secretstream;
+pub mod secure_mem;
+pub mod selftest;
+pub mod stream;
 pub mod totp;
+
+pub use selftest::self_test;
@@ -0,0 +1,334 @@
+//! Token model and unified code generation
+//!
+//! [`TokenSpec`] describes everything needed to turn a secret into a code;
+//! [`OtpGenerator`] is the single dispatch point used by the vault, the
+//! Tauri commands and any other front end. A new token type is added by
+//! extending [`TokenKind`] / [`CodeEncoder`] here, not at every call site.
+
+use std::str::FromStr;
+
+use crate::error::CryptoError;
+use crate::hotp::{self, Algorithm};
+
+/// Name of the time-based token type.
+pub const TYPE_TOTP: &str = "totp";
+/// Name of the counter-based token type.
+pub const TYPE_HOTP: &str = "hotp";
+/// Name of the Steam Guard token type (TOTP with the Steam encoder).
+pub const TYPE_STEAM: &str = "steam";
+
+/// Steam Guard codes are always five characters.
+pub const STEAM_DIGITS: u32 = 5;
+/// Steam Guard codes always change every 30 seconds.
+pub const STEAM_PERIOD: u64 = 30;
+const STEAM_ALPHABET: &[u8; 26] = b"23456789BCDFGHJKMNPQRTVWXY";
+
+/// How the moving factor is derived.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Time-based (RFC 6238).
+    Totp,
+    /// Counter-based (RFC 4226).
+    Hotp,
+}
+
+/// How the truncated HMAC value is rendered as a code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeEncoder {
+    /// Zero-padded decimal digits (RFC 4226).
+    Decimal,
+    /// Steam Guard's 26-character alphabet.
+    Steam,
+}
+
+/// Full description of how to generate codes for one token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenSpec {
+    pub kind: TokenKind,
+    pub algorithm: Algorithm,
+    pub digits: u32,
+    /// TOTP step in seconds (ignored for HOTP).
+    pub period: u64,
+    /// HOTP moving factor (ignored for TOTP).
+    pub counter: u64,
+    pub encoder: CodeEncoder,
+}
+
+impl TokenSpec {
+    /// Standard TOTP with decimal codes.
+    pub fn totp(algorithm: Algorithm, digits: u32, period: u64) -> Self {
+        Self {
+            kind: TokenKind::Totp,
+            algorithm,
+            digits,
+            period,
+            counter: 0,
+            encoder: CodeEncoder::Decimal,
+        }
+    }
+
+    /// Standard HOTP with decimal codes.
+    pub fn hotp(algorithm: Algorithm, digits: u32, counter: u64) -> Self {
+        Self {
+            kind: TokenKind::Hotp,
+            algorithm,
+            digits,
+            period: 0,
+            counter,
+            encoder: CodeEncoder::Decimal,
+        }
+    }
+
+    /// Steam Guard: SHA1, 30 s period, five-character codes.
+    pub fn steam() -> Self {
+        Self {
+            kind: TokenKind::Totp,
+            algorithm: Algorithm::SHA1,
+            digits: STEAM_DIGITS,
+            period: STEAM_PERIOD,
+            counter: 0,
+            encoder: CodeEncoder::Steam,
+        }
+    }
+
+    /// Build a spec from the string/integer fields stored on a vault token.
+    ///
+    /// `token_type` is one of [`TYPE_TOTP`], [`TYPE_HOTP`] or [`TYPE_STEAM`]
+    /// (case-insensitive). The result is validated before it is returned.
+    pub fn from_parts(
+        token_type: &str,
+        algorithm: &str,
+        digits: u32,
+        period: u64,
+        counter: u64,
+    ) -> Result<Self, String> {
+        let algorithm = Algorithm::from_str(algorithm)?;
+        let spec = match token_type.to_ascii_lowercase().as_str() {
+            TYPE_TOTP => Self::totp(algorithm, digits, period),
+            TYPE_HOTP => Self::hotp(algorithm, digits, counter),
+            TYPE_STEAM => Self {
+                algorithm,
+                digits,
+                period,
+                ..Self::steam()
+            },
+            _ => return Err(CryptoError::UnsupportedTokenType(token_type.to_string()).into()),
+        };
+        spec.validate()?;
+        Ok(spec)
+    }
+
+    /// The stored type name for this spec (inverse of [`TokenSpec::from_parts`]).
+    pub fn type_name(&self) -> &'static str {
+        match (self.kind, self.encoder) {
+            (TokenKind::Totp, CodeEncoder::Steam) => TYPE_STEAM,
+            (TokenKind::Totp, CodeEncoder::Decimal) => TYPE_TOTP,
+            (TokenKind::Hotp, _) => TYPE_HOTP,
+        }
+    }
+
+    /// Check digits and period against the kind and encoder. Steam Guard
+    /// only exists as SHA1 with a 30 s period.
+    pub fn validate(&self) -> Result<(), String> {
+        let digits_ok = match self.encoder {
+            CodeEncoder::Decimal => hotp::SUPPORTED_DIGITS.contains(&self.digits),
+            CodeEncoder::Steam => self.digits == STEAM_DIGITS,
+        };
+        if !digits_ok {
+            return Err(CryptoError::UnsupportedDigits(self.digits).into());
+        }
+        if self.kind == TokenKind::Totp && self.period == 0 {
+            return Err(CryptoError::InvalidPeriod.into());
+        }
+        if self.encoder == CodeEncoder::Steam {
+            if self.algorithm != Algorithm::SHA1 {
+                return Err(
+                    CryptoError::UnsupportedAlgorithm(self.algorithm.as_str().to_string()).into(),
+                );
+            }
+            if self.period != STEAM_PERIOD {
+                return Err(CryptoError::UnsupportedPeriod(self.period).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Inputs that vary per generation call rather than per token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OtpContext {
+    /// Unix time in seconds.
+    pub time: u64,
+}
+
+impl OtpContext {
+    pub fn at(time: u64) -> Self {
+        Self { time }
+    }
+
+    /// Context for the current system time.
+    pub fn now() -> Self {
+        let time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self { time }
+    }
+}
+
+/// A generated code and the window in which it is valid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OtpCode {
+    pub code: String,
+    /// Start of the TOTP step (inclusive); `None` for HOTP.
+    pub valid_from: Option<u64>,
+    /// End of the TOTP step (exclusive); `None` for HOTP, whose codes stay
+    /// valid until the counter advances.
+    pub valid_until: Option<u64>,
+}
+
+impl OtpCode {
+    /// Seconds left in the validity window at `time`, if the code expires.
+    pub fn remaining(&self, time: u64) -> Option<u64> {
+        self.valid_until.map(|until| until.saturating_sub(time))
+    }
+}
+
+/// Anything that can turn a secret into a one-time code.
+pub trait OtpGenerator {
+    fn generate(&self, secret: &[u8], ctx: &OtpContext) -> Result<OtpCode, String>;
+}
+
+impl OtpGenerator for TokenSpec {
+    fn generate(&self, secret: &[u8], ctx: &OtpContext) -> Result<OtpCode, String> {
+        self.validate()?;
+
+        let (counter, valid_from, valid_until) = match self.kind {
+            TokenKind::Totp => {
+                let step = ctx.time / self.period;
+                let start = step * self.period;
+                (step, Some(start), Some(start + self.period))
+            }
+            TokenKind::Hotp => (self.counter, None, None),
+        };
+
+        let value = hotp::truncate(secret, counter, self.algorithm);
+        let code = match self.encoder {
+            CodeEncoder::Decimal => encode_decimal(value, self.digits),
+            CodeEncoder::Steam => encode_steam(value, self.digits),
+        };
+
+        Ok(OtpCode {
+            code,
+            valid_from,
+            valid_until,
+        })
+    }
+}
+
+fn encode_decimal(value: u32, digits: u32) -> String {
+    let otp = value % 10u32.pow(digits);
+    format!("{:0>width$}", otp, width = digits as usize)
+}
+
+fn encode_steam(mut value: u32, digits: u32) -> String {
+    let base = STEAM_ALPHABET.len() as u32;
+    (0..digits)
+        .map(|_| {
+            let c = STEAM_ALPHABET[(value % base) as usize] as char;
+            value /= base;
+            c
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::totp;
+
+    const SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn test_totp_matches_totp_module() {
+        let spec = TokenSpec::totp(Algorithm::SHA1, 8, 30);
+        let code = spec.generate(SECRET, &OtpContext::at(59)).unwrap();
+        assert_eq!(code.code, "94287082");
+        assert_eq!(
+            code.code,
+            totp::generate(SECRET, 59, 30, 8, Algorithm::SHA1)
+        );
+        assert_eq!(code.valid_from, Some(30));
+        assert_eq!(code.valid_until, Some(60));
+        assert_eq!(code.remaining(59), Some(1));
+    }
+
+    #[test]
+    fn test_hotp_uses_spec_counter() {
+        let spec = TokenSpec::hotp(Algorithm::SHA1, 6, 1);
+        let code = spec.generate(SECRET, &OtpContext::at(12345)).unwrap();
+        assert_eq!(code.code, "287082");
+        assert_eq!(code.valid_until, None);
+        assert_eq!(code.remaining(0), None);
+    }
+
+    #[test]
+    fn test_steam_code_shape() {
+        let code = TokenSpec::steam()
+            .generate(SECRET, &OtpContext::at(59))
+            .unwrap()
+            .code;
+        assert_eq!(code.len(), 5);
+        assert!(code.bytes().all(|b| STEAM_ALPHABET.contains(&b)));
+    }
+
+    #[test]
+    fn test_steam_known_value() {
+        // Counter 1 truncates to 1094287082 (RFC 4226 Appendix D).
+        let code = TokenSpec::steam()
+            .generate(SECRET, &OtpContext::at(59))
+            .unwrap();
+        assert_eq!(code.code, "PV9M4");
+    }
+
+    #[test]
+    fn test_from_parts_roundtrip() {
+        for (ty, digits) in [("totp", 6), ("HOTP", 8), ("steam", 5)] {
+            let spec = TokenSpec::from_parts(ty, "sha1", digits, 30, 3).unwrap();
+            assert_eq!(spec.type_name(), ty.to_ascii_lowercase());
+        }
+    }
+
+    #[test]
+    fn test_from_parts_rejects_invalid() {
+        assert!(TokenSpec::from_parts("motp", "SHA1", 6, 30, 0).is_err());
+        assert!(TokenSpec::from_parts("totp", "MD5", 6, 30, 0).is_err());
+        assert!(TokenSpec::from_parts("totp", "SHA1", 7, 30, 0).is_err());
+        assert!(TokenSpec::from_parts("totp", "SHA1", 6, 0, 0).is_err());
+        assert!(TokenSpec::from_parts("steam", "SHA1", 6, 30, 0).is_err());
+    }
+
+    #[test]
+    fn test_from_parts_rejects_non_steam_parameters() {
+        assert_eq!(
+            TokenSpec::from_parts("steam", "SHA256", 5, 30, 0).unwrap_err(),
+            "Unsupported algorithm: SHA256"
+        );
+        assert_eq!(
+            TokenSpec::from_parts("steam", "SHA1", 5, 60, 0).unwrap_err(),
+            "Unsupported period: 60s"
+        );
+        assert_eq!(
+            TokenSpec::from_parts("steam", "sha1", 5, 30, 0).unwrap(),
+            TokenSpec::steam()
+        );
+        // HOTP does not care about the period.
+        assert!(TokenSpec::from_parts("hotp", "SHA1", 6, 0, 0).is_ok());
+    }
+
+    #[test]
+    fn test_generate_rejects_invalid_spec_instead_of_panicking() {
+        let spec = TokenSpec::totp(Algorithm::SHA1, 9, 30);
+        assert!(spec.generate(SECRET, &OtpContext::at(0)).is_err());
+    }
+}
@@ -0,0 +1,457 @@
+//! Master password strength estimation
+//!
+//! A deliberately small estimator: start from the character-pool entropy
+//! of each character, then charge dictionary words, keyboard walks,
+//! alphabetic/numeric sequences and repetition at a fraction of that cost.
+//! The resulting entropy is turned into an offline crack-time estimate
+//! under the Argon2id parameters the vault will actually use.
+
+use std::fmt;
+
+use crate::error::CryptoError;
+use crate::kdf::KdfParams;
+
+/// Memory bandwidth assumed for an offline attacker, in bytes per second
+/// (roughly a hundred high-end GPUs).
+pub const ATTACKER_BANDWIDTH_BYTES_PER_SEC: f64 = 1e14;
+
+/// Bits charged for a character that continues a pattern.
+const PATTERN_CHAR_BITS: f64 = 1.0;
+/// Shortest run that counts as a keyboard walk / sequence / repeat.
+const MIN_PATTERN_RUN: usize = 3;
+/// Shortest dictionary word that is matched inside a password.
+const MIN_WORD_LEN: usize = 4;
+
+/// Why an estimate was marked down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrengthWarning {
+    Empty,
+    TooShort,
+    /// Contains a common password or dictionary word.
+    CommonWord,
+    /// Contains a run of adjacent keys (`qwerty`, `asdf`).
+    KeyboardPattern,
+    /// Contains an alphabetic or numeric sequence (`abcd`, `4321`).
+    Sequence,
+    /// Contains repeated characters or a repeated chunk (`aaaa`, `abcabc`).
+    Repetition,
+}
+
+impl StrengthWarning {
+    /// Stable snake_case identifier for UIs and serialization.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Empty => "empty",
+            Self::TooShort => "too_short",
+            Self::CommonWord => "common_word",
+            Self::KeyboardPattern => "keyboard_pattern",
+            Self::Sequence => "sequence",
+            Self::Repetition => "repetition",
+        }
+    }
+}
+
+impl fmt::Display for StrengthWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Empty => "Enter a password",
+            Self::TooShort => "Too short",
+            Self::CommonWord => "Contains a common password or word",
+            Self::KeyboardPattern => "Contains a keyboard pattern",
+            Self::Sequence => "Contains a sequence of letters or digits",
+            Self::Repetition => "Contains repeated characters",
+        })
+    }
+}
+
+/// Result of [`estimate`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrengthEstimate {
+    /// Estimated guessing entropy in bits.
+    pub entropy_bits: f64,
+    /// Expected seconds for an offline attacker to find the password
+    /// (half the search space) under the given Argon2id parameters.
+    pub crack_seconds: f64,
+    /// Coarse 0 (terrible) to 4 (strong) score for UI meters.
+    pub score: u8,
+    pub warnings: Vec<StrengthWarning>,
+}
+
+/// Minimum requirements for a master password.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasswordPolicy {
+    /// Minimum length in characters.
+    pub min_length: usize,
+    /// Minimum estimated entropy in bits.
+    pub min_entropy_bits: f64,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            min_entropy_bits: 40.0,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// Estimate `password`, flagging it [`StrengthWarning::TooShort`] below
+    /// this policy's minimum length.
+    pub fn estimate(&self, password: &str, params: &KdfParams) -> StrengthEstimate {
+        estimate_with_min_length(password, params, self.min_length)
+    }
+
+    /// Whether `password` with the given estimate meets the policy.
+    pub fn accepts(&self, password: &str, estimate: &StrengthEstimate) -> bool {
+        password.chars().count() >= self.min_length
+            && estimate.entropy_bits >= self.min_entropy_bits
+    }
+
+    /// Estimate `password` and reject it if it falls below the policy.
+    pub fn check(&self, password: &str, params: &KdfParams) -> Result<StrengthEstimate, String> {
+        let estimate = self.estimate(password, params);
+        if !self.accepts(password, &estimate) {
+            return Err(CryptoError::WeakPassword {
+                entropy_bits: estimate.entropy_bits.floor() as u32,
+                required_bits: self.min_entropy_bits.ceil() as u32,
+                min_length: self.min_length,
+            }
+            .into());
+        }
+        Ok(estimate)
+    }
+}
+
+/// Estimate the strength of `password` against an offline attacker who
+/// must run Argon2id with `params` for every guess. Length is judged
+/// against the default [`PasswordPolicy`]; see [`PasswordPolicy::estimate`].
+pub fn estimate(password: &str, params: &KdfParams) -> StrengthEstimate {
+    PasswordPolicy::default().estimate(password, params)
+}
+
+fn estimate_with_min_length(
+    password: &str,
+    params: &KdfParams,
+    min_length: usize,
+) -> StrengthEstimate {
+    let chars: Vec<char> = password.chars().collect();
+    let mut warnings = Vec::new();
+
+    if chars.is_empty() {
+        warnings.push(StrengthWarning::Empty);
+        return finish(0.0, params, warnings);
+    }
+
+    let entropy = match repeated_unit(&chars) {
+        Some((unit, count)) => {
+            warnings.push(StrengthWarning::Repetition);
+            scan(unit, &mut warnings) + (count as f64).log2()
+        }
+        None => scan(&chars, &mut warnings),
+    };
+
+    if chars.len() < min_length {
+        warnings.push(StrengthWarning::TooShort);
+    }
+    warnings.dedup();
+    finish(entropy, params, warnings)
+}
+
+fn finish(
+    entropy_bits: f64,
+    params: &KdfParams,
+    warnings: Vec<StrengthWarning>,
+) -> StrengthEstimate {
+    let guesses = if entropy_bits <= 1.0 {
+        1.0
+    } else {
+        2f64.powf(entropy_bits - 1.0)
+    };
+    let crack_seconds = guesses / guesses_per_second(params);
+    let score = match entropy_bits {
+        b if b < 28.0 => 0,
+        b if b < 36.0 => 1,
+        b if b < 50.0 => 2,
+        b if b < 64.0 => 3,
+        _ => 4,
+    };
+    StrengthEstimate {
+        entropy_bits,
+        crack_seconds,
+        score,
+        warnings,
+    }
+}
+
+/// Argon2 is memory-hard: each guess reads and writes the whole memory
+/// block once per pass, so throughput is bounded by memory bandwidth.
+pub fn guesses_per_second(params: &KdfParams) -> f64 {
+    let bytes_per_guess =
+        2.0 * 1024.0 * f64::from(params.memory_kib.max(8)) * f64::from(params.time_cost.max(1));
+    ATTACKER_BANDWIDTH_BYTES_PER_SEC / bytes_per_guess
+}
+
+/// Entropy of `chars` as a sum of per-character costs, with dictionary
+/// words and pattern continuations charged at a discount.
+fn scan(chars: &[char], warnings: &mut Vec<StrengthWarning>) -> f64 {
+    let pool_bits = (pool_size(chars) as f64).log2();
+    let lowered: Vec<char> = chars.iter().map(|c| c.to_ascii_lowercase()).collect();
+    let unleeted: Vec<char> = lowered.iter().map(|c| unleet(*c)).collect();
+    let dictionary_bits = (COMMON_WORDS.len() as f64).log2();
+
+    let mut bits = 0.0;
+    let mut i = 0;
+    while i < chars.len() {
+        let word = longest_word_at(&lowered, i).max(longest_word_at(&unleeted, i));
+        if let Some(len) = word {
+            warnings.push(StrengthWarning::CommonWord);
+            // One bit for "capitalised or not", as attackers try both.
+            let capitalised = chars[i..i + len].iter().any(|c| c.is_uppercase());
+            bits += dictionary_bits + if capitalised { 1.0 } else { 0.0 };
+            i += len;
+            continue;
+        }
+
+        match pattern_at(chars, i) {
+            Some(warning) => {
+                warnings.push(warning);
+                bits += PATTERN_CHAR_BITS;
+            }
+            None => bits += pool_bits,
+        }
+        i += 1;
+    }
+    bits
+}
+
+/// Size of the character pool an attacker would need to cover.
+fn pool_size(chars: &[char]) -> u32 {
+    let mut pool = 0;
+    if chars.iter().any(|c| c.is_ascii_lowercase()) {
+        pool += 26;
+    }
+    if chars.iter().any(|c| c.is_ascii_uppercase()) {
+        pool += 26;
+    }
+    if chars.iter().any(|c| c.is_ascii_digit()) {
+        pool += 10;
+    }
+    if chars.iter().any(|c| c.is_ascii_punctuation() || *c == ' ') {
+        pool += 33;
+    }
+    if chars.iter().any(|c| !c.is_ascii()) {
+        pool += 100;
+    }
+    pool.max(1)
+}
+
+/// If `chars` is one chunk repeated two or more times, return the chunk
+/// and the repeat count.
+fn repeated_unit(chars: &[char]) -> Option<(&[char], usize)> {
+    let n = chars.len();
+    (1..=n / 2)
+        .filter(|unit| n.is_multiple_of(*unit))
+        .find(|&unit| chars.chunks(unit).all(|c| c == &chars[..unit]))
+        .map(|unit| (&chars[..unit], n / unit))
+}
+
+/// Whether `chars[i]` is the third-or-later element of a run of repeats,
+/// a +/-1 sequence or a keyboard walk.
+fn pattern_at(chars: &[char], i: usize) -> Option<StrengthWarning> {
+    if i + 1 < MIN_PATTERN_RUN {
+        return None;
+    }
+    let (a, b, c) = (chars[i - 2], chars[i - 1], chars[i]);
+    let la = a.to_ascii_lowercase();
+    let lb = b.to_ascii_lowercase();
+    let lc = c.to_ascii_lowercase();
+
+    if la == lb && lb == lc {
+        return Some(StrengthWarning::Repetition);
+    }
+
+    let step1 = lb as i64 - la as i64;
+    let step2 = lc as i64 - lb as i64;
+    if step1 == step2 && step1.abs() == 1 && la.is_ascii_alphanumeric() {
+        return Some(StrengthWarning::Sequence);
+    }
+
+    match (keyboard_pos(la), keyboard_pos(lb), keyboard_pos(lc)) {
+        (Some((ra, ca)), Some((rb, cb)), Some((rc, cc)))
+            if ra == rb && rb == rc && cb - ca == cc - cb && (cb - ca).abs() == 1 =>
+        {
+            Some(StrengthWarning::KeyboardPattern)
+        }
+        _ => None,
+    }
+}
+
+const KEYBOARD_ROWS: [&str; 4] = ["1234567890", "qwertyuiop", "asdfghjkl", "zxcvbnm"];
+
+fn keyboard_pos(c: char) -> Option<(usize, i64)> {
+    KEYBOARD_ROWS
+        .iter()
+        .enumerate()
+        .find_map(|(row, keys)| keys.find(c).map(|col| (row, col as i64)))
+}
+
+/// Undo the common "leet" substitutions before dictionary lookup.
+fn unleet(c: char) -> char {
+    match c {
+        '0' => 'o',
+        '1' | '!' => 'i',
+        '3' => 'e',
+        '4' | '@' => 'a',
+        '5' | '$' => 's',
+        '7' => 't',
+        _ => c,
+    }
+}
+
+fn longest_word_at(lowered: &[char], i: usize) -> Option<usize> {
+    COMMON_WORDS
+        .iter()
+        .filter(|w| w.len() >= MIN_WORD_LEN && w.len() <= lowered.len() - i)
+        .filter(|w| w.chars().zip(&lowered[i..]).all(|(a, b)| a == *b))
+        .map(|w| w.len())
+        .max()
+}
+
+/// Most common leaked passwords and English words seen in them.
+#[rustfmt::skip]
+const COMMON_WORDS: &[&str] = &[
+    "password", "passw0rd", "qwerty", "letmein", "welcome", "admin", "login", "master", "dragon",
+    "monkey", "football", "baseball", "soccer", "hockey", "batman", "superman", "iloveyou", "love",
+    "sunshine", "princess", "shadow", "michael", "jennifer", "jordan", "hunter", "ranger",
+    "harley", "thomas", "robert", "daniel", "charlie", "andrew", "matthew", "jessica", "ashley",
+    "amanda", "nicole", "summer", "winter", "spring", "autumn", "secret", "freedom", "whatever",
+    "trustno1", "starwars", "pokemon", "killer", "cheese", "computer", "internet", "google",
+    "apple", "orange", "banana", "chocolate", "cookie", "pepper", "ginger", "tigger", "buster",
+    "maggie", "bailey", "coffee", "flower", "hello", "hello123", "access", "mustang", "cowboy",
+    "silver", "golden", "diamond", "phoenix", "thunder", "lightning", "matrix", "ninja", "pirate",
+    "wizard", "angel", "devil", "heaven", "family", "friend", "friends", "forever", "purple",
+    "yellow", "black", "white", "green", "blue", "pink", "money", "office", "school", "london",
+    "paris", "berlin", "america", "canada", "india", "keyforge", "vault", "token", "authenticator",
+    "correct", "horse", "battery", "staple", "changeme", "default", "guest", "root", "toor",
+    "user", "test", "testing", "demo", "abc123", "qazwsx", "zaq12wsx", "asdfgh", "zxcvbn",
+    "1q2w3e4r", "q1w2e3r4", "aaaaaa", "111111", "123123", "654321", "666666", "696969", "121212",
+    "000000", "112233", "baby", "babygirl", "lovely", "loveme", "jesus", "christ", "blessed",
+    "mother", "father", "sister", "brother", "martin", "george", "jackson", "taylor", "hannah",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> KdfParams {
+        KdfParams::default()
+    }
+
+    fn bits(password: &str) -> f64 {
+        estimate(password, &params()).entropy_bits
+    }
+
+    #[test]
+    fn test_policy_sets_too_short_threshold() {
+        let policy = PasswordPolicy {
+            min_length: 20,
+            min_entropy_bits: 0.0,
+        };
+        let password = "Tr0ub4dor&3xyz!";
+        let strict = policy.estimate(password, &KdfParams::default());
+        assert!(strict.warnings.contains(&StrengthWarning::TooShort));
+        assert!(!policy.accepts(password, &strict));
+        assert!(!estimate(password, &KdfParams::default())
+            .warnings
+            .contains(&StrengthWarning::TooShort));
+        assert_eq!(StrengthWarning::TooShort.code(), "too_short");
+    }
+
+    #[test]
+    fn test_empty_password() {
+        let e = estimate("", &params());
+        assert_eq!(e.entropy_bits, 0.0);
+        assert_eq!(e.score, 0);
+        assert!(e.warnings.contains(&StrengthWarning::Empty));
+    }
+
+    #[test]
+    fn test_common_password_is_weak() {
+        let e = estimate("P@ssw0rd", &params());
+        assert!(e.entropy_bits < 20.0, "got {}", e.entropy_bits);
+        assert!(e.warnings.contains(&StrengthWarning::CommonWord));
+    }
+
+    #[test]
+    fn test_keyboard_walk_penalised() {
+        let e = estimate("qwertyuiop", &params());
+        assert!(
+            e.warnings.contains(&StrengthWarning::KeyboardPattern)
+                || e.warnings.contains(&StrengthWarning::CommonWord)
+        );
+        assert!(bits("qwertyuiop") < bits("qpwoeirutz") / 2.0);
+    }
+
+    #[test]
+    fn test_sequences_penalised() {
+        let e = estimate("abcdefgh12345678", &params());
+        assert!(e.warnings.contains(&StrengthWarning::Sequence));
+        assert!(e.entropy_bits < 35.0, "got {}", e.entropy_bits);
+    }
+
+    #[test]
+    fn test_repetition_penalised() {
+        assert!(estimate("zzzzzzzzzz", &params())
+            .warnings
+            .contains(&StrengthWarning::Repetition));
+        assert!(bits("x7#kx7#kx7#k") < bits("x7#kq2!mw9&p") - 20.0);
+    }
+
+    #[test]
+    fn test_random_password_is_strong() {
+        let e = estimate("vT9#qLm2$xWp8!rZ", &params());
+        assert!(e.entropy_bits > 90.0, "got {}", e.entropy_bits);
+        assert_eq!(e.score, 4);
+        assert!(e.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_passphrase_beats_single_word() {
+        assert!(bits("correct-horse-battery-staple") > 40.0);
+        assert!(bits("correct") < 10.0);
+    }
+
+    #[test]
+    fn test_crack_time_scales_with_kdf_cost() {
+        let cheap = KdfParams {
+            memory_kib: 1024,
+            time_cost: 1,
+            parallelism: 1,
+        };
+        let slow = estimate("vT9#qLm2", &params()).crack_seconds;
+        let fast = estimate("vT9#qLm2", &cheap).crack_seconds;
+        assert!(slow > fast * 100.0);
+    }
+
+    #[test]
+    fn test_policy_rejects_weak_and_short() {
+        let policy = PasswordPolicy::default();
+        assert!(policy.check("", &params()).is_err());
+        assert!(policy.check("password123", &params()).is_err());
+        assert!(policy.check("vT9#qL", &params()).is_err());
+        assert!(policy.check("vT9#qLm2$xWp", &params()).is_ok());
+
+        let err = policy.check("password", &params()).unwrap_err();
+        assert!(err.contains("too weak"), "got {err}");
+    }
+
+    #[test]
+    fn test_policy_is_configurable() {
+        let lenient = PasswordPolicy {
+            min_length: 1,
+            min_entropy_bits: 0.0,
+        };
+        assert!(lenient.check("a", &params()).is_ok());
+    }
+}
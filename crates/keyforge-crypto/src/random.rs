@@ -1,27 +1,43 @@
 //! Secure random generation
-
-use rand::RngCore;
+//!
+//! Native targets use `rand`'s thread-local CSPRNG, seeded from the OS.
+//! On `wasm32-unknown-unknown` there is no OS, so bytes come straight from
+//! the browser's Web Crypto `getRandomValues`.
 
 use crate::aead;
 
 /// Size of an Argon2 salt in bytes.
 pub const SALT_SIZE: usize = 16;
 
+/// Fill `bytes` from the target's secure random source.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub fn fill(bytes: &mut [u8]) {
+    use rand::RngCore;
+    rand::thread_rng().fill_bytes(bytes);
+}
+
+/// Fill `bytes` from the target's secure random source.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub fn fill(bytes: &mut [u8]) {
+    // A missing `crypto.getRandomValues` leaves no safe fallback.
+    getrandom::getrandom(bytes).expect("Web Crypto getRandomValues unavailable");
+}
+
 pub fn generate_bytes(length: usize) -> Vec<u8> {
     let mut bytes = vec![0u8; length];
-    rand::thread_rng().fill_bytes(&mut bytes);
+    fill(&mut bytes);
     bytes
 }
 
 pub fn generate_salt() -> [u8; SALT_SIZE] {
     let mut salt = [0u8; SALT_SIZE];
-    rand::thread_rng().fill_bytes(&mut salt);
+    fill(&mut salt);
     salt
 }
 
 pub fn generate_nonce() -> [u8; aead::NONCE_SIZE] {
     let mut nonce = [0u8; aead::NONCE_SIZE];
-    rand::thread_rng().fill_bytes(&mut nonce);
+    fill(&mut nonce);
     nonce
 }
 
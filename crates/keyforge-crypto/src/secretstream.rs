@@ -0,0 +1,296 @@
+//! libsodium-compatible XChaCha20-Poly1305 secretstream
+//!
+//! Implements `crypto_secretstream_xchacha20poly1305` so files sealed by
+//! libsodium-based apps (Ente Auth) can be opened without linking libsodium.
+//! A 24-byte header yields a per-stream subkey via HChaCha20; each message
+//! is sealed as `encrypted tag(1) ‖ ciphertext ‖ mac(16)` with a nonce that
+//! is ratcheted by the previous MAC, so messages cannot be reordered or
+//! dropped undetected. A [`TAG_FINAL`] message marks the end of the stream.
+
+use chacha20::cipher::consts::U10;
+use chacha20::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+use chacha20::{hchacha, ChaCha20};
+use poly1305::universal_hash::{KeyInit, UniversalHash};
+use poly1305::Poly1305;
+use zeroize::Zeroize;
+
+use crate::error::CryptoError;
+use crate::secure_mem::{LockedBuffer, LockedKey};
+
+/// Size of the stream header stored before the first message.
+pub const HEADER_SIZE: usize = 24;
+/// Bytes each sealed message adds to its plaintext (tag byte + MAC).
+pub const ABYTES: usize = 1 + MAC_SIZE;
+/// An ordinary message.
+pub const TAG_MESSAGE: u8 = 0;
+/// The end of a set of messages, but not of the stream.
+pub const TAG_PUSH: u8 = 1;
+/// Derive a new key after this message.
+pub const TAG_REKEY: u8 = 2;
+/// The last message of the stream.
+pub const TAG_FINAL: u8 = TAG_PUSH | TAG_REKEY;
+
+const MAC_SIZE: usize = 16;
+const COUNTER_SIZE: usize = 4;
+const INONCE_SIZE: usize = 8;
+const BLOCK_SIZE: usize = 64;
+
+/// Per-stream key and nonce, `counter(4, LE) ‖ inonce(8)`.
+struct State {
+    key: LockedKey,
+    nonce: [u8; COUNTER_SIZE + INONCE_SIZE],
+}
+
+impl State {
+    fn new(key: &[u8; 32], header: &[u8; HEADER_SIZE]) -> Self {
+        let mut subkey = hchacha::<U10>(key.into(), header[..16].into());
+        let mut state = Self {
+            key: LockedKey::zeroed(),
+            nonce: [0u8; COUNTER_SIZE + INONCE_SIZE],
+        };
+        state.key.expose_mut().copy_from_slice(&subkey);
+        subkey.zeroize();
+        state.nonce[COUNTER_SIZE..].copy_from_slice(&header[16..]);
+        state.reset_counter();
+        state
+    }
+
+    fn cipher(&self, block_counter: u32) -> ChaCha20 {
+        let mut cipher = ChaCha20::new(self.key.expose().into(), (&self.nonce).into());
+        cipher.seek(block_counter as u64 * BLOCK_SIZE as u64);
+        cipher
+    }
+
+    /// Poly1305 keyed from keystream block 0, fed with the associated data
+    /// and the first keystream block (whose first byte carries the tag).
+    fn mac(&self, ad: &[u8], tag_block: &[u8; BLOCK_SIZE]) -> Poly1305 {
+        let mut poly_key = [0u8; BLOCK_SIZE];
+        self.cipher(0).apply_keystream(&mut poly_key);
+        let mut mac = Poly1305::new(poly_key[..32].into());
+        poly_key.zeroize();
+        mac.update_padded(ad);
+        mac.update_padded(tag_block);
+        mac
+    }
+
+    /// Tag block: keystream block 1 with its first byte XORed with `tag`.
+    fn tag_block(&self, first: u8) -> [u8; BLOCK_SIZE] {
+        let mut block = [0u8; BLOCK_SIZE];
+        block[0] = first;
+        self.cipher(1).apply_keystream(&mut block);
+        block
+    }
+
+    /// Finish the MAC over `ciphertext`. libsodium pads the ciphertext
+    /// with `len % 16` zero bytes (not up to a block boundary), so the rest
+    /// of the input is unaligned.
+    fn finish_mac(mac: Poly1305, ad_len: usize, ciphertext: &[u8]) -> [u8; MAC_SIZE] {
+        let mut tail = Vec::with_capacity(ciphertext.len() + 2 * MAC_SIZE);
+        tail.extend_from_slice(ciphertext);
+        tail.resize(tail.len() + ciphertext.len() % 16, 0);
+        tail.extend_from_slice(&(ad_len as u64).to_le_bytes());
+        tail.extend_from_slice(&((BLOCK_SIZE + ciphertext.len()) as u64).to_le_bytes());
+        mac.compute_unpadded(&tail).into()
+    }
+
+    /// Ratchet the nonce with the message MAC and rekey when asked to or
+    /// when the counter wraps.
+    fn advance(&mut self, mac: &[u8; MAC_SIZE], tag: u8) {
+        for (n, m) in self.nonce[COUNTER_SIZE..].iter_mut().zip(mac) {
+            *n ^= m;
+        }
+        let counter = u32::from_le_bytes(self.nonce[..COUNTER_SIZE].try_into().unwrap());
+        let counter = counter.wrapping_add(1);
+        self.nonce[..COUNTER_SIZE].copy_from_slice(&counter.to_le_bytes());
+        if tag & TAG_REKEY != 0 || counter == 0 {
+            self.rekey();
+        }
+    }
+
+    fn rekey(&mut self) {
+        let mut next = [0u8; 32 + INONCE_SIZE];
+        next[..32].copy_from_slice(self.key.expose());
+        next[32..].copy_from_slice(&self.nonce[COUNTER_SIZE..]);
+        self.cipher(0).apply_keystream(&mut next);
+        self.key.expose_mut().copy_from_slice(&next[..32]);
+        self.nonce[COUNTER_SIZE..].copy_from_slice(&next[32..]);
+        next.zeroize();
+        self.reset_counter();
+    }
+
+    fn reset_counter(&mut self) {
+        self.nonce[..COUNTER_SIZE].copy_from_slice(&1u32.to_le_bytes());
+    }
+}
+
+impl Drop for State {
+    fn drop(&mut self) {
+        self.nonce.zeroize();
+    }
+}
+
+/// Sealing half of a secretstream.
+pub struct SecretStreamEncryptor {
+    state: State,
+}
+
+impl SecretStreamEncryptor {
+    /// Start a stream under `key`, returning the header to store before the
+    /// first message.
+    pub fn new(key: &[u8; 32]) -> (Self, [u8; HEADER_SIZE]) {
+        let mut header = [0u8; HEADER_SIZE];
+        crate::random::fill(&mut header);
+        (Self::with_header(key, &header), header)
+    }
+
+    fn with_header(key: &[u8; 32], header: &[u8; HEADER_SIZE]) -> Self {
+        Self {
+            state: State::new(key, header),
+        }
+    }
+
+    /// Seal one message with its `tag` and associated data.
+    pub fn push(&mut self, message: &[u8], ad: &[u8], tag: u8) -> Vec<u8> {
+        let mut tag_block = self.state.tag_block(tag);
+        let mac = self.state.mac(ad, &tag_block);
+
+        let mut out = Vec::with_capacity(message.len() + ABYTES);
+        out.push(tag_block[0]);
+        tag_block.zeroize();
+        out.extend_from_slice(message);
+        self.state.cipher(2).apply_keystream(&mut out[1..]);
+        let mac = State::finish_mac(mac, ad.len(), &out[1..]);
+        out.extend_from_slice(&mac);
+        self.state.advance(&mac, tag);
+        out
+    }
+}
+
+/// Opening half of a secretstream.
+pub struct SecretStreamDecryptor {
+    state: State,
+}
+
+impl SecretStreamDecryptor {
+    pub fn new(key: &[u8; 32], header: &[u8; HEADER_SIZE]) -> Self {
+        Self {
+            state: State::new(key, header),
+        }
+    }
+
+    /// Open the next message, returning its plaintext and tag. On failure
+    /// the stream state is left unchanged.
+    pub fn pull(&mut self, sealed: &[u8], ad: &[u8]) -> Result<(LockedBuffer, u8), String> {
+        if sealed.len() < ABYTES {
+            return Err(CryptoError::CiphertextTooShort.into());
+        }
+        let (ciphertext, expected) = sealed[1..].split_at(sealed.len() - ABYTES);
+
+        let mut tag_block = self.state.tag_block(sealed[0]);
+        let tag = tag_block[0];
+        tag_block[0] = sealed[0];
+        let mac = self.state.mac(ad, &tag_block);
+        tag_block.zeroize();
+        let mut computed = State::finish_mac(mac, ad.len(), ciphertext);
+        let authentic = constant_time_eq(&computed, expected);
+        computed.zeroize();
+        if !authentic {
+            return Err(CryptoError::DecryptionAuth.into());
+        }
+
+        let mut plaintext = LockedBuffer::from_slice(ciphertext);
+        self.state
+            .cipher(2)
+            .apply_keystream(plaintext.as_mut_slice());
+        let mac: [u8; MAC_SIZE] = expected.try_into().unwrap();
+        self.state.advance(&mac, tag);
+        Ok((plaintext, tag))
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn key() -> [u8; 32] {
+        std::array::from_fn(|i| i as u8)
+    }
+
+    // Produced by libsodium's crypto_secretstream_xchacha20poly1305_push.
+    const HEADER: &str = "b73d7c6016bad8ca33a579cbd0a8183c515e7c02d60c20e7";
+    const MESSAGES: [(&[u8], &[u8], u8, &str); 3] = [
+        (
+            b"Hello, secretstream",
+            b"ad",
+            TAG_MESSAGE,
+            "8c782fc5170a1d77ffb589b9eaed80f5d30a83492ddb5be0f5e1bc0433fc4ef2b44087ea",
+        ),
+        (b"", b"", TAG_REKEY, "ea02561eadd53f5b665147a4a086361770"),
+        (
+            b"final chunk",
+            b"",
+            TAG_FINAL,
+            "56bf229405cc000d828d95c2a1e8e55b7578b55f4a6b6ca389f145f5",
+        ),
+    ];
+
+    fn header() -> [u8; HEADER_SIZE] {
+        hex(HEADER).try_into().unwrap()
+    }
+
+    #[test]
+    fn test_libsodium_vectors() {
+        let mut pull = SecretStreamDecryptor::new(&key(), &header());
+        let mut push = SecretStreamEncryptor::with_header(&key(), &header());
+        for (message, ad, tag, sealed) in MESSAGES {
+            let sealed = hex(sealed);
+            assert_eq!(push.push(message, ad, tag), sealed);
+            let (plaintext, got_tag) = pull.pull(&sealed, ad).unwrap();
+            assert_eq!(plaintext.as_slice(), message);
+            assert_eq!(got_tag, tag);
+        }
+    }
+
+    #[test]
+    fn test_rejects_tampering_and_reordering() {
+        let sealed: Vec<Vec<u8>> = MESSAGES.iter().map(|m| hex(m.3)).collect();
+
+        let mut pull = SecretStreamDecryptor::new(&key(), &header());
+        assert!(pull.pull(&sealed[1], b"").is_err());
+        assert!(pull.pull(&sealed[0], b"other ad").is_err());
+        let mut flipped = sealed[0].clone();
+        flipped[3] ^= 1;
+        assert!(pull.pull(&flipped, b"ad").is_err());
+        assert!(pull.pull(&sealed[0][..ABYTES - 1], b"ad").is_err());
+        // A failed pull leaves the stream where it was.
+        assert!(pull.pull(&sealed[0], b"ad").is_ok());
+
+        let mut wrong_key = key();
+        wrong_key[0] ^= 1;
+        let mut pull = SecretStreamDecryptor::new(&wrong_key, &header());
+        assert!(pull.pull(&sealed[0], b"ad").is_err());
+    }
+
+    #[test]
+    fn test_round_trip_with_random_header() {
+        let (mut push, header) = SecretStreamEncryptor::new(&key());
+        let first = push.push(b"one", b"", TAG_MESSAGE);
+        let last = push.push(b"two", b"", TAG_FINAL);
+
+        let mut pull = SecretStreamDecryptor::new(&key(), &header);
+        assert_eq!(pull.pull(&first, b"").unwrap().0.as_slice(), b"one");
+        let (plaintext, tag) = pull.pull(&last, b"").unwrap();
+        assert_eq!((plaintext.as_slice(), tag), (&b"two"[..], TAG_FINAL));
+    }
+}
@@ -0,0 +1,279 @@
+//! Locked memory for key material
+//!
+//! On Unix, [`LockedBuffer`] places its bytes in a dedicated `mmap` region
+//! surrounded by `PROT_NONE` guard pages, `mlock`s the data pages so they
+//! are never written to swap and (on Linux) excludes them from core dumps.
+//! The contents are zeroized before the region is unmapped.
+//!
+//! When the pages cannot be locked — typically because `RLIMIT_MEMLOCK` is
+//! too low — the buffer still works; [`LockedBuffer::is_locked`] reports
+//! `false`. Targets without `mmap` fall back to a zeroize-on-drop heap
+//! allocation.
+
+use std::fmt;
+
+use zeroize::Zeroize;
+
+use crate::kdf::KEY_LENGTH;
+
+/// A fixed-size byte buffer held in locked, guarded memory.
+pub struct LockedBuffer {
+    ptr: *mut u8,
+    len: usize,
+    backing: Backing,
+}
+
+enum Backing {
+    #[cfg(unix)]
+    Mapped {
+        region: *mut libc::c_void,
+        region_len: usize,
+        data: *mut u8,
+        data_len: usize,
+        locked: bool,
+    },
+    /// Owns the allocation `ptr` points into; only dropped, never read.
+    Heap { _allocation: Box<[u8]> },
+}
+
+// SAFETY: the buffer exclusively owns its allocation; the raw pointers are
+// never shared outside of borrows tied to `&self` / `&mut self`.
+unsafe impl Send for LockedBuffer {}
+unsafe impl Sync for LockedBuffer {}
+
+impl LockedBuffer {
+    /// Allocate a zero-filled buffer of `len` bytes.
+    pub fn new(len: usize) -> Self {
+        #[cfg(unix)]
+        if let Some(buffer) = Self::new_mapped(len) {
+            return buffer;
+        }
+        Self::new_heap(len)
+    }
+
+    /// Allocate a buffer holding a copy of `bytes`.
+    pub fn from_slice(bytes: &[u8]) -> Self {
+        let mut buffer = Self::new(bytes.len());
+        buffer.as_mut_slice().copy_from_slice(bytes);
+        buffer
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether the data pages are pinned in RAM via `mlock`.
+    pub fn is_locked(&self) -> bool {
+        match &self.backing {
+            #[cfg(unix)]
+            Backing::Mapped { locked, .. } => *locked,
+            Backing::Heap { .. } => false,
+        }
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        // SAFETY: `ptr` is valid for `len` bytes for the lifetime of `self`.
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        // SAFETY: `ptr` is valid for `len` bytes and uniquely borrowed.
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+
+    fn new_heap(len: usize) -> Self {
+        let mut heap = vec![0u8; len].into_boxed_slice();
+        let ptr = heap.as_mut_ptr();
+        Self {
+            ptr,
+            len,
+            backing: Backing::Heap { _allocation: heap },
+        }
+    }
+
+    /// Layout: `[guard][data pages][guard]`, with the bytes right-aligned
+    /// against the trailing guard so an overrun faults immediately.
+    #[cfg(unix)]
+    fn new_mapped(len: usize) -> Option<Self> {
+        let page = page_size()?;
+        let data_len = len.div_ceil(page).max(1) * page;
+        let region_len = data_len.checked_add(2 * page)?;
+
+        // SAFETY: anonymous private mapping; every result is checked before use.
+        unsafe {
+            let region = libc::mmap(
+                std::ptr::null_mut(),
+                region_len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            );
+            if region == libc::MAP_FAILED {
+                return None;
+            }
+
+            let base = region as *mut u8;
+            let data = base.add(page);
+            let rear_guard = data.add(data_len);
+
+            if libc::mprotect(region, page, libc::PROT_NONE) != 0
+                || libc::mprotect(rear_guard as *mut libc::c_void, page, libc::PROT_NONE) != 0
+            {
+                libc::munmap(region, region_len);
+                return None;
+            }
+
+            let locked = libc::mlock(data as *const libc::c_void, data_len) == 0;
+
+            #[cfg(target_os = "linux")]
+            libc::madvise(data as *mut libc::c_void, data_len, libc::MADV_DONTDUMP);
+
+            Some(Self {
+                ptr: data.add(data_len - len),
+                len,
+                backing: Backing::Mapped {
+                    region,
+                    region_len,
+                    data,
+                    data_len,
+                    locked,
+                },
+            })
+        }
+    }
+}
+
+#[cfg(unix)]
+fn page_size() -> Option<usize> {
+    // SAFETY: sysconf has no preconditions.
+    let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    usize::try_from(size).ok().filter(|s| *s > 0)
+}
+
+impl Drop for LockedBuffer {
+    fn drop(&mut self) {
+        self.as_mut_slice().zeroize();
+        match &mut self.backing {
+            #[cfg(unix)]
+            Backing::Mapped {
+                region,
+                region_len,
+                data,
+                data_len,
+                locked,
+            } => {
+                // SAFETY: the region was mapped in `new_mapped` and is unmapped once.
+                unsafe {
+                    if *locked {
+                        libc::munlock(*data as *const libc::c_void, *data_len);
+                    }
+                    libc::munmap(*region, *region_len);
+                }
+            }
+            Backing::Heap { .. } => {}
+        }
+    }
+}
+
+impl fmt::Debug for LockedBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LockedBuffer")
+            .field("len", &self.len)
+            .field("locked", &self.is_locked())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A 256-bit key held in a [`LockedBuffer`].
+#[derive(Debug)]
+pub struct LockedKey(LockedBuffer);
+
+impl LockedKey {
+    /// Allocate an all-zero key, to be filled in place (e.g. by a KDF).
+    pub fn zeroed() -> Self {
+        Self(LockedBuffer::new(KEY_LENGTH))
+    }
+
+    pub fn expose(&self) -> &[u8; KEY_LENGTH] {
+        self.0
+            .as_slice()
+            .try_into()
+            .expect("LockedKey is always KEY_LENGTH bytes")
+    }
+
+    pub fn expose_mut(&mut self) -> &mut [u8; KEY_LENGTH] {
+        self.0
+            .as_mut_slice()
+            .try_into()
+            .expect("LockedKey is always KEY_LENGTH bytes")
+    }
+
+    /// Whether the key's pages are pinned in RAM.
+    pub fn is_locked(&self) -> bool {
+        self.0.is_locked()
+    }
+}
+
+/// Move a key into locked memory, zeroizing the source. Taken by
+/// reference so the caller's array is the one wiped, not a copy of it.
+impl From<&mut [u8; KEY_LENGTH]> for LockedKey {
+    fn from(key: &mut [u8; KEY_LENGTH]) -> Self {
+        let mut locked = Self::zeroed();
+        locked.expose_mut().copy_from_slice(key);
+        key.zeroize();
+        locked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_is_zeroed() {
+        let buffer = LockedBuffer::new(48);
+        assert_eq!(buffer.len(), 48);
+        assert!(buffer.as_slice().iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn test_write_and_read_back() {
+        let mut buffer = LockedBuffer::new(5);
+        buffer.as_mut_slice().copy_from_slice(b"hello");
+        assert_eq!(buffer.as_slice(), b"hello");
+    }
+
+    #[test]
+    fn test_from_slice_spanning_pages() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+        let buffer = LockedBuffer::from_slice(&data);
+        assert_eq!(buffer.as_slice(), data.as_slice());
+    }
+
+    #[test]
+    fn test_empty_buffer() {
+        let buffer = LockedBuffer::new(0);
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.as_slice(), b"");
+    }
+
+    #[test]
+    fn test_locked_key_from_array() {
+        let mut source = [0x42u8; KEY_LENGTH];
+        let key = LockedKey::from(&mut source);
+        assert_eq!(key.expose(), &[0x42u8; KEY_LENGTH]);
+        assert_eq!(source, [0u8; KEY_LENGTH]);
+    }
+
+    #[test]
+    fn test_debug_does_not_leak_contents() {
+        let buffer = LockedBuffer::from_slice(b"top-secret");
+        let debug = format!("{:?}", buffer);
+        assert!(!debug.contains("top-secret"));
+    }
+}
@@ -0,0 +1,296 @@
+//! Power-on known-answer self-tests (KATs)
+//!
+//! Runs published test vectors through every primitive the vault depends on
+//! so a miscompiled or tampered build is detected before any key material
+//! is derived.
+
+use crate::error::CryptoError;
+use crate::hotp::{self, Algorithm};
+use crate::kdf::{self, KdfParams};
+use crate::secretstream::{SecretStreamDecryptor, TAG_FINAL};
+use crate::{aead, totp};
+
+/// Outcome of a single known-answer test.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfTestResult {
+    /// Primitive under test (`"hotp"`, `"totp"`, `"aead"`, `"kdf"`, `"pbkdf2"`,
+    /// `"scrypt"`, `"secretstream"`).
+    pub name: &'static str,
+    /// Whether every vector matched.
+    pub passed: bool,
+    /// Description of the first mismatch, `None` on success.
+    pub detail: Option<String>,
+}
+
+/// Structured report produced by [`self_test`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfTestReport {
+    pub results: Vec<SelfTestResult>,
+}
+
+impl SelfTestReport {
+    /// `true` when every known-answer test passed.
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|r| r.passed)
+    }
+
+    /// Iterate over the failed tests only.
+    pub fn failures(&self) -> impl Iterator<Item = &SelfTestResult> {
+        self.results.iter().filter(|r| !r.passed)
+    }
+
+    /// Convert the report into an error naming the failed primitives.
+    pub fn into_result(self) -> Result<(), String> {
+        if self.passed() {
+            return Ok(());
+        }
+        let names: Vec<&str> = self.failures().map(|r| r.name).collect();
+        Err(CryptoError::SelfTestFailed(names.join(", ")).into())
+    }
+}
+
+/// Run every known-answer test and return the combined report.
+pub fn self_test() -> SelfTestReport {
+    SelfTestReport {
+        results: vec![
+            check("hotp", kat_hotp),
+            check("totp", kat_totp),
+            check("aead", kat_aead),
+            check("kdf", kat_kdf),
+            check("pbkdf2", kat_pbkdf2),
+            check("scrypt", kat_scrypt),
+            check("secretstream", kat_secretstream),
+        ],
+    }
+}
+
+fn check(name: &'static str, kat: fn() -> Result<(), String>) -> SelfTestResult {
+    // A panicking primitive counts as a failure rather than aborting startup.
+    let outcome = std::panic::catch_unwind(kat)
+        .unwrap_or_else(|_| Err("primitive panicked during self-test".to_string()));
+    match outcome {
+        Ok(()) => SelfTestResult {
+            name,
+            passed: true,
+            detail: None,
+        },
+        Err(detail) => SelfTestResult {
+            name,
+            passed: false,
+            detail: Some(detail),
+        },
+    }
+}
+
+// ── Vectors ──────────────────────────────────────────────────────────
+
+/// RFC 4226 Appendix D, counters 0..=9.
+const HOTP_VECTORS: [&str; 10] = [
+    "755224", "287082", "359152", "969429", "338314", "254676", "287922", "162583", "399871",
+    "520489",
+];
+
+const RFC_SHA1_SECRET: &[u8] = b"12345678901234567890";
+const RFC_SHA256_SECRET: &[u8] = b"12345678901234567890123456789012";
+const RFC_SHA512_SECRET: &[u8] =
+    b"1234567890123456789012345678901234567890123456789012345678901234";
+
+/// RFC 6238 Appendix B (8 digits, 30 s period).
+const TOTP_VECTORS: [(u64, Algorithm, &str); 6] = [
+    (59, Algorithm::SHA1, "94287082"),
+    (59, Algorithm::SHA256, "46119246"),
+    (59, Algorithm::SHA512, "90693936"),
+    (20000000000, Algorithm::SHA1, "65353130"),
+    (20000000000, Algorithm::SHA256, "77737706"),
+    (20000000000, Algorithm::SHA512, "47863826"),
+];
+
+/// AES-256-GCM, McGrew & Viega test case 15 (no AAD).
+const AEAD_KEY: &str = "feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308";
+const AEAD_NONCE: &str = "cafebabefacedbaddecaf888";
+const AEAD_PLAINTEXT: &str = "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+                              1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b391aafd255";
+const AEAD_CIPHERTEXT: &str = "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa\
+                               8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f662898015ad";
+const AEAD_TAG: &str = "b094dac5d93471bdec1a502270e3cc6c";
+
+/// Argon2id v1.3 with the RFC 9106 §5.3 password, salt and cost parameters
+/// (no secret or associated data), cross-checked against OpenSSL.
+const KDF_PASSWORD: [u8; 32] = [0x01; 32];
+const KDF_SALT: [u8; 16] = [0x02; 16];
+const KDF_EXPECTED: &str = "03aab965c12001c9d7d0d2de33192c0494b684bb148196d73c1df1acaf6d0c2e";
+
+/// PBKDF2-HMAC-SHA256, RFC 7914 §11 (c = 1), first 32 bytes.
+const PBKDF2_PASSWORD: &[u8] = b"passwd";
+const PBKDF2_SALT: &[u8] = b"salt";
+const PBKDF2_EXPECTED: &str = "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc";
+
+/// scrypt, RFC 7914 §12 (empty password and salt, N = 16, r = 1, p = 1),
+/// first 32 bytes.
+const SCRYPT_EXPECTED: &str = "77d6576238657b203b19ca42c18a0497f16b4844e3074ae8dfdffa3fede21442";
+
+/// XChaCha20-Poly1305 secretstream, sealed by libsodium under the key
+/// 00 01 .. 1f: one final message "final chunk" with no associated data.
+const SECRETSTREAM_HEADER: &str = "33cf745248fb83ff3f3d87d8bc75b8e08ba4fd1a53514b17";
+const SECRETSTREAM_SEALED: &str = "5ba27402c8830f8e607d49e7a61bf49b4faab4d1038275967a891329";
+
+// ── Known-answer tests ───────────────────────────────────────────────
+
+fn kat_hotp() -> Result<(), String> {
+    for (counter, expected) in HOTP_VECTORS.iter().enumerate() {
+        let code = hotp::generate(RFC_SHA1_SECRET, counter as u64, 6, Algorithm::SHA1);
+        if code != *expected {
+            return Err(format!(
+                "counter {counter}: expected {expected}, got {code}"
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn kat_totp() -> Result<(), String> {
+    for (time, algorithm, expected) in TOTP_VECTORS {
+        let secret = match algorithm {
+            Algorithm::SHA1 => RFC_SHA1_SECRET,
+            Algorithm::SHA256 => RFC_SHA256_SECRET,
+            Algorithm::SHA512 => RFC_SHA512_SECRET,
+        };
+        let code = totp::generate(secret, time, 30, 8, algorithm);
+        if code != expected {
+            return Err(format!(
+                "time {time} {algorithm:?}: expected {expected}, got {code}"
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn kat_aead() -> Result<(), String> {
+    let key: [u8; 32] = hex(AEAD_KEY)
+        .try_into()
+        .map_err(|_| "bad AEAD key vector".to_string())?;
+    let nonce = hex(AEAD_NONCE);
+    let plaintext = hex(AEAD_PLAINTEXT);
+
+    let mut expected = nonce.clone();
+    expected.extend(hex(AEAD_CIPHERTEXT));
+    expected.extend(hex(AEAD_TAG));
+
+    let mut sealed = aead::encrypt_with_nonce(&plaintext, &key, &nonce)?;
+    if sealed != expected {
+        return Err("ciphertext or tag mismatch".to_string());
+    }
+    if aead::decrypt(&sealed, &key)? != plaintext {
+        return Err("decryption did not recover plaintext".to_string());
+    }
+
+    let last = sealed.len() - 1;
+    sealed[last] ^= 0x01;
+    if aead::decrypt(&sealed, &key).is_ok() {
+        return Err("tampered tag was accepted".to_string());
+    }
+    Ok(())
+}
+
+fn kat_kdf() -> Result<(), String> {
+    let params = KdfParams {
+        memory_kib: 32,
+        time_cost: 3,
+        parallelism: 4,
+    };
+    let key = kdf::derive_key(&KDF_PASSWORD, &KDF_SALT, &params)?;
+    if key.as_slice() != hex(KDF_EXPECTED).as_slice() {
+        return Err("derived key mismatch".to_string());
+    }
+    Ok(())
+}
+
+fn kat_pbkdf2() -> Result<(), String> {
+    let key = kdf::derive_key_pbkdf2(PBKDF2_PASSWORD, PBKDF2_SALT, 1)?;
+    if key.as_slice() != hex(PBKDF2_EXPECTED).as_slice() {
+        return Err("derived key mismatch".to_string());
+    }
+    Ok(())
+}
+
+fn kat_scrypt() -> Result<(), String> {
+    let key = kdf::derive_key_scrypt(b"", b"", 4, 1, 1)?;
+    if key.as_slice() != hex(SCRYPT_EXPECTED).as_slice() {
+        return Err("derived key mismatch".to_string());
+    }
+    Ok(())
+}
+
+fn kat_secretstream() -> Result<(), String> {
+    let key: [u8; 32] = std::array::from_fn(|i| i as u8);
+    let header = hex(SECRETSTREAM_HEADER)
+        .try_into()
+        .map_err(|_| "bad secretstream header vector".to_string())?;
+    let mut sealed = hex(SECRETSTREAM_SEALED);
+
+    let (plaintext, tag) = SecretStreamDecryptor::new(&key, &header).pull(&sealed, &[])?;
+    if plaintext.as_slice() != b"final chunk" || tag != TAG_FINAL {
+        return Err("decryption did not recover plaintext".to_string());
+    }
+
+    sealed[1] ^= 0x01;
+    if SecretStreamDecryptor::new(&key, &header)
+        .pull(&sealed, &[])
+        .is_ok()
+    {
+        return Err("tampered ciphertext was accepted".to_string());
+    }
+    Ok(())
+}
+
+fn hex(s: &str) -> Vec<u8> {
+    s.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let digits = std::str::from_utf8(pair).expect("hex vectors are ASCII");
+            u8::from_str_radix(digits, 16).expect("hex vectors are well-formed")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_test_passes() {
+        let report = self_test();
+        assert_eq!(report.results.len(), 7);
+        for result in &report.results {
+            assert!(result.passed, "{} failed: {:?}", result.name, result.detail);
+        }
+        assert!(report.into_result().is_ok());
+    }
+
+    #[test]
+    fn test_failure_is_reported_by_name() {
+        let report = SelfTestReport {
+            results: vec![
+                check("ok", || Ok(())),
+                check("broken", || Err("mismatch".to_string())),
+            ],
+        };
+        assert!(!report.passed());
+        assert_eq!(report.failures().count(), 1);
+        assert_eq!(
+            report.results[1].detail.as_deref(),
+            Some("mismatch"),
+            "detail should carry the KAT message"
+        );
+
+        let err = report.into_result().unwrap_err();
+        assert!(err.contains("broken"));
+        assert!(!err.contains("ok,"));
+    }
+
+    #[test]
+    fn test_panicking_kat_is_a_failure() {
+        let result = check("panics", || panic!("boom"));
+        assert!(!result.passed);
+    }
+}
@@ -0,0 +1,419 @@
+//! Chunked streaming AEAD (STREAM construction over AES-256-GCM)
+//!
+//! Plaintext is split into fixed-size chunks, each sealed with its own
+//! nonce `prefix(7) ‖ counter(4, BE) ‖ last(1)`. The stream header is bound
+//! to every chunk as associated data. Only the final chunk carries the
+//! `last` flag and it is always shorter than a full chunk (possibly empty),
+//! so dropping, reordering or truncating chunks fails authentication, and
+//! data after the final chunk is rejected.
+//!
+//! Wire format: `[chunk_size: u32 BE][nonce prefix: 7]` followed by
+//! `[ciphertext ‖ tag]` chunks of `chunk_size + 16` bytes, the last one
+//! shorter.
+
+use std::io::{self, Read, Write};
+
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::aead::{NONCE_SIZE, TAG_SIZE};
+use crate::error::CryptoError;
+
+/// Default plaintext bytes per chunk.
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+/// Largest chunk size accepted when reading a header.
+pub const MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+/// Bytes of random nonce prefix stored in the header.
+pub const NONCE_PREFIX_SIZE: usize = 7;
+/// Size of the stream header.
+pub const HEADER_SIZE: usize = 4 + NONCE_PREFIX_SIZE;
+
+fn chunk_nonce(prefix: &[u8; NONCE_PREFIX_SIZE], counter: u32, last: bool) -> [u8; NONCE_SIZE] {
+    let mut nonce = [0u8; NONCE_SIZE];
+    nonce[..NONCE_PREFIX_SIZE].copy_from_slice(prefix);
+    nonce[NONCE_PREFIX_SIZE..NONCE_SIZE - 1].copy_from_slice(&counter.to_be_bytes());
+    nonce[NONCE_SIZE - 1] = u8::from(last);
+    nonce
+}
+
+fn invalid_data(e: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.into())
+}
+
+/// Encrypting writer. Call [`StreamEncryptor::finish`] to emit the final
+/// chunk; a stream dropped without it is rejected as truncated.
+pub struct StreamEncryptor<W: Write> {
+    inner: W,
+    cipher: Aes256Gcm,
+    header: [u8; HEADER_SIZE],
+    prefix: [u8; NONCE_PREFIX_SIZE],
+    counter: u32,
+    chunk_size: usize,
+    buffer: Zeroizing<Vec<u8>>,
+}
+
+impl<W: Write> StreamEncryptor<W> {
+    /// Start a stream with [`DEFAULT_CHUNK_SIZE`], writing the header to `inner`.
+    pub fn new(key: &[u8; 32], inner: W) -> Result<Self, String> {
+        Self::with_chunk_size(key, inner, DEFAULT_CHUNK_SIZE)
+    }
+
+    pub fn with_chunk_size(
+        key: &[u8; 32],
+        mut inner: W,
+        chunk_size: usize,
+    ) -> Result<Self, String> {
+        if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+            return Err(CryptoError::StreamHeader(format!("chunk size {chunk_size}")).into());
+        }
+        let cipher =
+            Aes256Gcm::new_from_slice(key).map_err(|e| CryptoError::CipherInit(e.to_string()))?;
+
+        let mut prefix = [0u8; NONCE_PREFIX_SIZE];
+        crate::random::fill(&mut prefix);
+
+        let mut header = [0u8; HEADER_SIZE];
+        header[..4].copy_from_slice(&(chunk_size as u32).to_be_bytes());
+        header[4..].copy_from_slice(&prefix);
+        inner
+            .write_all(&header)
+            .map_err(|e| CryptoError::Io(e.to_string()))?;
+
+        Ok(Self {
+            inner,
+            cipher,
+            header,
+            prefix,
+            counter: 0,
+            chunk_size,
+            buffer: Zeroizing::new(Vec::with_capacity(chunk_size)),
+        })
+    }
+
+    fn seal_chunk(&mut self, len: usize, last: bool) -> io::Result<()> {
+        let nonce = chunk_nonce(&self.prefix, self.counter, last);
+        let sealed = self
+            .cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &self.buffer[..len],
+                    aad: &self.header,
+                },
+            )
+            .map_err(|e| invalid_data(CryptoError::Encryption(e.to_string())))?;
+        self.inner.write_all(&sealed)?;
+
+        self.buffer[..len].zeroize();
+        self.buffer.drain(..len);
+        self.counter = self
+            .counter
+            .checked_add(1)
+            .ok_or_else(|| invalid_data(CryptoError::StreamTooLong))?;
+        Ok(())
+    }
+
+    /// Seal the remaining buffered bytes as the final chunk and return the
+    /// inner writer.
+    pub fn finish(mut self) -> Result<W, String> {
+        let len = self.buffer.len();
+        self.seal_chunk(len, true)
+            .and_then(|_| self.inner.flush())
+            .map_err(|e| CryptoError::Io(e.to_string()))?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for StreamEncryptor<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let take = data.len().min(self.chunk_size - self.buffer.len());
+        self.buffer.extend_from_slice(&data[..take]);
+        // A full buffer is never the last chunk: `finish` always seals a
+        // shorter (possibly empty) one, so full chunks can go out eagerly.
+        if self.buffer.len() == self.chunk_size {
+            self.seal_chunk(self.chunk_size, false)?;
+        }
+        Ok(take)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Decrypting reader. Yields plaintext only after each chunk authenticates
+/// and reports `InvalidData` if the stream ends before its final chunk.
+pub struct StreamDecryptor<R: Read> {
+    inner: R,
+    cipher: Aes256Gcm,
+    header: [u8; HEADER_SIZE],
+    prefix: [u8; NONCE_PREFIX_SIZE],
+    counter: u32,
+    chunk_size: usize,
+    plaintext: Zeroizing<Vec<u8>>,
+    position: usize,
+    finished: bool,
+}
+
+impl<R: Read> StreamDecryptor<R> {
+    /// Read and validate the stream header from `inner`.
+    pub fn new(key: &[u8; 32], mut inner: R) -> Result<Self, String> {
+        let mut header = [0u8; HEADER_SIZE];
+        inner
+            .read_exact(&mut header)
+            .map_err(|_| CryptoError::StreamHeader("missing header".to_string()))?;
+
+        let chunk_size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+            return Err(CryptoError::StreamHeader(format!("chunk size {chunk_size}")).into());
+        }
+        let mut prefix = [0u8; NONCE_PREFIX_SIZE];
+        prefix.copy_from_slice(&header[4..]);
+
+        let cipher =
+            Aes256Gcm::new_from_slice(key).map_err(|e| CryptoError::CipherInit(e.to_string()))?;
+
+        Ok(Self {
+            inner,
+            cipher,
+            header,
+            prefix,
+            counter: 0,
+            chunk_size,
+            plaintext: Zeroizing::new(Vec::new()),
+            position: 0,
+            finished: false,
+        })
+    }
+
+    /// Read the next sealed chunk into `self.plaintext`.
+    fn open_next_chunk(&mut self) -> io::Result<()> {
+        let full = self.chunk_size + TAG_SIZE;
+        let mut sealed = vec![0u8; full];
+        let mut filled = 0;
+        while filled < full {
+            match self.inner.read(&mut sealed[filled..])? {
+                0 => break,
+                n => filled += n,
+            }
+        }
+
+        // A full chunk is never the last one; anything shorter must be.
+        let last = filled < full;
+        if filled < TAG_SIZE {
+            return Err(invalid_data(CryptoError::StreamTruncated));
+        }
+
+        let nonce = chunk_nonce(&self.prefix, self.counter, last);
+        self.plaintext = Zeroizing::new(
+            self.cipher
+                .decrypt(
+                    Nonce::from_slice(&nonce),
+                    Payload {
+                        msg: &sealed[..filled],
+                        aad: &self.header,
+                    },
+                )
+                .map_err(|_| invalid_data(CryptoError::DecryptionAuth))?,
+        );
+        self.position = 0;
+        self.finished = last;
+        if last && self.inner.read(&mut [0u8; 1])? != 0 {
+            return Err(invalid_data(CryptoError::StreamTrailingData));
+        }
+        self.counter = self
+            .counter
+            .checked_add(1)
+            .ok_or_else(|| invalid_data(CryptoError::StreamTooLong))?;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for StreamDecryptor<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.plaintext.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            self.open_next_chunk()?;
+        }
+        let n = out.len().min(self.plaintext.len() - self.position);
+        out[..n].copy_from_slice(&self.plaintext[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+/// Encrypt everything from `reader` into `writer`; returns plaintext bytes.
+pub fn encrypt_stream<R: Read, W: Write>(
+    key: &[u8; 32],
+    mut reader: R,
+    writer: W,
+) -> Result<u64, String> {
+    let mut encryptor = StreamEncryptor::new(key, writer)?;
+    let copied =
+        io::copy(&mut reader, &mut encryptor).map_err(|e| CryptoError::Io(e.to_string()))?;
+    encryptor.finish()?;
+    Ok(copied)
+}
+
+/// Decrypt a stream from `reader` into `writer`; returns plaintext bytes.
+///
+/// Plaintext from chunks that authenticated may already have been written
+/// when a later chunk fails, so callers should discard `writer` on error.
+pub fn decrypt_stream<R: Read, W: Write>(
+    key: &[u8; 32],
+    reader: R,
+    mut writer: W,
+) -> Result<u64, String> {
+    let mut decryptor = StreamDecryptor::new(key, reader)?;
+    io::copy(&mut decryptor, &mut writer).map_err(|e| -> String {
+        match e.get_ref() {
+            Some(inner) => inner.to_string(),
+            None => CryptoError::Io(e.to_string()).into(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [0x42; 32];
+    const CHUNK: usize = 64;
+
+    fn seal(plaintext: &[u8]) -> Vec<u8> {
+        let mut encryptor = StreamEncryptor::with_chunk_size(&KEY, Vec::new(), CHUNK).unwrap();
+        encryptor.write_all(plaintext).unwrap();
+        encryptor.finish().unwrap()
+    }
+
+    fn open(sealed: &[u8]) -> Result<Vec<u8>, String> {
+        let mut out = Vec::new();
+        decrypt_stream(&KEY, sealed, &mut out)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn test_roundtrip_sizes() {
+        for len in [0, 1, CHUNK - 1, CHUNK, CHUNK + 1, 3 * CHUNK, 3 * CHUNK + 7] {
+            let plaintext: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let sealed = seal(&plaintext);
+            let chunks = len / CHUNK + 1;
+            assert_eq!(sealed.len(), HEADER_SIZE + len + chunks * TAG_SIZE);
+            assert_eq!(open(&sealed).unwrap(), plaintext, "len {len}");
+        }
+    }
+
+    #[test]
+    fn test_default_chunk_size_roundtrip() {
+        let plaintext = vec![0xABu8; 3 * DEFAULT_CHUNK_SIZE / 2];
+        let mut sealed = Vec::new();
+        encrypt_stream(&KEY, plaintext.as_slice(), &mut sealed).unwrap();
+        let mut out = Vec::new();
+        let n = decrypt_stream(&KEY, sealed.as_slice(), &mut out).unwrap();
+        assert_eq!(n as usize, plaintext.len());
+        assert_eq!(out, plaintext);
+    }
+
+    #[test]
+    fn test_truncation_at_chunk_boundary_detected() {
+        let sealed = seal(&[7u8; 2 * CHUNK + 10]);
+        // Drop the final chunk entirely: the last remaining chunk is full,
+        // so it was not flagged as final.
+        let cut = HEADER_SIZE + 2 * (CHUNK + TAG_SIZE);
+        assert!(open(&sealed[..cut]).is_err());
+        // Exactly one full chunk of data, final (empty) chunk removed.
+        let sealed = seal(&[7u8; CHUNK]);
+        assert!(open(&sealed[..HEADER_SIZE + CHUNK + TAG_SIZE]).is_err());
+    }
+
+    #[test]
+    fn test_truncation_mid_chunk_detected() {
+        let sealed = seal(&[7u8; 2 * CHUNK + 10]);
+        assert!(open(&sealed[..sealed.len() - 1]).is_err());
+        assert!(open(&sealed[..HEADER_SIZE + 5]).is_err());
+        assert!(open(&sealed[..HEADER_SIZE - 1]).is_err());
+    }
+
+    /// Hands out `parts` one `read` at a time; an empty part reads as a
+    /// premature end of file.
+    struct Parts(Vec<Vec<u8>>);
+
+    impl Read for Parts {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            let Some(part) = self.0.first_mut() else {
+                return Ok(0);
+            };
+            let n = out.len().min(part.len());
+            out[..n].copy_from_slice(&part[..n]);
+            part.drain(..n);
+            if part.is_empty() {
+                self.0.remove(0);
+            }
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_trailing_data_detected() {
+        let sealed = seal(&[7u8; CHUNK + 10]);
+        let reader = Parts(vec![sealed.clone(), Vec::new(), b"junk".to_vec()]);
+        let err = decrypt_stream(&KEY, reader, Vec::new()).unwrap_err();
+        assert_eq!(err, CryptoError::StreamTrailingData.to_string());
+
+        let reader = Parts(vec![sealed, Vec::new()]);
+        assert!(decrypt_stream(&KEY, reader, Vec::new()).is_ok());
+    }
+
+    #[test]
+    fn test_reordered_chunks_detected() {
+        let sealed = seal(&[1u8; 3 * CHUNK]);
+        let c = CHUNK + TAG_SIZE;
+        let mut swapped = sealed[..HEADER_SIZE].to_vec();
+        swapped.extend_from_slice(&sealed[HEADER_SIZE + c..HEADER_SIZE + 2 * c]);
+        swapped.extend_from_slice(&sealed[HEADER_SIZE..HEADER_SIZE + c]);
+        swapped.extend_from_slice(&sealed[HEADER_SIZE + 2 * c..]);
+        assert!(open(&swapped).is_err());
+    }
+
+    #[test]
+    fn test_tampered_header_and_body_detected() {
+        let sealed = seal(b"header and body are both authenticated");
+        let mut bad_header = sealed.clone();
+        bad_header[5] ^= 1;
+        assert!(open(&bad_header).is_err());
+
+        let mut bad_body = sealed.clone();
+        bad_body[HEADER_SIZE + 3] ^= 1;
+        assert!(open(&bad_body).is_err());
+    }
+
+    #[test]
+    fn test_unfinished_stream_rejected() {
+        let mut out = Vec::new();
+        {
+            let mut encryptor = StreamEncryptor::with_chunk_size(&KEY, &mut out, CHUNK).unwrap();
+            encryptor.write_all(&[9u8; CHUNK * 2]).unwrap();
+            // dropped without finish()
+        }
+        assert!(open(&out).is_err());
+    }
+
+    #[test]
+    fn test_wrong_key_fails() {
+        let sealed = seal(b"secret");
+        let mut out = Vec::new();
+        assert!(decrypt_stream(&[0x43; 32], sealed.as_slice(), &mut out).is_err());
+    }
+
+    #[test]
+    fn test_oversized_chunk_header_rejected() {
+        let mut sealed = seal(b"x");
+        sealed[..4].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(open(&sealed).is_err());
+    }
+}
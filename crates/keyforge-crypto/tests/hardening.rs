@@ -0,0 +1,55 @@
+//! Verifies process hardening against what the kernel reports for the
+//! test process. Runs as its own binary so the non-dumpable flag does not
+//! leak into other test suites.
+
+#![cfg(target_os = "linux")]
+
+use keyforge_crypto::hardening::{self, HardeningConfig};
+
+fn proc_field(file: &str, prefix: &str) -> String {
+    let contents = std::fs::read_to_string(format!("/proc/self/{file}")).unwrap();
+    contents
+        .lines()
+        .find(|line| line.starts_with(prefix))
+        .unwrap_or_else(|| panic!("{prefix} not found in /proc/self/{file}"))
+        .to_string()
+}
+
+#[test]
+fn hardening_disables_core_dumps_and_ptrace() {
+    // `/proc/self/status` has no dumpable field; confirm nothing is tracing
+    // us there, then read the flag and core limit back from the kernel.
+    let tracer = proc_field("status", "TracerPid:");
+    assert_eq!(tracer.split_whitespace().nth(1), Some("0"));
+
+    let report = hardening::apply(&HardeningConfig::default());
+    assert!(report.core_dumps_disabled);
+    assert!(report.ptrace_denied);
+
+    // SAFETY: PR_GET_DUMPABLE takes no arguments.
+    let dumpable = unsafe { libc::prctl(libc::PR_GET_DUMPABLE, 0, 0, 0, 0) };
+    assert_eq!(dumpable, 0);
+
+    let core = proc_field("limits", "Max core file size");
+    let fields: Vec<&str> = core.split_whitespace().collect();
+    assert_eq!(fields[4..6], ["0", "0"], "unexpected core limit: {core}");
+
+    // A non-dumpable process's /proc entries belong to root, so other
+    // users' debuggers cannot open them; and still nothing is attached.
+    let tracer = proc_field("status", "TracerPid:");
+    assert_eq!(tracer.split_whitespace().nth(1), Some("0"));
+    let status = std::fs::metadata("/proc/self/status").unwrap();
+    assert_eq!(std::os::unix::fs::MetadataExt::uid(&status), 0);
+}
+
+#[test]
+fn allow_debugger_env_relaxes_config() {
+    std::env::set_var(hardening::ALLOW_DEBUGGER_ENV, "1");
+    assert_eq!(HardeningConfig::from_env(), HardeningConfig::permissive());
+
+    std::env::set_var(hardening::ALLOW_DEBUGGER_ENV, "0");
+    assert_eq!(HardeningConfig::from_env(), HardeningConfig::default());
+
+    std::env::remove_var(hardening::ALLOW_DEBUGGER_ENV);
+    assert_eq!(HardeningConfig::from_env(), HardeningConfig::default());
+}
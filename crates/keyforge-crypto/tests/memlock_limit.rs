@@ -0,0 +1,34 @@
+//! Checks that [`LockedBuffer`] degrades to unlocked memory when `mlock`
+//! is refused. Runs as its own single-test binary because it lowers the
+//! process-wide `RLIMIT_MEMLOCK` and, as root, drops privileges for good.
+
+#![cfg(unix)]
+
+use keyforge_crypto::secure_mem::LockedBuffer;
+
+/// `nobody` on Linux and the BSDs.
+const UNPRIVILEGED_ID: libc::uid_t = 65534;
+
+#[test]
+fn degrades_when_memlock_limit_is_zero() {
+    // SAFETY: plain credential and rlimit calls on the current process;
+    // no other test shares it.
+    unsafe {
+        // Root may lock memory whatever the limit says.
+        if libc::geteuid() == 0 {
+            assert_eq!(libc::setgroups(0, std::ptr::null()), 0);
+            assert_eq!(libc::setgid(UNPRIVILEGED_ID), 0);
+            assert_eq!(libc::setuid(UNPRIVILEGED_ID), 0);
+        }
+        let zero = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        assert_eq!(libc::setrlimit(libc::RLIMIT_MEMLOCK, &zero), 0);
+    }
+
+    let mut buffer = LockedBuffer::new(32);
+    assert!(!buffer.is_locked());
+    buffer.as_mut_slice().copy_from_slice(&[7u8; 32]);
+    assert_eq!(buffer.as_slice(), &[7u8; 32]);
+}
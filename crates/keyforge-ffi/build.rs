@@ -0,0 +1,51 @@
+//! Generates the UniFFI scaffolding plus the Kotlin and Swift bindings.
+//!
+//! Bindings land in `$OUT_DIR/bindings/{kotlin,swift}` unless
+//! `KEYFORGE_FFI_BINDINGS_DIR` points the mobile build somewhere else.
+
+use std::path::{Path, PathBuf};
+
+use camino::Utf8Path;
+use uniffi::{KotlinBindingGenerator, SwiftBindingGenerator};
+
+const UDL: &str = "src/keyforge.udl";
+const BINDINGS_DIR_ENV: &str = "KEYFORGE_FFI_BINDINGS_DIR";
+
+fn utf8(path: &Path) -> &Utf8Path {
+    Utf8Path::from_path(path).expect("bindings directory must be valid UTF-8")
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=uniffi.toml");
+    println!("cargo:rerun-if-env-changed={BINDINGS_DIR_ENV}");
+
+    uniffi::generate_scaffolding(UDL).expect("UniFFI scaffolding generation failed");
+
+    let out_dir = std::env::var_os(BINDINGS_DIR_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(std::env::var("OUT_DIR").unwrap()).join("bindings"));
+
+    let udl = Utf8Path::new(UDL);
+    let kotlin_dir = out_dir.join("kotlin");
+    let swift_dir = out_dir.join("swift");
+    uniffi::generate_bindings(
+        udl,
+        None,
+        KotlinBindingGenerator,
+        Some(utf8(&kotlin_dir)),
+        None,
+        Some("keyforge_ffi"),
+        false,
+    )
+    .expect("Kotlin binding generation failed");
+    uniffi::generate_bindings(
+        udl,
+        None,
+        SwiftBindingGenerator,
+        Some(utf8(&swift_dir)),
+        None,
+        Some("keyforge_ffi"),
+        false,
+    )
+    .expect("Swift binding generation failed");
+}
@@ -0,0 +1,53 @@
+//! Error type surfaced to Kotlin and Swift.
+//!
+//! UniFFI exposes this as a flat error: the foreign side sees the variant
+//! and the `Display` message.
+
+use std::fmt;
+
+use keyforge_vault::error::VaultError;
+
+#[derive(Debug)]
+pub enum KeyforgeError {
+    /// No vault exists in the given directory.
+    NoVault,
+    /// A vault already exists in the given directory.
+    VaultExists,
+    /// The vault was locked; unlock it again to continue.
+    Locked,
+    /// The master password did not open the vault.
+    WrongPassword,
+    /// The master password failed the strength policy.
+    WeakPassword(String),
+    /// The crypto power-on self-test failed; the vault stays unavailable.
+    SelfTestFailed(String),
+    /// Any other vault or crypto failure.
+    Failed(String),
+}
+
+impl fmt::Display for KeyforgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoVault => write!(f, "No vault found — create one first"),
+            Self::VaultExists => write!(f, "A vault already exists"),
+            Self::Locked => write!(f, "Vault is locked"),
+            Self::WrongPassword => write!(f, "{}", VaultError::WrongPasswordOrCorrupted),
+            Self::WeakPassword(e) | Self::SelfTestFailed(e) | Self::Failed(e) => {
+                write!(f, "{}", e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for KeyforgeError {}
+
+/// Lift the `String` errors used throughout the Rust crates.
+impl From<String> for KeyforgeError {
+    fn from(e: String) -> Self {
+        if e == VaultError::WrongPasswordOrCorrupted.to_string() {
+            Self::WrongPassword
+        } else {
+            Self::Failed(e)
+        }
+    }
+}
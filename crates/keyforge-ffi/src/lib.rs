@@ -0,0 +1,444 @@
+//! UniFFI bindings for the mobile shells
+//!
+//! Exposes the vault lifecycle, token CRUD, code generation and
+//! import/export to Kotlin and Swift (see `keyforge.udl`). The Android and
+//! iOS apps keep biometric and keystore glue on the native side and call
+//! into this crate for everything that touches vault data.
+//!
+//! A vault directory uses the same layout as the desktop app:
+//! `keyforge.vault` (SQLCipher) plus `keyforge.salts` (Argon2id salts).
+
+// The generated scaffolding (included below, must live at the crate root)
+// trips this lint.
+#![allow(clippy::empty_line_after_doc_comments)]
+
+mod error;
+
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use keyforge_crypto::kdf::{self, KdfParams};
+use keyforge_crypto::otp::OtpContext;
+use keyforge_crypto::password::PasswordPolicy;
+use keyforge_crypto::random::{generate_salt, SALT_SIZE};
+use keyforge_vault::db::Vault;
+use keyforge_vault::import::{DuplicateEntry, FailedEntry, ImportMode, ImportReport};
+use keyforge_vault::token::{NewToken, Token};
+use serde::{Deserialize, Serialize};
+
+pub use error::KeyforgeError;
+pub use keyforge_vault::import::DuplicateKind;
+
+uniffi::include_scaffolding!("keyforge");
+
+const VAULT_FILE: &str = "keyforge.vault";
+const SALTS_FILE: &str = "keyforge.salts";
+
+/// Per-vault Argon2id salts, stored next to the database.
+#[derive(Serialize, Deserialize)]
+struct VaultSalts {
+    sqlcipher_salt: [u8; SALT_SIZE],
+    secret_salt: [u8; SALT_SIZE],
+}
+
+/// Run the crypto self-test once per process and gate every unlock on it.
+fn ensure_self_test_passed() -> Result<(), KeyforgeError> {
+    static RESULT: OnceLock<Result<(), String>> = OnceLock::new();
+    RESULT
+        .get_or_init(|| keyforge_crypto::self_test().into_result())
+        .clone()
+        .map_err(KeyforgeError::SelfTestFailed)
+}
+
+fn vault_paths(dir: &str) -> (PathBuf, PathBuf) {
+    let dir = Path::new(dir);
+    (dir.join(VAULT_FILE), dir.join(SALTS_FILE))
+}
+
+fn io_failed(context: &str, e: impl std::fmt::Display) -> KeyforgeError {
+    KeyforgeError::Failed(format!("{context}: {e}"))
+}
+
+pub fn vault_exists(dir: String) -> bool {
+    vault_paths(&dir).0.exists()
+}
+
+/// A token as listed in the vault (never includes the secret).
+pub struct TokenRecord {
+    pub id: String,
+    pub issuer: String,
+    pub account: String,
+    pub algorithm: String,
+    pub digits: u32,
+    pub token_type: String,
+    pub period: u32,
+    pub counter: u64,
+    pub icon: Option<String>,
+    pub sort_order: i32,
+}
+
+impl From<Token> for TokenRecord {
+    fn from(token: Token) -> Self {
+        Self {
+            id: token.id,
+            issuer: token.issuer,
+            account: token.account,
+            algorithm: token.algorithm,
+            digits: token.digits,
+            token_type: token.token_type,
+            period: token.period,
+            counter: token.counter,
+            icon: token.icon,
+            sort_order: token.sort_order,
+        }
+    }
+}
+
+/// Input for [`KeyforgeVault::add_token`].
+pub struct NewTokenRecord {
+    pub issuer: String,
+    pub account: String,
+    pub secret: Vec<u8>,
+    pub algorithm: String,
+    pub digits: u32,
+    pub token_type: String,
+    pub period: u32,
+    pub counter: u64,
+    pub icon: Option<String>,
+}
+
+impl From<NewTokenRecord> for NewToken {
+    fn from(token: NewTokenRecord) -> Self {
+        Self {
+            issuer: token.issuer,
+            account: token.account,
+            secret: token.secret,
+            algorithm: token.algorithm,
+            digits: token.digits,
+            token_type: token.token_type,
+            period: token.period,
+            counter: token.counter,
+            icon: token.icon,
+        }
+    }
+}
+
+/// An import entry matching a vault token, numbered from 1 in its source.
+pub struct DuplicateRecord {
+    pub number: u32,
+    pub label: String,
+    pub token_id: String,
+    pub kind: DuplicateKind,
+}
+
+impl From<DuplicateEntry> for DuplicateRecord {
+    fn from(entry: DuplicateEntry) -> Self {
+        Self {
+            number: entry.number as u32,
+            label: entry.label,
+            token_id: entry.duplicate.token_id,
+            kind: entry.duplicate.kind,
+        }
+    }
+}
+
+/// An import entry that was not imported, and why.
+pub struct FailedEntryRecord {
+    pub number: u32,
+    pub label: String,
+    pub reason: String,
+}
+
+impl From<FailedEntry> for FailedEntryRecord {
+    fn from(entry: FailedEntry) -> Self {
+        Self {
+            number: entry.number as u32,
+            label: entry.label,
+            reason: entry.reason.to_string(),
+        }
+    }
+}
+
+/// What an import did, entry by entry.
+pub struct ImportReportRecord {
+    pub imported: Vec<TokenRecord>,
+    pub duplicates: Vec<DuplicateRecord>,
+    pub label_matches: Vec<DuplicateRecord>,
+    pub failed: Vec<FailedEntryRecord>,
+}
+
+impl From<ImportReport> for ImportReportRecord {
+    fn from(report: ImportReport) -> Self {
+        Self {
+            imported: report.imported.into_iter().map(Into::into).collect(),
+            duplicates: report.duplicates.into_iter().map(Into::into).collect(),
+            label_matches: report.label_matches.into_iter().map(Into::into).collect(),
+            failed: report.failed.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// A code plus its TOTP validity window (`None` for HOTP).
+pub struct GeneratedCode {
+    pub code: String,
+    pub valid_until: Option<u64>,
+    pub remaining: Option<u64>,
+}
+
+/// An open vault. After [`KeyforgeVault::lock`] every call fails with
+/// [`KeyforgeError::Locked`]; unlock again to get a new handle.
+pub struct KeyforgeVault {
+    vault: Mutex<Option<Vault>>,
+}
+
+impl KeyforgeVault {
+    /// Create a new vault in `dir` and return it unlocked.
+    pub fn create(dir: String, password: String) -> Result<Self, KeyforgeError> {
+        ensure_self_test_passed()?;
+        let params = KdfParams::default();
+        PasswordPolicy::default()
+            .check(&password, &params)
+            .map_err(KeyforgeError::WeakPassword)?;
+
+        let (vault_path, salts_path) = vault_paths(&dir);
+        if vault_path.exists() {
+            return Err(KeyforgeError::VaultExists);
+        }
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| io_failed("Failed to create vault directory", e))?;
+
+        let salts = VaultSalts {
+            sqlcipher_salt: generate_salt(),
+            secret_salt: generate_salt(),
+        };
+        let (sqlcipher_key, secret_key) = kdf::derive_key_pair_locked(
+            password.as_bytes(),
+            &salts.sqlcipher_salt,
+            &salts.secret_salt,
+            &params,
+        )?;
+
+        let vault = Vault::create(
+            &vault_path.to_string_lossy(),
+            sqlcipher_key.expose(),
+            secret_key,
+        )?;
+        let salts_json =
+            serde_json::to_vec(&salts).map_err(|e| io_failed("Failed to serialize salts", e))?;
+        std::fs::write(&salts_path, salts_json)
+            .map_err(|e| io_failed("Failed to write salts", e))?;
+
+        Ok(Self {
+            vault: Mutex::new(Some(vault)),
+        })
+    }
+
+    /// Open the existing vault in `dir` with the master password.
+    pub fn unlock(dir: String, password: String) -> Result<Self, KeyforgeError> {
+        ensure_self_test_passed()?;
+        let (vault_path, salts_path) = vault_paths(&dir);
+        if !vault_path.exists() {
+            return Err(KeyforgeError::NoVault);
+        }
+
+        let salts_json =
+            std::fs::read(&salts_path).map_err(|e| io_failed("Failed to read salts", e))?;
+        let salts: VaultSalts = serde_json::from_slice(&salts_json)
+            .map_err(|e| io_failed("Failed to parse salts", e))?;
+
+        let (sqlcipher_key, secret_key) = kdf::derive_key_pair_locked(
+            password.as_bytes(),
+            &salts.sqlcipher_salt,
+            &salts.secret_salt,
+            &KdfParams::default(),
+        )?;
+        let vault = Vault::open(
+            &vault_path.to_string_lossy(),
+            sqlcipher_key.expose(),
+            secret_key,
+        )?;
+
+        Ok(Self {
+            vault: Mutex::new(Some(vault)),
+        })
+    }
+
+    /// Drop the open vault, zeroizing its keys.
+    pub fn lock(&self) {
+        if let Ok(mut guard) = self.vault.lock() {
+            *guard = None;
+        }
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.vault
+            .lock()
+            .map(|guard| guard.is_none())
+            .unwrap_or(true)
+    }
+
+    fn with_vault<T>(
+        &self,
+        f: impl FnOnce(&Vault) -> Result<T, String>,
+    ) -> Result<T, KeyforgeError> {
+        let guard = self
+            .vault
+            .lock()
+            .map_err(|e| KeyforgeError::Failed(e.to_string()))?;
+        let vault = guard.as_ref().ok_or(KeyforgeError::Locked)?;
+        Ok(f(vault)?)
+    }
+
+    pub fn list_tokens(&self) -> Result<Vec<TokenRecord>, KeyforgeError> {
+        self.with_vault(|v| Ok(v.list_tokens()?.into_iter().map(Into::into).collect()))
+    }
+
+    pub fn add_token(&self, token: NewTokenRecord) -> Result<TokenRecord, KeyforgeError> {
+        self.with_vault(|v| v.add_token(token.into()).map(Into::into))
+    }
+
+    pub fn update_token(
+        &self,
+        id: String,
+        issuer: String,
+        account: String,
+    ) -> Result<(), KeyforgeError> {
+        self.with_vault(|v| v.update_token(&id, &issuer, &account))
+    }
+
+    pub fn delete_token(&self, id: String) -> Result<(), KeyforgeError> {
+        self.with_vault(|v| v.delete_token(&id))
+    }
+
+    pub fn reorder_tokens(&self, ids: Vec<String>) -> Result<(), KeyforgeError> {
+        self.with_vault(|v| v.reorder_tokens(&ids))
+    }
+
+    pub fn increment_counter(&self, id: String) -> Result<u64, KeyforgeError> {
+        self.with_vault(|v| v.increment_counter(&id))
+    }
+
+    /// Generate the code for token `id` at Unix time `time` (seconds).
+    pub fn generate_code(&self, id: String, time: u64) -> Result<GeneratedCode, KeyforgeError> {
+        self.with_vault(|v| {
+            let code = v.generate_code(&id, &OtpContext::at(time))?;
+            Ok(GeneratedCode {
+                remaining: code.remaining(time),
+                valid_until: code.valid_until,
+                code: code.code,
+            })
+        })
+    }
+
+    pub fn import_uris(&self, uris: Vec<String>) -> Result<ImportReportRecord, KeyforgeError> {
+        self.with_vault(|v| Ok(v.import_uris(&uris, ImportMode::Atomic)?.into()))
+    }
+
+    pub fn export_uris(&self) -> Result<Vec<String>, KeyforgeError> {
+        self.with_vault(|v| v.export_uris())
+    }
+
+    pub fn export_encrypted(&self, password: String) -> Result<Vec<u8>, KeyforgeError> {
+        self.with_vault(|v| v.export_encrypted(password.as_bytes()))
+    }
+
+    pub fn import_encrypted(
+        &self,
+        data: Vec<u8>,
+        password: String,
+    ) -> Result<ImportReportRecord, KeyforgeError> {
+        self.with_vault(|v| Ok(v.import_encrypted(&data, password.as_bytes())?.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASSWORD: &str = "correct horse battery staple";
+
+    fn dir_string(dir: &tempfile::TempDir) -> String {
+        dir.path().to_string_lossy().to_string()
+    }
+
+    fn github() -> NewTokenRecord {
+        NewTokenRecord {
+            issuer: "GitHub".to_string(),
+            account: "user@example.com".to_string(),
+            secret: b"12345678901234567890".to_vec(),
+            algorithm: "SHA1".to_string(),
+            digits: 8,
+            token_type: "totp".to_string(),
+            period: 30,
+            counter: 0,
+            icon: None,
+        }
+    }
+
+    #[test]
+    fn test_lifecycle_and_codes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        assert!(!vault_exists(dir_string(&dir)));
+
+        let vault = KeyforgeVault::create(dir_string(&dir), PASSWORD.to_string()).unwrap();
+        assert!(vault_exists(dir_string(&dir)));
+        let token = vault.add_token(github()).unwrap();
+        let code = vault.generate_code(token.id.clone(), 59).unwrap();
+        assert_eq!(code.code, "94287082");
+        assert_eq!(code.remaining, Some(1));
+
+        vault.lock();
+        assert!(vault.is_locked());
+        assert!(matches!(vault.list_tokens(), Err(KeyforgeError::Locked)));
+
+        let vault = KeyforgeVault::unlock(dir_string(&dir), PASSWORD.to_string()).unwrap();
+        assert_eq!(vault.list_tokens().unwrap().len(), 1);
+        assert!(matches!(
+            KeyforgeVault::create(dir_string(&dir), PASSWORD.to_string()),
+            Err(KeyforgeError::VaultExists)
+        ));
+    }
+
+    #[test]
+    fn test_import_reports_every_failure() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let vault = KeyforgeVault::create(dir_string(&dir), PASSWORD.to_string()).unwrap();
+        vault.add_token(github()).unwrap();
+
+        let uris = vec![
+            "otpauth://totp/Other:a?secret=GEZDGNBV".to_string(),
+            "not a uri".to_string(),
+            "otpauth://totp/X:b?secret=GEZDGNBV&digits=7".to_string(),
+        ];
+        let report = vault.import_uris(uris.clone()).unwrap();
+        assert!(report.imported.is_empty());
+        let failed: Vec<u32> = report.failed.iter().map(|f| f.number).collect();
+        assert_eq!(failed, [2, 3]);
+
+        let report = vault.import_uris(uris[..1].to_vec()).unwrap();
+        assert_eq!(report.imported[0].issuer, "Other");
+        let again = vault.import_uris(uris[..1].to_vec()).unwrap();
+        assert!(again.imported.is_empty());
+        assert_eq!(again.duplicates[0].token_id, report.imported[0].id);
+        assert!(matches!(again.duplicates[0].kind, DuplicateKind::Secret));
+    }
+
+    #[test]
+    fn test_unlock_errors() {
+        let dir = tempfile::TempDir::new().unwrap();
+        assert!(matches!(
+            KeyforgeVault::unlock(dir_string(&dir), PASSWORD.to_string()),
+            Err(KeyforgeError::NoVault)
+        ));
+        assert!(matches!(
+            KeyforgeVault::create(dir_string(&dir), "short".to_string()),
+            Err(KeyforgeError::WeakPassword(_))
+        ));
+
+        KeyforgeVault::create(dir_string(&dir), PASSWORD.to_string()).unwrap();
+        assert!(matches!(
+            KeyforgeVault::unlock(dir_string(&dir), "wrong password entirely".to_string()),
+            Err(KeyforgeError::WrongPassword)
+        ));
+    }
+}
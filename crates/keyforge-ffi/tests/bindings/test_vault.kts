@@ -0,0 +1,35 @@
+import com.keyforge.ffi.*
+import java.nio.file.Files
+
+val password = "correct horse battery staple"
+val dir = Files.createTempDirectory("keyforge").resolve("vault").toString()
+
+assert(!vaultExists(dir))
+var vault = KeyforgeVault.create(dir, password)
+val token = vault.addToken(NewTokenRecord(
+    issuer = "GitHub", account = "user@example.com",
+    secret = "12345678901234567890".toByteArray(), algorithm = "SHA1",
+    digits = 8u, tokenType = "totp", period = 30u, counter = 0uL, icon = null,
+))
+val code = vault.generateCode(token.id, 59uL)
+assert(code.code == "94287082")
+assert(code.remaining == 1uL)
+
+val backup = vault.exportEncrypted("backup password")
+vault.deleteToken(token.id)
+val report = vault.importEncrypted(backup, "backup password")
+assert(report.imported.size == 1 && report.failed.isEmpty())
+
+vault.lock()
+try {
+    vault.listTokens()
+    throw RuntimeException("expected Locked")
+} catch (e: KeyforgeException.Locked) {}
+
+try {
+    KeyforgeVault.unlock(dir, "not the password at all")
+    throw RuntimeException("expected WrongPassword")
+} catch (e: KeyforgeException.WrongPassword) {}
+
+vault = KeyforgeVault.unlock(dir, password)
+assert(vault.listTokens().size == 1)
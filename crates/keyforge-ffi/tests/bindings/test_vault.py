@@ -0,0 +1,50 @@
+import tempfile
+
+from keyforge import *
+
+PASSWORD = "correct horse battery staple"
+SECRET = b"12345678901234567890"
+
+with tempfile.TemporaryDirectory() as path:
+    vault_dir = path + "/vault"
+    assert not vault_exists(vault_dir)
+
+    vault = KeyforgeVault.create(vault_dir, PASSWORD)
+    token = vault.add_token(NewTokenRecord(
+        issuer="GitHub", account="user@example.com", secret=SECRET,
+        algorithm="SHA1", digits=8, token_type="totp", period=30,
+        counter=0, icon=None,
+    ))
+    code = vault.generate_code(token.id, 59)
+    assert code.code == "94287082", code.code
+    assert code.remaining == 1
+
+    vault.update_token(token.id, "GitHub", "renamed@example.com")
+    assert vault.list_tokens()[0].account == "renamed@example.com"
+
+    backup = vault.export_encrypted("backup password")
+    vault.delete_token(token.id)
+    assert vault.list_tokens() == []
+    report = vault.import_encrypted(backup, "backup password")
+    assert len(report.imported) == 1 and report.failed == []
+
+    report = vault.import_uris(["otpauth://totp/A:a?secret=GEZDGNBV", "not a uri"])
+    assert report.imported == []
+    assert report.failed[0].number == 2, report.failed
+
+    vault.lock()
+    assert vault.is_locked()
+    try:
+        vault.list_tokens()
+        raise AssertionError("expected Locked")
+    except KeyforgeError.Locked:
+        pass
+
+    try:
+        KeyforgeVault.unlock(vault_dir, "not the password at all")
+        raise AssertionError("expected WrongPassword")
+    except KeyforgeError.WrongPassword:
+        pass
+
+    vault = KeyforgeVault.unlock(vault_dir, PASSWORD)
+    assert len(vault.export_uris()) == 1
@@ -0,0 +1,37 @@
+import Foundation
+import KeyForge
+
+let password = "correct horse battery staple"
+let dir = FileManager.default.temporaryDirectory
+    .appendingPathComponent(UUID().uuidString)
+    .appendingPathComponent("vault").path
+
+assert(!vaultExists(dir: dir))
+var vault = try! KeyforgeVault.create(dir: dir, password: password)
+let token = try! vault.addToken(token: NewTokenRecord(
+    issuer: "GitHub", account: "user@example.com",
+    secret: Data("12345678901234567890".utf8), algorithm: "SHA1",
+    digits: 8, tokenType: "totp", period: 30, counter: 0, icon: nil
+))
+let code = try! vault.generateCode(id: token.id, time: 59)
+assert(code.code == "94287082")
+assert(code.remaining == 1)
+
+let backup = try! vault.exportEncrypted(password: "backup password")
+try! vault.deleteToken(id: token.id)
+let report = try! vault.importEncrypted(data: backup, password: "backup password")
+assert(report.imported.count == 1 && report.failed.isEmpty)
+
+vault.lock()
+do {
+    _ = try vault.listTokens()
+    fatalError("expected Locked")
+} catch KeyforgeError.Locked {}
+
+do {
+    _ = try KeyforgeVault.unlock(dir: dir, password: "not the password at all")
+    fatalError("expected WrongPassword")
+} catch KeyforgeError.WrongPassword {}
+
+vault = try! KeyforgeVault.unlock(dir: dir, password: password)
+assert(try! vault.listTokens().count == 1)
@@ -0,0 +1,39 @@
+//! Runs the generated foreign-language bindings against the host cdylib.
+//!
+//! Python runs everywhere CI does. Kotlin needs `kotlinc` plus JNA on the
+//! `CLASSPATH` and Swift needs `swiftc`, so those tests are ignored by
+//! default; CI installs both toolchains and runs them with `--ignored`.
+
+const PACKAGE: &str = "keyforge-ffi";
+
+#[test]
+fn python_bindings() {
+    uniffi::python_test::run_test(
+        env!("CARGO_TARGET_TMPDIR"),
+        PACKAGE,
+        "tests/bindings/test_vault.py",
+    )
+    .unwrap();
+}
+
+#[test]
+#[ignore = "needs kotlinc and JNA on the CLASSPATH"]
+fn kotlin_bindings() {
+    uniffi::kotlin_test::run_test(
+        env!("CARGO_TARGET_TMPDIR"),
+        PACKAGE,
+        "tests/bindings/test_vault.kts",
+    )
+    .unwrap();
+}
+
+#[test]
+#[ignore = "needs swiftc"]
+fn swift_bindings() {
+    uniffi::swift_test::run_test(
+        env!("CARGO_TARGET_TMPDIR"),
+        PACKAGE,
+        "tests/bindings/test_vault.swift",
+    )
+    .unwrap();
+}
@@ -0,0 +1,191 @@
+//! Encrypted export container
+//!
+//! Reads and writes the password-protected export format independently of
+//! the SQLCipher database, so the same code backs [`Vault::export_encrypted`]
+//! and the WebAssembly bindings.
+//!
+//! Layout: `[EXPORT_STREAM_MAGIC][salt][STREAM ciphertext]`. The key is
+//! Argon2id(password, salt) and the plaintext is a JSON array of
+//! `otpauth://` URIs. Data without the magic prefix is the legacy
+//! `[salt][nonce][ciphertext]` single-blob form, which is still readable.
+//!
+//! [`Vault::export_encrypted`]: crate::db::Vault::export_encrypted
+
+use std::io::{Read, Write};
+
+use keyforge_crypto::kdf::{self, KdfParams};
+use keyforge_crypto::stream::{StreamDecryptor, StreamEncryptor};
+use zeroize::Zeroizing;
+
+use crate::constants::{EXPORT_MAX_SIZE, EXPORT_SALT_SIZE, EXPORT_STREAM_MAGIC};
+use crate::error::VaultError;
+
+fn io_error(e: std::io::Error) -> String {
+    // Stream errors carry a `CryptoError` message; surface it unchanged.
+    match e.get_ref() {
+        Some(inner) => inner.to_string(),
+        None => VaultError::Io(e.to_string()).to_string(),
+    }
+}
+
+/// Streams URIs into an encrypted export. Each URI is encrypted as soon as
+/// it is pushed, so the whole export is never held in one plaintext buffer.
+pub struct ExportWriter<W: Write> {
+    encryptor: StreamEncryptor<W>,
+    count: usize,
+}
+
+impl<W: Write> ExportWriter<W> {
+    /// Derive the export key and write the container header to `writer`.
+    pub fn new(password: &[u8], mut writer: W) -> Result<Self, String> {
+        let salt = keyforge_crypto::random::generate_salt();
+        let key = kdf::derive_key_locked(password, &salt, &KdfParams::default())?;
+
+        writer
+            .write_all(EXPORT_STREAM_MAGIC)
+            .and_then(|_| writer.write_all(&salt))
+            .map_err(io_error)?;
+
+        let mut encryptor = StreamEncryptor::new(key.expose(), writer)?;
+        encryptor.write_all(b"[").map_err(io_error)?;
+        Ok(Self {
+            encryptor,
+            count: 0,
+        })
+    }
+
+    /// Append one `otpauth://` URI.
+    pub fn push(&mut self, uri: &str) -> Result<(), String> {
+        if self.count > 0 {
+            self.encryptor.write_all(b",").map_err(io_error)?;
+        }
+        serde_json::to_writer(&mut self.encryptor, uri)
+            .map_err(|e| VaultError::Serialization(e.to_string()))?;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Close the JSON array, seal the final chunk and return the writer.
+    pub fn finish(mut self) -> Result<W, String> {
+        self.encryptor.write_all(b"]").map_err(io_error)?;
+        self.encryptor.finish()
+    }
+}
+
+/// Write `uris` as a complete encrypted export.
+pub fn write_export<W: Write>(uris: &[String], password: &[u8], writer: W) -> Result<W, String> {
+    let mut export = ExportWriter::new(password, writer)?;
+    for uri in uris {
+        export.push(uri)?;
+    }
+    export.finish()
+}
+
+/// Read a streamed export from `reader`.
+///
+/// The plaintext is gathered in a buffer of `size_hint` bytes (the
+/// stream's length when known, e.g. from file metadata, else 0), capped at
+/// [`EXPORT_MAX_SIZE`]. Should it need to grow, each buffer it outgrows is
+/// wiped; plaintext past the cap is rejected.
+///
+/// Nothing is returned unless the whole stream authenticates, so a
+/// truncated or tampered file never yields a partial list.
+pub fn read_export<R: Read>(
+    mut reader: R,
+    password: &[u8],
+    size_hint: usize,
+) -> Result<Vec<String>, String> {
+    let mut magic = [0u8; 4];
+    let mut salt = [0u8; EXPORT_SALT_SIZE];
+    reader
+        .read_exact(&mut magic)
+        .and_then(|_| reader.read_exact(&mut salt))
+        .map_err(|_| VaultError::InvalidExportFile)?;
+    if &magic != EXPORT_STREAM_MAGIC {
+        return Err(VaultError::InvalidExportFile.to_string());
+    }
+
+    let key = kdf::derive_key_locked(password, &salt, &KdfParams::default())?;
+
+    let mut decryptor = StreamDecryptor::new(key.expose(), reader)?;
+    let limit = EXPORT_MAX_SIZE as usize;
+    let mut json = Zeroizing::new(Vec::with_capacity(size_hint.min(limit)));
+    let mut chunk = Zeroizing::new([0u8; 8192]);
+    loop {
+        let n = match decryptor.read(&mut chunk[..]) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(io_error(e)),
+        };
+        if json.len() + n > limit {
+            return Err(VaultError::InvalidExportFile.to_string());
+        }
+        if json.capacity() - json.len() < n {
+            let mut grown = Zeroizing::new(Vec::with_capacity((2 * (json.len() + n)).min(limit)));
+            grown.extend_from_slice(&json);
+            json = grown;
+        }
+        json.extend_from_slice(&chunk[..n]);
+    }
+    parse_uri_list(&json)
+}
+
+/// Read an export held in memory, in either the streamed or legacy format.
+pub fn read_export_bytes(data: &[u8], password: &[u8]) -> Result<Vec<String>, String> {
+    if data.starts_with(EXPORT_STREAM_MAGIC) {
+        return read_export(data, password, data.len());
+    }
+    if data.len() < EXPORT_SALT_SIZE {
+        return Err(VaultError::InvalidExportFile.to_string());
+    }
+    let (salt_bytes, encrypted) = data.split_at(EXPORT_SALT_SIZE);
+    let mut salt = [0u8; EXPORT_SALT_SIZE];
+    salt.copy_from_slice(salt_bytes);
+
+    let key = kdf::derive_key_locked(password, &salt, &KdfParams::default())?;
+    let json = keyforge_crypto::aead::decrypt_locked(encrypted, key.expose())?;
+    parse_uri_list(json.as_slice())
+}
+
+fn parse_uri_list(json: &[u8]) -> Result<Vec<String>, String> {
+    serde_json::from_slice(json).map_err(|e| VaultError::Serialization(e.to_string()).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uris() -> Vec<String> {
+        vec![
+            "otpauth://totp/A:a?secret=GEZDGNBV&issuer=A".to_string(),
+            "otpauth://hotp/B:\"quoted\"?secret=GEZDGNBV&counter=3".to_string(),
+        ]
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let data = write_export(&uris(), b"pw", Vec::new()).unwrap();
+        assert!(data.starts_with(EXPORT_STREAM_MAGIC));
+        assert_eq!(read_export(data.as_slice(), b"pw", 0).unwrap(), uris());
+        assert_eq!(read_export_bytes(&data, b"pw").unwrap(), uris());
+        assert_eq!(
+            read_export(data.as_slice(), b"pw", usize::MAX).unwrap(),
+            uris()
+        );
+    }
+
+    #[test]
+    fn test_empty_export() {
+        let data = write_export(&[], b"pw", Vec::new()).unwrap();
+        assert!(read_export_bytes(&data, b"pw").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rejects_wrong_password_and_garbage() {
+        let data = write_export(&uris(), b"pw", Vec::new()).unwrap();
+        assert!(read_export_bytes(&data, b"other").is_err());
+        assert!(read_export_bytes(b"short", b"pw").is_err());
+        assert!(read_export(&b"KFX1................"[..], b"pw", 0).is_err());
+    }
+}
@@ -1,5 +1,7 @@
 //! Shared constants for the vault crate.
 
+use std::ops::RangeInclusive;
+
 /// The `otpauth://` URI scheme prefix.
 pub const OTPAUTH_SCHEME: &str = "otpauth://";
 /// Length of the `otpauth://` scheme prefix.
@@ -7,6 +9,98 @@ pub const OTPAUTH_SCHEME_LEN: usize = OTPAUTH_SCHEME.len();
 
 /// Salt size in bytes for encrypted exports.
 pub const EXPORT_SALT_SIZE: usize = 16;
+/// Leading bytes of a streamed (chunked AEAD) encrypted export. Exports
+/// without it are the legacy single-blob `[salt][nonce][ciphertext]` form.
+pub const EXPORT_STREAM_MAGIC: &[u8; 4] = b"KFX2";
+/// Upper bound on an encrypted export and on the plaintext read from it,
+/// far above any real vault.
+pub const EXPORT_MAX_SIZE: u64 = 64 << 20;
+
+/// Format version of the browser extension vault document.
+pub const EXTENSION_VAULT_VERSION: u32 = 1;
+/// KDF named in an extension vault's `kdf_params` (Web Crypto spelling).
+pub const EXTENSION_KDF_NAME: &str = "PBKDF2";
+/// PRF hash named in an extension vault's `kdf_params`.
+pub const EXTENSION_KDF_HASH: &str = "SHA-256";
+/// Upper bound on PBKDF2 iterations accepted from an extension vault, so a
+/// crafted file cannot stall the import.
+pub const EXTENSION_MAX_ITERATIONS: u32 = 10_000_000;
+/// Shortest salt accepted from an extension vault.
+pub const EXTENSION_MIN_SALT_SIZE: usize = 16;
+
+/// Prefix of a Google Authenticator "Transfer accounts" QR payload.
+pub const GOOGLE_MIGRATION_PREFIX: &str = "otpauth-migration://offline?";
+/// Most accounts Google Authenticator itself puts in one transfer QR code.
+pub const GOOGLE_MIGRATION_MAX_ENTRIES: usize = 10;
+/// Largest protobuf payload per transfer QR code. Even with every base64
+/// character percent-escaped the URI stays under the 2,331 bytes a
+/// version-40 QR code holds at medium error correction.
+pub const GOOGLE_MIGRATION_MAX_PAYLOAD: usize = 512;
+
+/// Aegis export file format version this crate reads.
+pub const AEGIS_VERSION: u32 = 1;
+/// Aegis database (`db.version`) layout this crate writes, and the newest
+/// it reads.
+pub const AEGIS_DB_VERSION: u32 = 3;
+/// scrypt cost (`n`, `r`, `p`) for the password slot of an exported Aegis
+/// vault; the values Aegis itself uses.
+pub const AEGIS_SCRYPT_N: u64 = 1 << 15;
+pub const AEGIS_SCRYPT_R: u32 = 8;
+pub const AEGIS_SCRYPT_P: u32 = 1;
+/// Upper bound on scrypt memory (`128 · r · n` bytes) accepted from an
+/// Aegis password slot, so a crafted file cannot exhaust memory. Aegis
+/// itself uses 32 MiB.
+pub const AEGIS_MAX_SCRYPT_MEMORY: u64 = 1 << 30;
+/// Upper bound on scrypt parallelism accepted from an Aegis password slot.
+pub const AEGIS_MAX_SCRYPT_P: u32 = 16;
+
+/// Newest 2FAS backup schema the importer understands.
+pub const TWOFAS_MAX_SCHEMA_VERSION: u32 = 4;
+/// PBKDF2-HMAC-SHA256 iterations 2FAS uses for encrypted backups.
+pub const TWOFAS_PBKDF2_ITERATIONS: u32 = 10_000;
+
+/// Ente encrypted export format version.
+pub const ENTE_EXPORT_VERSION: u32 = 1;
+/// Upper bound on the Argon2id memory (bytes) accepted from an Ente export.
+/// Ente itself starts at 1 GiB and halves it on devices that cannot cope.
+pub const ENTE_MAX_MEM_LIMIT: u64 = 1 << 30;
+/// Upper bound on Argon2id passes accepted from an Ente export. Ente doubles
+/// the passes each time it halves memory, down to 64 MiB.
+pub const ENTE_MAX_OPS_LIMIT: u32 = 64;
+
+/// Upper bounds on the key derivation cost accepted from a Bitwarden export.
+/// These are Bitwarden's own maxima.
+pub const BITWARDEN_MAX_PBKDF2_ITERATIONS: u32 = 2_000_000;
+pub const BITWARDEN_MAX_ARGON2_ITERATIONS: u32 = 10;
+pub const BITWARDEN_MAX_ARGON2_MEMORY_MIB: u32 = 1024;
+pub const BITWARDEN_MAX_ARGON2_PARALLELISM: u32 = 16;
+
+/// Upper bound on PBKDF2 iterations accepted from an andOTP backup.
+pub const ANDOTP_MAX_PBKDF2_ITERATIONS: u32 = 1_000_000;
+/// The PBKDF2 iterations andOTP itself picks. An encrypted backup has no
+/// other marker, so only a count in this range is sniffed as one.
+pub const ANDOTP_PBKDF2_ITERATIONS: RangeInclusive<u32> = 140_000..=160_000;
+
+/// Argon2id cost Authenticator Pro uses for encrypted backups.
+pub const AUTHENTICATOR_PRO_ARGON2_MEMORY_KIB: u32 = 65536;
+pub const AUTHENTICATOR_PRO_ARGON2_ITERATIONS: u32 = 3;
+pub const AUTHENTICATOR_PRO_ARGON2_PARALLELISM: u32 = 4;
+/// PBKDF2-HMAC-SHA1 iterations of Authenticator Pro's legacy backup format.
+pub const AUTHENTICATOR_PRO_LEGACY_PBKDF2_ITERATIONS: u32 = 64_000;
+
+/// Upper bounds on the key derivation cost accepted from a KeePass
+/// database, well above what KeePassXC's one-second benchmark picks.
+pub const KEEPASS_MAX_ARGON2_MEMORY: u64 = 1 << 30;
+pub const KEEPASS_MAX_ARGON2_ITERATIONS: u32 = 100;
+pub const KEEPASS_MAX_ARGON2_PARALLELISM: u32 = 64;
+pub const KEEPASS_MAX_AES_KDF_ROUNDS: u64 = 200_000_000;
+/// Upper bound on the decompressed XML of a KeePass database.
+pub const KEEPASS_MAX_XML_SIZE: u64 = 64 << 20;
+
+/// Upper bound on PBKDF2 iterations accepted from a PSKC key container.
+pub const PSKC_MAX_PBKDF2_ITERATIONS: u32 = 2_000_000;
+/// PBKDF2-HMAC-SHA1 iterations for password-protected PSKC exports.
+pub const PSKC_EXPORT_PBKDF2_ITERATIONS: u32 = 100_000;
 
 /// Default issuer when the URI does not specify one.
 pub const DEFAULT_ISSUER: &str = "Unknown";
@@ -20,11 +114,12 @@ pub const DEFAULT_PERIOD: u32 = 30;
 pub const DEFAULT_COUNTER: u64 = 0;
 
 /// Supported OTP token types.
-pub const TOKEN_TYPE_TOTP: &str = "totp";
-pub const TOKEN_TYPE_HOTP: &str = "hotp";
+pub const TOKEN_TYPE_TOTP: &str = keyforge_crypto::otp::TYPE_TOTP;
+pub const TOKEN_TYPE_HOTP: &str = keyforge_crypto::otp::TYPE_HOTP;
+pub const TOKEN_TYPE_STEAM: &str = keyforge_crypto::otp::TYPE_STEAM;
 
 /// Initial sort-order sentinel (no tokens exist yet).
 pub const INITIAL_SORT_ORDER: i32 = -1;
 
 /// Current schema version.
-pub const SCHEMA_VERSION: i32 = 1;
+pub const SCHEMA_VERSION: i32 = 2;
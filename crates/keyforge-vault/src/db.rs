@@ -1,14 +1,15 @@
 //! SQLCipher vault
 
+use keyforge_crypto::secure_mem::{LockedBuffer, LockedKey};
 use rusqlite::Connection;
-use zeroize::Zeroize;
 
 use crate::error::VaultError;
 use crate::migrations;
 
 pub struct Vault {
     conn: Connection,
-    secret_key: [u8; 32],
+    /// Held in locked memory; zeroized and unmapped when the vault is dropped.
+    secret_key: LockedKey,
 }
 
 impl Vault {
@@ -16,13 +17,16 @@ impl Vault {
     pub fn create(
         path: &str,
         sqlcipher_key: &[u8; 32],
-        secret_key: [u8; 32],
+        secret_key: impl Into<LockedKey>,
     ) -> Result<Self, String> {
         let conn = Connection::open(path).map_err(|e| VaultError::DatabaseOpen(e.to_string()))?;
 
         Self::set_key(&conn, sqlcipher_key)?;
 
-        let vault = Vault { conn, secret_key };
+        let vault = Vault {
+            conn,
+            secret_key: secret_key.into(),
+        };
         migrations::run_migrations(&vault.conn)?;
 
         Ok(vault)
@@ -32,29 +36,41 @@ impl Vault {
     pub fn open(
         path: &str,
         sqlcipher_key: &[u8; 32],
-        secret_key: [u8; 32],
+        secret_key: impl Into<LockedKey>,
     ) -> Result<Self, String> {
         let conn = Connection::open(path).map_err(|e| VaultError::DatabaseOpen(e.to_string()))?;
 
         Self::set_key(&conn, sqlcipher_key)?;
 
-        let vault = Vault { conn, secret_key };
+        let vault = Vault {
+            conn,
+            secret_key: secret_key.into(),
+        };
         migrations::run_migrations(&vault.conn)?;
 
         Ok(vault)
     }
 
     fn set_key(conn: &Connection, key: &[u8; 32]) -> Result<(), String> {
-        let mut hex_key: String = key.iter().map(|b| format!("{:02x}", b)).collect();
-        let mut pragma_value = format!("x'{}'", hex_key);
-        let result = conn
-            .pragma_update(None, "key", &pragma_value)
-            .map_err(|e| VaultError::SetEncryptionKey(e.to_string()));
-
-        // Zeroize key material from heap strings
-        hex_key.zeroize();
-        pragma_value.zeroize();
-        result?;
+        // Build the whole statement in locked memory and hand it to SQLite
+        // as is: `pragma_update` would first quote the key into a heap
+        // `String` that is never wiped.
+        const HEX: &[u8; 16] = b"0123456789abcdef";
+        const PREFIX: &[u8] = b"PRAGMA key = \"x'";
+        const SUFFIX: &[u8] = b"'\";";
+        let mut statement = LockedBuffer::new(PREFIX.len() + key.len() * 2 + SUFFIX.len());
+        let (prefix, rest) = statement.as_mut_slice().split_at_mut(PREFIX.len());
+        let (hex, suffix) = rest.split_at_mut(key.len() * 2);
+        prefix.copy_from_slice(PREFIX);
+        for (pair, b) in hex.chunks_exact_mut(2).zip(key) {
+            pair[0] = HEX[(b >> 4) as usize];
+            pair[1] = HEX[(b & 0x0f) as usize];
+        }
+        suffix.copy_from_slice(SUFFIX);
+
+        let sql = std::str::from_utf8(statement.as_slice()).expect("key pragma is ASCII");
+        conn.execute_batch(sql)
+            .map_err(|e| VaultError::SetEncryptionKey(e.to_string()))?;
 
         conn.execute_batch("SELECT count(*) FROM sqlite_master;")
             .map_err(|_| VaultError::WrongPasswordOrCorrupted)?;
@@ -69,12 +85,6 @@ impl Vault {
 
     /// Get the secret encryption key.
     pub(crate) fn secret_key(&self) -> &[u8; 32] {
-        &self.secret_key
-    }
-}
-
-impl Drop for Vault {
-    fn drop(&mut self) {
-        self.secret_key.zeroize();
+        self.secret_key.expose()
     }
 }
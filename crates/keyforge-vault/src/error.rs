@@ -35,6 +35,27 @@ pub enum VaultError {
     InvalidBase32Secret,
     /// An unknown OTP token type was encountered.
     UnknownTokenType(String),
+    /// Token parameters cannot generate codes (bad digits, period, ...).
+    InvalidToken(String),
+    /// Reading or writing an export stream failed.
+    Io(String),
+    /// A foreign authenticator export could not be decoded.
+    InvalidImport(String),
+    /// The password for an encrypted foreign export did not decrypt it.
+    WrongImportPassword,
+    /// A used-code store (OTP replay protection) could not be accessed.
+    CodeStore(String),
+    /// A foreign vault uses a version or KDF this crate cannot read.
+    UnsupportedFormat(String),
+    /// No importer recognised the file.
+    UnrecognizedImport,
+    /// The file is encrypted and no password was given. Holds the format.
+    ImportPasswordRequired(String),
+    /// The file needs a keyfile or key, alone or with a password, and was
+    /// given none or the wrong kind. Holds the format and what it needs.
+    ImportKeyRequired(String, &'static str),
+    /// An import preview has no candidate with this ID.
+    UnknownImportCandidate(usize),
 }
 
 impl fmt::Display for VaultError {
@@ -55,6 +76,20 @@ impl fmt::Display for VaultError {
             Self::MissingUriParam(name) => write!(f, "Missing URI parameter: {}", name),
             Self::InvalidBase32Secret => write!(f, "Invalid base32 secret"),
             Self::UnknownTokenType(t) => write!(f, "Unknown token type: {}", t),
+            Self::InvalidToken(e) => write!(f, "Invalid token: {}", e),
+            Self::Io(e) => write!(f, "I/O error: {}", e),
+            Self::InvalidImport(e) => write!(f, "Invalid import file: {}", e),
+            Self::WrongImportPassword => write!(f, "Wrong password for the import file"),
+            Self::CodeStore(e) => write!(f, "Used-code store error: {}", e),
+            Self::UnsupportedFormat(e) => write!(f, "Unsupported vault format: {}", e),
+            Self::UnrecognizedImport => write!(f, "Unrecognized import file format"),
+            Self::ImportPasswordRequired(format) => {
+                write!(f, "A password is required for this {} file", format)
+            }
+            Self::ImportKeyRequired(format, needed) => {
+                write!(f, "{} is required for this {} file", needed, format)
+            }
+            Self::UnknownImportCandidate(id) => write!(f, "No import candidate with ID {}", id),
         }
     }
 }
@@ -68,3 +103,76 @@ impl From<VaultError> for String {
         e.to_string()
     }
 }
+
+/// Why an `otpauth://` URI was rejected by
+/// [`OtpAuthUri::parse`](crate::otpauth::OtpAuthUri::parse).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UriError {
+    /// The URI does not start with `otpauth://`.
+    Scheme,
+    /// No `/` separates the token type from the label.
+    MissingType,
+    /// The token type is not `totp`, `hotp` or `steam`.
+    UnknownType(String),
+    /// No `?` introduces the parameters.
+    MissingQuery,
+    /// A percent-escape is malformed or does not decode to UTF-8 (strict).
+    InvalidEscape(String),
+    /// A parameter has no `=` (strict).
+    MalformedParameter(String),
+    /// A known parameter appears more than once (strict).
+    DuplicateParameter(String),
+    /// A required parameter is absent.
+    MissingParameter(&'static str),
+    /// The secret is not valid unpadded base32.
+    InvalidSecret,
+    /// A numeric parameter does not parse.
+    InvalidNumber { param: &'static str, value: String },
+    /// The algorithm, digits or period cannot generate codes.
+    Unsupported(String),
+    /// The label issuer and the `issuer` parameter disagree (strict).
+    IssuerMismatch { label: String, param: String },
+    /// The account name is empty (strict).
+    EmptyAccount,
+    /// The label cannot be written so that it reads back the same: an
+    /// account with a `:` but no issuer, or leading spaces after the issuer.
+    AmbiguousLabel,
+}
+
+impl fmt::Display for UriError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid otpauth URI: ")?;
+        match self {
+            Self::Scheme => write!(f, "not an otpauth:// URI"),
+            Self::MissingType => write!(f, "missing token type"),
+            Self::UnknownType(t) => write!(f, "unknown token type: {}", t),
+            Self::MissingQuery => write!(f, "missing query parameters"),
+            Self::InvalidEscape(s) => write!(f, "invalid percent-encoding in {:?}", s),
+            Self::MalformedParameter(p) => write!(f, "malformed parameter {:?}", p),
+            Self::DuplicateParameter(p) => write!(f, "duplicate parameter {}", p),
+            Self::MissingParameter(p) => write!(f, "missing parameter {}", p),
+            Self::InvalidSecret => write!(f, "invalid base32 secret"),
+            Self::InvalidNumber { param, value } => {
+                write!(f, "{} is not a number: {:?}", param, value)
+            }
+            Self::Unsupported(e) => write!(f, "{}", e),
+            Self::IssuerMismatch { label, param } => {
+                write!(
+                    f,
+                    "label issuer {:?} does not match issuer {:?}",
+                    label, param
+                )
+            }
+            Self::EmptyAccount => write!(f, "empty account name"),
+            Self::AmbiguousLabel => write!(f, "label does not round-trip"),
+        }
+    }
+}
+
+impl std::error::Error for UriError {}
+
+impl From<UriError> for String {
+    fn from(e: UriError) -> String {
+        e.to_string()
+    }
+}
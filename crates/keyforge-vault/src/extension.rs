@@ -0,0 +1,421 @@
+//! Browser extension vault format
+//!
+//! The browser extension (and the planned web app) cannot run Argon2id, so
+//! it keeps its vault in `chrome.storage.local` as a JSON document keyed by
+//! PBKDF2-HMAC-SHA256 instead:
+//!
+//! ```text
+//! {"vault": {"version": 1,
+//!            "salt": "<base64>",
+//!            "kdf_params": {"name": "PBKDF2", "hash": "SHA-256", "iterations": 600000},
+//!            "tokens_encrypted": "<base64 [nonce][ciphertext][tag]>"}}
+//! ```
+//!
+//! `tokens_encrypted` is AES-256-GCM over a JSON array of
+//! [`ExtensionToken`]s: the shared TypeScript `Token` shape plus a base32
+//! `secret`. This module reads and writes that document without SQLite, so
+//! the desktop app can import an extension vault and produce one.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use keyforge_crypto::kdf::{self, PBKDF2_DEFAULT_ITERATIONS};
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::constants::{
+    EXTENSION_KDF_HASH, EXTENSION_KDF_NAME, EXTENSION_MAX_ITERATIONS, EXTENSION_MIN_SALT_SIZE,
+    EXTENSION_VAULT_VERSION,
+};
+use crate::error::VaultError;
+use crate::formats::{ParsedTokens, PortableToken};
+use crate::import::{Confidence, ImportCandidate, ImportSecret, Importer, SniffedFile};
+use crate::token::NewToken;
+
+#[cfg(feature = "sqlite")]
+use {
+    crate::db::Vault,
+    crate::import::{ImportMode, ImportReport},
+};
+
+/// Top-level `chrome.storage.local` document.
+#[derive(Serialize, Deserialize)]
+struct ExtensionStorage {
+    vault: ExtensionVault,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ExtensionVault {
+    version: u32,
+    salt: String,
+    kdf_params: ExtensionKdfParams,
+    tokens_encrypted: String,
+}
+
+/// The `kdf_params` object, spelled the way Web Crypto's `deriveKey` takes it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtensionKdfParams {
+    pub name: String,
+    pub hash: String,
+    pub iterations: u32,
+}
+
+impl Default for ExtensionKdfParams {
+    fn default() -> Self {
+        Self {
+            name: EXTENSION_KDF_NAME.to_string(),
+            hash: EXTENSION_KDF_HASH.to_string(),
+            iterations: PBKDF2_DEFAULT_ITERATIONS,
+        }
+    }
+}
+
+impl ExtensionKdfParams {
+    fn validate(&self) -> Result<(), VaultError> {
+        if self.name != EXTENSION_KDF_NAME || self.hash != EXTENSION_KDF_HASH {
+            return Err(VaultError::UnsupportedFormat(format!(
+                "KDF {}/{}",
+                self.name, self.hash
+            )));
+        }
+        if self.iterations == 0 || self.iterations > EXTENSION_MAX_ITERATIONS {
+            return Err(VaultError::UnsupportedFormat(format!(
+                "{} PBKDF2 iterations",
+                self.iterations
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// One token inside `tokens_encrypted`. Fields the vault does not store
+/// (`lastModified`, sync metadata) are ignored on read.
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionToken {
+    pub id: String,
+    pub issuer: String,
+    pub account: String,
+    /// Base32 (RFC 4648) secret.
+    pub secret: String,
+    pub algorithm: String,
+    pub digits: u32,
+    #[serde(rename = "type")]
+    pub token_type: String,
+    pub period: u32,
+    pub counter: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub sort_order: i32,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+impl ExtensionToken {
+    /// Convert to a [`NewToken`], decoding and validating the secret and
+    /// code-generation parameters.
+    pub fn to_new_token(&self) -> Result<NewToken, String> {
+        let mut normalized: String = self
+            .secret
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '=')
+            .collect::<String>()
+            .to_uppercase();
+        let secret = base32::decode(base32::Alphabet::Rfc4648 { padding: false }, &normalized);
+        normalized.zeroize();
+
+        let token = NewToken {
+            issuer: self.issuer.clone(),
+            account: self.account.clone(),
+            secret: secret.ok_or(VaultError::InvalidBase32Secret)?,
+            algorithm: self.algorithm.to_uppercase(),
+            digits: self.digits,
+            token_type: self.token_type.to_lowercase(),
+            period: self.period,
+            counter: self.counter,
+            icon: self.icon.clone(),
+        };
+        if let Err(e) = token.spec() {
+            return Err(VaultError::InvalidToken(e).into());
+        }
+        Ok(token)
+    }
+}
+
+/// Decrypt an extension vault document and return its tokens in the
+/// order they were stored.
+pub fn read_extension_vault(data: &[u8], password: &[u8]) -> Result<Vec<ExtensionToken>, String> {
+    let storage: ExtensionStorage =
+        serde_json::from_slice(data).map_err(|e| VaultError::Serialization(e.to_string()))?;
+    let vault = storage.vault;
+    if vault.version != EXTENSION_VAULT_VERSION {
+        return Err(VaultError::UnsupportedFormat(format!("version {}", vault.version)).into());
+    }
+    vault.kdf_params.validate()?;
+
+    let salt = BASE64
+        .decode(&vault.salt)
+        .map_err(|_| VaultError::InvalidExportFile)?;
+    if salt.len() < EXTENSION_MIN_SALT_SIZE {
+        return Err(VaultError::InvalidExportFile.into());
+    }
+    let encrypted = BASE64
+        .decode(&vault.tokens_encrypted)
+        .map_err(|_| VaultError::InvalidExportFile)?;
+
+    let key = kdf::derive_key_pbkdf2_locked(password, &salt, vault.kdf_params.iterations)?;
+    let json = keyforge_crypto::aead::decrypt_locked(&encrypted, key.expose())
+        .map_err(|_| VaultError::WrongImportPassword)?;
+    serde_json::from_slice(json.as_slice())
+        .map_err(|e| VaultError::Serialization(e.to_string()).into())
+}
+
+/// Decrypt an extension vault document and decode its tokens in stored
+/// order. Entries that cannot be stored are skipped, keeping their index
+/// in the document.
+pub fn parse_extension_vault(data: &[u8], password: &[u8]) -> Result<ParsedTokens, String> {
+    let mut tokens: Vec<(usize, ExtensionToken)> = read_extension_vault(data, password)?
+        .into_iter()
+        .enumerate()
+        .collect();
+    tokens.sort_by_key(|(_, token)| token.sort_order);
+
+    let mut parsed = ParsedTokens::default();
+    for (index, token) in &tokens {
+        match token.to_new_token() {
+            Ok(new) => parsed.add(*index, PortableToken::from(new)),
+            Err(reason) => parsed.skip(
+                *index,
+                format!("{}:{}", token.issuer, token.account),
+                reason,
+            ),
+        }
+    }
+    Ok(parsed)
+}
+
+/// Encrypt `tokens` into an extension vault document.
+pub fn write_extension_vault(
+    tokens: &[ExtensionToken],
+    password: &[u8],
+    params: &ExtensionKdfParams,
+) -> Result<Vec<u8>, String> {
+    params.validate()?;
+    let salt = keyforge_crypto::random::generate_salt();
+    let key = kdf::derive_key_pbkdf2_locked(password, &salt, params.iterations)?;
+
+    let mut json =
+        serde_json::to_vec(tokens).map_err(|e| VaultError::Serialization(e.to_string()))?;
+    let encrypted = keyforge_crypto::aead::encrypt(&json, key.expose());
+    json.zeroize();
+
+    let storage = ExtensionStorage {
+        vault: ExtensionVault {
+            version: EXTENSION_VAULT_VERSION,
+            salt: BASE64.encode(salt),
+            kdf_params: params.clone(),
+            tokens_encrypted: BASE64.encode(encrypted?),
+        },
+    };
+    serde_json::to_vec(&storage).map_err(|e| VaultError::Serialization(e.to_string()).into())
+}
+
+/// Browser extension vault documents.
+pub struct ExtensionImporter;
+
+impl Importer for ExtensionImporter {
+    fn id(&self) -> &'static str {
+        "extension"
+    }
+
+    fn name(&self) -> &'static str {
+        "KeyForge browser extension"
+    }
+
+    fn sniff(&self, file: &SniffedFile<'_>) -> Confidence {
+        let tokens = file
+            .json
+            .as_ref()
+            .and_then(|json| json.get("vault")?.get("tokens_encrypted"));
+        if tokens.is_some() {
+            Confidence::Likely
+        } else {
+            Confidence::No
+        }
+    }
+
+    fn is_encrypted(&self, _data: &[u8]) -> bool {
+        true
+    }
+
+    fn parse(
+        &self,
+        data: &[u8],
+        secret: Option<ImportSecret<'_>>,
+    ) -> Result<Vec<ImportCandidate>, String> {
+        let password = ImportSecret::password_only(secret, self.name())?
+            .ok_or(VaultError::ImportPasswordRequired(self.name().into()))?;
+        Ok(parse_extension_vault(data, password)?.into_candidates())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl Vault {
+    /// Import every token from a browser extension vault in stored order.
+    ///
+    /// All tokens are decoded and validated before the first insert, then
+    /// added in one transaction.
+    pub fn import_extension_vault(
+        &self,
+        data: &[u8],
+        password: &[u8],
+        mode: ImportMode,
+    ) -> Result<ImportReport, String> {
+        let parsed = parse_extension_vault(data, password)?;
+        self.import_parsed(parsed, mode)
+    }
+
+    /// Export the vault as a browser extension vault document keyed by
+    /// PBKDF2 with [`PBKDF2_DEFAULT_ITERATIONS`].
+    pub fn export_extension_vault(&self, password: &[u8]) -> Result<Vec<u8>, String> {
+        let mut tokens = Vec::new();
+        for token in self.list_tokens()? {
+            let mut secret = self.get_token_secret(&token.id)?;
+            let secret_b32 = base32::encode(base32::Alphabet::Rfc4648 { padding: false }, &secret);
+            secret.zeroize();
+            tokens.push(ExtensionToken {
+                id: token.id.clone(),
+                issuer: token.issuer.clone(),
+                account: token.account.clone(),
+                secret: secret_b32,
+                algorithm: token.algorithm.clone(),
+                digits: token.digits,
+                token_type: token.token_type.clone(),
+                period: token.period,
+                counter: token.counter,
+                icon: token.icon.clone(),
+                sort_order: token.sort_order,
+                created_at: token.created_at.clone(),
+                updated_at: token.updated_at.clone(),
+            });
+        }
+        write_extension_vault(&tokens, password, &ExtensionKdfParams::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fast_params() -> ExtensionKdfParams {
+        ExtensionKdfParams {
+            iterations: 10,
+            ..ExtensionKdfParams::default()
+        }
+    }
+
+    fn token(id: &str, secret: &str, sort_order: i32) -> ExtensionToken {
+        ExtensionToken {
+            id: id.to_string(),
+            issuer: "GitHub".to_string(),
+            account: "user@example.com".to_string(),
+            secret: secret.to_string(),
+            algorithm: "SHA1".to_string(),
+            digits: 6,
+            token_type: "totp".to_string(),
+            period: 30,
+            counter: 0,
+            icon: None,
+            sort_order,
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            updated_at: "2026-01-01T00:00:00Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let tokens = vec![token("a", "GEZDGNBVGY3TQOJQ", 0), token("b", "JBSWY3DP", 1)];
+        let data = write_extension_vault(&tokens, b"pw", &fast_params()).unwrap();
+
+        let read = read_extension_vault(&data, b"pw").unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[0].id, "a");
+        assert_eq!(read[1].secret, "JBSWY3DP");
+        assert_eq!(read[0].to_new_token().unwrap().secret, b"1234567890");
+    }
+
+    #[test]
+    fn test_document_shape() {
+        let data = write_extension_vault(&[], b"pw", &fast_params()).unwrap();
+        let doc: serde_json::Value = serde_json::from_slice(&data).unwrap();
+        let vault = &doc["vault"];
+        assert_eq!(vault["version"], 1);
+        assert_eq!(vault["kdf_params"]["name"], "PBKDF2");
+        assert_eq!(vault["kdf_params"]["hash"], "SHA-256");
+        assert_eq!(vault["kdf_params"]["iterations"], 10);
+        assert_eq!(
+            BASE64
+                .decode(vault["salt"].as_str().unwrap())
+                .unwrap()
+                .len(),
+            16
+        );
+    }
+
+    #[test]
+    fn test_reads_web_crypto_document() {
+        // Produced with Web Crypto-equivalent primitives (Python
+        // `cryptography`): PBKDF2-HMAC-SHA256, 1000 iterations, AES-GCM.
+        let data = br#"{"vault":{"version":1,"salt":"AAECAwQFBgcICQoLDA0ODw==","kdf_params":{"name":"PBKDF2","hash":"SHA-256","iterations":1000},"tokens_encrypted":"EBESExQVFhcYGRob0vyVzLdKD+90Qu/YSl4kDVYjGXI8DjTqjLe1g/PoiUeTamGTuQo6tQLQ/g6XUJQ3WQosObzaiM79ubsThS0c5o4ZhRnoUjR5YD4yXoWArJz34/nfscC5RTp5qxKJCV+ziw61gkbQe4PfsZmSmraYKlf6Is61F/EiRnR89GHAsx0zpawi4sDVdOvfJ3t3K5aYMMxXHoLkp394+x++rKVdw48K2QXBBYzTqo2r3aAy3QyRm1KPJYIsVHCoMl7WQec691iMZ+zLBL10LBpNuYNy006vvL/rEjj8Y11kZgmCFdSWsGLx6GFE3ffwfOEp3Z8uMCix420RhYerQgisnYQpDFWoQfh+D9JgS/rirIVJQpP+yMXRyJBuflj3oxCZ7uQmR1vVW9nxYo+UMwJvga4ePtNVNwkB1cniaYxoBpmbaozGbykSHq47S+7EGuSnumhA2dMXL/mLRV6DU3INtG02UYa+tWg="}}"#;
+        let tokens = read_extension_vault(data, b"hunter2").unwrap();
+        assert_eq!(tokens.len(), 1);
+        let new_token = tokens[0].to_new_token().unwrap();
+        assert_eq!(new_token.issuer, "GitHub");
+        assert_eq!(new_token.secret, b"12345678901234567890");
+        assert_eq!(new_token.digits, 8);
+    }
+
+    #[test]
+    fn test_rejects_wrong_password_and_foreign_params() {
+        let data =
+            write_extension_vault(&[token("a", "JBSWY3DP", 0)], b"pw", &fast_params()).unwrap();
+        assert_eq!(
+            read_extension_vault(&data, b"other").err().unwrap(),
+            VaultError::WrongImportPassword.to_string()
+        );
+        assert!(read_extension_vault(b"{}", b"pw").is_err());
+
+        let mut doc: serde_json::Value = serde_json::from_slice(&data).unwrap();
+        doc["vault"]["kdf_params"]["hash"] = "SHA-1".into();
+        let err = read_extension_vault(&serde_json::to_vec(&doc).unwrap(), b"pw")
+            .err()
+            .unwrap();
+        assert!(err.contains("Unsupported"), "{err}");
+
+        doc["vault"]["kdf_params"]["hash"] = "SHA-256".into();
+        doc["vault"]["kdf_params"]["iterations"] = u32::MAX.into();
+        assert!(read_extension_vault(&serde_json::to_vec(&doc).unwrap(), b"pw").is_err());
+
+        doc["vault"]["kdf_params"]["iterations"] = 10.into();
+        doc["vault"]["version"] = 2.into();
+        assert!(read_extension_vault(&serde_json::to_vec(&doc).unwrap(), b"pw").is_err());
+    }
+
+    #[test]
+    fn test_to_new_token_validates() {
+        assert_eq!(
+            token("a", "jbsw y3dp====", 0)
+                .to_new_token()
+                .unwrap()
+                .secret,
+            b"Hello"
+        );
+        assert!(token("a", "not base32!", 0).to_new_token().is_err());
+
+        let mut bad = token("a", "JBSWY3DP", 0);
+        bad.digits = 42;
+        assert!(bad.to_new_token().is_err());
+    }
+}
@@ -0,0 +1,782 @@
+//! Aegis Authenticator import and export
+//!
+//! Reads and writes Aegis JSON exports, plain or encrypted:
+//!
+//! ```text
+//! {"version": 1,
+//!  "header": {"slots": [...] | null, "params": {"nonce": "<hex>", "tag": "<hex>"} | null},
+//!  "db": {"version": 3, "entries": [...], "groups": [...]} | "<base64 ciphertext>"}
+//! ```
+//!
+//! An encrypted export stores a random master key wrapped once per slot.
+//! Password slots (type 1) wrap it with AES-256-GCM under
+//! scrypt(password, salt, n, r, p); the master key then decrypts `db` with
+//! the header's nonce and tag. Biometric and raw slots cannot be opened
+//! outside Aegis and are ignored. Exports carry a single password slot.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use keyforge_crypto::aead::{NONCE_SIZE, TAG_SIZE};
+use keyforge_crypto::kdf::{self, KEY_LENGTH};
+use keyforge_crypto::secure_mem::{LockedBuffer, LockedKey};
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use uuid::Uuid;
+use zeroize::{Zeroize, Zeroizing};
+
+use super::{decode_hex, decode_secret, encode_hex, invalid, ParsedTokens, PortableToken};
+use crate::constants::{
+    AEGIS_DB_VERSION, AEGIS_MAX_SCRYPT_MEMORY, AEGIS_MAX_SCRYPT_P, AEGIS_SCRYPT_N, AEGIS_SCRYPT_P,
+    AEGIS_SCRYPT_R, AEGIS_VERSION, DEFAULT_ISSUER, DEFAULT_PERIOD, TOKEN_TYPE_HOTP,
+    TOKEN_TYPE_STEAM, TOKEN_TYPE_TOTP,
+};
+use crate::error::VaultError;
+use crate::import::{Confidence, ImportCandidate, ImportSecret, Importer, SniffedFile};
+use crate::token::NewToken;
+
+#[cfg(feature = "sqlite")]
+use crate::db::Vault;
+
+const NAME: &str = "Aegis";
+
+/// Slot type of a password-wrapped master key.
+const SLOT_PASSWORD: u32 = 1;
+/// Salt length Aegis uses for password slots.
+const SLOT_SALT_SIZE: usize = 32;
+/// Icon formats Aegis can display.
+const ICON_MIMES: [&str; 3] = ["image/png", "image/jpeg", "image/svg+xml"];
+
+/// The top-level document. `db` is the [`Database`] object when plain and
+/// a base64 string when encrypted.
+#[derive(Serialize, Deserialize)]
+struct AegisFile<D> {
+    version: u32,
+    header: Header,
+    db: D,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    slots: Option<Vec<Slot>>,
+    params: Option<KeyParams>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Slot {
+    #[serde(rename = "type")]
+    slot_type: u32,
+    #[serde(default)]
+    uuid: String,
+    key: String,
+    key_params: KeyParams,
+    n: Option<u64>,
+    r: Option<u32>,
+    p: Option<u32>,
+    salt: Option<String>,
+    #[serde(default)]
+    repaired: bool,
+    #[serde(default)]
+    is_backup: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeyParams {
+    nonce: String,
+    tag: String,
+}
+
+/// Entries are read one at a time (left as raw JSON until then) so a
+/// malformed entry can be skipped, and written typed.
+#[derive(Serialize, Deserialize)]
+struct Database<E> {
+    version: u32,
+    entries: Vec<E>,
+    #[serde(default)]
+    groups: Vec<Group>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Group {
+    uuid: String,
+    name: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    #[serde(rename = "type")]
+    entry_type: String,
+    #[serde(default)]
+    uuid: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    issuer: String,
+    #[serde(default)]
+    note: Option<String>,
+    #[serde(default)]
+    favorite: bool,
+    #[serde(default)]
+    icon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    icon_mime: Option<String>,
+    info: Info,
+    /// Group UUIDs (database version 3).
+    #[serde(default)]
+    groups: Vec<String>,
+    /// Group name (database version 2 and earlier).
+    #[serde(default, skip_serializing)]
+    group: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Info {
+    secret: String,
+    #[serde(default = "default_algo")]
+    algo: String,
+    digits: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    period: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    counter: Option<u64>,
+}
+
+impl Drop for Info {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+/// What a malformed entry is reported as.
+#[derive(Deserialize)]
+struct EntryName {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    issuer: String,
+}
+
+fn default_algo() -> String {
+    "SHA1".to_string()
+}
+
+/// scrypt cost of an Aegis password slot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AegisScryptParams {
+    pub n: u64,
+    pub r: u32,
+    pub p: u32,
+}
+
+impl Default for AegisScryptParams {
+    fn default() -> Self {
+        Self {
+            n: AEGIS_SCRYPT_N,
+            r: AEGIS_SCRYPT_R,
+            p: AEGIS_SCRYPT_P,
+        }
+    }
+}
+
+impl AegisScryptParams {
+    /// `n` must be a power of two, and memory (`128 · r · n` bytes) and
+    /// parallelism stay within what a file may demand of this device.
+    pub fn validate(&self) -> Result<(), String> {
+        let Self { n, r, p } = *self;
+        if !n.is_power_of_two()
+            || n < 2
+            || r == 0
+            || p == 0
+            || p > AEGIS_MAX_SCRYPT_P
+            || n.saturating_mul(u64::from(r)).saturating_mul(128) > AEGIS_MAX_SCRYPT_MEMORY
+        {
+            return Err(VaultError::UnsupportedFormat(format!(
+                "Aegis scrypt parameters n={n}, r={r}, p={p}"
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    fn derive(&self, password: &[u8], salt: &[u8]) -> Result<LockedKey, String> {
+        kdf::derive_key_scrypt_locked(password, salt, self.n.ilog2() as u8, self.r, self.p)
+    }
+}
+
+/// Whether an Aegis export is password-protected.
+fn is_encrypted(data: &[u8]) -> Result<bool, String> {
+    let file = parse_file(data)?;
+    Ok(file.header.slots.is_some())
+}
+
+/// Decode an Aegis export. `password` is required for encrypted exports
+/// and ignored for plain ones.
+///
+/// Entries KeyForge cannot hold (mOTP, Yandex, malformed secrets) are
+/// skipped and reported. A password that opens no slot fails with
+/// [`VaultError::WrongImportPassword`].
+pub fn read_aegis(data: &[u8], password: Option<&[u8]>) -> Result<ParsedTokens, String> {
+    let file = parse_file(data)?;
+    let plaintext;
+    let db = match (&file.header.slots, file.db.get().as_bytes().first()) {
+        (None, Some(b'{')) => file.db.get().as_bytes(),
+        (Some(slots), Some(b'"')) => {
+            let password = password.ok_or_else(|| invalid(NAME, "the export is encrypted"))?;
+            let db: String =
+                serde_json::from_str(file.db.get()).map_err(|e| invalid(NAME, &e.to_string()))?;
+            plaintext = decrypt_database(slots, file.header.params.as_ref(), &db, password)?;
+            plaintext.as_slice()
+        }
+        _ => return Err(invalid(NAME, "header and db do not agree on encryption")),
+    };
+    let database: Database<&RawValue> =
+        serde_json::from_slice(db).map_err(|e| invalid(NAME, &e.to_string()))?;
+    if database.version > AEGIS_DB_VERSION {
+        return Err(VaultError::UnsupportedFormat(format!(
+            "Aegis database version {}",
+            database.version
+        ))
+        .into());
+    }
+
+    let mut parsed = ParsedTokens::default();
+    for (index, raw) in database.entries.iter().enumerate() {
+        let entry: Entry = match serde_json::from_str(raw.get()) {
+            Ok(entry) => entry,
+            Err(e) => {
+                let label = serde_json::from_str::<EntryName>(raw.get())
+                    .map(|e| format!("{}:{}", e.issuer, e.name))
+                    .unwrap_or_else(|_| ":".to_string());
+                parsed.skip(index, label, format!("malformed entry: {e}"));
+                continue;
+            }
+        };
+        match map_entry(&entry, &database.groups) {
+            Ok(token) => parsed.add(index, token),
+            Err(reason) => parsed.skip(index, format!("{}:{}", entry.issuer, entry.name), reason),
+        }
+    }
+    Ok(parsed)
+}
+
+fn parse_file(data: &[u8]) -> Result<AegisFile<&RawValue>, String> {
+    let file: AegisFile<&RawValue> =
+        serde_json::from_slice(data).map_err(|e| invalid(NAME, &e.to_string()))?;
+    if file.version != AEGIS_VERSION {
+        return Err(
+            VaultError::UnsupportedFormat(format!("Aegis version {}", file.version)).into(),
+        );
+    }
+    Ok(file)
+}
+
+/// Unwrap the master key with the first password slot `password` opens,
+/// then decrypt the database with it.
+fn decrypt_database(
+    slots: &[Slot],
+    params: Option<&KeyParams>,
+    db: &str,
+    password: &[u8],
+) -> Result<LockedBuffer, String> {
+    let params = params.ok_or_else(|| invalid(NAME, "missing header params"))?;
+    let password_slots: Vec<&Slot> = slots
+        .iter()
+        .filter(|slot| slot.slot_type == SLOT_PASSWORD)
+        .collect();
+    if password_slots.is_empty() {
+        return Err(
+            VaultError::UnsupportedFormat("Aegis export has no password slot".to_string()).into(),
+        );
+    }
+
+    let mut master = None;
+    for slot in password_slots {
+        if let Some(key) = open_slot(slot, password)? {
+            master = Some(key);
+            break;
+        }
+    }
+    let master = master.ok_or(VaultError::WrongImportPassword)?;
+
+    let ciphertext = BASE64
+        .decode(db.trim())
+        .map_err(|_| invalid(NAME, "db is not valid base64"))?;
+    let sealed = seal_parts(params, &ciphertext)?;
+    keyforge_crypto::aead::decrypt_locked(&sealed, master.expose())
+        .map_err(|_| invalid(NAME, "database failed authentication"))
+}
+
+/// `Ok(None)` when the password does not open this slot.
+fn open_slot(slot: &Slot, password: &[u8]) -> Result<Option<LockedKey>, String> {
+    let (Some(n), Some(r), Some(p), Some(salt)) = (slot.n, slot.r, slot.p, &slot.salt) else {
+        return Err(invalid(NAME, "password slot is missing scrypt parameters"));
+    };
+    let params = AegisScryptParams { n, r, p };
+    params.validate()?;
+
+    let salt = hex(salt)?;
+    let slot_key = params.derive(password, salt.as_slice())?;
+    let wrapped = hex(&slot.key)?;
+    let sealed = seal_parts(&slot.key_params, wrapped.as_slice())?;
+    let Ok(unwrapped) = keyforge_crypto::aead::decrypt_locked(&sealed, slot_key.expose()) else {
+        return Ok(None);
+    };
+    if unwrapped.len() != KEY_LENGTH {
+        return Err(invalid(NAME, "master key has the wrong length"));
+    }
+    let mut master = LockedKey::zeroed();
+    master.expose_mut().copy_from_slice(unwrapped.as_slice());
+    Ok(Some(master))
+}
+
+/// Reassemble `nonce ‖ ciphertext ‖ tag` for [`keyforge_crypto::aead`].
+fn seal_parts(params: &KeyParams, ciphertext: &[u8]) -> Result<Vec<u8>, String> {
+    let nonce = hex(&params.nonce)?;
+    let tag = hex(&params.tag)?;
+    if nonce.len() != NONCE_SIZE || tag.len() != TAG_SIZE {
+        return Err(invalid(NAME, "bad nonce or tag length"));
+    }
+    Ok([nonce.as_slice(), ciphertext, tag.as_slice()].concat())
+}
+
+fn map_entry(entry: &Entry, groups: &[Group]) -> Result<PortableToken, String> {
+    let info = &entry.info;
+    let (token_type, period, counter) = match entry.entry_type.as_str() {
+        "totp" => (TOKEN_TYPE_TOTP, info.period.unwrap_or(DEFAULT_PERIOD), 0),
+        "hotp" => (TOKEN_TYPE_HOTP, DEFAULT_PERIOD, info.counter.unwrap_or(0)),
+        "steam" => (TOKEN_TYPE_STEAM, info.period.unwrap_or(DEFAULT_PERIOD), 0),
+        other => return Err(format!("{other} tokens are not supported")),
+    };
+    let secret = decode_secret(&info.secret)?;
+
+    let issuer = entry.issuer.trim();
+    let token = NewToken {
+        issuer: if issuer.is_empty() {
+            DEFAULT_ISSUER.to_string()
+        } else {
+            issuer.to_string()
+        },
+        account: entry.name.trim().to_string(),
+        secret,
+        algorithm: info.algo.to_ascii_uppercase(),
+        digits: info.digits,
+        token_type: token_type.to_string(),
+        period,
+        counter,
+        icon: icon_data_uri(entry),
+    };
+    let mut token = PortableToken::checked(token)?;
+
+    token.note = entry.note.clone().filter(|n| !n.trim().is_empty());
+    token.groups = match &entry.group {
+        Some(name) => vec![name.clone()],
+        None => entry
+            .groups
+            .iter()
+            .filter_map(|uuid| groups.iter().find(|g| &g.uuid == uuid))
+            .map(|g| g.name.clone())
+            .collect(),
+    };
+    Ok(token)
+}
+
+/// Aegis stores icons as base64 image data; KeyForge keeps them as a
+/// `data:` URI the UI can render directly.
+fn icon_data_uri(entry: &Entry) -> Option<String> {
+    let icon = entry.icon.as_deref().filter(|i| !i.is_empty())?;
+    let mime = entry.icon_mime.as_deref().unwrap_or("image/png");
+    if !ICON_MIMES.contains(&mime) {
+        return None;
+    }
+    BASE64.decode(icon).ok()?;
+    Some(format!("data:{mime};base64,{icon}"))
+}
+
+/// Encode `tokens` as an Aegis export, encrypted under `password` with a
+/// single scrypt password slot, or plain when `password` is `None`.
+pub fn write_aegis(
+    tokens: &[PortableToken],
+    password: Option<&[u8]>,
+    params: &AegisScryptParams,
+) -> Result<Vec<u8>, String> {
+    params.validate()?;
+    let database = export_database(tokens)?;
+    let Some(password) = password else {
+        let file = AegisFile {
+            version: AEGIS_VERSION,
+            header: Header {
+                slots: None,
+                params: None,
+            },
+            db: &database,
+        };
+        return serde_json::to_vec(&file)
+            .map_err(|e| VaultError::Serialization(e.to_string()).into());
+    };
+
+    let mut master = LockedKey::zeroed();
+    keyforge_crypto::random::fill(master.expose_mut());
+    let salt = keyforge_crypto::random::generate_bytes(SLOT_SALT_SIZE);
+    let slot_key = params.derive(password, &salt)?;
+    let wrapped = keyforge_crypto::aead::encrypt(master.expose(), slot_key.expose())?;
+    let (key_params, wrapped_key) = split_sealed(&wrapped);
+
+    let json = Zeroizing::new(
+        serde_json::to_vec(&database).map_err(|e| VaultError::Serialization(e.to_string()))?,
+    );
+    let sealed = keyforge_crypto::aead::encrypt(&json, master.expose())?;
+    let (db_params, ciphertext) = split_sealed(&sealed);
+
+    let file = AegisFile {
+        version: AEGIS_VERSION,
+        header: Header {
+            slots: Some(vec![Slot {
+                slot_type: SLOT_PASSWORD,
+                uuid: Uuid::new_v4().to_string(),
+                key: encode_hex(wrapped_key),
+                key_params,
+                n: Some(params.n),
+                r: Some(params.r),
+                p: Some(params.p),
+                salt: Some(encode_hex(&salt)),
+                repaired: true,
+                is_backup: false,
+            }]),
+            params: Some(db_params),
+        },
+        db: BASE64.encode(ciphertext),
+    };
+    serde_json::to_vec(&file).map_err(|e| VaultError::Serialization(e.to_string()).into())
+}
+
+fn export_database(tokens: &[PortableToken]) -> Result<Database<Entry>, String> {
+    let mut groups: Vec<Group> = Vec::new();
+    let mut entries = Vec::with_capacity(tokens.len());
+    for portable in tokens {
+        let token = &portable.token;
+        let (entry_type, period, counter) = match token.token_type.as_str() {
+            TOKEN_TYPE_TOTP => ("totp", Some(token.period), None),
+            TOKEN_TYPE_HOTP => ("hotp", None, Some(token.counter)),
+            TOKEN_TYPE_STEAM => ("steam", Some(token.period), None),
+            other => {
+                return Err(VaultError::UnknownTokenType(other.to_string()).into());
+            }
+        };
+
+        let mut group_ids = Vec::new();
+        for name in &portable.groups {
+            let uuid = match groups.iter().find(|g| &g.name == name) {
+                Some(group) => group.uuid.clone(),
+                None => {
+                    let uuid = Uuid::new_v4().to_string();
+                    groups.push(Group {
+                        uuid: uuid.clone(),
+                        name: name.clone(),
+                    });
+                    uuid
+                }
+            };
+            group_ids.push(uuid);
+        }
+
+        let (icon_mime, icon) = split_data_uri(token.icon.as_deref()).unzip();
+        entries.push(Entry {
+            entry_type: entry_type.to_string(),
+            uuid: Uuid::new_v4().to_string(),
+            name: token.account.clone(),
+            issuer: if token.issuer == DEFAULT_ISSUER {
+                String::new()
+            } else {
+                token.issuer.clone()
+            },
+            note: Some(portable.note.clone().unwrap_or_default()),
+            favorite: false,
+            icon,
+            icon_mime,
+            info: Info {
+                secret: base32::encode(base32::Alphabet::Rfc4648 { padding: false }, &token.secret),
+                algo: token.algorithm.to_ascii_uppercase(),
+                digits: token.digits,
+                period,
+                counter,
+            },
+            groups: group_ids,
+            group: None,
+        });
+    }
+    Ok(Database {
+        version: AEGIS_DB_VERSION,
+        entries,
+        groups,
+    })
+}
+
+/// `(mime, base64 data)` of a `data:` URI icon Aegis can display.
+fn split_data_uri(icon: Option<&str>) -> Option<(String, String)> {
+    let (mime, data) = icon?.strip_prefix("data:")?.split_once(";base64,")?;
+    ICON_MIMES
+        .contains(&mime)
+        .then(|| (mime.to_string(), data.to_string()))
+}
+
+/// Split [`keyforge_crypto::aead`] output into Aegis's separate hex nonce
+/// and tag around the ciphertext.
+fn split_sealed(sealed: &[u8]) -> (KeyParams, &[u8]) {
+    let (nonce, rest) = sealed.split_at(NONCE_SIZE);
+    let (ciphertext, tag) = rest.split_at(rest.len() - TAG_SIZE);
+    let params = KeyParams {
+        nonce: encode_hex(nonce),
+        tag: encode_hex(tag),
+    };
+    (params, ciphertext)
+}
+
+fn hex(text: &str) -> Result<LockedBuffer, String> {
+    decode_hex(text.as_bytes()).ok_or_else(|| invalid(NAME, "invalid hex"))
+}
+
+/// Aegis JSON exports, plain or encrypted.
+pub struct AegisImporter;
+
+impl Importer for AegisImporter {
+    fn id(&self) -> &'static str {
+        "aegis"
+    }
+
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn sniff(&self, file: &SniffedFile<'_>) -> Confidence {
+        match &file.json {
+            Some(json) if json.get("header").is_some() && json.get("db").is_some() => {
+                Confidence::Likely
+            }
+            _ => Confidence::No,
+        }
+    }
+
+    fn is_encrypted(&self, data: &[u8]) -> bool {
+        is_encrypted(data).unwrap_or(false)
+    }
+
+    fn parse(
+        &self,
+        data: &[u8],
+        secret: Option<ImportSecret<'_>>,
+    ) -> Result<Vec<ImportCandidate>, String> {
+        let password = ImportSecret::password_only(secret, NAME)?;
+        Ok(read_aegis(data, password)?.into_candidates())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl Vault {
+    /// Export the vault as an Aegis export with notes, groups and icons,
+    /// encrypted under `password` or plain when it is `None`.
+    pub fn export_aegis(&self, password: Option<&[u8]>) -> Result<Vec<u8>, String> {
+        let mut tokens = ParsedTokens::default();
+        for token in self.list_tokens()? {
+            let mut portable = PortableToken::from(self.export_token(&token)?);
+            portable.note = self.token_note(&token.id)?;
+            portable.groups = self.token_groups(&token.id)?;
+            tokens.tokens.push(portable);
+        }
+        write_aegis(&tokens.tokens, password, &AegisScryptParams::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAIN: &str = include_str!("../../tests/fixtures/aegis_plain.json");
+    const ENCRYPTED: &str = include_str!("../../tests/fixtures/aegis_encrypted.json");
+
+    #[test]
+    fn test_plain_export() {
+        assert!(!is_encrypted(PLAIN.as_bytes()).unwrap());
+        let parsed = read_aegis(PLAIN.as_bytes(), None).unwrap();
+        let tokens = &parsed.tokens;
+        assert_eq!(tokens.len(), 3);
+
+        let totp = &tokens[0];
+        assert_eq!(totp.token.issuer, "GitHub");
+        assert_eq!(totp.token.account, "alice@example.com");
+        assert_eq!(totp.token.secret, b"12345678901234567890");
+        assert_eq!(totp.token.algorithm, "SHA256");
+        assert_eq!((totp.token.digits, totp.token.period), (8, 60));
+        assert_eq!(totp.note.as_deref(), Some("Recovery codes in the safe"));
+        assert_eq!(totp.groups, ["Work"]);
+        assert_eq!(
+            totp.token.icon.as_deref(),
+            Some("data:image/svg+xml;base64,PHN2Zy8+")
+        );
+
+        let hotp = &tokens[1];
+        assert_eq!(hotp.token.token_type, "hotp");
+        assert_eq!(hotp.token.counter, 7);
+        assert_eq!(hotp.token.issuer, "Unknown");
+        assert_eq!(hotp.groups, ["Personal", "Work"]);
+        assert!(hotp.note.is_none());
+
+        let steam = &tokens[2];
+        assert_eq!(steam.token.token_type, "steam");
+        assert_eq!(steam.token.digits, 5);
+        assert!(steam.token.spec().is_ok());
+
+        assert_eq!(
+            parsed.skipped_labels(),
+            [(3, "Yandex:bob"), (4, "Broken:carol")]
+        );
+        assert!(parsed.skipped[0].reason.contains("yandex"));
+    }
+
+    #[test]
+    fn test_any_password_slot_opens_the_export() {
+        assert!(is_encrypted(ENCRYPTED.as_bytes()).unwrap());
+        let plain = read_aegis(PLAIN.as_bytes(), None).unwrap();
+        // Each of the two password slots wraps the same master key.
+        for password in [&b"test"[..], b"other"] {
+            let parsed = read_aegis(ENCRYPTED.as_bytes(), Some(password)).unwrap();
+            assert_eq!(parsed.summary(), plain.summary());
+            assert_eq!(parsed.skipped, plain.skipped);
+        }
+        let err = read_aegis(ENCRYPTED.as_bytes(), Some(b"nope"))
+            .err()
+            .unwrap();
+        assert_eq!(err, VaultError::WrongImportPassword.to_string());
+    }
+
+    #[test]
+    fn test_rejects_tampered_database_and_costly_params() {
+        let mut file: serde_json::Value = serde_json::from_str(ENCRYPTED).unwrap();
+        file["header"]["params"]["tag"] = "00".repeat(16).into();
+        let data = serde_json::to_vec(&file).unwrap();
+        let err = read_aegis(&data, Some(b"test")).err().unwrap();
+        assert!(err.contains("authentication"));
+
+        let mut file: serde_json::Value = serde_json::from_str(ENCRYPTED).unwrap();
+        file["header"]["slots"][1]["n"] = (1u64 << 62).into();
+        let data = serde_json::to_vec(&file).unwrap();
+        let err = read_aegis(&data, Some(b"test")).err().unwrap();
+        assert!(err.contains("scrypt"));
+    }
+
+    #[test]
+    fn test_version_2_group_names() {
+        let data = r#"{"version": 1, "header": {"slots": null, "params": null},
+            "db": {"version": 2, "entries": [{"type": "totp", "name": "dave",
+                "issuer": "Acme", "group": "Banking", "note": "  ",
+                "info": {"secret": "GEZDGNBV", "algo": "SHA1", "digits": 6, "period": 30}}]}}"#;
+        let parsed = read_aegis(data.as_bytes(), None).unwrap();
+        assert_eq!(parsed.tokens[0].groups, ["Banking"]);
+        assert!(parsed.tokens[0].note.is_none());
+        assert!(parsed.tokens[0].token.icon.is_none());
+    }
+
+    #[test]
+    fn test_rejects_unknown_versions() {
+        let data = r#"{"version": 2, "header": {"slots": null, "params": null}, "db": {}}"#;
+        assert!(read_aegis(data.as_bytes(), None).is_err());
+        let data = r#"{"version": 1, "header": {"slots": null, "params": null},
+            "db": {"version": 4, "entries": []}}"#;
+        assert!(read_aegis(data.as_bytes(), None).is_err());
+        let data = r#"{"version": 1, "header": {"slots": null, "params": null}, "db": "AAAA"}"#;
+        assert!(read_aegis(data.as_bytes(), None).is_err());
+    }
+
+    fn fast_params() -> AegisScryptParams {
+        AegisScryptParams {
+            n: 1 << 10,
+            ..AegisScryptParams::default()
+        }
+    }
+
+    fn fixture_tokens() -> ParsedTokens {
+        read_aegis(PLAIN.as_bytes(), None).unwrap()
+    }
+
+    fn assert_same_tokens(read: &[PortableToken], written: &[PortableToken]) {
+        assert_eq!(read.len(), written.len());
+        for (read, written) in read.iter().zip(written) {
+            let (a, b) = (&read.token, &written.token);
+            assert_eq!(
+                (&a.issuer, &a.account, &a.secret, &a.algorithm),
+                (&b.issuer, &b.account, &b.secret, &b.algorithm)
+            );
+            assert_eq!(
+                (a.digits, &a.token_type, a.period, a.counter, &a.icon),
+                (b.digits, &b.token_type, b.period, b.counter, &b.icon)
+            );
+            assert_eq!((&read.note, &read.groups), (&written.note, &written.groups));
+        }
+    }
+
+    #[test]
+    fn test_export_round_trips_plain() {
+        let tokens = fixture_tokens();
+        let data = write_aegis(&tokens.tokens, None, &fast_params()).unwrap();
+        assert!(!is_encrypted(&data).unwrap());
+        let read = read_aegis(&data, None).unwrap();
+        assert!(read.skipped.is_empty());
+        assert_same_tokens(&read.tokens, &tokens.tokens);
+    }
+
+    #[test]
+    fn test_export_round_trips_encrypted() {
+        let tokens = fixture_tokens();
+        let data = write_aegis(&tokens.tokens, Some(b"export-pw"), &fast_params()).unwrap();
+        assert!(is_encrypted(&data).unwrap());
+        assert_eq!(
+            read_aegis(&data, Some(b"wrong")).err().unwrap(),
+            VaultError::WrongImportPassword.to_string()
+        );
+        let read = read_aegis(&data, Some(b"export-pw")).unwrap();
+        assert_same_tokens(&read.tokens, &tokens.tokens);
+    }
+
+    #[test]
+    fn test_export_document_shape() {
+        let tokens = fixture_tokens();
+        let data = write_aegis(&tokens.tokens, None, &fast_params()).unwrap();
+        let file: serde_json::Value = serde_json::from_slice(&data).unwrap();
+        let db = &file["db"];
+        assert_eq!(db["version"], 3);
+        assert_eq!(db["groups"].as_array().unwrap().len(), 2);
+
+        let hotp = &db["entries"][1];
+        assert_eq!(hotp["issuer"], "");
+        assert_eq!(hotp["note"], "");
+        assert_eq!(hotp["info"]["counter"], 7);
+        assert!(hotp["info"].get("period").is_none());
+        assert!(hotp.get("group").is_none());
+        assert_eq!(hotp["groups"][1], db["entries"][0]["groups"][0]);
+        let uuid = hotp["uuid"].as_str().unwrap();
+        assert_eq!((uuid.len(), &uuid[14..15]), (36, "4"));
+
+        let encrypted = write_aegis(&tokens.tokens, Some(b"pw"), &fast_params()).unwrap();
+        let file: serde_json::Value = serde_json::from_slice(&encrypted).unwrap();
+        let slot = &file["header"]["slots"][0];
+        assert_eq!(
+            (slot["type"].clone(), slot["n"].clone()),
+            (1.into(), 1024.into())
+        );
+        assert_eq!(slot["salt"].as_str().unwrap().len(), 64);
+    }
+
+    #[test]
+    fn test_export_rejects_bad_params_and_types() {
+        let tokens = fixture_tokens();
+        let params = AegisScryptParams {
+            n: 1000,
+            ..AegisScryptParams::default()
+        };
+        assert!(write_aegis(&tokens.tokens, Some(b"pw"), &params).is_err());
+
+        let token = PortableToken::from(NewToken {
+            token_type: "motp".to_string(),
+            ..fixture_tokens().tokens.remove(0).token
+        });
+        assert!(write_aegis(&[token], None, &fast_params()).is_err());
+    }
+}
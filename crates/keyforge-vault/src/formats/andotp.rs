@@ -0,0 +1,331 @@
+//! andOTP import
+//!
+//! A plain andOTP backup is a JSON array of entries. An encrypted
+//! `.json.aes` backup is
+//!
+//! ```text
+//! iterations(4, BE) ‖ salt(12) ‖ nonce(12) ‖ ciphertext ‖ tag(16)
+//! ```
+//!
+//! with the same JSON sealed by AES-256-GCM under
+//! PBKDF2-HMAC-SHA1(password, salt, iterations).
+
+use keyforge_crypto::aead::{NONCE_SIZE, TAG_SIZE};
+use keyforge_crypto::kdf;
+use keyforge_crypto::secure_mem::LockedBuffer;
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use zeroize::Zeroize;
+
+use super::{decode_secret, invalid, ParsedTokens, PortableToken};
+use crate::constants::{
+    ANDOTP_MAX_PBKDF2_ITERATIONS, ANDOTP_PBKDF2_ITERATIONS, DEFAULT_DIGITS, DEFAULT_ISSUER,
+    DEFAULT_PERIOD, TOKEN_TYPE_HOTP, TOKEN_TYPE_STEAM, TOKEN_TYPE_TOTP,
+};
+use crate::error::VaultError;
+use crate::import::{Confidence, ImportCandidate, ImportSecret, Importer, SniffedFile};
+use crate::token::NewToken;
+
+const NAME: &str = "andOTP";
+
+const ITERATIONS_SIZE: usize = 4;
+const SALT_SIZE: usize = 12;
+const HEADER_SIZE: usize = ITERATIONS_SIZE + SALT_SIZE;
+/// Thumbnail andOTP shows when the user picked none.
+const DEFAULT_THUMBNAIL: &str = "Default";
+
+#[derive(Deserialize)]
+struct Entry {
+    secret: String,
+    #[serde(default)]
+    issuer: String,
+    #[serde(default)]
+    label: String,
+    #[serde(rename = "type")]
+    entry_type: String,
+    digits: Option<u32>,
+    period: Option<u32>,
+    counter: Option<u64>,
+    algorithm: Option<String>,
+    thumbnail: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+impl Drop for Entry {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+/// What a malformed entry is reported as.
+#[derive(Deserialize)]
+struct EntryLabel {
+    #[serde(default)]
+    label: String,
+}
+
+/// Whether `data` is an encrypted `.json.aes` backup rather than plain JSON.
+fn is_encrypted(data: &[u8]) -> bool {
+    !data.trim_ascii_start().starts_with(b"[")
+}
+
+/// Decode an andOTP backup. `password` is required for encrypted backups
+/// and ignored for plain ones.
+///
+/// Tags become groups and a chosen thumbnail becomes the token's icon
+/// identifier. mOTP entries cannot be stored yet and are reported as
+/// skipped, keeping their index in the backup.
+pub fn read_andotp(data: &[u8], password: Option<&[u8]>) -> Result<ParsedTokens, String> {
+    let decrypted;
+    let json = if is_encrypted(data) {
+        let password = password.ok_or_else(|| invalid(NAME, "the backup is encrypted"))?;
+        decrypted = decrypt_backup(data, password)?;
+        decrypted.as_slice()
+    } else {
+        data
+    };
+    let entries: Vec<&RawValue> =
+        serde_json::from_slice(json).map_err(|e| invalid(NAME, &e.to_string()))?;
+
+    let mut parsed = ParsedTokens::default();
+    for (index, raw) in entries.iter().enumerate() {
+        let entry: Entry = match serde_json::from_str(raw.get()) {
+            Ok(entry) => entry,
+            Err(e) => {
+                let label = serde_json::from_str::<EntryLabel>(raw.get())
+                    .map(|e| e.label)
+                    .unwrap_or_default();
+                parsed.skip(index, label, format!("malformed entry: {e}"));
+                continue;
+            }
+        };
+        match map_entry(&entry) {
+            Ok(token) => parsed.add(index, token),
+            Err(reason) => {
+                let (issuer, account) = names(&entry);
+                parsed.skip(index, format!("{issuer}:{account}"), reason);
+            }
+        }
+    }
+    Ok(parsed)
+}
+
+fn decrypt_backup(data: &[u8], password: &[u8]) -> Result<LockedBuffer, String> {
+    if data.len() < HEADER_SIZE + NONCE_SIZE + TAG_SIZE {
+        return Err(invalid(NAME, "the backup is too short"));
+    }
+    let (header, sealed) = data.split_at(HEADER_SIZE);
+    let (iterations, salt) = header.split_at(ITERATIONS_SIZE);
+    let iterations = u32::from_be_bytes(iterations.try_into().unwrap());
+    if iterations > ANDOTP_MAX_PBKDF2_ITERATIONS {
+        return Err(VaultError::UnsupportedFormat(format!(
+            "andOTP key derivation cost ({iterations} iterations)"
+        ))
+        .into());
+    }
+    let key = kdf::derive_key_pbkdf2_sha1_locked(password, salt, iterations)?;
+    keyforge_crypto::aead::decrypt_locked(sealed, key.expose())
+        .map_err(|_| VaultError::WrongImportPassword.into())
+}
+
+fn map_entry(entry: &Entry) -> Result<PortableToken, String> {
+    let entry_type = entry.entry_type.to_ascii_uppercase();
+    let (token_type, digits) = match entry_type.as_str() {
+        "TOTP" => (TOKEN_TYPE_TOTP, entry.digits.unwrap_or(DEFAULT_DIGITS)),
+        "HOTP" => (TOKEN_TYPE_HOTP, entry.digits.unwrap_or(DEFAULT_DIGITS)),
+        "STEAM" => (TOKEN_TYPE_STEAM, keyforge_crypto::otp::STEAM_DIGITS),
+        _ => return Err(format!("{entry_type} tokens are not supported")),
+    };
+    let (issuer, account) = names(entry);
+    let mut token = PortableToken::checked(NewToken {
+        issuer,
+        account,
+        secret: decode_secret(&entry.secret)?,
+        algorithm: entry
+            .algorithm
+            .as_deref()
+            .unwrap_or("SHA1")
+            .to_ascii_uppercase(),
+        digits,
+        token_type: token_type.to_string(),
+        period: entry.period.unwrap_or(DEFAULT_PERIOD),
+        counter: match token_type {
+            TOKEN_TYPE_HOTP => entry.counter.unwrap_or(0),
+            _ => 0,
+        },
+        icon: entry
+            .thumbnail
+            .clone()
+            .filter(|t| !t.is_empty() && t != DEFAULT_THUMBNAIL),
+    })?;
+    token.groups = entry.tags.clone();
+    Ok(token)
+}
+
+/// Issuer and account. Backups from before andOTP had an issuer field
+/// carry both in the label as `Issuer:account`.
+fn names(entry: &Entry) -> (String, String) {
+    let label = entry.label.trim();
+    let issuer = entry.issuer.trim();
+    if !issuer.is_empty() {
+        return (issuer.to_string(), label.to_string());
+    }
+    match label.split_once(':') {
+        Some((issuer, account)) if !issuer.trim().is_empty() => {
+            (issuer.trim().to_string(), account.trim().to_string())
+        }
+        _ => (DEFAULT_ISSUER.to_string(), label.to_string()),
+    }
+}
+
+/// andOTP backups, plain JSON or encrypted `.json.aes`.
+pub struct AndOtpImporter;
+
+impl Importer for AndOtpImporter {
+    fn id(&self) -> &'static str {
+        "andotp"
+    }
+
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn sniff(&self, file: &SniffedFile<'_>) -> Confidence {
+        let data = file.data;
+        if !is_encrypted(data) {
+            let entry = file.json.as_ref().and_then(|json| json.first());
+            return match entry {
+                Some(entry) if entry.get("secret").is_some() && entry.get("type").is_some() => {
+                    Confidence::Likely
+                }
+                _ => Confidence::No,
+            };
+        }
+        // Encrypted backups have no marker, only the iteration count andOTP
+        // picks in front. Other counts still decrypt when chosen by hand.
+        if data.len() < HEADER_SIZE + NONCE_SIZE + TAG_SIZE {
+            return Confidence::No;
+        }
+        let iterations = u32::from_be_bytes(data[..ITERATIONS_SIZE].try_into().unwrap());
+        if ANDOTP_PBKDF2_ITERATIONS.contains(&iterations) {
+            Confidence::Weak
+        } else {
+            Confidence::No
+        }
+    }
+
+    fn is_encrypted(&self, data: &[u8]) -> bool {
+        is_encrypted(data)
+    }
+
+    fn parse(
+        &self,
+        data: &[u8],
+        secret: Option<ImportSecret<'_>>,
+    ) -> Result<Vec<ImportCandidate>, String> {
+        let password = ImportSecret::password_only(secret, NAME)?;
+        Ok(read_andotp(data, password)?.into_candidates())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAIN: &[u8] = include_bytes!("../../tests/fixtures/andotp_plain.json");
+    const ENCRYPTED: &[u8] = include_bytes!("../../tests/fixtures/andotp_encrypted.json.aes");
+
+    #[test]
+    fn test_plain_backup() {
+        assert!(!is_encrypted(PLAIN));
+        let parsed = read_andotp(PLAIN, None).unwrap();
+        assert_eq!(
+            parsed.summary(),
+            [
+                ("GitHub", "alice@example.com", "totp"),
+                ("Bank", "bob", "hotp"),
+                ("Steam", "gamer", "steam"),
+                ("Legacy", "erin", "totp"),
+            ]
+        );
+
+        let github = &parsed.tokens[0];
+        assert_eq!(github.token.secret, b"12345678901234567890");
+        assert_eq!(github.token.algorithm, "SHA256");
+        assert_eq!((github.token.digits, github.token.period), (8, 60));
+        assert_eq!(github.token.icon.as_deref(), Some("Github"));
+        assert_eq!(github.groups, ["Work"]);
+        let bank = &parsed.tokens[1];
+        assert_eq!((bank.token.counter, bank.token.icon.as_deref()), (5, None));
+        let steam = &parsed.tokens[2];
+        assert_eq!(steam.token.digits, 5);
+        assert_eq!(steam.groups, ["Personal", "Games"]);
+
+        assert_eq!(
+            parsed.skipped_labels(),
+            [(3, "Motp:carol"), (4, "Broken:dave")]
+        );
+        assert_eq!(parsed.skipped[0].reason, "MOTP tokens are not supported");
+        assert_eq!(parsed.skipped[1].reason, "invalid base32 secret");
+    }
+
+    #[test]
+    fn test_encrypted_backup_holds_the_plain_one() {
+        assert!(is_encrypted(ENCRYPTED));
+        let encrypted = read_andotp(ENCRYPTED, Some(b"test")).unwrap();
+        let plain = read_andotp(PLAIN, None).unwrap();
+        assert_eq!(encrypted.summary(), plain.summary());
+        assert_eq!(encrypted.skipped, plain.skipped);
+    }
+
+    #[test]
+    fn test_damaged_and_costly_backups() {
+        assert!(read_andotp(ENCRYPTED, None).is_err());
+        assert!(read_andotp(&ENCRYPTED[..40], Some(b"test")).is_err());
+
+        let mut costly = ENCRYPTED.to_vec();
+        costly[..4].copy_from_slice(&(ANDOTP_MAX_PBKDF2_ITERATIONS + 1).to_be_bytes());
+        let err = read_andotp(&costly, Some(b"test")).err().unwrap();
+        assert!(err.contains("cost"));
+    }
+
+    #[test]
+    fn test_names_from_issuer_or_label() {
+        let backup = br#"[
+            {"secret": "JBSWY3DP", "issuer": " Acme ", "label": "a:b", "type": "TOTP"},
+            {"secret": "JBSWY3DP", "label": " Old : erin ", "type": "totp"},
+            {"secret": "JBSWY3DP", "label": ":bob", "type": "TOTP"},
+            {"secret": "JBSWY3DP", "type": "TOTP"}
+        ]"#;
+        let parsed = read_andotp(backup, None).unwrap();
+        assert_eq!(
+            parsed.summary(),
+            [
+                ("Acme", "a:b", "totp"),
+                ("Old", "erin", "totp"),
+                ("Unknown", ":bob", "totp"),
+                ("Unknown", "", "totp"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_entry_defaults_and_steam_digits() {
+        let backup = br#"[
+            {"secret": "JBSWY3DP", "label": "a", "type": "HOTP", "thumbnail": ""},
+            {"secret": "JBSWY3DP", "label": "b", "type": "STEAM", "digits": 6},
+            {"label": "c", "type": "TOTP"}
+        ]"#;
+        let parsed = read_andotp(backup, None).unwrap();
+        let hotp = &parsed.tokens[0].token;
+        assert_eq!(
+            (hotp.algorithm.as_str(), hotp.digits, hotp.counter),
+            ("SHA1", 6, 0)
+        );
+        assert_eq!(hotp.icon, None);
+        assert_eq!(parsed.tokens[1].token.digits, 5);
+        assert_eq!(parsed.skipped_labels(), [(2, "c")]);
+        assert!(parsed.skipped[0].reason.starts_with("malformed entry"));
+    }
+}
@@ -0,0 +1,426 @@
+//! Authenticator Pro import
+//!
+//! A plain backup is JSON with `Authenticators`, `Categories`, the
+//! `AuthenticatorCategories` joining them (by secret) and `CustomIcons`.
+//! An encrypted backup seals the same JSON behind a 16-byte ASCII header:
+//!
+//! ```text
+//! "AUTHENTICATORPRO" ‖ salt(16) ‖ iv(12) ‖ ciphertext ‖ tag(16)
+//! "AuthenticatorPro" ‖ salt(20) ‖ iv(16) ‖ ciphertext          (legacy)
+//! ```
+//!
+//! The current format is AES-256-GCM under Argon2id (64 MiB, 3 passes, 4
+//! lanes). The legacy one is AES-256-CBC under PBKDF2-HMAC-SHA1 (64,000
+//! iterations) with no MAC, so a wrong password is only noticed when the
+//! padding or the JSON does not survive decryption.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use keyforge_crypto::aead::{NONCE_SIZE, TAG_SIZE};
+use keyforge_crypto::kdf::{self, KdfParams};
+use keyforge_crypto::secure_mem::LockedBuffer;
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use zeroize::Zeroize;
+
+use super::{decode_secret, invalid, ParsedTokens, PortableToken};
+use crate::constants::{
+    AUTHENTICATOR_PRO_ARGON2_ITERATIONS, AUTHENTICATOR_PRO_ARGON2_MEMORY_KIB,
+    AUTHENTICATOR_PRO_ARGON2_PARALLELISM, AUTHENTICATOR_PRO_LEGACY_PBKDF2_ITERATIONS,
+    DEFAULT_DIGITS, DEFAULT_ISSUER, DEFAULT_PERIOD, TOKEN_TYPE_HOTP, TOKEN_TYPE_STEAM,
+    TOKEN_TYPE_TOTP,
+};
+use crate::error::VaultError;
+use crate::import::{Confidence, ImportCandidate, ImportSecret, Importer, SniffedFile};
+use crate::token::NewToken;
+
+const NAME: &str = "Authenticator Pro";
+
+const HEADER: &[u8] = b"AUTHENTICATORPRO";
+const SALT_SIZE: usize = 16;
+const LEGACY_HEADER: &[u8] = b"AuthenticatorPro";
+const LEGACY_SALT_SIZE: usize = 20;
+const LEGACY_IV_SIZE: usize = keyforge_crypto::cbc_hmac::IV_SIZE;
+/// Prefix of an `Icon` that names an entry in `CustomIcons` rather than
+/// one of the app's built-in icons.
+const CUSTOM_ICON_PREFIX: char = '@';
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Backup<'a> {
+    #[serde(default, borrow)]
+    authenticators: Vec<&'a RawValue>,
+    #[serde(default)]
+    categories: Vec<Category>,
+    #[serde(default)]
+    authenticator_categories: Vec<AuthenticatorCategory>,
+    #[serde(default)]
+    custom_icons: Vec<CustomIcon>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Authenticator {
+    #[serde(rename = "Type")]
+    auth_type: u8,
+    #[serde(default)]
+    issuer: String,
+    username: Option<String>,
+    secret: String,
+    icon: Option<String>,
+    #[serde(default)]
+    algorithm: u8,
+    digits: Option<u32>,
+    period: Option<u32>,
+    #[serde(default)]
+    counter: u64,
+    #[serde(default)]
+    ranking: i64,
+}
+
+impl Drop for Authenticator {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+/// The issuer a malformed authenticator is reported under.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AuthenticatorIssuer {
+    #[serde(default)]
+    issuer: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Category {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AuthenticatorCategory {
+    category_id: String,
+    authenticator_secret: String,
+}
+
+impl Drop for AuthenticatorCategory {
+    fn drop(&mut self) {
+        self.authenticator_secret.zeroize();
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CustomIcon {
+    id: String,
+    data: String,
+}
+
+/// Whether `data` is an encrypted backup, in either format.
+fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(HEADER) || data.starts_with(LEGACY_HEADER)
+}
+
+/// Decode an Authenticator Pro backup. `password` is required for
+/// encrypted backups and ignored for plain ones.
+///
+/// Tokens come back in the app's custom order (`Ranking`). Categories
+/// become groups; custom icons become `data:` URIs and built-in icons keep
+/// their name. mOTP and Yandex entries cannot be stored yet and are
+/// reported as skipped, keeping their index in `Authenticators`.
+pub fn read_authenticator_pro(
+    data: &[u8],
+    password: Option<&[u8]>,
+) -> Result<ParsedTokens, String> {
+    let decrypted;
+    let backup: Backup = if is_encrypted(data) {
+        let password = password.ok_or_else(|| invalid(NAME, "the backup is encrypted"))?;
+        decrypted = decrypt_backup(data, password)?;
+        serde_json::from_slice(decrypted.as_slice()).map_err(|e| {
+            if data.starts_with(LEGACY_HEADER) {
+                VaultError::WrongImportPassword.into()
+            } else {
+                invalid(NAME, &e.to_string())
+            }
+        })?
+    } else {
+        serde_json::from_slice(data).map_err(|e| invalid(NAME, &e.to_string()))?
+    };
+
+    let mut ordered = Vec::new();
+    let mut parsed = ParsedTokens::default();
+    for (index, raw) in backup.authenticators.iter().enumerate() {
+        let auth: Authenticator = match serde_json::from_str(raw.get()) {
+            Ok(auth) => auth,
+            Err(e) => {
+                let issuer = serde_json::from_str::<AuthenticatorIssuer>(raw.get())
+                    .map(|a| a.issuer)
+                    .unwrap_or_default();
+                parsed.skip(index, issuer, format!("malformed entry: {e}"));
+                continue;
+            }
+        };
+        match map_authenticator(&auth, &backup) {
+            Ok(mut token) => {
+                token.index = index;
+                ordered.push((auth.ranking, token));
+            }
+            Err(reason) => {
+                let (issuer, account) = names(&auth);
+                parsed.skip(index, format!("{issuer}:{account}"), reason);
+            }
+        }
+    }
+    ordered.sort_by_key(|(ranking, _)| *ranking);
+    parsed.tokens = ordered.into_iter().map(|(_, token)| token).collect();
+    Ok(parsed)
+}
+
+fn decrypt_backup(data: &[u8], password: &[u8]) -> Result<LockedBuffer, String> {
+    let (salt_size, min_sealed) = if data.starts_with(HEADER) {
+        (SALT_SIZE, NONCE_SIZE + TAG_SIZE)
+    } else {
+        // The IV and at least one (padded) block.
+        (LEGACY_SALT_SIZE, 2 * LEGACY_IV_SIZE)
+    };
+    let body = &data[HEADER.len()..];
+    if body.len() < salt_size + min_sealed {
+        return Err(invalid(NAME, "the backup is too short"));
+    }
+    let (salt, sealed) = body.split_at(salt_size);
+
+    if data.starts_with(HEADER) {
+        let params = KdfParams {
+            memory_kib: AUTHENTICATOR_PRO_ARGON2_MEMORY_KIB,
+            time_cost: AUTHENTICATOR_PRO_ARGON2_ITERATIONS,
+            parallelism: AUTHENTICATOR_PRO_ARGON2_PARALLELISM,
+        };
+        let key = kdf::derive_key_argon2id_locked(password, salt, &params)?;
+        keyforge_crypto::aead::decrypt_locked(sealed, key.expose())
+            .map_err(|_| VaultError::WrongImportPassword.into())
+    } else {
+        let key = kdf::derive_key_pbkdf2_sha1_locked(
+            password,
+            salt,
+            AUTHENTICATOR_PRO_LEGACY_PBKDF2_ITERATIONS,
+        )?;
+        let (iv, ciphertext) = sealed.split_at(LEGACY_IV_SIZE);
+        keyforge_crypto::cbc_hmac::decrypt_unauthenticated_locked(ciphertext, key.expose(), iv)
+            .map_err(|_| VaultError::WrongImportPassword.into())
+    }
+}
+
+fn map_authenticator(auth: &Authenticator, backup: &Backup) -> Result<PortableToken, String> {
+    let (token_type, digits, counter) = match auth.auth_type {
+        1 => (TOKEN_TYPE_HOTP, auth.digits, auth.counter),
+        2 => (TOKEN_TYPE_TOTP, auth.digits, 0),
+        4 => (
+            TOKEN_TYPE_STEAM,
+            Some(keyforge_crypto::otp::STEAM_DIGITS),
+            0,
+        ),
+        3 => return Err("mOTP tokens are not supported".to_string()),
+        5 => return Err("Yandex tokens are not supported".to_string()),
+        other => return Err(format!("unknown token type {other}")),
+    };
+    let algorithm = match auth.algorithm {
+        0 => "SHA1",
+        1 => "SHA256",
+        2 => "SHA512",
+        other => return Err(format!("unknown algorithm {other}")),
+    };
+
+    let (issuer, account) = names(auth);
+    let mut token = PortableToken::checked(NewToken {
+        issuer,
+        account,
+        secret: decode_secret(&auth.secret)?,
+        algorithm: algorithm.to_string(),
+        digits: digits.unwrap_or(DEFAULT_DIGITS),
+        token_type: token_type.to_string(),
+        period: auth.period.filter(|p| *p > 0).unwrap_or(DEFAULT_PERIOD),
+        counter,
+        icon: icon(auth, backup),
+    })?;
+
+    token.groups = backup
+        .categories
+        .iter()
+        .filter(|c| {
+            backup
+                .authenticator_categories
+                .iter()
+                .any(|ac| ac.category_id == c.id && ac.authenticator_secret == auth.secret)
+        })
+        .map(|c| c.name.clone())
+        .collect();
+    Ok(token)
+}
+
+/// A built-in icon's name, or a custom icon as a `data:` URI. A custom
+/// icon missing from `CustomIcons` is dropped.
+fn icon(auth: &Authenticator, backup: &Backup) -> Option<String> {
+    let icon = auth.icon.as_deref().filter(|i| !i.is_empty())?;
+    let Some(id) = icon.strip_prefix(CUSTOM_ICON_PREFIX) else {
+        return Some(icon.to_string());
+    };
+    let custom = backup.custom_icons.iter().find(|c| c.id == id)?;
+    BASE64.decode(&custom.data).ok()?;
+    Some(format!("data:image/png;base64,{}", custom.data))
+}
+
+fn names(auth: &Authenticator) -> (String, String) {
+    let issuer = match auth.issuer.trim() {
+        "" => DEFAULT_ISSUER,
+        issuer => issuer,
+    };
+    let account = auth.username.as_deref().unwrap_or("").trim();
+    (issuer.to_string(), account.to_string())
+}
+
+/// Authenticator Pro backups, plain or encrypted.
+pub struct AuthenticatorProImporter;
+
+impl Importer for AuthenticatorProImporter {
+    fn id(&self) -> &'static str {
+        "authenticator_pro"
+    }
+
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn sniff(&self, file: &SniffedFile<'_>) -> Confidence {
+        if is_encrypted(file.data) {
+            return Confidence::Certain;
+        }
+        match &file.json {
+            Some(json) if json.get("Authenticators").is_some() => Confidence::Likely,
+            _ => Confidence::No,
+        }
+    }
+
+    fn is_encrypted(&self, data: &[u8]) -> bool {
+        is_encrypted(data)
+    }
+
+    fn parse(
+        &self,
+        data: &[u8],
+        secret: Option<ImportSecret<'_>>,
+    ) -> Result<Vec<ImportCandidate>, String> {
+        let password = ImportSecret::password_only(secret, NAME)?;
+        Ok(read_authenticator_pro(data, password)?.into_candidates())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAIN: &[u8] = include_bytes!("../../tests/fixtures/authenticator_pro_plain.json");
+    const ENCRYPTED: &[u8] = include_bytes!("../../tests/fixtures/authenticator_pro_encrypted.bin");
+    const LEGACY: &[u8] = include_bytes!("../../tests/fixtures/authenticator_pro_legacy.bin");
+
+    #[test]
+    fn test_plain_backup() {
+        assert!(!is_encrypted(PLAIN));
+        let parsed = read_authenticator_pro(PLAIN, None).unwrap();
+        let summary = parsed.summary();
+        // Ranking order, not file order.
+        assert_eq!(
+            summary,
+            [
+                ("Bank", "bob", "hotp"),
+                ("GitHub", "alice@example.com", "totp"),
+                ("Steam", "gamer", "steam"),
+            ]
+        );
+
+        let bank = &parsed.tokens[0];
+        assert_eq!(bank.token.counter, 5);
+        assert!(bank
+            .token
+            .icon
+            .as_deref()
+            .unwrap()
+            .starts_with("data:image/png;base64,iVBORw0KGgo"));
+        assert!(bank.groups.is_empty());
+        let github = &parsed.tokens[1];
+        assert_eq!(github.token.secret, b"12345678901234567890");
+        assert_eq!(github.token.algorithm, "SHA256");
+        assert_eq!((github.token.digits, github.token.period), (8, 60));
+        assert_eq!(github.token.icon.as_deref(), Some("github"));
+        assert_eq!(github.groups, ["Work"]);
+        let steam = &parsed.tokens[2];
+        assert_eq!(steam.token.digits, 5);
+        assert_eq!(steam.groups, ["Work", "Games"]);
+
+        assert_eq!(
+            parsed.skipped_labels(),
+            [(3, "Motp:carol"), (4, "Broken:dave")]
+        );
+        assert_eq!(parsed.skipped[0].reason, "mOTP tokens are not supported");
+        assert_eq!(parsed.skipped[1].reason, "invalid base32 secret");
+    }
+
+    #[test]
+    fn test_encrypted_backups_hold_the_plain_one() {
+        let plain = read_authenticator_pro(PLAIN, None).unwrap();
+        for data in [ENCRYPTED, LEGACY] {
+            assert!(is_encrypted(data));
+            let parsed = read_authenticator_pro(data, Some(b"test")).unwrap();
+            assert_eq!(parsed.summary(), plain.summary());
+            assert_eq!(parsed.skipped, plain.skipped);
+        }
+    }
+
+    #[test]
+    fn test_wrong_password_and_damage() {
+        // The legacy format has no MAC: a wrong key shows up as garbage
+        // that is not JSON, which is still reported as a wrong password.
+        let err = read_authenticator_pro(LEGACY, Some(b"nope")).err().unwrap();
+        assert_eq!(err, VaultError::WrongImportPassword.to_string());
+        for data in [ENCRYPTED, LEGACY] {
+            assert!(read_authenticator_pro(data, None).is_err());
+            assert!(read_authenticator_pro(&data[..40], Some(b"test")).is_err());
+        }
+        assert!(read_authenticator_pro(b"not json", None).is_err());
+    }
+
+    #[test]
+    fn test_icons_and_defaults() {
+        let backup = br#"{
+            "Authenticators": [
+                {"Type": 2, "Issuer": "", "Secret": "JBSWY3DP", "Icon": "@missing", "Period": 0},
+                {"Type": 2, "Issuer": "B", "Secret": "JBSWY3DP", "Icon": "@bad"},
+                {"Type": 2, "Issuer": "C", "Secret": "JBSWY3DP", "Icon": ""},
+                {"Type": 2, "Issuer": "D", "Secret": "JBSWY3DP", "Algorithm": 9},
+                {"Type": 5, "Issuer": "E", "Secret": "JBSWY3DP"},
+                {"Issuer": "F", "Secret": "JBSWY3DP"}
+            ],
+            "CustomIcons": [{"Id": "bad", "Data": "not base64!"}]
+        }"#;
+        let parsed = read_authenticator_pro(backup, None).unwrap();
+        assert_eq!(
+            parsed.summary(),
+            [
+                ("Unknown", "", "totp"),
+                ("B", "", "totp"),
+                ("C", "", "totp")
+            ]
+        );
+        for token in &parsed.tokens {
+            assert_eq!(token.token.icon, None);
+        }
+        let first = &parsed.tokens[0].token;
+        assert_eq!((first.digits, first.period), (6, 30));
+        assert_eq!(parsed.skipped_labels(), [(3, "D:"), (4, "E:"), (5, "F")]);
+        assert_eq!(parsed.skipped[0].reason, "unknown algorithm 9");
+        assert_eq!(parsed.skipped[1].reason, "Yandex tokens are not supported");
+        assert!(parsed.skipped[2].reason.starts_with("malformed entry"));
+    }
+}
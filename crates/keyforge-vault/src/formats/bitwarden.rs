@@ -0,0 +1,501 @@
+//! Bitwarden / Vaultwarden import
+//!
+//! Reads the TOTP seeds out of Bitwarden's JSON exports. A plain export
+//! lists `items`; a password-protected one holds the same document in
+//! `data` as a type 2 encrypted string, `2.<iv>|<ciphertext>|<mac>` (AES-256-CBC
+//! + HMAC-SHA256 under keys stretched from the password-derived key):
+//!
+//! ```text
+//! {"encrypted": true, "passwordProtected": true, "salt": "<string>",
+//!  "kdfType": 0 | 1, "kdfIterations": n, "kdfMemory": MiB, "kdfParallelism": p,
+//!  "encKeyValidation_DO_NOT_EDIT": "2.…", "data": "2.…"}
+//! ```
+//!
+//! The key is PBKDF2-HMAC-SHA256 over the salt string (`kdfType` 0) or
+//! Argon2id over its SHA-256 (`kdfType` 1). Exports encrypted with the
+//! account key instead of a password cannot be opened outside Bitwarden.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use keyforge_crypto::cbc_hmac::{self, CbcHmacKeys, CbcHmacSealed};
+use keyforge_crypto::kdf::{self, KdfParams};
+use keyforge_crypto::secure_mem::{LockedBuffer, LockedKey};
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
+
+use super::{decode_secret, invalid, ParsedTokens, PortableToken};
+use crate::constants::{
+    BITWARDEN_MAX_ARGON2_ITERATIONS, BITWARDEN_MAX_ARGON2_MEMORY_MIB,
+    BITWARDEN_MAX_ARGON2_PARALLELISM, BITWARDEN_MAX_PBKDF2_ITERATIONS, DEFAULT_ALGORITHM,
+    DEFAULT_COUNTER, DEFAULT_DIGITS, DEFAULT_ISSUER, DEFAULT_PERIOD, OTPAUTH_SCHEME,
+    TOKEN_TYPE_STEAM, TOKEN_TYPE_TOTP,
+};
+use crate::error::VaultError;
+use crate::import::{Confidence, ImportCandidate, ImportSecret, Importer, SniffedFile};
+use crate::otpauth::{OtpAuthUri, ParseMode};
+use crate::token::NewToken;
+
+const NAME: &str = "Bitwarden";
+
+const KDF_PBKDF2: u32 = 0;
+const KDF_ARGON2ID: u32 = 1;
+const ITEM_LOGIN: u32 = 1;
+const STEAM_SCHEME: &str = "steam://";
+/// Prefix of an AES-256-CBC + HMAC-SHA256 encrypted string.
+const ENC_TYPE_AES_CBC_HMAC: &str = "2.";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Export {
+    #[serde(default)]
+    encrypted: bool,
+    #[serde(default)]
+    password_protected: bool,
+    salt: Option<String>,
+    kdf_type: Option<u32>,
+    kdf_iterations: Option<u32>,
+    kdf_memory: Option<u32>,
+    kdf_parallelism: Option<u32>,
+    #[serde(rename = "encKeyValidation_DO_NOT_EDIT")]
+    key_validation: Option<String>,
+    data: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Document<'a> {
+    #[serde(default)]
+    folders: Vec<Folder>,
+    /// Decoded one at a time so a malformed item is reported rather than
+    /// failing the export. They borrow from the (locked) document.
+    #[serde(default, borrow)]
+    items: Vec<&'a RawValue>,
+}
+
+#[derive(Deserialize)]
+struct Folder {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Item {
+    #[serde(rename = "type")]
+    item_type: u32,
+    #[serde(default)]
+    name: String,
+    folder_id: Option<String>,
+    login: Option<Login>,
+}
+
+/// What is left of an item that could not be decoded, for its label.
+#[derive(Deserialize)]
+struct ItemName {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct Login {
+    username: Option<String>,
+    totp: Option<String>,
+}
+
+impl Drop for Login {
+    fn drop(&mut self) {
+        if let Some(totp) = &mut self.totp {
+            totp.zeroize();
+        }
+    }
+}
+
+/// Whether a Bitwarden export is encrypted.
+fn is_encrypted(data: &[u8]) -> Result<bool, String> {
+    let export: Export = serde_json::from_slice(data).map_err(|e| invalid(NAME, &e.to_string()))?;
+    Ok(export.encrypted)
+}
+
+/// Decode a Bitwarden export. `password` is required for
+/// password-protected exports and ignored for plain ones.
+///
+/// Only login items with a TOTP value become tokens. The value may be a
+/// bare base32 secret, an `otpauth://` URI or a `steam://` secret; the
+/// item name and username become issuer and account, falling back to the
+/// URI's own label when empty. Folders become groups. Skipped entries keep
+/// their index in `items`.
+pub fn read_bitwarden(data: &[u8], password: Option<&[u8]>) -> Result<ParsedTokens, String> {
+    let export: Export = serde_json::from_slice(data).map_err(|e| invalid(NAME, &e.to_string()))?;
+    let decrypted;
+    let document = if export.encrypted {
+        if !export.password_protected {
+            return Err(VaultError::UnsupportedFormat(
+                "Bitwarden export encrypted with the account key; export with a password instead"
+                    .to_string(),
+            )
+            .into());
+        }
+        let password = password.ok_or_else(|| invalid(NAME, "the export is encrypted"))?;
+        decrypted = decrypt_document(&export, password)?;
+        decrypted.as_slice()
+    } else {
+        data
+    };
+    let document: Document =
+        serde_json::from_slice(document).map_err(|e| invalid(NAME, &e.to_string()))?;
+
+    let mut parsed = ParsedTokens::default();
+    for (index, raw) in document.items.iter().enumerate() {
+        let item: Item = match serde_json::from_str(raw.get()) {
+            Ok(item) => item,
+            Err(e) => {
+                let label = serde_json::from_str::<ItemName>(raw.get())
+                    .map(|item| item.name)
+                    .unwrap_or_default();
+                parsed.skip(index, label, format!("malformed entry: {e}"));
+                continue;
+            }
+        };
+        let Some(login) = item.login.as_ref().filter(|_| item.item_type == ITEM_LOGIN) else {
+            continue;
+        };
+        let Some(totp) = login
+            .totp
+            .as_deref()
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+        else {
+            continue;
+        };
+        let username = login.username.as_deref().unwrap_or("").trim();
+        match map_totp(totp, item.name.trim(), username) {
+            Ok(mut token) => {
+                token.groups = item
+                    .folder_id
+                    .as_ref()
+                    .and_then(|id| document.folders.iter().find(|f| &f.id == id))
+                    .map(|f| vec![f.name.clone()])
+                    .unwrap_or_default();
+                parsed.add(index, token);
+            }
+            Err(reason) => parsed.skip(index, format!("{}:{username}", item.name.trim()), reason),
+        }
+    }
+    Ok(parsed)
+}
+
+fn decrypt_document(export: &Export, password: &[u8]) -> Result<LockedBuffer, String> {
+    let (Some(salt), Some(validation), Some(data)) =
+        (&export.salt, &export.key_validation, &export.data)
+    else {
+        return Err(invalid(NAME, "missing salt, key validation or data"));
+    };
+    let key = derive_key(export, salt.as_bytes(), password)?;
+    let keys = CbcHmacKeys::stretch(key.expose())?;
+
+    cbc_hmac::decrypt_locked(&parse_enc_string(validation)?, &keys)
+        .map_err(|_| VaultError::WrongImportPassword)?;
+    cbc_hmac::decrypt_locked(&parse_enc_string(data)?, &keys)
+        .map_err(|_| invalid(NAME, "data failed authentication"))
+}
+
+/// Derive the export key, bounding file-supplied costs first.
+fn derive_key(export: &Export, salt: &[u8], password: &[u8]) -> Result<LockedKey, String> {
+    let iterations = kdf_param(export.kdf_iterations, "kdfIterations")?;
+    match export.kdf_type.unwrap_or(KDF_PBKDF2) {
+        KDF_PBKDF2 => {
+            if iterations > BITWARDEN_MAX_PBKDF2_ITERATIONS {
+                return Err(too_costly(export));
+            }
+            kdf::derive_key_pbkdf2_locked(password, salt, iterations)
+        }
+        KDF_ARGON2ID => {
+            let memory_mib = kdf_param(export.kdf_memory, "kdfMemory")?;
+            let parallelism = kdf_param(export.kdf_parallelism, "kdfParallelism")?;
+            if iterations > BITWARDEN_MAX_ARGON2_ITERATIONS
+                || memory_mib > BITWARDEN_MAX_ARGON2_MEMORY_MIB
+                || parallelism > BITWARDEN_MAX_ARGON2_PARALLELISM
+            {
+                return Err(too_costly(export));
+            }
+            let params = KdfParams {
+                memory_kib: memory_mib * 1024,
+                time_cost: iterations,
+                parallelism,
+            };
+            kdf::derive_key_argon2id_locked(password, &Sha256::digest(salt), &params)
+        }
+        other => Err(VaultError::UnsupportedFormat(format!("Bitwarden KDF type {other}")).into()),
+    }
+}
+
+/// A KDF cost the export must state, and which cannot be 0.
+fn kdf_param(value: Option<u32>, name: &str) -> Result<u32, String> {
+    match value {
+        Some(value) if value > 0 => Ok(value),
+        _ => Err(invalid(NAME, &format!("missing or zero {name}"))),
+    }
+}
+
+/// Split `2.<iv>|<ciphertext>|<mac>`.
+fn parse_enc_string(value: &str) -> Result<CbcHmacSealed, String> {
+    let Some(parts) = value.strip_prefix(ENC_TYPE_AES_CBC_HMAC) else {
+        return Err(invalid(NAME, "unsupported encryption type"));
+    };
+    let parts: Vec<&str> = parts.split('|').collect();
+    let [iv, ciphertext, mac] = parts[..] else {
+        return Err(invalid(NAME, "encrypted string is not iv|ciphertext|mac"));
+    };
+    let decode = |part: &str| {
+        BASE64
+            .decode(part)
+            .map_err(|_| invalid(NAME, "encrypted string is not valid base64"))
+    };
+    Ok(CbcHmacSealed {
+        iv: decode(iv)?,
+        ciphertext: decode(ciphertext)?,
+        mac: decode(mac)?,
+    })
+}
+
+fn map_totp(totp: &str, name: &str, username: &str) -> Result<PortableToken, String> {
+    let is_scheme = |scheme: &str| {
+        totp.get(..scheme.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(scheme))
+    };
+    let mut token = if is_scheme(OTPAUTH_SCHEME) {
+        OtpAuthUri::parse(totp, ParseMode::Lenient)?.to_new_token()?
+    } else {
+        let (token_type, digits, secret) = if is_scheme(STEAM_SCHEME) {
+            let secret = &totp[STEAM_SCHEME.len()..];
+            (TOKEN_TYPE_STEAM, keyforge_crypto::otp::STEAM_DIGITS, secret)
+        } else {
+            (TOKEN_TYPE_TOTP, DEFAULT_DIGITS, totp)
+        };
+        NewToken {
+            issuer: String::new(),
+            account: String::new(),
+            secret: decode_secret(secret)?,
+            algorithm: DEFAULT_ALGORITHM.to_string(),
+            digits,
+            token_type: token_type.to_string(),
+            period: DEFAULT_PERIOD,
+            counter: DEFAULT_COUNTER,
+            icon: None,
+        }
+    };
+    if !name.is_empty() {
+        token.issuer = name.to_string();
+    } else if token.issuer.is_empty() {
+        token.issuer = DEFAULT_ISSUER.to_string();
+    }
+    if !username.is_empty() {
+        token.account = username.to_string();
+    }
+    PortableToken::checked(token)
+}
+
+fn too_costly(export: &Export) -> String {
+    VaultError::UnsupportedFormat(format!(
+        "Bitwarden key derivation cost (type {:?}, iterations {:?}, memory {:?} MiB, parallelism {:?})",
+        export.kdf_type, export.kdf_iterations, export.kdf_memory, export.kdf_parallelism
+    ))
+    .into()
+}
+
+/// Bitwarden and Vaultwarden JSON exports, plain or password-protected.
+pub struct BitwardenImporter;
+
+impl Importer for BitwardenImporter {
+    fn id(&self) -> &'static str {
+        "bitwarden"
+    }
+
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn sniff(&self, file: &SniffedFile<'_>) -> Confidence {
+        match &file.json {
+            Some(json)
+                if json.get("items").is_some_and(|i| i.is_array())
+                    || json.get("encKeyValidation_DO_NOT_EDIT").is_some() =>
+            {
+                Confidence::Likely
+            }
+            _ => Confidence::No,
+        }
+    }
+
+    fn is_encrypted(&self, data: &[u8]) -> bool {
+        is_encrypted(data).unwrap_or(false)
+    }
+
+    fn parse(
+        &self,
+        data: &[u8],
+        secret: Option<ImportSecret<'_>>,
+    ) -> Result<Vec<ImportCandidate>, String> {
+        let password = ImportSecret::password_only(secret, NAME)?;
+        Ok(read_bitwarden(data, password)?.into_candidates())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hmac::{Hmac, Mac};
+
+    const PLAIN: &str = include_str!("../../tests/fixtures/bitwarden_plain.json");
+    const ENCRYPTED: &str = include_str!("../../tests/fixtures/bitwarden_encrypted.json");
+
+    /// Assert that `parsed` holds what the plain fixture does.
+    fn assert_matches_plain(parsed: &ParsedTokens) {
+        let plain = read_bitwarden(PLAIN.as_bytes(), None).unwrap();
+        assert_eq!(parsed.summary(), plain.summary());
+        assert_eq!(parsed.skipped, plain.skipped);
+    }
+
+    #[test]
+    fn test_plain_export() {
+        assert!(!is_encrypted(PLAIN.as_bytes()).unwrap());
+        let parsed = read_bitwarden(PLAIN.as_bytes(), None).unwrap();
+        let summary = parsed.summary();
+        assert_eq!(
+            summary,
+            [
+                ("GitHub", "alice@example.com", "totp"),
+                ("Bank", "bob", "totp"),
+                ("Steam", "gamer", "steam"),
+                ("Acme", "carol", "totp"),
+            ]
+        );
+
+        let github = &parsed.tokens[0];
+        assert_eq!(github.token.secret, b"12345678901234567890");
+        assert_eq!(github.token.algorithm, "SHA256");
+        assert_eq!((github.token.digits, github.token.period), (8, 60));
+        assert_eq!(github.groups, ["Work"]);
+        assert_eq!(parsed.tokens[1].token.secret, b"Hello!\xde\xad\xbe\xef");
+        assert_eq!(parsed.tokens[2].token.digits, 5);
+
+        assert_eq!(parsed.skipped_labels(), [(5, "Broken:dave")]);
+    }
+
+    #[test]
+    fn test_password_protected_export() {
+        assert!(is_encrypted(ENCRYPTED.as_bytes()).unwrap());
+        assert_matches_plain(&read_bitwarden(ENCRYPTED.as_bytes(), Some(b"test")).unwrap());
+    }
+
+    #[test]
+    fn test_wrong_password_and_account_key_exports() {
+        let err = read_bitwarden(ENCRYPTED.as_bytes(), Some(b"nope"))
+            .err()
+            .unwrap();
+        assert_eq!(err, VaultError::WrongImportPassword.to_string());
+        assert!(read_bitwarden(ENCRYPTED.as_bytes(), None).is_err());
+
+        let account = br#"{"encrypted": true, "encKeyValidation_DO_NOT_EDIT": "2.a|b|c",
+            "folders": [], "items": []}"#;
+        assert!(read_bitwarden(account, None)
+            .err()
+            .unwrap()
+            .contains("account key"));
+    }
+
+    #[test]
+    fn test_argon2id_export() {
+        let salt = "c2FsdHNhbHRzYWx0c2FsdA==";
+        let params = KdfParams {
+            memory_kib: 16 * 1024,
+            time_cost: 2,
+            parallelism: 2,
+        };
+        let key = kdf::derive_key_argon2id_locked(b"pw", &Sha256::digest(salt.as_bytes()), &params)
+            .unwrap();
+        let keys = CbcHmacKeys::stretch(key.expose()).unwrap();
+        // Encrypt-then-MAC over `iv ‖ ciphertext`, as Bitwarden seals.
+        let seal = |plaintext: &[u8]| {
+            let sealed = cbc_hmac::encrypt_unauthenticated(plaintext, keys.enc.expose()).unwrap();
+            let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(keys.mac.expose()).unwrap();
+            mac.update(&sealed);
+            let (iv, ciphertext) = sealed.split_at(cbc_hmac::IV_SIZE);
+            format!(
+                "2.{}|{}|{}",
+                BASE64.encode(iv),
+                BASE64.encode(ciphertext),
+                BASE64.encode(mac.finalize().into_bytes())
+            )
+        };
+        let export = serde_json::json!({
+            "encrypted": true,
+            "passwordProtected": true,
+            "salt": salt,
+            "kdfType": 1,
+            "kdfIterations": 2,
+            "kdfMemory": 16,
+            "kdfParallelism": 2,
+            "encKeyValidation_DO_NOT_EDIT": seal(b"check"),
+            "data": seal(PLAIN.as_bytes()),
+        });
+        let data = serde_json::to_vec(&export).unwrap();
+        assert_matches_plain(&read_bitwarden(&data, Some(b"pw")).unwrap());
+
+        let mut costly = export.clone();
+        costly["kdfMemory"] = (BITWARDEN_MAX_ARGON2_MEMORY_MIB + 1).into();
+        let data = serde_json::to_vec(&costly).unwrap();
+        assert!(read_bitwarden(&data, Some(b"pw"))
+            .err()
+            .unwrap()
+            .contains("cost"));
+
+        let mut uncosted = export.clone();
+        uncosted.as_object_mut().unwrap().remove("kdfIterations");
+        let data = serde_json::to_vec(&uncosted).unwrap();
+        assert!(read_bitwarden(&data, Some(b"pw"))
+            .err()
+            .unwrap()
+            .contains("missing or zero kdfIterations"));
+    }
+
+    #[test]
+    fn test_malformed_items_are_reported() {
+        let mut export: serde_json::Value = serde_json::from_str(PLAIN).unwrap();
+        let items = export["items"].as_array_mut().unwrap();
+        items.push(serde_json::json!({"type": 1, "name": "Odd", "login": "totp"}));
+        items.push(serde_json::json!(42));
+        let count = items.len();
+        let data = serde_json::to_vec(&export).unwrap();
+
+        let parsed = read_bitwarden(&data, None).unwrap();
+        assert_eq!(parsed.tokens.len(), 4);
+        assert_eq!(
+            parsed.skipped_labels(),
+            [(5, "Broken:dave"), (count - 2, "Odd"), (count - 1, "")]
+        );
+        assert!(parsed.skipped[1].reason.starts_with("malformed entry"));
+        assert!(parsed.skipped[2].reason.starts_with("malformed entry"));
+    }
+
+    #[test]
+    fn test_map_totp_forms() {
+        let token = map_totp("jbswy3dpehpk3pxp", "", "").unwrap();
+        assert_eq!(
+            (token.token.issuer.as_str(), token.token.account.as_str()),
+            ("Unknown", "")
+        );
+        let token = map_totp(
+            "otpauth://totp/Label:user?secret=JBSWY3DPEHPK3PXP&issuer=Param",
+            "",
+            "",
+        )
+        .unwrap();
+        assert_eq!(
+            (token.token.issuer.as_str(), token.token.account.as_str()),
+            ("Param", "user")
+        );
+        assert!(map_totp("steam://", "Steam", "x").is_err());
+        assert!(map_totp("otpauth://totp/x?secret=JBSWY3DPEHPK3PXP&digits=7", "", "").is_err());
+    }
+}
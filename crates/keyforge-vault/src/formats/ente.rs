@@ -0,0 +1,364 @@
+//! Ente Auth import
+//!
+//! Ente exports one `otpauth://` URI per line, usually JSON-quoted, each
+//! carrying Ente's display state in a `codeDisplay` parameter (pinned,
+//! trashed, tags, note, position). An encrypted export wraps that text in
+//! JSON:
+//!
+//! ```text
+//! {"version": 1,
+//!  "kdfParams": {"memLimit": <bytes>, "opsLimit": <passes>, "salt": "<base64>"},
+//!  "encryptedData": "<base64>", "encryptionNonce": "<base64 header>"}
+//! ```
+//!
+//! The key is libsodium's `crypto_pwhash` (Argon2id v1.3, one lane) and the
+//! text is a single XChaCha20-Poly1305 secretstream message tagged final.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use keyforge_crypto::kdf::{self, KdfParams};
+use keyforge_crypto::secretstream::{self, SecretStreamDecryptor};
+use keyforge_crypto::secure_mem::LockedBuffer;
+use serde::Deserialize;
+use zeroize::Zeroize;
+
+use super::{invalid, ParsedTokens, PortableToken};
+use crate::constants::{ENTE_EXPORT_VERSION, ENTE_MAX_MEM_LIMIT, ENTE_MAX_OPS_LIMIT};
+use crate::error::VaultError;
+use crate::import::{
+    starts_with_ignore_case, Confidence, ImportCandidate, ImportSecret, Importer, SniffedFile,
+};
+use crate::otpauth::{OtpAuthUri, ParseMode};
+
+const NAME: &str = "Ente Auth";
+const PARAM_CODE_DISPLAY: &str = "codeDisplay";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EncryptedExport {
+    version: u32,
+    kdf_params: KdfParamsJson,
+    encrypted_data: String,
+    encryption_nonce: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KdfParamsJson {
+    mem_limit: u64,
+    ops_limit: u32,
+    salt: String,
+}
+
+/// Ente's per-code display state. Only the fields the vault can use.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct CodeDisplay {
+    pinned: bool,
+    trashed: bool,
+    tags: Vec<String>,
+    note: String,
+    position: i64,
+}
+
+/// Whether `data` is an encrypted Ente export rather than plain text.
+fn is_encrypted(data: &[u8]) -> bool {
+    data.trim_ascii_start().starts_with(b"{")
+}
+
+/// Decode an Ente export. `password` is required for encrypted exports and
+/// ignored for plain ones.
+///
+/// Pinned codes come first, then the rest in Ente's order, since the vault
+/// keeps no pinned flag of its own. Tags become groups. Codes in Ente's
+/// trash are skipped. Skipped entries are indexed by line, counting only
+/// non-empty lines.
+pub fn read_ente(data: &[u8], password: Option<&[u8]>) -> Result<ParsedTokens, String> {
+    if is_encrypted(data) {
+        let password = password.ok_or_else(|| invalid(NAME, "the export is encrypted"))?;
+        let text = decrypt_export(data, password)?;
+        parse_text(text.as_slice())
+    } else {
+        parse_text(data)
+    }
+}
+
+fn decrypt_export(data: &[u8], password: &[u8]) -> Result<LockedBuffer, String> {
+    let export: EncryptedExport =
+        serde_json::from_slice(data).map_err(|e| invalid(NAME, &e.to_string()))?;
+    if export.version != ENTE_EXPORT_VERSION {
+        return Err(VaultError::UnsupportedFormat(format!(
+            "Ente export version {}",
+            export.version
+        ))
+        .into());
+    }
+    let params = &export.kdf_params;
+    if params.mem_limit > ENTE_MAX_MEM_LIMIT || params.ops_limit > ENTE_MAX_OPS_LIMIT {
+        return Err(VaultError::UnsupportedFormat(format!(
+            "Ente key derivation cost (memLimit {}, opsLimit {})",
+            params.mem_limit, params.ops_limit
+        ))
+        .into());
+    }
+    let salt: [u8; 16] = decode_base64(&params.salt)?
+        .try_into()
+        .map_err(|_| invalid(NAME, "bad salt length"))?;
+    let header: [u8; secretstream::HEADER_SIZE] = decode_base64(&export.encryption_nonce)?
+        .try_into()
+        .map_err(|_| invalid(NAME, "bad nonce length"))?;
+    let ciphertext = decode_base64(&export.encrypted_data)?;
+
+    let kdf_params = KdfParams {
+        memory_kib: (params.mem_limit / 1024) as u32,
+        time_cost: params.ops_limit,
+        parallelism: 1,
+    };
+    if ciphertext.len() < secretstream::ABYTES {
+        return Err(invalid(NAME, "the encrypted data is truncated"));
+    }
+
+    let key = kdf::derive_key_locked(password, &salt, &kdf_params)?;
+    // The export is a single message, so failing to authenticate it is the
+    // first-chunk failure a wrong key causes.
+    let (text, tag) = SecretStreamDecryptor::new(key.expose(), &header)
+        .pull(&ciphertext, &[])
+        .map_err(|_| VaultError::WrongImportPassword)?;
+    if tag != secretstream::TAG_FINAL {
+        return Err(invalid(NAME, "the encrypted data is truncated"));
+    }
+    Ok(text)
+}
+
+fn parse_text(text: &[u8]) -> Result<ParsedTokens, String> {
+    let text = std::str::from_utf8(text).map_err(|_| invalid(NAME, "the export is not UTF-8"))?;
+    let mut parsed = ParsedTokens::default();
+    let mut ordered = Vec::new();
+    for (index, line) in text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .enumerate()
+    {
+        // Current exports JSON-quote each URI; older ones do not.
+        let mut line = if line.starts_with('"') {
+            match serde_json::from_str::<String>(line) {
+                Ok(unquoted) => unquoted,
+                Err(e) => {
+                    parsed.skip(index, String::new(), format!("malformed line: {e}"));
+                    continue;
+                }
+            }
+        } else {
+            line.to_string()
+        };
+        let uri = OtpAuthUri::parse(&line, ParseMode::Lenient);
+        let label = raw_label(&line);
+        line.zeroize();
+        let uri = match uri {
+            Ok(uri) => uri,
+            Err(e) => {
+                parsed.skip(index, label, e.to_string());
+                continue;
+            }
+        };
+        let label = format!("{}:{}", uri.resolved_issuer(), uri.account);
+        let display = code_display(&uri);
+        if display.trashed {
+            parsed.skip(index, label, "in Ente's trash");
+            continue;
+        }
+        match uri.to_new_token() {
+            Ok(token) => {
+                let mut token = PortableToken::from(token);
+                token.note = Some(display.note).filter(|n| !n.trim().is_empty());
+                token.groups = display.tags;
+                token.index = index;
+                ordered.push(((!display.pinned, display.position), token));
+            }
+            Err(e) => parsed.skip(index, label, e.to_string()),
+        }
+    }
+    ordered.sort_by_key(|(key, _)| *key);
+    parsed.tokens = ordered.into_iter().map(|(_, token)| token).collect();
+    Ok(parsed)
+}
+
+/// Ente's display state, or the defaults when it is absent or unreadable.
+fn code_display(uri: &OtpAuthUri) -> CodeDisplay {
+    uri.extra
+        .iter()
+        .find(|(key, _)| key == PARAM_CODE_DISPLAY)
+        .and_then(|(_, value)| serde_json::from_str(value).ok())
+        .unwrap_or_default()
+}
+
+/// The undecoded label of a URI that failed to parse, for the skip report.
+fn raw_label(uri: &str) -> String {
+    let path = uri.splitn(4, '/').nth(3).unwrap_or("");
+    path.split('?').next().unwrap_or("").to_string()
+}
+
+fn decode_base64(value: &str) -> Result<Vec<u8>, String> {
+    BASE64
+        .decode(value.trim())
+        .map_err(|_| invalid(NAME, "invalid base64"))
+}
+
+/// Ente Auth exports, plain text or encrypted.
+pub struct EnteImporter;
+
+impl Importer for EnteImporter {
+    fn id(&self) -> &'static str {
+        "ente"
+    }
+
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn sniff(&self, file: &SniffedFile<'_>) -> Confidence {
+        let data = file.data;
+        if is_encrypted(data) {
+            return match &file.json {
+                Some(json)
+                    if json.get("kdfParams").is_some() && json.get("encryptedData").is_some() =>
+                {
+                    Confidence::Likely
+                }
+                _ => Confidence::No,
+            };
+        }
+        // Plain exports are URI lists; Ente's quote each line and carry
+        // its display state in `codeDisplay`.
+        let Ok(text) = std::str::from_utf8(data) else {
+            return Confidence::No;
+        };
+        let first = text.lines().map(str::trim).find(|l| !l.is_empty());
+        if first.is_some_and(|l| starts_with_ignore_case(l, "\"otpauth://"))
+            || text.contains("codeDisplay=")
+        {
+            Confidence::Likely
+        } else {
+            Confidence::No
+        }
+    }
+
+    fn is_encrypted(&self, data: &[u8]) -> bool {
+        is_encrypted(data)
+    }
+
+    fn parse(
+        &self,
+        data: &[u8],
+        secret: Option<ImportSecret<'_>>,
+    ) -> Result<Vec<ImportCandidate>, String> {
+        let password = ImportSecret::password_only(secret, NAME)?;
+        Ok(read_ente(data, password)?.into_candidates())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAIN: &str = include_str!("../../tests/fixtures/ente_plain.txt");
+    const ENCRYPTED: &str = include_str!("../../tests/fixtures/ente_encrypted.json");
+
+    #[test]
+    fn test_plain_export() {
+        assert!(!is_encrypted(PLAIN.as_bytes()));
+        let parsed = read_ente(PLAIN.as_bytes(), None).unwrap();
+        let issuers: Vec<&str> = parsed
+            .tokens
+            .iter()
+            .map(|t| t.token.issuer.as_str())
+            .collect();
+        // Pinned first.
+        assert_eq!(issuers, ["Bank", "GitHub", "Steam"]);
+
+        let bank = &parsed.tokens[0];
+        assert_eq!(
+            (bank.token.token_type.as_str(), bank.token.counter),
+            ("hotp", 5)
+        );
+        assert_eq!(bank.note, None);
+        let github = &parsed.tokens[1];
+        assert_eq!(github.token.account, "alice@example.com");
+        assert_eq!(github.token.secret, b"12345678901234567890");
+        assert_eq!((github.token.digits, github.token.period), (8, 60));
+        assert_eq!(github.note.as_deref(), Some("Recovery codes in the safe"));
+        assert_eq!(github.groups, ["Work"]);
+        let steam = &parsed.tokens[2];
+        assert_eq!(
+            (steam.token.token_type.as_str(), steam.token.digits),
+            ("steam", 5)
+        );
+        assert_eq!(steam.groups, ["Personal", "Games"]);
+
+        assert_eq!(
+            parsed.skipped_labels(),
+            [(3, "Old:erin"), (4, "Broken:dave")]
+        );
+        assert_eq!(parsed.skipped[0].reason, "in Ente's trash");
+    }
+
+    #[test]
+    fn test_encrypted_export_holds_the_plain_one() {
+        assert!(is_encrypted(ENCRYPTED.as_bytes()));
+        let parsed = read_ente(ENCRYPTED.as_bytes(), Some(b"test")).unwrap();
+        let plain = read_ente(PLAIN.as_bytes(), None).unwrap();
+        assert_eq!(parsed.summary(), plain.summary());
+        assert_eq!(parsed.skipped, plain.skipped);
+        assert_eq!(parsed.tokens[1].groups, plain.tokens[1].groups);
+    }
+
+    #[test]
+    fn test_wrong_password_and_truncated_stream() {
+        let err = read_ente(ENCRYPTED.as_bytes(), Some(b"nope"))
+            .err()
+            .unwrap();
+        assert_eq!(err, VaultError::WrongImportPassword.to_string());
+        assert!(read_ente(ENCRYPTED.as_bytes(), None).is_err());
+
+        let mut export: serde_json::Value = serde_json::from_str(ENCRYPTED).unwrap();
+        export["encryptedData"] = "AAAA".into();
+        let data = serde_json::to_vec(&export).unwrap();
+        let err = read_ente(&data, Some(b"test")).err().unwrap();
+        assert_eq!(
+            err,
+            "Invalid import file: Ente Auth: the encrypted data is truncated"
+        );
+    }
+
+    #[test]
+    fn test_rejects_unbounded_kdf_and_unknown_versions() {
+        let mut export: serde_json::Value = serde_json::from_str(ENCRYPTED).unwrap();
+        export["kdfParams"]["memLimit"] = (ENTE_MAX_MEM_LIMIT * 2).into();
+        let data = serde_json::to_vec(&export).unwrap();
+        assert!(read_ente(&data, Some(b"test"))
+            .err()
+            .unwrap()
+            .contains("cost"));
+
+        let mut export: serde_json::Value = serde_json::from_str(ENCRYPTED).unwrap();
+        export["version"] = 2.into();
+        let data = serde_json::to_vec(&export).unwrap();
+        assert!(read_ente(&data, Some(b"test"))
+            .err()
+            .unwrap()
+            .contains("version"));
+    }
+
+    #[test]
+    fn test_lines_without_display_state() {
+        let parsed = read_ente(
+            b"\n  otpauth://totp/Acme:bob?secret=JBSWY3DPEHPK3PXP  \r\n\"not a uri\"\n",
+            None,
+        )
+        .unwrap();
+        assert_eq!(parsed.tokens.len(), 1);
+        assert!(parsed.tokens[0].groups.is_empty());
+        assert_eq!(parsed.skipped[0].index, 1);
+    }
+}
@@ -0,0 +1,242 @@
+//! FreeOTP+ import
+//!
+//! FreeOTP+ exports JSON with the tokens under `tokens` and their display
+//! order under `tokenOrder`, as token IDs (`issuerInt:label`, else
+//! `issuerExt:label`, else just the label). Secrets are raw bytes written
+//! as Java's signed bytes. Custom images are paths on the phone and cannot
+//! be carried over.
+
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use zeroize::Zeroize;
+
+use super::{invalid, ParsedTokens, PortableToken};
+use crate::constants::{
+    DEFAULT_DIGITS, DEFAULT_ISSUER, DEFAULT_PERIOD, TOKEN_TYPE_HOTP, TOKEN_TYPE_TOTP,
+};
+use crate::import::{Confidence, ImportCandidate, ImportSecret, Importer, SniffedFile};
+use crate::token::NewToken;
+
+const NAME: &str = "FreeOTP+";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Backup<'a> {
+    #[serde(default)]
+    token_order: Vec<String>,
+    #[serde(borrow)]
+    tokens: Vec<&'a RawValue>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Token {
+    secret: Vec<i8>,
+    #[serde(rename = "type")]
+    token_type: Option<String>,
+    algo: Option<String>,
+    digits: Option<u32>,
+    period: Option<u32>,
+    counter: Option<u64>,
+    issuer_int: Option<String>,
+    issuer_ext: Option<String>,
+    issuer_alt: Option<String>,
+    #[serde(default)]
+    label: String,
+    label_alt: Option<String>,
+}
+
+impl Drop for Token {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+/// The label a malformed token is reported under.
+#[derive(Deserialize)]
+struct TokenLabel {
+    #[serde(default)]
+    label: String,
+}
+
+impl Token {
+    /// The ID `tokenOrder` refers to this token by.
+    fn id(&self) -> String {
+        let issuer = [self.issuer_int.as_deref(), self.issuer_ext.as_deref()]
+            .into_iter()
+            .flatten()
+            .find(|issuer| !issuer.is_empty());
+        match issuer {
+            Some(issuer) => format!("{issuer}:{}", self.label),
+            None => self.label.clone(),
+        }
+    }
+}
+
+/// Decode a FreeOTP+ JSON export.
+///
+/// Tokens come back in FreeOTP+'s display order; any the order does not
+/// mention follow in file order. Skipped entries keep their index in
+/// `tokens`.
+pub fn read_freeotp(data: &[u8]) -> Result<ParsedTokens, String> {
+    let backup: Backup = serde_json::from_slice(data).map_err(|e| invalid(NAME, &e.to_string()))?;
+
+    let mut ordered = Vec::new();
+    let mut parsed = ParsedTokens::default();
+    for (index, raw) in backup.tokens.iter().enumerate() {
+        let token: Token = match serde_json::from_str(raw.get()) {
+            Ok(token) => token,
+            Err(e) => {
+                let label = serde_json::from_str::<TokenLabel>(raw.get())
+                    .map(|t| t.label)
+                    .unwrap_or_default();
+                parsed.skip(index, label, format!("malformed entry: {e}"));
+                continue;
+            }
+        };
+        match map_token(&token) {
+            Ok(mut mapped) => {
+                mapped.index = index;
+                let id = token.id();
+                let position = backup.token_order.iter().position(|o| *o == id);
+                ordered.push((position.unwrap_or(usize::MAX), mapped));
+            }
+            Err(reason) => {
+                let (issuer, account) = names(&token);
+                parsed.skip(index, format!("{issuer}:{account}"), reason);
+            }
+        }
+    }
+    ordered.sort_by_key(|(position, _)| *position);
+    parsed.tokens = ordered.into_iter().map(|(_, token)| token).collect();
+    Ok(parsed)
+}
+
+fn map_token(token: &Token) -> Result<PortableToken, String> {
+    let token_type = token.token_type.as_deref().unwrap_or("TOTP");
+    let (token_type, counter) = match token_type.to_ascii_uppercase().as_str() {
+        "TOTP" => (TOKEN_TYPE_TOTP, 0),
+        "HOTP" => (TOKEN_TYPE_HOTP, token.counter.unwrap_or(0)),
+        _ => return Err(format!("{token_type} tokens are not supported")),
+    };
+    if token.secret.is_empty() {
+        return Err("empty secret".to_string());
+    }
+
+    let (issuer, account) = names(token);
+    PortableToken::checked(NewToken {
+        issuer,
+        account,
+        secret: token.secret.iter().map(|b| *b as u8).collect(),
+        algorithm: token.algo.as_deref().unwrap_or("SHA1").to_ascii_uppercase(),
+        digits: token.digits.unwrap_or(DEFAULT_DIGITS),
+        token_type: token_type.to_string(),
+        period: token.period.filter(|p| *p > 0).unwrap_or(DEFAULT_PERIOD),
+        counter,
+        icon: None,
+    })
+}
+
+/// Issuer and account as FreeOTP+ shows them: the user's edits
+/// (`issuerAlt`, `labelAlt`) over the provisioned values.
+fn names(token: &Token) -> (String, String) {
+    let pick = |values: [Option<&str>; 2]| {
+        values
+            .into_iter()
+            .flatten()
+            .map(str::trim)
+            .find(|s| !s.is_empty())
+            .map(str::to_string)
+    };
+    let issuer = pick([token.issuer_alt.as_deref(), token.issuer_ext.as_deref()])
+        .unwrap_or_else(|| DEFAULT_ISSUER.to_string());
+    let account = pick([token.label_alt.as_deref(), Some(&token.label)]).unwrap_or_default();
+    (issuer, account)
+}
+
+/// FreeOTP+ JSON exports.
+pub struct FreeOtpImporter;
+
+impl Importer for FreeOtpImporter {
+    fn id(&self) -> &'static str {
+        "freeotp"
+    }
+
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn sniff(&self, file: &SniffedFile<'_>) -> Confidence {
+        let Some(json) = &file.json else {
+            return Confidence::No;
+        };
+        let Some(tokens) = json.get("tokens").filter(|t| t.is_array()) else {
+            return Confidence::No;
+        };
+        // Secrets are arrays of signed bytes, unlike every other format.
+        let byte_secrets = tokens
+            .first()
+            .and_then(|t| t.get("secret"))
+            .is_some_and(|s| s.is_array());
+        if json.get("tokenOrder").is_some() || byte_secrets {
+            Confidence::Likely
+        } else {
+            Confidence::No
+        }
+    }
+
+    fn parse(
+        &self,
+        data: &[u8],
+        _secret: Option<ImportSecret<'_>>,
+    ) -> Result<Vec<ImportCandidate>, String> {
+        Ok(read_freeotp(data)?.into_candidates())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BACKUP: &[u8] = include_bytes!("../../tests/fixtures/freeotp_plus.json");
+
+    #[test]
+    fn test_backup() {
+        let parsed = read_freeotp(BACKUP).unwrap();
+        let summary = parsed.summary();
+        // Display order, not file order. Shop has no issuerInt, so its ID
+        // falls back to issuerExt.
+        assert_eq!(
+            summary,
+            [
+                ("Shop", "frank", "totp"),
+                ("Bank", "bob", "hotp"),
+                ("GitHub", "alice@example.com", "totp"),
+                ("Example", "erin", "totp"),
+            ]
+        );
+
+        let bank = &parsed.tokens[1].token;
+        assert_eq!(bank.secret, b"Hello!\xde\xad\xbe\xef");
+        assert_eq!((bank.counter, bank.digits), (5, 6));
+        let github = &parsed.tokens[2].token;
+        assert_eq!(github.secret, b"12345678901234567890");
+        assert_eq!(github.algorithm, "SHA256");
+        assert_eq!((github.digits, github.period), (8, 60));
+
+        assert_eq!(
+            parsed.skipped_labels(),
+            [(2, "Broken:carol"), (3, "Odd:dave")]
+        );
+        assert_eq!(parsed.skipped[0].reason, "empty secret");
+        assert_eq!(parsed.skipped[1].reason, "Unsupported algorithm: MD5");
+    }
+
+    #[test]
+    fn test_rejects_malformed_files() {
+        assert!(read_freeotp(b"not json").is_err());
+        assert!(read_freeotp(br#"{"tokenOrder": []}"#).is_err());
+        let parsed = read_freeotp(br#"{"tokens": [{"label": "x", "secret": "AAAA"}]}"#).unwrap();
+        assert!(parsed.skipped[0].reason.starts_with("malformed entry"));
+    }
+}
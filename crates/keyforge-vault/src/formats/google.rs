@@ -0,0 +1,742 @@
+//! Google Authenticator migration import and export
+//!
+//! Reads and writes the `otpauth-migration://offline?data=` URIs behind
+//! Google Authenticator's "Transfer accounts" QR codes. `data` is base64 of
+//! a `MigrationPayload` protobuf; a large export is split across several QR
+//! codes that share a `batch_id` and carry their own `batch_index` out of
+//! `batch_size`.
+
+use std::collections::{BTreeMap, HashSet};
+
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use serde::Serialize;
+use zeroize::{Zeroize, Zeroizing};
+
+use super::protobuf::{len_field_size, Reader, Writer};
+use super::{invalid, ParsedTokens, PortableToken, SkippedEntry};
+use crate::constants::{
+    DEFAULT_COUNTER, DEFAULT_DIGITS, DEFAULT_ISSUER, DEFAULT_PERIOD, GOOGLE_MIGRATION_MAX_ENTRIES,
+    GOOGLE_MIGRATION_MAX_PAYLOAD, GOOGLE_MIGRATION_PREFIX, TOKEN_TYPE_HOTP, TOKEN_TYPE_TOTP,
+};
+use crate::import::{Confidence, ImportCandidate, ImportSecret, Importer, SniffedFile};
+use crate::token::NewToken;
+
+#[cfg(feature = "sqlite")]
+use crate::db::Vault;
+
+const NAME: &str = "Google Authenticator";
+
+// MigrationPayload fields.
+const PAYLOAD_OTP_PARAMETERS: u64 = 1;
+const PAYLOAD_VERSION: u64 = 2;
+const PAYLOAD_BATCH_SIZE: u64 = 3;
+const PAYLOAD_BATCH_INDEX: u64 = 4;
+const PAYLOAD_BATCH_ID: u64 = 5;
+
+// OtpParameters fields.
+const OTP_SECRET: u64 = 1;
+const OTP_NAME: u64 = 2;
+const OTP_ISSUER: u64 = 3;
+const OTP_ALGORITHM: u64 = 4;
+const OTP_DIGITS: u64 = 5;
+const OTP_TYPE: u64 = 6;
+const OTP_COUNTER: u64 = 7;
+
+/// Payload format version written by current Google Authenticator builds.
+const MIGRATION_VERSION: i32 = 1;
+
+/// Room reserved for the payload's own fields: version, and batch size,
+/// index and id at their widest non-negative encodings.
+const HEADER_BUDGET: usize = 2 + 3 * 6;
+
+/// One decoded migration QR code.
+#[derive(Debug)]
+pub struct MigrationBatch {
+    pub version: i32,
+    pub batch_id: i32,
+    pub batch_index: i32,
+    pub batch_size: i32,
+    pub tokens: ParsedTokens,
+}
+
+/// `OtpParameters` as it appears on the wire, before mapping.
+#[derive(Default)]
+struct RawEntry {
+    secret: Vec<u8>,
+    name: String,
+    issuer: String,
+    algorithm: u64,
+    digits: u64,
+    otp_type: u64,
+    counter: i64,
+}
+
+impl Drop for RawEntry {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+/// Decode an `otpauth-migration://offline?data=...` URI.
+pub fn parse_migration_uri(uri: &str) -> Result<MigrationBatch, String> {
+    let query = uri
+        .trim()
+        .strip_prefix(GOOGLE_MIGRATION_PREFIX)
+        .ok_or_else(|| invalid(NAME, "not an otpauth-migration://offline URI"))?;
+    let data = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("data="))
+        .ok_or_else(|| invalid(NAME, "missing data parameter"))?;
+
+    let mut encoded = percent_decode(data)?;
+    let decoded = decode_base64(&encoded);
+    encoded.zeroize();
+    let mut payload = decoded?;
+    let batch = parse_migration_payload(&payload);
+    payload.zeroize();
+    batch
+}
+
+/// Decode a raw `MigrationPayload` protobuf.
+pub fn parse_migration_payload(payload: &[u8]) -> Result<MigrationBatch, String> {
+    let mut batch = MigrationBatch {
+        version: 0,
+        batch_id: 0,
+        batch_index: 0,
+        batch_size: 1,
+        tokens: ParsedTokens::default(),
+    };
+    let mut entries = Vec::new();
+
+    let mut reader = Reader::new(payload);
+    while let Some((field, value)) = reader.next_field()? {
+        match field {
+            PAYLOAD_OTP_PARAMETERS => entries.push(parse_entry(value.bytes()?)?),
+            PAYLOAD_VERSION => batch.version = int32(value.varint()?),
+            PAYLOAD_BATCH_SIZE => batch.batch_size = int32(value.varint()?),
+            PAYLOAD_BATCH_INDEX => batch.batch_index = int32(value.varint()?),
+            PAYLOAD_BATCH_ID => batch.batch_id = int32(value.varint()?),
+            _ => {}
+        }
+    }
+    if batch.batch_size < 1 || batch.batch_index < 0 || batch.batch_index >= batch.batch_size {
+        return Err(invalid(
+            NAME,
+            &format!(
+                "batch index {} out of range for batch size {}",
+                batch.batch_index, batch.batch_size
+            ),
+        ));
+    }
+
+    for (index, entry) in entries.iter().enumerate() {
+        let (issuer, account) = split_name(&entry.name, &entry.issuer);
+        match map_entry(entry, &issuer, &account) {
+            Ok(token) => batch.tokens.add(index, token),
+            Err(reason) => batch
+                .tokens
+                .skip(index, format!("{issuer}:{account}"), reason),
+        }
+    }
+    Ok(batch)
+}
+
+fn parse_entry(data: &[u8]) -> Result<RawEntry, String> {
+    let mut entry = RawEntry::default();
+    let mut reader = Reader::new(data);
+    while let Some((field, value)) = reader.next_field()? {
+        match field {
+            OTP_SECRET => entry.secret = value.bytes()?.to_vec(),
+            OTP_NAME => entry.name = value.string()?,
+            OTP_ISSUER => entry.issuer = value.string()?,
+            OTP_ALGORITHM => entry.algorithm = value.varint()?,
+            OTP_DIGITS => entry.digits = value.varint()?,
+            OTP_TYPE => entry.otp_type = value.varint()?,
+            OTP_COUNTER => entry.counter = value.varint()? as i64,
+            _ => {}
+        }
+    }
+    Ok(entry)
+}
+
+/// Map one entry to a token, or explain why it cannot be imported.
+fn map_entry(entry: &RawEntry, issuer: &str, account: &str) -> Result<PortableToken, String> {
+    let algorithm = match entry.algorithm {
+        0 | 1 => "SHA1",
+        2 => "SHA256",
+        3 => "SHA512",
+        4 => return Err("MD5 tokens are not supported".to_string()),
+        other => return Err(format!("unknown algorithm {other}")),
+    };
+    let digits = match entry.digits {
+        0 | 1 => DEFAULT_DIGITS,
+        2 => 8,
+        other => return Err(format!("unknown digit count {other}")),
+    };
+    let token_type = match entry.otp_type {
+        0 | 2 => TOKEN_TYPE_TOTP,
+        1 => TOKEN_TYPE_HOTP,
+        other => return Err(format!("unknown token type {other}")),
+    };
+    let counter = if token_type == TOKEN_TYPE_HOTP {
+        u64::try_from(entry.counter).map_err(|_| "negative HOTP counter".to_string())?
+    } else {
+        DEFAULT_COUNTER
+    };
+    if entry.secret.is_empty() {
+        return Err("empty secret".to_string());
+    }
+
+    PortableToken::checked(NewToken {
+        issuer: issuer.to_string(),
+        account: account.to_string(),
+        secret: entry.secret.clone(),
+        algorithm: algorithm.to_string(),
+        digits,
+        token_type: token_type.to_string(),
+        period: DEFAULT_PERIOD,
+        counter,
+        icon: None,
+    })
+}
+
+/// Google stores the label as `name`, usually `Issuer:account`.
+fn split_name(name: &str, issuer: &str) -> (String, String) {
+    let issuer = issuer.trim();
+    if !issuer.is_empty() {
+        let account = name
+            .strip_prefix(issuer)
+            .and_then(|rest| rest.strip_prefix(':'))
+            .unwrap_or(name);
+        return (issuer.to_string(), account.trim().to_string());
+    }
+    match name.split_once(':') {
+        Some((issuer, account)) if !issuer.trim().is_empty() => {
+            (issuer.trim().to_string(), account.trim().to_string())
+        }
+        _ => (DEFAULT_ISSUER.to_string(), name.trim().to_string()),
+    }
+}
+
+/// Proto3 `int32` values are sign-extended to 64 bits on the wire.
+fn int32(value: u64) -> i32 {
+    value as i64 as i32
+}
+
+/// Percent-decode the `data` value. `+` is kept as-is: it is part of the
+/// base64 alphabet, and scanners that un-escape it produce a space.
+fn percent_decode(s: &str) -> Result<Vec<u8>, String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = bytes
+                    .get(i + 1..i + 3)
+                    .and_then(|h| std::str::from_utf8(h).ok())
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                    .ok_or_else(|| invalid(NAME, "invalid percent escape in data"))?;
+                out.push(hex);
+                i += 3;
+            }
+            b' ' => {
+                out.push(b'+');
+                i += 1;
+            }
+            b => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Accept standard or URL-safe base64, padded or not.
+fn decode_base64(data: &[u8]) -> Result<Vec<u8>, String> {
+    let end = data.iter().rposition(|&b| b != b'=').map_or(0, |i| i + 1);
+    let trimmed = &data[..end];
+    STANDARD_NO_PAD
+        .decode(trimmed)
+        .or_else(|_| URL_SAFE_NO_PAD.decode(trimmed))
+        .map_err(|_| invalid(NAME, "data is not valid base64"))
+}
+
+/// Decode the payloads of one or more transfer QR codes.
+///
+/// A malformed URI fails the whole read, as does a batch with a code
+/// missing. Rescanning a code from the same batch is ignored rather than
+/// decoded twice. Entries are numbered across codes in scan order.
+pub fn read_migration_uris<S: AsRef<str>>(uris: &[S]) -> Result<ParsedTokens, String> {
+    let mut batches: BTreeMap<i32, (i32, HashSet<i32>)> = BTreeMap::new();
+    let mut parsed = ParsedTokens::default();
+    let mut offset = 0;
+    for uri in uris {
+        let mut batch = parse_migration_uri(uri.as_ref())?;
+        let (_, scanned) = batches
+            .entry(batch.batch_id)
+            .or_insert_with(|| (batch.batch_size, HashSet::new()));
+        if !scanned.insert(batch.batch_index) {
+            continue;
+        }
+        let entries = batch.tokens.tokens.len() + batch.tokens.skipped.len();
+        for token in &mut batch.tokens.tokens {
+            token.index += offset;
+        }
+        for skipped in &mut batch.tokens.skipped {
+            skipped.index += offset;
+        }
+        offset += entries;
+        parsed.append(batch.tokens);
+    }
+    for (size, scanned) in batches.values() {
+        if let Some(missing) = (0..*size).find(|i| !scanned.contains(i)) {
+            return Err(invalid(
+                NAME,
+                &format!("QR code {} of {size} was not scanned", missing + 1),
+            ));
+        }
+    }
+    Ok(parsed)
+}
+
+/// Transfer QR payloads for a set of tokens.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MigrationExport {
+    /// One `otpauth-migration://` URI per QR code, in batch order.
+    pub uris: Vec<String>,
+    /// Tokens Google Authenticator cannot represent.
+    pub skipped: Vec<SkippedEntry>,
+}
+
+/// Encode `tokens` as transfer QR payloads under a fresh random batch id.
+pub fn write_migration_uris(tokens: &[NewToken]) -> MigrationExport {
+    let mut id = [0u8; 4];
+    keyforge_crypto::random::fill(&mut id);
+    write_migration_batches(tokens, i32::from_le_bytes(id) & i32::MAX)
+}
+
+/// Encode `tokens` as transfer QR payloads sharing `batch_id`.
+///
+/// Tokens are packed in order, starting a new QR code whenever the next
+/// one would exceed [`GOOGLE_MIGRATION_MAX_ENTRIES`] or
+/// [`GOOGLE_MIGRATION_MAX_PAYLOAD`].
+pub fn write_migration_batches(tokens: &[NewToken], batch_id: i32) -> MigrationExport {
+    let mut skipped = Vec::new();
+    let mut batches: Vec<Vec<Zeroizing<Vec<u8>>>> = Vec::new();
+    let mut batch_bytes = 0;
+
+    for (index, token) in tokens.iter().enumerate() {
+        let entry = match encode_entry(token) {
+            Ok(entry) => entry,
+            Err(reason) => {
+                skipped.push(SkippedEntry {
+                    index,
+                    label: format!("{}:{}", token.issuer, token.account),
+                    reason,
+                });
+                continue;
+            }
+        };
+        let size = len_field_size(entry.len());
+        let fits = batches.last().is_some_and(|batch| {
+            batch.len() < GOOGLE_MIGRATION_MAX_ENTRIES
+                && HEADER_BUDGET + batch_bytes + size <= GOOGLE_MIGRATION_MAX_PAYLOAD
+        });
+        if !fits {
+            batches.push(Vec::new());
+            batch_bytes = 0;
+        }
+        batch_bytes += size;
+        batches.last_mut().expect("batch pushed above").push(entry);
+    }
+
+    let batch_size = batches.len() as i32;
+    let uris = batches
+        .iter()
+        .enumerate()
+        .map(|(batch_index, entries)| {
+            let mut payload = Writer::new();
+            for entry in entries {
+                payload.bytes(PAYLOAD_OTP_PARAMETERS, entry);
+            }
+            payload.varint(PAYLOAD_VERSION, uint32(MIGRATION_VERSION));
+            payload.varint(PAYLOAD_BATCH_SIZE, uint32(batch_size));
+            payload.varint(PAYLOAD_BATCH_INDEX, uint32(batch_index as i32));
+            payload.varint(PAYLOAD_BATCH_ID, uint32(batch_id));
+            migration_uri(&payload.finish())
+        })
+        .collect();
+    MigrationExport { uris, skipped }
+}
+
+/// Encode one token as `OtpParameters`, or explain why Google
+/// Authenticator cannot hold it.
+fn encode_entry(token: &NewToken) -> Result<Zeroizing<Vec<u8>>, String> {
+    let algorithm = match token.algorithm.to_ascii_uppercase().as_str() {
+        "SHA1" => 1,
+        "SHA256" => 2,
+        "SHA512" => 3,
+        other => return Err(format!("algorithm {other} is not supported")),
+    };
+    let digits = match token.digits {
+        6 => 1,
+        8 => 2,
+        other => return Err(format!("{other}-digit codes are not supported")),
+    };
+    let otp_type = match token.token_type.as_str() {
+        TOKEN_TYPE_HOTP => 1,
+        TOKEN_TYPE_TOTP if token.period == DEFAULT_PERIOD => 2,
+        TOKEN_TYPE_TOTP => return Err(format!("{}-second period is not supported", token.period)),
+        other => return Err(format!("token type {other} is not supported")),
+    };
+    if token.secret.is_empty() {
+        return Err("empty secret".to_string());
+    }
+
+    let (name, issuer) = if token.issuer == DEFAULT_ISSUER {
+        (token.account.clone(), "")
+    } else {
+        (
+            format!("{}:{}", token.issuer, token.account),
+            token.issuer.as_str(),
+        )
+    };
+    let mut entry = Writer::new();
+    entry.bytes(OTP_SECRET, &token.secret);
+    entry.bytes(OTP_NAME, name.as_bytes());
+    entry.bytes(OTP_ISSUER, issuer.as_bytes());
+    entry.varint(OTP_ALGORITHM, algorithm);
+    entry.varint(OTP_DIGITS, digits);
+    entry.varint(OTP_TYPE, otp_type);
+    if otp_type == 1 {
+        entry.varint(OTP_COUNTER, token.counter);
+    }
+    if len_field_size(entry.len()) + HEADER_BUDGET > GOOGLE_MIGRATION_MAX_PAYLOAD {
+        return Err("too large for a transfer QR code".to_string());
+    }
+    Ok(entry.finish())
+}
+
+/// Standard padded base64, percent-escaped for the query string.
+fn migration_uri(payload: &[u8]) -> String {
+    let encoded = Zeroizing::new(STANDARD.encode(payload));
+    let mut uri = format!("{GOOGLE_MIGRATION_PREFIX}data=");
+    for c in encoded.chars() {
+        match c {
+            '+' => uri.push_str("%2B"),
+            '/' => uri.push_str("%2F"),
+            '=' => uri.push_str("%3D"),
+            c => uri.push(c),
+        }
+    }
+    uri
+}
+
+/// Inverse of [`int32`].
+fn uint32(value: i32) -> u64 {
+    i64::from(value) as u64
+}
+
+/// Text with one Google Authenticator `otpauth-migration://` URI per line.
+pub struct GoogleMigrationImporter;
+
+impl Importer for GoogleMigrationImporter {
+    fn id(&self) -> &'static str {
+        "google_migration"
+    }
+
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn sniff(&self, file: &SniffedFile<'_>) -> Confidence {
+        let first = std::str::from_utf8(file.data)
+            .ok()
+            .and_then(|text| text.lines().map(str::trim).find(|l| !l.is_empty()));
+        if first.is_some_and(|l| l.starts_with(GOOGLE_MIGRATION_PREFIX)) {
+            Confidence::Certain
+        } else {
+            Confidence::No
+        }
+    }
+
+    fn parse(
+        &self,
+        data: &[u8],
+        _secret: Option<ImportSecret<'_>>,
+    ) -> Result<Vec<ImportCandidate>, String> {
+        let text =
+            std::str::from_utf8(data).map_err(|_| invalid(NAME, "the file is not UTF-8 text"))?;
+        let uris: Vec<&str> = text
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .collect();
+        Ok(read_migration_uris(&uris)?.into_candidates())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl Vault {
+    /// Encode tokens as Google Authenticator transfer QR payloads.
+    ///
+    /// `ids` selects a subset (exported in vault order); `None` exports the
+    /// whole vault. Skipped entries are indexed within the selection.
+    pub fn export_google_migration(
+        &self,
+        ids: Option<&[String]>,
+    ) -> Result<MigrationExport, String> {
+        let mut exported = self.export_selected(ids)?;
+        let result = write_migration_uris(&exported);
+        for token in &mut exported {
+            token.secret.zeroize();
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A single-account export from Google Authenticator.
+    const SINGLE: &str = "otpauth-migration://offline?data=CjUKCkhlbGxvId6tvu8SGEV4YW1wbGU6YWxpY2VAZ29vZ2xlLmNvbRoHRXhhbXBsZSABKAEwAhABGAEgACiJyYH9Aw%3D%3D";
+
+    /// Second QR code of a two-code batch (id -7): an 8-digit SHA512 HOTP
+    /// token, an MD5 token, a token of unknown type and one relying on the
+    /// unspecified defaults.
+    const MIXED: &str = "otpauth-migration://offline?data=CikKFDEyMzQ1Njc4OTAxMjM0NTY3ODkwEgNib2IaBEFjbWUgAygCMAE4KgouChQxMjM0NTY3ODkwMTIzNDU2Nzg5MBIMTGVnYWN5OmNhcm9sGgAgBCgBMAI4AAosChQxMjM0NTY3ODkwMTIzNDU2Nzg5MBIEZGF2ZRoGRnV0dXJlIAEoATAHOAAKJgoUMTIzNDU2Nzg5MDEyMzQ1Njc4OTASBGVyaW4aACACKAAwADgAEAEYAiABKPn%2F%2F%2F%2F%2F%2F%2F%2F%2F%2FwE%3D";
+
+    /// First QR code of the same batch, holding one account.
+    const FIRST: &str = "otpauth-migration://offline?data=CiwKFDEyMzQ1Njc4OTAxMjM0NTY3ODkwEghBY21lOnpvZRoEQWNtZSABKAEwAhABGAIgACj5%2F%2F%2F%2F%2F%2F%2F%2F%2F%2F8B";
+
+    #[test]
+    fn test_single_account() {
+        let batch = parse_migration_uri(SINGLE).unwrap();
+        assert_eq!(
+            (batch.version, batch.batch_size, batch.batch_index),
+            (1, 1, 0)
+        );
+        assert!(batch.tokens.skipped.is_empty());
+        let token = &batch.tokens.tokens[0].token;
+        assert_eq!(token.issuer, "Example");
+        assert_eq!(token.account, "alice@google.com");
+        assert_eq!(token.secret, b"Hello!\xde\xad\xbe\xef");
+        assert_eq!(token.algorithm, "SHA1");
+        assert_eq!(token.digits, 6);
+        assert_eq!(token.token_type, "totp");
+        assert_eq!(token.period, 30);
+    }
+
+    #[test]
+    fn test_mixed_batch_maps_and_skips() {
+        let batch = parse_migration_uri(MIXED).unwrap();
+        assert_eq!(
+            (batch.batch_id, batch.batch_index, batch.batch_size),
+            (-7, 1, 2)
+        );
+
+        let tokens: Vec<&NewToken> = batch.tokens.tokens.iter().map(|t| &t.token).collect();
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(
+            (tokens[0].issuer.as_str(), tokens[0].account.as_str()),
+            ("Acme", "bob")
+        );
+        assert_eq!(tokens[0].algorithm, "SHA512");
+        assert_eq!(tokens[0].digits, 8);
+        assert_eq!(tokens[0].token_type, "hotp");
+        assert_eq!(tokens[0].counter, 42);
+        assert_eq!(
+            (tokens[1].issuer.as_str(), tokens[1].account.as_str()),
+            ("Unknown", "erin")
+        );
+        assert_eq!(tokens[1].algorithm, "SHA256");
+        assert_eq!(tokens[1].digits, 6);
+        assert_eq!(tokens[1].token_type, "totp");
+
+        let skipped = &batch.tokens.skipped;
+        assert_eq!(skipped.len(), 2);
+        assert_eq!(
+            (skipped[0].index, skipped[0].label.as_str()),
+            (1, "Legacy:carol")
+        );
+        assert!(skipped[0].reason.contains("MD5"));
+        assert_eq!(
+            (skipped[1].index, skipped[1].label.as_str()),
+            (2, "Future:dave")
+        );
+        assert!(skipped[1].reason.contains("type"));
+    }
+
+    #[test]
+    fn test_lenient_data_encoding() {
+        // Unescaped padding, and URL-safe base64 without it.
+        let raw = SINGLE.replace("%3D", "=");
+        assert_eq!(parse_migration_uri(&raw).unwrap().tokens.tokens.len(), 1);
+        let url_safe = MIXED.replace("%2F", "_").replace("%3D", "");
+        assert_eq!(
+            parse_migration_uri(&url_safe).unwrap().tokens.tokens.len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_rejects_malformed_uris() {
+        assert!(parse_migration_uri("otpauth://totp/x?secret=AA").is_err());
+        assert!(parse_migration_uri("otpauth-migration://offline?foo=bar").is_err());
+        assert!(parse_migration_uri("otpauth-migration://offline?data=%%%").is_err());
+        assert!(parse_migration_uri("otpauth-migration://offline?data=CgE").is_err());
+        // batch_index 3 of batch_size 2
+        assert!(parse_migration_payload(&[0x18, 0x02, 0x20, 0x03]).is_err());
+    }
+
+    #[test]
+    fn test_batches_number_entries_across_codes() {
+        let parsed = read_migration_uris(&[MIXED, FIRST, SINGLE, MIXED]).unwrap();
+        let accounts: Vec<(usize, &str)> = parsed
+            .tokens
+            .iter()
+            .map(|t| (t.index, t.token.account.as_str()))
+            .collect();
+        assert_eq!(
+            accounts,
+            [(0, "bob"), (3, "erin"), (4, "zoe"), (5, "alice@google.com")]
+        );
+        assert_eq!(
+            parsed.skipped_labels(),
+            [(1, "Legacy:carol"), (2, "Future:dave")]
+        );
+    }
+
+    #[test]
+    fn test_rejects_incomplete_batch() {
+        let err = read_migration_uris(&[MIXED, SINGLE]).unwrap_err();
+        assert!(err.contains("QR code 1 of 2"));
+        assert!(read_migration_uris(&[FIRST, MIXED]).is_ok());
+    }
+
+    #[test]
+    fn test_split_name() {
+        assert_eq!(
+            split_name("Acme:bob", "Acme"),
+            ("Acme".into(), "bob".into())
+        );
+        assert_eq!(split_name("bob", "Acme"), ("Acme".into(), "bob".into()));
+        assert_eq!(
+            split_name("Other:bob", "Acme"),
+            ("Acme".into(), "Other:bob".into())
+        );
+        assert_eq!(split_name(":bob", ""), ("Unknown".into(), ":bob".into()));
+    }
+
+    fn token(issuer: &str, account: &str) -> NewToken {
+        NewToken {
+            issuer: issuer.to_string(),
+            account: account.to_string(),
+            secret: b"12345678901234567890".to_vec(),
+            algorithm: "SHA1".to_string(),
+            digits: 6,
+            token_type: "totp".to_string(),
+            period: 30,
+            counter: 0,
+            icon: None,
+        }
+    }
+
+    #[test]
+    fn test_export_matches_google_encoding() {
+        let mut batch = parse_migration_uri(SINGLE).unwrap();
+        let tokens: Vec<NewToken> = std::mem::take(&mut batch.tokens.tokens)
+            .into_iter()
+            .map(|t| t.token)
+            .collect();
+        let export = write_migration_batches(&tokens, batch.batch_id);
+        assert_eq!(export.uris, [SINGLE]);
+        assert!(export.skipped.is_empty());
+    }
+
+    #[test]
+    fn test_export_round_trips_through_import() {
+        let mut hotp = token("Acme", "bob");
+        hotp.algorithm = "SHA512".to_string();
+        hotp.digits = 8;
+        hotp.token_type = "hotp".to_string();
+        hotp.counter = 42;
+        let tokens = [hotp, token("Unknown", "erin"), token("Example", "a:b")];
+
+        let export = write_migration_uris(&tokens);
+        assert_eq!(export.uris.len(), 1);
+        let batch = parse_migration_uri(&export.uris[0]).unwrap();
+        assert!(batch.batch_id >= 0);
+        for (read, written) in batch.tokens.tokens.iter().map(|t| &t.token).zip(&tokens) {
+            assert_eq!(read.issuer, written.issuer);
+            assert_eq!(read.account, written.account);
+            assert_eq!(read.secret, written.secret);
+            assert_eq!(read.algorithm, written.algorithm);
+            assert_eq!(read.digits, written.digits);
+            assert_eq!(read.token_type, written.token_type);
+            assert_eq!(read.counter, written.counter);
+        }
+    }
+
+    #[test]
+    fn test_export_splits_batches() {
+        let tokens: Vec<NewToken> = (0..25)
+            .map(|i| token("Acme", &format!("user{i}")))
+            .collect();
+        let export = write_migration_batches(&tokens, 99);
+        assert_eq!(export.uris.len(), 3);
+
+        let mut accounts = Vec::new();
+        for (index, uri) in export.uris.iter().enumerate() {
+            let batch = parse_migration_uri(uri).unwrap();
+            assert_eq!(
+                (batch.batch_id, batch.batch_index, batch.batch_size),
+                (99, index as i32, 3)
+            );
+            assert!(batch.tokens.tokens.len() <= GOOGLE_MIGRATION_MAX_ENTRIES);
+            accounts.extend(batch.tokens.tokens.iter().map(|t| t.token.account.clone()));
+        }
+        let expected: Vec<String> = (0..25).map(|i| format!("user{i}")).collect();
+        assert_eq!(accounts, expected);
+    }
+
+    #[test]
+    fn test_export_respects_payload_limit() {
+        let long = "x".repeat(150);
+        let tokens: Vec<NewToken> = (0..6).map(|_| token("Acme", &long)).collect();
+        let export = write_migration_batches(&tokens, i32::MAX);
+        assert_eq!(export.uris.len(), 3);
+        for uri in &export.uris {
+            let data = uri
+                .strip_prefix("otpauth-migration://offline?data=")
+                .unwrap();
+            let payload = decode_base64(&percent_decode(data).unwrap()).unwrap();
+            assert!(payload.len() <= GOOGLE_MIGRATION_MAX_PAYLOAD);
+            assert_eq!(parse_migration_payload(&payload).unwrap().batch_size, 3);
+        }
+    }
+
+    #[test]
+    fn test_export_skips_unrepresentable_tokens() {
+        let mut slow = token("Acme", "slow");
+        slow.period = 60;
+        let mut seven = token("Acme", "seven");
+        seven.digits = 7;
+        let huge = token("Acme", &"x".repeat(GOOGLE_MIGRATION_MAX_PAYLOAD));
+        let tokens = [slow, token("Acme", "ok"), seven, huge];
+
+        let export = write_migration_batches(&tokens, 1);
+        assert_eq!(export.uris.len(), 1);
+        let indexes: Vec<usize> = export.skipped.iter().map(|s| s.index).collect();
+        assert_eq!(indexes, [0, 2, 3]);
+        assert_eq!(export.skipped[0].label, "Acme:slow");
+        assert!(export.skipped[0].reason.contains("period"));
+        assert!(export.skipped[2].reason.contains("too large"));
+
+        assert!(write_migration_batches(&[], 1).uris.is_empty());
+    }
+}
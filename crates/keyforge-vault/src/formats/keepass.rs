@@ -0,0 +1,1074 @@
+//! KeePass (KDBX 4) import of OTP entries
+//!
+//! A KDBX 4 file is
+//!
+//! ```text
+//! signatures(8) ‖ version(4) ‖ header fields ‖ SHA-256(header) ‖ HMAC(header)
+//!   ‖ blocks of [HMAC-SHA256(32) ‖ size(4, LE) ‖ data], ending with size 0
+//! ```
+//!
+//! The composite key is SHA-256 over SHA-256(password) and the keyfile's
+//! key, stretched by the KDF named in the header (Argon2d, Argon2id or
+//! AES-KDF). The concatenated block data is AES-256-CBC or ChaCha20
+//! ciphertext, optionally gzipped, and starts with an inner header that
+//! keys the ChaCha20 stream masking `Protected="True"` values in the XML.
+//!
+//! Only entries carrying an OTP are read; ordinary password entries are
+//! ignored rather than reported. Entries in the recycle bin and in entry
+//! history are never imported.
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use flate2::read::GzDecoder;
+use hmac::{Hmac, Mac};
+use keyforge_crypto::kdf::{self, KdfParams};
+use keyforge_crypto::keystream::ChaCha20Keystream;
+use keyforge_crypto::secure_mem::{LockedBuffer, LockedKey};
+use roxmltree::{Document, Node, NodeId};
+use sha2::{Digest, Sha256, Sha512};
+use zeroize::Zeroize;
+
+use super::{decode_hex, decode_secret, invalid, xml_child, ParsedTokens, PortableToken};
+use crate::constants::{
+    DEFAULT_DIGITS, DEFAULT_ISSUER, DEFAULT_PERIOD, KEEPASS_MAX_AES_KDF_ROUNDS,
+    KEEPASS_MAX_ARGON2_ITERATIONS, KEEPASS_MAX_ARGON2_MEMORY, KEEPASS_MAX_ARGON2_PARALLELISM,
+    KEEPASS_MAX_XML_SIZE, TOKEN_TYPE_HOTP, TOKEN_TYPE_STEAM, TOKEN_TYPE_TOTP,
+};
+use crate::error::VaultError;
+use crate::import::{Confidence, ImportCandidate, ImportSecret, Importer, SniffedFile};
+use crate::otpauth::{OtpAuthUri, ParseMode};
+use crate::token::NewToken;
+
+const NAME: &str = "KeePass";
+
+const SIGNATURE_1: u32 = 0x9AA2_D903;
+const SIGNATURE_2: u32 = 0xB54B_FB67;
+const MAJOR_VERSION: u16 = 4;
+
+const HEADER_END: u8 = 0;
+const HEADER_CIPHER_ID: u8 = 2;
+const HEADER_COMPRESSION: u8 = 3;
+const HEADER_MASTER_SEED: u8 = 4;
+const HEADER_ENCRYPTION_IV: u8 = 7;
+const HEADER_KDF_PARAMETERS: u8 = 11;
+
+const INNER_HEADER_END: u8 = 0;
+const INNER_STREAM_ID: u8 = 1;
+const INNER_STREAM_KEY: u8 = 2;
+const INNER_STREAM_CHACHA20: u32 = 3;
+
+const CIPHER_AES256: [u8; 16] = uuid(0x31c1f2e6_bf71_4350_be58_05216afc5aff);
+const CIPHER_CHACHA20: [u8; 16] = uuid(0xd6038a2b_8b6f_4cb5_a524_339a31dbb59a);
+const KDF_AES: [u8; 16] = uuid(0xc9d9f39a_628a_4460_bf74_0d08c18a4fea);
+const KDF_ARGON2D: [u8; 16] = uuid(0xef636ddf_8c29_444b_91f7_a9a403e30a0c);
+const KDF_ARGON2ID: [u8; 16] = uuid(0x9e298b19_56db_4773_b23d_fc3ec6f0a1e6);
+const ARGON2_VERSION_13: u32 = 0x13;
+
+const SHA256_SIZE: usize = 32;
+const HMAC_SIZE: usize = 32;
+const MASTER_SEED_SIZE: usize = 32;
+
+const FIELD_TITLE: &str = "Title";
+const FIELD_USERNAME: &str = "UserName";
+/// KeePassXC's current field (an `otpauth://` URI), also used by KeeOTP.
+const FIELD_OTP: &str = "otp";
+/// KeePassXC's legacy fields, shared with KeeTrayTOTP.
+const FIELD_TOTP_SEED: &str = "TOTP Seed";
+const FIELD_TOTP_SETTINGS: &str = "TOTP Settings";
+const OTP_FIELDS: [&str; 5] = [
+    FIELD_TITLE,
+    FIELD_USERNAME,
+    FIELD_OTP,
+    FIELD_TOTP_SEED,
+    FIELD_TOTP_SETTINGS,
+];
+/// Digits value in `TOTP Settings` that selects Steam's encoding.
+const STEAM_SETTINGS_DIGITS: &str = "S";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// A KeePass UUID in its on-disk byte order.
+const fn uuid(value: u128) -> [u8; 16] {
+    value.to_be_bytes()
+}
+
+/// Decode the OTP entries of a KDBX 4 database.
+///
+/// `password` and `keyfile` are the database's key components; a database
+/// protected by only one of them gets `None` for the other. Tokens come
+/// back in tree order, each tagged with its group path (without the root
+/// group). Skipped entries are indexed by their position among all
+/// entries of the tree.
+pub fn read_keepass(
+    data: &[u8],
+    password: Option<&[u8]>,
+    keyfile: Option<&[u8]>,
+) -> Result<ParsedTokens, String> {
+    if password.is_none() && keyfile.is_none() {
+        return Err(invalid(NAME, "a password or keyfile is required"));
+    }
+    let mut reader = Reader::new(data);
+    if reader.u32()? != SIGNATURE_1 || reader.u32()? != SIGNATURE_2 {
+        return Err(invalid(NAME, "not a KeePass database"));
+    }
+    let _minor = reader.u16()?;
+    let major = reader.u16()?;
+    if major != MAJOR_VERSION {
+        return Err(VaultError::UnsupportedFormat(format!(
+            "KDBX {major}; save the database as KDBX 4 in KeePassXC first"
+        ))
+        .into());
+    }
+
+    let header = OuterHeader::read(&mut reader)?;
+    let header_bytes = &data[..reader.pos];
+    if reader.take(SHA256_SIZE)? != Sha256::digest(header_bytes).as_slice() {
+        return Err(invalid(NAME, "the header is corrupt"));
+    }
+
+    let composite = composite_key(password, keyfile)?;
+    let transformed = header.kdf.transform(&composite)?;
+    let mut seeded = Sha256::new();
+    seeded.update(header.master_seed);
+    seeded.update(transformed.expose());
+    let mut master_key = LockedKey::zeroed();
+    let mut hash = seeded.finalize();
+    master_key.expose_mut().copy_from_slice(&hash);
+    hash.zeroize();
+    let mut hmac_base = Sha512::new();
+    hmac_base.update(header.master_seed);
+    hmac_base.update(transformed.expose());
+    hmac_base.update([1u8]);
+    let mut hmac_base = hmac_base.finalize();
+
+    let header_mac = block_mac(&hmac_base, u64::MAX, &[header_bytes]);
+    let verified = header_mac.verify_slice(reader.take(HMAC_SIZE)?);
+    if verified.is_err() {
+        hmac_base.zeroize();
+        return Err(VaultError::WrongImportPassword.into());
+    }
+    let ciphertext = read_blocks(&mut reader, &hmac_base);
+    hmac_base.zeroize();
+    let payload = header.decrypt(&ciphertext?, &master_key)?;
+    let payload = if header.compressed {
+        gunzip(payload.as_slice())?
+    } else {
+        payload
+    };
+
+    let mut inner = Reader::new(payload.as_slice());
+    let mut stream = read_inner_header(&mut inner)?;
+    let xml = std::str::from_utf8(&payload.as_slice()[inner.pos..])
+        .map_err(|_| invalid(NAME, "the XML is not UTF-8"))?;
+    let doc = Document::parse(xml).map_err(|e| invalid(NAME, &e.to_string()))?;
+    let protected = ProtectedValues::unmask(&doc, &mut stream)?;
+
+    let mut parsed = ParsedTokens::default();
+    let root = xml_child(doc.root_element(), "Root")
+        .and_then(|root| xml_child(root, "Group"))
+        .ok_or_else(|| invalid(NAME, "the database has no root group"))?;
+    let recycle_bin = recycle_bin(&doc);
+    let mut index = 0;
+    walk_group(root, &[], recycle_bin, &protected, &mut index, &mut parsed);
+    Ok(parsed)
+}
+
+/// Bounds-checked little-endian reader.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| invalid(NAME, "the file is truncated"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    /// A `u32` length followed by that many bytes.
+    fn field(&mut self) -> Result<&'a [u8], String> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+}
+
+struct OuterHeader<'a> {
+    cipher: [u8; 16],
+    compressed: bool,
+    master_seed: &'a [u8],
+    iv: &'a [u8],
+    kdf: Kdf,
+}
+
+impl<'a> OuterHeader<'a> {
+    fn read(reader: &mut Reader<'a>) -> Result<Self, String> {
+        let (mut cipher, mut compressed, mut master_seed, mut iv, mut kdf) =
+            (None, false, None, None, None);
+        loop {
+            let id = reader.u8()?;
+            let value = reader.field()?;
+            match id {
+                HEADER_END => break,
+                HEADER_CIPHER_ID => cipher = value.try_into().ok(),
+                HEADER_COMPRESSION => compressed = value.first().is_some_and(|c| *c != 0),
+                HEADER_MASTER_SEED => master_seed = Some(value),
+                HEADER_ENCRYPTION_IV => iv = Some(value),
+                HEADER_KDF_PARAMETERS => kdf = Some(Kdf::parse(value)?),
+                _ => {}
+            }
+        }
+        let missing = |name: &str| invalid(NAME, &format!("the header has no {name}"));
+        Ok(Self {
+            cipher: cipher.ok_or_else(|| missing("cipher"))?,
+            compressed,
+            master_seed: master_seed
+                .filter(|s| s.len() == MASTER_SEED_SIZE)
+                .ok_or_else(|| missing("master seed"))?,
+            iv: iv.ok_or_else(|| missing("encryption IV"))?,
+            kdf: kdf.ok_or_else(|| missing("KDF parameters"))?,
+        })
+    }
+
+    fn decrypt(&self, ciphertext: &[u8], key: &LockedKey) -> Result<LockedBuffer, String> {
+        match self.cipher {
+            CIPHER_AES256 => keyforge_crypto::cbc_hmac::decrypt_unauthenticated_locked(
+                ciphertext,
+                key.expose(),
+                self.iv,
+            )
+            .map_err(|_| invalid(NAME, "the payload does not decrypt")),
+            CIPHER_CHACHA20 => {
+                let nonce: &[u8; 12] = self
+                    .iv
+                    .try_into()
+                    .map_err(|_| invalid(NAME, "bad ChaCha20 nonce length"))?;
+                let mut payload = LockedBuffer::from_slice(ciphertext);
+                ChaCha20Keystream::new(key.expose(), nonce).apply(payload.as_mut_slice());
+                Ok(payload)
+            }
+            _ => Err(VaultError::UnsupportedFormat(
+                "KeePass cipher (only AES-256 and ChaCha20 are supported)".into(),
+            )
+            .into()),
+        }
+    }
+}
+
+enum Kdf {
+    Aes {
+        seed: [u8; 32],
+        rounds: u64,
+    },
+    Argon2 {
+        id: bool,
+        salt: Vec<u8>,
+        params: KdfParams,
+    },
+}
+
+impl Kdf {
+    /// Parse the KDF parameters (a KeePass `VariantDictionary`) and check
+    /// the cost against the import limits before any work is done.
+    fn parse(data: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader::new(data);
+        let _version = reader.u16()?;
+        let mut items = HashMap::new();
+        loop {
+            let kind = reader.u8()?;
+            if kind == 0 {
+                break;
+            }
+            let name = String::from_utf8_lossy(reader.field()?).into_owned();
+            items.insert(name, reader.field()?);
+        }
+        let bytes = |name: &str| {
+            items
+                .get(name)
+                .copied()
+                .ok_or_else(|| invalid(NAME, &format!("KDF parameter {name} is missing")))
+        };
+        let uint = |name: &str| -> Result<u64, String> {
+            match bytes(name)? {
+                v if v.len() == 4 => Ok(u32::from_le_bytes(v.try_into().unwrap()) as u64),
+                v if v.len() == 8 => Ok(u64::from_le_bytes(v.try_into().unwrap())),
+                _ => Err(invalid(
+                    NAME,
+                    &format!("KDF parameter {name} is not an integer"),
+                )),
+            }
+        };
+        let too_costly = |what: String| -> String {
+            VaultError::UnsupportedFormat(format!("KeePass key derivation cost ({what})")).into()
+        };
+
+        let kdf_id: [u8; 16] = bytes("$UUID")?
+            .try_into()
+            .map_err(|_| invalid(NAME, "bad KDF UUID"))?;
+        match kdf_id {
+            KDF_AES => {
+                let rounds = uint("R")?;
+                if rounds > KEEPASS_MAX_AES_KDF_ROUNDS {
+                    return Err(too_costly(format!("{rounds} AES rounds")));
+                }
+                let seed = bytes("S")?
+                    .try_into()
+                    .map_err(|_| invalid(NAME, "bad AES-KDF seed length"))?;
+                Ok(Self::Aes { seed, rounds })
+            }
+            KDF_ARGON2D | KDF_ARGON2ID => {
+                let version = uint("V")?;
+                if version != ARGON2_VERSION_13 as u64 {
+                    return Err(VaultError::UnsupportedFormat(format!(
+                        "Argon2 version {version:#x}"
+                    ))
+                    .into());
+                }
+                let (memory, iterations, parallelism) = (uint("M")?, uint("I")?, uint("P")?);
+                if memory > KEEPASS_MAX_ARGON2_MEMORY {
+                    return Err(too_costly(format!("{} MiB of memory", memory >> 20)));
+                }
+                if iterations > KEEPASS_MAX_ARGON2_ITERATIONS as u64 {
+                    return Err(too_costly(format!("{iterations} iterations")));
+                }
+                if parallelism > KEEPASS_MAX_ARGON2_PARALLELISM as u64 {
+                    return Err(too_costly(format!("{parallelism} lanes")));
+                }
+                Ok(Self::Argon2 {
+                    id: kdf_id == KDF_ARGON2ID,
+                    salt: bytes("S")?.to_vec(),
+                    params: KdfParams {
+                        memory_kib: (memory / 1024) as u32,
+                        time_cost: iterations as u32,
+                        parallelism: parallelism as u32,
+                    },
+                })
+            }
+            _ => {
+                Err(VaultError::UnsupportedFormat("KeePass key derivation function".into()).into())
+            }
+        }
+    }
+
+    fn transform(&self, composite: &LockedKey) -> Result<LockedKey, String> {
+        match self {
+            Self::Aes { seed, rounds } => Ok(kdf::derive_key_aes_kdf_locked(
+                composite.expose(),
+                seed,
+                *rounds,
+            )),
+            Self::Argon2 {
+                id: true,
+                salt,
+                params,
+            } => kdf::derive_key_argon2id_locked(composite.expose(), salt, params),
+            Self::Argon2 {
+                id: false,
+                salt,
+                params,
+            } => kdf::derive_key_argon2d_locked(composite.expose(), salt, params),
+        }
+    }
+}
+
+/// SHA-256 over the hashed password and the keyfile's key, whichever are
+/// present.
+fn composite_key(password: Option<&[u8]>, keyfile: Option<&[u8]>) -> Result<LockedKey, String> {
+    let mut composite = Sha256::new();
+    if let Some(password) = password {
+        let mut hash = Sha256::digest(password);
+        composite.update(hash);
+        hash.zeroize();
+    }
+    if let Some(keyfile) = keyfile {
+        let key = keyfile_key(keyfile)?;
+        composite.update(key.expose());
+    }
+    let mut key = LockedKey::zeroed();
+    let mut hash = composite.finalize();
+    key.expose_mut().copy_from_slice(&hash);
+    hash.zeroize();
+    Ok(key)
+}
+
+/// The 32-byte key of a keyfile: an XML keyfile (version 1.0 or 2.0), 32
+/// raw bytes, 64 hex digits, or else the SHA-256 of the whole file.
+fn keyfile_key(keyfile: &[u8]) -> Result<LockedKey, String> {
+    let mut key = LockedKey::zeroed();
+    if let Some(decoded) = xml_keyfile(keyfile)? {
+        key.expose_mut().copy_from_slice(decoded.as_slice());
+    } else if keyfile.len() == 32 {
+        key.expose_mut().copy_from_slice(keyfile);
+    } else if let Some(decoded) = (keyfile.len() == 64)
+        .then(|| decode_hex(keyfile))
+        .flatten()
+        .filter(|d| d.len() == 32)
+    {
+        key.expose_mut().copy_from_slice(decoded.as_slice());
+    } else {
+        let mut hash = Sha256::digest(keyfile);
+        key.expose_mut().copy_from_slice(&hash);
+        hash.zeroize();
+    }
+    Ok(key)
+}
+
+/// The key of an XML keyfile, `None` if `keyfile` is not one.
+fn xml_keyfile(keyfile: &[u8]) -> Result<Option<LockedBuffer>, String> {
+    let Some(doc) = std::str::from_utf8(keyfile)
+        .ok()
+        .and_then(|text| Document::parse(text).ok())
+        .filter(|doc| doc.root_element().has_tag_name("KeyFile"))
+    else {
+        return Ok(None);
+    };
+    let bad = || invalid(NAME, "unreadable keyfile");
+    let root = doc.root_element();
+    let version = xml_child(root, "Meta")
+        .and_then(|meta| xml_child(meta, "Version"))
+        .and_then(|v| v.text())
+        .unwrap_or("")
+        .trim();
+    let data = xml_child(root, "Key")
+        .and_then(|k| xml_child(k, "Data"))
+        .ok_or_else(bad)?;
+    let mut text: String = data
+        .text()
+        .unwrap_or("")
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    let key = if version.starts_with("2.") {
+        decode_hex(text.as_bytes()).ok_or_else(bad).and_then(|key| {
+            let Some(hash) = data.attribute("Hash") else {
+                return Ok(key);
+            };
+            let expected = decode_hex(hash.as_bytes()).ok_or_else(bad)?;
+            if Sha256::digest(key.as_slice()).starts_with(expected.as_slice()) {
+                Ok(key)
+            } else {
+                Err(invalid(NAME, "the keyfile is corrupt"))
+            }
+        })
+    } else {
+        BASE64
+            .decode(&text)
+            .map(|mut bytes| {
+                let key = LockedBuffer::from_slice(&bytes);
+                bytes.zeroize();
+                key
+            })
+            .map_err(|_| bad())
+    };
+    text.zeroize();
+    let key = key?;
+    if key.len() != 32 {
+        return Err(bad());
+    }
+    Ok(Some(key))
+}
+
+/// HMAC-SHA256 of a block under the block's own key,
+/// SHA-512(index ‖ base key).
+fn block_mac(base: &[u8], index: u64, parts: &[&[u8]]) -> HmacSha256 {
+    let mut key = Sha512::new();
+    key.update(index.to_le_bytes());
+    key.update(base);
+    let mut key = key.finalize();
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(&key).expect("HMAC accepts any key length");
+    key.zeroize();
+    for part in parts {
+        mac.update(part);
+    }
+    mac
+}
+
+/// Verify and concatenate the HMAC blocks holding the ciphertext.
+fn read_blocks(reader: &mut Reader<'_>, hmac_base: &[u8]) -> Result<Vec<u8>, String> {
+    let mut ciphertext = Vec::new();
+    for index in 0u64.. {
+        let mac = reader.take(HMAC_SIZE)?;
+        let size = reader.u32()?;
+        let block = reader.take(size as usize)?;
+        block_mac(
+            hmac_base,
+            index,
+            &[&index.to_le_bytes(), &size.to_le_bytes(), block],
+        )
+        .verify_slice(mac)
+        .map_err(|_| invalid(NAME, &format!("block {index} failed authentication")))?;
+        if block.is_empty() {
+            break;
+        }
+        ciphertext.extend_from_slice(block);
+    }
+    Ok(ciphertext)
+}
+
+/// Decompress into locked memory, up to [`KEEPASS_MAX_XML_SIZE`]. The
+/// output grows by doubling into fresh locked buffers, so no plaintext is
+/// left behind in a reallocated one: each outgrown buffer is zeroized as
+/// it is dropped.
+fn gunzip(compressed: &[u8]) -> Result<LockedBuffer, String> {
+    const INITIAL_SIZE: usize = 64 << 10;
+    let limit = KEEPASS_MAX_XML_SIZE as usize;
+    let mut decoder = GzDecoder::new(compressed);
+    let mut buffer = LockedBuffer::new(INITIAL_SIZE);
+    let mut filled = 0;
+    loop {
+        if filled == buffer.len() {
+            if filled > limit {
+                return Err(invalid(NAME, "the database is too large"));
+            }
+            let mut grown = LockedBuffer::new((filled * 2).min(limit + 1));
+            grown.as_mut_slice()[..filled].copy_from_slice(buffer.as_slice());
+            buffer = grown;
+        }
+        let read = decoder
+            .read(&mut buffer.as_mut_slice()[filled..])
+            .map_err(|e| invalid(NAME, &format!("the payload does not decompress: {e}")))?;
+        if read == 0 {
+            break;
+        }
+        filled += read;
+    }
+    Ok(LockedBuffer::from_slice(&buffer.as_slice()[..filled]))
+}
+
+/// Read the inner header and set up the stream masking protected values.
+fn read_inner_header(reader: &mut Reader<'_>) -> Result<ChaCha20Keystream, String> {
+    let (mut stream_id, mut stream_key) = (None, None);
+    loop {
+        let id = reader.u8()?;
+        let value = reader.field()?;
+        match id {
+            INNER_HEADER_END => break,
+            INNER_STREAM_ID if value.len() == 4 => {
+                stream_id = Some(u32::from_le_bytes(value.try_into().unwrap()));
+            }
+            INNER_STREAM_KEY => stream_key = Some(value),
+            _ => {}
+        }
+    }
+    if stream_id != Some(INNER_STREAM_CHACHA20) {
+        return Err(VaultError::UnsupportedFormat(
+            "KeePass inner stream (only ChaCha20 is supported)".into(),
+        )
+        .into());
+    }
+    let stream_key = stream_key.ok_or_else(|| invalid(NAME, "the inner stream key is missing"))?;
+    let mut hash = Sha512::digest(stream_key);
+    let key: [u8; 32] = hash[..32].try_into().unwrap();
+    let nonce: [u8; 12] = hash[32..44].try_into().unwrap();
+    let stream = ChaCha20Keystream::new(&key, &nonce);
+    hash.zeroize();
+    Ok(stream)
+}
+
+/// Unmasked values of the protected fields an OTP import needs, by node.
+/// Every protected value is unmasked in document order, since each one
+/// consumes the stream; the rest (passwords) are wiped straight away.
+struct ProtectedValues(HashMap<NodeId, String>);
+
+impl ProtectedValues {
+    fn unmask(doc: &Document<'_>, stream: &mut ChaCha20Keystream) -> Result<Self, String> {
+        let mut values = HashMap::new();
+        for node in doc.descendants() {
+            if node.attribute("Protected") != Some("True") {
+                continue;
+            }
+            let mut value = BASE64
+                .decode(node.text().unwrap_or("").trim())
+                .map_err(|_| invalid(NAME, "a protected value is not base64"))?;
+            stream.apply(&mut value);
+            let wanted = node.has_tag_name("Value")
+                && node
+                    .parent()
+                    .and_then(|string| xml_child(string, "Key"))
+                    .and_then(|key| key.text())
+                    .is_some_and(|key| OTP_FIELDS.contains(&key));
+            match String::from_utf8(value) {
+                Ok(text) if wanted => {
+                    values.insert(node.id(), text);
+                }
+                Ok(mut text) => text.zeroize(),
+                Err(e) => e.into_bytes().zeroize(),
+            }
+        }
+        Ok(Self(values))
+    }
+}
+
+impl Drop for ProtectedValues {
+    fn drop(&mut self) {
+        for value in self.0.values_mut() {
+            value.zeroize();
+        }
+    }
+}
+
+/// UUID of the recycle bin group, if the database uses one.
+fn recycle_bin<'a>(doc: &'a Document<'_>) -> Option<&'a str> {
+    let meta = xml_child(doc.root_element(), "Meta")?;
+    let enabled = xml_child(meta, "RecycleBinEnabled").and_then(|e| e.text());
+    if enabled.is_some_and(|e| !e.trim().eq_ignore_ascii_case("true")) {
+        return None;
+    }
+    xml_child(meta, "RecycleBinUUID")
+        .and_then(|u| u.text())
+        .map(str::trim)
+}
+
+fn walk_group(
+    group: Node<'_, '_>,
+    path: &[String],
+    recycle_bin: Option<&str>,
+    protected: &ProtectedValues,
+    index: &mut usize,
+    parsed: &mut ParsedTokens,
+) {
+    for node in group.children().filter(|n| n.is_element()) {
+        if node.has_tag_name("Entry") {
+            let fields = entry_fields(node, protected);
+            if let Some(result) = map_entry(&fields) {
+                match result {
+                    Ok(mut token) => {
+                        if !path.is_empty() {
+                            token.groups = vec![path.join("/")];
+                        }
+                        parsed.add(*index, token);
+                    }
+                    Err(reason) => {
+                        let (issuer, account) = names(&fields);
+                        parsed.skip(*index, format!("{issuer}:{account}"), reason);
+                    }
+                }
+            }
+            *index += 1;
+        } else if node.has_tag_name("Group") {
+            let uuid = xml_child(node, "UUID")
+                .and_then(|u| u.text())
+                .map(str::trim);
+            if uuid.is_some() && uuid == recycle_bin {
+                continue;
+            }
+            let name = xml_child(node, "Name").and_then(|n| n.text()).unwrap_or("");
+            let mut path = path.to_vec();
+            path.push(name.trim().to_string());
+            walk_group(node, &path, recycle_bin, protected, index, parsed);
+        }
+    }
+}
+
+/// The entry's OTP-related string fields, protected ones unmasked.
+struct EntryFields<'a>(HashMap<&'a str, &'a str>);
+
+impl<'a> EntryFields<'a> {
+    fn get(&self, key: &str) -> Option<&'a str> {
+        self.0
+            .get(key)
+            .copied()
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+    }
+}
+
+fn entry_fields<'a>(entry: Node<'a, '_>, protected: &'a ProtectedValues) -> EntryFields<'a> {
+    let mut fields = HashMap::new();
+    for string in entry.children().filter(|c| c.has_tag_name("String")) {
+        let Some(key) = xml_child(string, "Key").and_then(|k| k.text()) else {
+            continue;
+        };
+        let Some(value) = xml_child(string, "Value") else {
+            continue;
+        };
+        let text = match protected.0.get(&value.id()) {
+            Some(text) => text.as_str(),
+            None => value.text().unwrap_or(""),
+        };
+        if OTP_FIELDS.contains(&key) {
+            fields.insert(key, text);
+        }
+    }
+    EntryFields(fields)
+}
+
+/// The entry's token, or `None` if it carries no OTP at all.
+fn map_entry(fields: &EntryFields<'_>) -> Option<Result<PortableToken, String>> {
+    let token = if let Some(otp) = fields.get(FIELD_OTP) {
+        if otp.starts_with("otpauth://") {
+            from_uri(otp, fields)
+        } else {
+            from_keeotp(otp, fields)
+        }
+    } else {
+        from_legacy(fields.get(FIELD_TOTP_SEED)?, fields)
+    };
+    Some(token.and_then(PortableToken::checked))
+}
+
+/// KeePassXC's `otp` field: an `otpauth://` URI. The entry's title and
+/// username fill in an issuer or account the URI lacks.
+fn from_uri(otp: &str, fields: &EntryFields<'_>) -> Result<NewToken, String> {
+    let uri = OtpAuthUri::parse(otp, ParseMode::Lenient).map_err(|e| e.to_string())?;
+    let mut token = uri.to_new_token().map_err(|e| e.to_string())?;
+    let (issuer, account) = names(fields);
+    if uri.issuer.is_none() && uri.label_issuer.is_none() {
+        token.issuer = issuer;
+    }
+    if token.account.trim().is_empty() {
+        token.account = account;
+    }
+    Ok(token)
+}
+
+/// KeeOTP's `otp` field: `key=<base32>&type=totp&size=6&step=30&...`.
+fn from_keeotp(otp: &str, fields: &EntryFields<'_>) -> Result<NewToken, String> {
+    let params: HashMap<&str, &str> = otp
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| (k.trim(), v.trim()))
+        .collect();
+    let secret = params.get("key").ok_or("no key in the otp field")?;
+    let (token_type, counter) = match params.get("type").copied().unwrap_or("totp") {
+        t if t.eq_ignore_ascii_case("totp") => (TOKEN_TYPE_TOTP, 0),
+        t if t.eq_ignore_ascii_case("hotp") => (
+            TOKEN_TYPE_HOTP,
+            keeotp_number(&params, "counter")?.unwrap_or(0),
+        ),
+        t => return Err(format!("{t} tokens are not supported")),
+    };
+    let (issuer, account) = names(fields);
+    Ok(NewToken {
+        issuer,
+        account,
+        secret: decode_secret(secret)?,
+        algorithm: params
+            .get("otpHashMode")
+            .copied()
+            .unwrap_or("SHA1")
+            .to_ascii_uppercase(),
+        digits: keeotp_number(&params, "size")?.unwrap_or(DEFAULT_DIGITS),
+        token_type: token_type.to_string(),
+        period: keeotp_number(&params, "step")?
+            .filter(|p| *p > 0)
+            .unwrap_or(DEFAULT_PERIOD),
+        counter,
+        icon: None,
+    })
+}
+
+/// A numeric KeeOTP parameter, rejected when it does not fit `T`.
+fn keeotp_number<T: std::str::FromStr>(
+    params: &HashMap<&str, &str>,
+    name: &str,
+) -> Result<Option<T>, String> {
+    params
+        .get(name)
+        .map(|v| {
+            v.parse()
+                .map_err(|_| format!("invalid {name} in the otp field"))
+        })
+        .transpose()
+}
+
+/// KeePassXC's legacy `TOTP Seed` and `TOTP Settings` (`period;digits`,
+/// where digits `S` means Steam; defaults `30;6`).
+fn from_legacy(seed: &str, fields: &EntryFields<'_>) -> Result<NewToken, String> {
+    let settings = fields.get(FIELD_TOTP_SETTINGS).unwrap_or("");
+    let mut parts = settings.split(';').map(str::trim);
+    let period = match parts.next().filter(|p| !p.is_empty()) {
+        Some(p) => p.parse().map_err(|_| "invalid TOTP Settings".to_string())?,
+        None => DEFAULT_PERIOD,
+    };
+    let (token_type, digits) = match parts.next().filter(|d| !d.is_empty()) {
+        Some(STEAM_SETTINGS_DIGITS) => (TOKEN_TYPE_STEAM, keyforge_crypto::otp::STEAM_DIGITS),
+        Some(d) => (
+            TOKEN_TYPE_TOTP,
+            d.parse().map_err(|_| "invalid TOTP Settings".to_string())?,
+        ),
+        None => (TOKEN_TYPE_TOTP, DEFAULT_DIGITS),
+    };
+    let (issuer, account) = names(fields);
+    Ok(NewToken {
+        issuer,
+        account,
+        secret: decode_secret(seed)?,
+        algorithm: "SHA1".to_string(),
+        digits,
+        token_type: token_type.to_string(),
+        period,
+        counter: 0,
+        icon: None,
+    })
+}
+
+/// Issuer and account from the entry's title and username.
+fn names(fields: &EntryFields<'_>) -> (String, String) {
+    (
+        fields
+            .get(FIELD_TITLE)
+            .unwrap_or(DEFAULT_ISSUER)
+            .to_string(),
+        fields.get(FIELD_USERNAME).unwrap_or("").to_string(),
+    )
+}
+
+/// KeePass KDBX 4 databases, unlocked by a password, a keyfile or both.
+pub struct KeePassImporter;
+
+impl Importer for KeePassImporter {
+    fn id(&self) -> &'static str {
+        "keepass"
+    }
+
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn sniff(&self, file: &SniffedFile<'_>) -> Confidence {
+        let signature = |at: usize| {
+            file.data
+                .get(at..at + 4)
+                .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        };
+        if signature(0) == Some(SIGNATURE_1) && signature(4) == Some(SIGNATURE_2) {
+            Confidence::Certain
+        } else {
+            Confidence::No
+        }
+    }
+
+    fn is_encrypted(&self, _data: &[u8]) -> bool {
+        true
+    }
+
+    fn missing_secret(&self, _data: &[u8]) -> VaultError {
+        VaultError::ImportKeyRequired(NAME.into(), "A password or keyfile")
+    }
+
+    fn parse(
+        &self,
+        data: &[u8],
+        secret: Option<ImportSecret<'_>>,
+    ) -> Result<Vec<ImportCandidate>, String> {
+        let (password, keyfile) = match secret {
+            None => (None, None),
+            Some(ImportSecret::Password(password)) => (Some(password), None),
+            Some(ImportSecret::Keyfile { keyfile, password }) => (password, Some(keyfile)),
+            Some(ImportSecret::Key(_)) => return Err(self.missing_secret(data).into()),
+        };
+        Ok(read_keepass(data, password, keyfile)?.into_candidates())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AES: &[u8] = include_bytes!("../../tests/fixtures/keepass_aes.kdbx");
+    const AES_KEYFILE: &[u8] = include_bytes!("../../tests/fixtures/keepass_aes.keyx");
+    const ARGON2D: &[u8] = include_bytes!("../../tests/fixtures/keepass_argon2d.kdbx");
+
+    #[test]
+    fn test_aes_kdf_with_keyfile() {
+        let parsed = read_keepass(AES, Some(b"test"), Some(AES_KEYFILE)).unwrap();
+        let summary: Vec<(&str, &str, &str, &[String])> = parsed
+            .tokens
+            .iter()
+            .map(|t| {
+                (
+                    t.token.issuer.as_str(),
+                    t.token.account.as_str(),
+                    t.token.token_type.as_str(),
+                    t.groups.as_slice(),
+                )
+            })
+            .collect();
+        // History versions and the recycle bin are left out.
+        assert_eq!(
+            summary,
+            [
+                ("GitHub", "alice@example.com", "totp", &[][..]),
+                ("Bank", "bob", "hotp", &["Work".to_string()][..]),
+                ("Legacy", "erin", "totp", &["Work/Servers".to_string()][..]),
+                ("Steam", "gamer", "steam", &["Work/Servers".to_string()][..]),
+            ]
+        );
+
+        let github = &parsed.tokens[0].token;
+        assert_eq!(github.secret, b"12345678901234567890");
+        assert_eq!(github.algorithm, "SHA256");
+        assert_eq!((github.digits, github.period), (8, 60));
+        let bank = &parsed.tokens[1].token;
+        assert_eq!((bank.counter, bank.digits), (5, 6));
+        let legacy = &parsed.tokens[2].token;
+        assert_eq!(legacy.secret, b"Hello!\xde\xad\xbe\xef");
+        assert_eq!((legacy.digits, legacy.period), (8, 30));
+        assert_eq!(parsed.tokens[3].token.digits, 5);
+
+        assert_eq!(parsed.skipped_labels(), [(5, "Broken:dave")]);
+    }
+
+    #[test]
+    fn test_argon2d_chacha20() {
+        // The same entries, sealed with the other KDF and cipher.
+        let parsed = read_keepass(ARGON2D, Some(b"test"), None).unwrap();
+        let aes = read_keepass(AES, Some(b"test"), Some(AES_KEYFILE)).unwrap();
+        assert_eq!(parsed.summary(), aes.summary());
+        assert_eq!(parsed.skipped, aes.skipped);
+        let groups = |p: &ParsedTokens| -> Vec<Vec<String>> {
+            p.tokens.iter().map(|t| t.groups.clone()).collect()
+        };
+        assert_eq!(groups(&parsed), groups(&aes));
+    }
+
+    #[test]
+    fn test_keeotp_rejects_out_of_range_numbers() {
+        let fields = EntryFields(HashMap::from([(FIELD_TITLE, "Acme")]));
+        let token = from_keeotp("key=GEZDGNBV&size=8&step=60", &fields).unwrap();
+        assert_eq!((token.digits, token.period), (8, 60));
+        assert_eq!(
+            from_keeotp("key=GEZDGNBV&size=4294967302", &fields).unwrap_err(),
+            "invalid size in the otp field"
+        );
+        assert_eq!(
+            from_keeotp("key=GEZDGNBV&step=4294967326", &fields).unwrap_err(),
+            "invalid step in the otp field"
+        );
+    }
+
+    #[test]
+    fn test_wrong_credentials() {
+        let wrong = VaultError::WrongImportPassword.to_string();
+        let err = |result: Result<ParsedTokens, String>| result.err().unwrap();
+        assert_eq!(
+            err(read_keepass(AES, Some(b"nope"), Some(AES_KEYFILE))),
+            wrong
+        );
+        assert_eq!(err(read_keepass(AES, Some(b"test"), None)), wrong);
+        assert_eq!(err(read_keepass(AES, Some(b"test"), Some(b"other"))), wrong);
+        assert_eq!(err(read_keepass(ARGON2D, Some(b"nope"), None)), wrong);
+        assert!(read_keepass(ARGON2D, None, None).is_err());
+    }
+
+    #[test]
+    fn test_damage_and_unsupported_files() {
+        let mut damaged = AES.to_vec();
+        let last = damaged.len() - 40;
+        damaged[last] ^= 1;
+        let err = read_keepass(&damaged, Some(b"test"), Some(AES_KEYFILE))
+            .err()
+            .unwrap();
+        assert!(err.contains("authentication"));
+
+        let mut kdbx3 = ARGON2D.to_vec();
+        kdbx3[10..12].copy_from_slice(&3u16.to_le_bytes());
+        let err = read_keepass(&kdbx3, Some(b"test"), None).err().unwrap();
+        assert!(err.contains("KDBX 3"));
+
+        assert!(read_keepass(b"not a database", Some(b"test"), None).is_err());
+        assert!(read_keepass(&ARGON2D[..100], Some(b"test"), None).is_err());
+    }
+
+    #[test]
+    fn test_kdf_cost_is_bounded() {
+        let costly = |memory: u64, iterations: u64, parallelism: u32| {
+            let mut kdf = 0x100u16.to_le_bytes().to_vec();
+            for (kind, name, value) in [
+                (0x42, "$UUID", KDF_ARGON2D.to_vec()),
+                (0x42, "S", vec![0; 32]),
+                (0x04, "P", parallelism.to_le_bytes().to_vec()),
+                (0x05, "M", memory.to_le_bytes().to_vec()),
+                (0x05, "I", iterations.to_le_bytes().to_vec()),
+                (0x04, "V", ARGON2_VERSION_13.to_le_bytes().to_vec()),
+            ] {
+                kdf.push(kind);
+                kdf.extend((name.len() as u32).to_le_bytes());
+                kdf.extend(name.as_bytes());
+                kdf.extend((value.len() as u32).to_le_bytes());
+                kdf.extend(value);
+            }
+            kdf.push(0);
+            Kdf::parse(&kdf)
+        };
+        assert!(costly(1 << 20, 1, 1).is_ok());
+        assert!(costly(
+            KEEPASS_MAX_ARGON2_MEMORY,
+            KEEPASS_MAX_ARGON2_ITERATIONS as u64,
+            KEEPASS_MAX_ARGON2_PARALLELISM
+        )
+        .is_ok());
+        for (memory, iterations, parallelism) in [
+            (KEEPASS_MAX_ARGON2_MEMORY + 1, 1, 1),
+            (1 << 20, KEEPASS_MAX_ARGON2_ITERATIONS as u64 + 1, 1),
+            (1 << 20, 1, KEEPASS_MAX_ARGON2_PARALLELISM + 1),
+        ] {
+            assert!(costly(memory, iterations, parallelism)
+                .err()
+                .unwrap()
+                .contains("cost"));
+        }
+    }
+
+    #[test]
+    fn test_gunzip_grows_past_its_first_buffer() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let xml: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+        encoder.write_all(&xml).unwrap();
+        let mut compressed = encoder.finish().unwrap();
+        assert_eq!(gunzip(&compressed).unwrap().as_slice(), xml);
+
+        // A trailer size that disagrees with the data is an error, not a
+        // buffer size.
+        let at = compressed.len() - 4;
+        compressed[at..].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(gunzip(&compressed).is_err());
+    }
+
+    #[test]
+    fn test_keyfile_formats() {
+        let key = [0xabu8; 32];
+        let raw = keyfile_key(&key).unwrap();
+        assert_eq!(raw.expose(), &key);
+        let hex = keyfile_key("ab".repeat(32).as_bytes()).unwrap();
+        assert_eq!(hex.expose(), &key);
+        let v1 = format!(
+            "<KeyFile><Meta><Version>1.00</Version></Meta><Key><Data>{}</Data></Key></KeyFile>",
+            BASE64.encode(key)
+        );
+        assert_eq!(keyfile_key(v1.as_bytes()).unwrap().expose(), &key);
+        let other = keyfile_key(b"any file at all").unwrap();
+        assert_eq!(
+            other.expose().as_slice(),
+            Sha256::digest(b"any file at all").as_slice()
+        );
+    }
+}
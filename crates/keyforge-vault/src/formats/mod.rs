@@ -0,0 +1,342 @@
+//! Foreign authenticator formats
+//!
+//! Each submodule decodes one app's export into [`ParsedTokens`]: the tokens
+//! KeyForge can store plus a report of the entries it had to skip. A single
+//! unsupported entry never aborts the rest of the file; only a file that
+//! cannot be read at all is an error. Formats that other apps import from
+//! KeyForge also have a writer.
+//!
+//! Each format is imported through its [`Importer`](crate::import::Importer)
+//! in the [`ImporterRegistry`](crate::import::ImporterRegistry).
+//! [`Vault::import_file`](crate::db::Vault::import_file) decrypts and
+//! decodes the whole file before the first insert, then adds every token in
+//! one transaction. Entries a file could not provide or the vault could not
+//! store, and those the vault already holds, are listed in the
+//! [`ImportReport`](crate::import::ImportReport). Like every import, they
+//! are atomic unless called with
+//! [`ImportMode::BestEffort`](crate::import::ImportMode::BestEffort).
+
+pub mod aegis;
+pub mod andotp;
+pub mod authenticator_pro;
+pub mod bitwarden;
+pub mod ente;
+pub mod freeotp;
+pub mod google;
+pub mod keepass;
+mod protobuf;
+pub mod pskc;
+pub mod twofas;
+
+use std::fmt;
+
+use keyforge_crypto::secure_mem::LockedBuffer;
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::Serialize;
+use zeroize::Zeroize;
+
+use crate::error::VaultError;
+use crate::token::NewToken;
+
+#[cfg(feature = "sqlite")]
+use {crate::db::Vault, std::collections::HashSet};
+
+/// An entry a decoder could not turn into a token.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SkippedEntry {
+    /// Position of the entry in its source file.
+    pub index: usize,
+    /// Issuer and account as far as they could be read, for display.
+    pub label: String,
+    pub reason: String,
+}
+
+/// A token plus the metadata foreign formats carry with it (notes and
+/// groups), as read by the importers and written by the exporters.
+#[derive(Debug)]
+pub struct PortableToken {
+    pub token: NewToken,
+    pub note: Option<String>,
+    pub groups: Vec<String>,
+    /// Position of the entry in its source file, as for
+    /// [`SkippedEntry::index`]; 0 for tokens not read from a file.
+    pub index: usize,
+}
+
+impl From<NewToken> for PortableToken {
+    fn from(token: NewToken) -> Self {
+        Self {
+            token,
+            note: None,
+            groups: Vec::new(),
+            index: 0,
+        }
+    }
+}
+
+impl PortableToken {
+    /// Wrap a decoded token, or say why the vault cannot store it. The
+    /// secret of a rejected token is zeroized.
+    pub(crate) fn checked(mut token: NewToken) -> Result<Self, String> {
+        if let Err(e) = token.spec() {
+            token.secret.zeroize();
+            return Err(e);
+        }
+        Ok(Self::from(token))
+    }
+}
+
+/// Tokens decoded from a foreign export. Secrets still held here are
+/// zeroized on drop.
+#[derive(Debug, Default)]
+pub struct ParsedTokens {
+    pub tokens: Vec<PortableToken>,
+    pub skipped: Vec<SkippedEntry>,
+}
+
+impl ParsedTokens {
+    /// Append another file's results.
+    pub fn append(&mut self, mut other: ParsedTokens) {
+        self.tokens.append(&mut other.tokens);
+        self.skipped.append(&mut other.skipped);
+    }
+
+    /// Add the token decoded from the entry at `index`.
+    pub(crate) fn add(&mut self, index: usize, mut token: PortableToken) {
+        token.index = index;
+        self.tokens.push(token);
+    }
+
+    pub(crate) fn skip(&mut self, index: usize, label: String, reason: impl Into<String>) {
+        self.skipped.push(SkippedEntry {
+            index,
+            label,
+            reason: reason.into(),
+        });
+    }
+}
+
+impl Drop for ParsedTokens {
+    fn drop(&mut self) {
+        for imported in &mut self.tokens {
+            imported.token.secret.zeroize();
+        }
+    }
+}
+
+#[cfg(test)]
+impl ParsedTokens {
+    /// Issuer, account and type of each token, for fixture assertions.
+    pub(crate) fn summary(&self) -> Vec<(&str, &str, &str)> {
+        self.tokens
+            .iter()
+            .map(|t| {
+                (
+                    t.token.issuer.as_str(),
+                    t.token.account.as_str(),
+                    t.token.token_type.as_str(),
+                )
+            })
+            .collect()
+    }
+
+    /// Index and label of each skipped entry.
+    pub(crate) fn skipped_labels(&self) -> Vec<(usize, &str)> {
+        self.skipped
+            .iter()
+            .map(|s| (s.index, s.label.as_str()))
+            .collect()
+    }
+}
+
+/// Decode a base32 secret as authenticator apps export it: any case, with
+/// or without padding and whitespace.
+pub(crate) fn decode_secret(secret: &str) -> Result<Vec<u8>, String> {
+    let mut normalized: String = secret
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '=')
+        .collect::<String>()
+        .to_ascii_uppercase();
+    let decoded = base32::decode(base32::Alphabet::Rfc4648 { padding: false }, &normalized);
+    normalized.zeroize();
+    decoded
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| "invalid base32 secret".to_string())
+}
+
+/// The error for a file in `format` that cannot be read at all.
+pub(crate) fn invalid(format: &str, detail: &str) -> String {
+    VaultError::InvalidImport(format!("{format}: {detail}")).into()
+}
+
+/// Decode the hex keys, salts and nonces formats store as text; `None`
+/// unless `hex` is whole bytes of hex digits.
+pub(crate) fn decode_hex(hex: &[u8]) -> Option<LockedBuffer> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    let mut out = LockedBuffer::new(hex.len() / 2);
+    for (byte, pair) in out.as_mut_slice().iter_mut().zip(hex.chunks_exact(2)) {
+        let digit = |c: u8| (c as char).to_digit(16);
+        *byte = (digit(pair[0])? * 16 + digit(pair[1])?) as u8;
+    }
+    Some(out)
+}
+
+/// Lowercase hex, as [`decode_hex`] reads it.
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// How many levels of a JSON file [`JsonShape::sniff`] outlines. Deeper
+/// objects and arrays are recorded empty.
+const SNIFF_DEPTH: usize = 3;
+
+/// The outline of a JSON file for sniffing: the keys of its objects and
+/// the first element of its arrays, with every string and number left
+/// out, so recognising a file copies none of its secrets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsonShape {
+    Object(Vec<(String, JsonShape)>),
+    Array(Option<Box<JsonShape>>),
+    /// A string, number, boolean or null.
+    Scalar,
+}
+
+impl JsonShape {
+    /// The outline of `data`, if it is a JSON object or array.
+    pub fn sniff(data: &[u8]) -> Option<Self> {
+        let trimmed = data.trim_ascii_start();
+        if !trimmed.starts_with(b"{") && !trimmed.starts_with(b"[") {
+            return None;
+        }
+        let mut deserializer = serde_json::Deserializer::from_slice(data);
+        let shape = ShapeSeed(SNIFF_DEPTH).deserialize(&mut deserializer).ok()?;
+        deserializer.end().ok()?;
+        Some(shape)
+    }
+
+    /// The value under `key`, if this is an object that has one.
+    pub fn get(&self, key: &str) -> Option<&JsonShape> {
+        match self {
+            Self::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// The first element, if this is a non-empty array.
+    pub fn first(&self) -> Option<&JsonShape> {
+        match self {
+            Self::Array(first) => first.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(self, Self::Array(_))
+    }
+}
+
+/// Reads a [`JsonShape`] with this many levels left to outline.
+struct ShapeSeed(usize);
+
+impl<'de> DeserializeSeed<'de> for ShapeSeed {
+    type Value = JsonShape;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<JsonShape, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ShapeSeed {
+    type Value = JsonShape;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_bool<E>(self, _: bool) -> Result<JsonShape, E> {
+        Ok(JsonShape::Scalar)
+    }
+
+    fn visit_i64<E>(self, _: i64) -> Result<JsonShape, E> {
+        Ok(JsonShape::Scalar)
+    }
+
+    fn visit_u64<E>(self, _: u64) -> Result<JsonShape, E> {
+        Ok(JsonShape::Scalar)
+    }
+
+    fn visit_f64<E>(self, _: f64) -> Result<JsonShape, E> {
+        Ok(JsonShape::Scalar)
+    }
+
+    fn visit_str<E>(self, _: &str) -> Result<JsonShape, E> {
+        Ok(JsonShape::Scalar)
+    }
+
+    fn visit_unit<E>(self) -> Result<JsonShape, E> {
+        Ok(JsonShape::Scalar)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<JsonShape, A::Error> {
+        let first = match self.0 {
+            0 => None,
+            depth => seq.next_element_seed(ShapeSeed(depth - 1))?,
+        };
+        while seq.next_element::<IgnoredAny>()?.is_some() {}
+        Ok(JsonShape::Array(first.map(Box::new)))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<JsonShape, A::Error> {
+        let mut entries = Vec::new();
+        if self.0 == 0 {
+            while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
+        } else {
+            while let Some(key) = map.next_key::<String>()? {
+                entries.push((key, map.next_value_seed(ShapeSeed(self.0 - 1))?));
+            }
+        }
+        Ok(JsonShape::Object(entries))
+    }
+}
+
+/// First child element of `node` with the local name `name`, whatever
+/// its namespace.
+pub(crate) fn xml_child<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+    name: &str,
+) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children().find(|c| c.has_tag_name(name))
+}
+
+#[cfg(feature = "sqlite")]
+impl Vault {
+    /// The tokens an exporter writes, secrets included: those named in
+    /// `ids`, or the whole vault for `None`, in vault order. Callers zeroize
+    /// the secrets when done.
+    pub(crate) fn export_selected(&self, ids: Option<&[String]>) -> Result<Vec<NewToken>, String> {
+        let mut tokens = self.list_tokens()?;
+        if let Some(ids) = ids {
+            let wanted: HashSet<&str> = ids.iter().map(String::as_str).collect();
+            tokens.retain(|token| wanted.contains(token.id.as_str()));
+            if tokens.len() != wanted.len() {
+                return Err(VaultError::TokenNotFound.into());
+            }
+        }
+
+        let mut exported = Vec::with_capacity(tokens.len());
+        for token in &tokens {
+            match self.export_token(token) {
+                Ok(token) => exported.push(token),
+                Err(e) => {
+                    for token in &mut exported {
+                        token.secret.zeroize();
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        Ok(exported)
+    }
+}
@@ -0,0 +1,218 @@
+//! Minimal protobuf wire format
+//!
+//! Just enough of the encoding (varints and length-delimited fields) to
+//! read and write the foreign formats that use it, without a code
+//! generator. Unknown fields are skipped, as protobuf requires.
+
+use zeroize::Zeroizing;
+
+use crate::error::VaultError;
+
+pub(crate) const WIRE_VARINT: u8 = 0;
+pub(crate) const WIRE_I64: u8 = 1;
+pub(crate) const WIRE_LEN: u8 = 2;
+pub(crate) const WIRE_I32: u8 = 5;
+
+/// A field value as it appears on the wire.
+pub(crate) enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    /// Fixed-width values, which none of the readers need.
+    Fixed,
+}
+
+impl<'a> Value<'a> {
+    pub(crate) fn varint(&self) -> Result<u64, String> {
+        match self {
+            Self::Varint(v) => Ok(*v),
+            _ => Err(malformed("expected varint")),
+        }
+    }
+
+    pub(crate) fn bytes(&self) -> Result<&'a [u8], String> {
+        match self {
+            Self::Bytes(b) => Ok(b),
+            _ => Err(malformed("expected length-delimited field")),
+        }
+    }
+
+    pub(crate) fn string(&self) -> Result<String, String> {
+        String::from_utf8(self.bytes()?.to_vec()).map_err(|_| malformed("invalid UTF-8"))
+    }
+}
+
+/// Iterates the `(field number, value)` pairs of one message.
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn read_varint(&mut self) -> Result<u64, String> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = self
+                .data
+                .split_first()
+                .ok_or_else(|| malformed("truncated varint"))?;
+            self.data = rest;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(malformed("varint too long"))
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if len > self.data.len() {
+            return Err(malformed("truncated field"));
+        }
+        let (head, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(head)
+    }
+
+    /// The next field, or `None` at the end of the message.
+    pub(crate) fn next_field(&mut self) -> Result<Option<(u64, Value<'a>)>, String> {
+        if self.data.is_empty() {
+            return Ok(None);
+        }
+        let key = self.read_varint()?;
+        let value = match (key & 0x07) as u8 {
+            WIRE_VARINT => Value::Varint(self.read_varint()?),
+            WIRE_LEN => {
+                let len = usize::try_from(self.read_varint()?)
+                    .map_err(|_| malformed("field too long"))?;
+                Value::Bytes(self.take(len)?)
+            }
+            WIRE_I64 => {
+                self.take(8)?;
+                Value::Fixed
+            }
+            WIRE_I32 => {
+                self.take(4)?;
+                Value::Fixed
+            }
+            other => return Err(malformed(&format!("unsupported wire type {other}"))),
+        };
+        Ok(Some((key >> 3, value)))
+    }
+}
+
+/// Builds one message. The buffer is zeroized on drop because messages
+/// carry token secrets.
+#[derive(Default)]
+pub(crate) struct Writer {
+    buf: Zeroizing<Vec<u8>>,
+}
+
+impl Writer {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    fn write_varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buf.push((value as u8 & 0x7f) | 0x80);
+            value >>= 7;
+        }
+        self.buf.push(value as u8);
+    }
+
+    pub(crate) fn varint(&mut self, field: u64, value: u64) {
+        self.write_varint(field << 3 | u64::from(WIRE_VARINT));
+        self.write_varint(value);
+    }
+
+    pub(crate) fn bytes(&mut self, field: u64, value: &[u8]) {
+        self.write_varint(field << 3 | u64::from(WIRE_LEN));
+        self.write_varint(value.len() as u64);
+        self.buf.extend_from_slice(value);
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub(crate) fn finish(self) -> Zeroizing<Vec<u8>> {
+        self.buf
+    }
+}
+
+/// Encoded size of a length-delimited field with a one-byte key.
+pub(crate) fn len_field_size(len: usize) -> usize {
+    let mut varint = 1;
+    let mut rest = len >> 7;
+    while rest > 0 {
+        varint += 1;
+        rest >>= 7;
+    }
+    1 + varint + len
+}
+
+fn malformed(detail: &str) -> String {
+    VaultError::InvalidImport(format!("malformed protobuf: {detail}")).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_fields_and_skips_unknown() {
+        // field 1 varint 300, field 9 fixed64, field 2 bytes "hi", field 3 fixed32
+        let data = [
+            0x08, 0xac, 0x02, 0x49, 1, 2, 3, 4, 5, 6, 7, 8, 0x12, 0x02, b'h', b'i', 0x1d, 1, 2, 3,
+            4,
+        ];
+        let mut reader = Reader::new(&data);
+        let (field, value) = reader.next_field().unwrap().unwrap();
+        assert_eq!((field, value.varint().unwrap()), (1, 300));
+        assert!(matches!(
+            reader.next_field().unwrap(),
+            Some((9, Value::Fixed))
+        ));
+        let (field, value) = reader.next_field().unwrap().unwrap();
+        assert_eq!((field, value.string().unwrap().as_str()), (2, "hi"));
+        assert!(matches!(
+            reader.next_field().unwrap(),
+            Some((3, Value::Fixed))
+        ));
+        assert!(reader.next_field().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_writer_round_trips_through_reader() {
+        let mut writer = Writer::new();
+        writer.varint(1, 300);
+        writer.bytes(2, &[7; 200]);
+        writer.varint(5, -7i64 as u64);
+        assert_eq!(writer.len(), 3 + len_field_size(200) + 11);
+
+        let data = writer.finish();
+        let mut reader = Reader::new(&data);
+        assert_eq!(
+            reader.next_field().unwrap().unwrap().1.varint().unwrap(),
+            300
+        );
+        assert_eq!(
+            reader.next_field().unwrap().unwrap().1.bytes().unwrap(),
+            [7; 200]
+        );
+        let (field, value) = reader.next_field().unwrap().unwrap();
+        assert_eq!((field, value.varint().unwrap() as i64), (5, -7));
+        assert!(reader.next_field().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_rejects_truncated_input() {
+        assert!(Reader::new(&[0x08]).next_field().is_err());
+        assert!(Reader::new(&[0x08, 0x80]).next_field().is_err());
+        assert!(Reader::new(&[0x12, 0x05, b'a']).next_field().is_err());
+        assert!(Reader::new(&[0x0b]).next_field().is_err());
+    }
+}
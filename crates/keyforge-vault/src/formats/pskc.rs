@@ -0,0 +1,1097 @@
+//! OATH PSKC import and export (RFC 6030)
+//!
+//! A Portable Symmetric Key Container is an XML `KeyContainer` with one
+//! `KeyPackage` per key, the way hardware-token vendors and identity
+//! providers hand out seeds. Only HOTP and TOTP keys are read; OCRA and
+//! vendor algorithms are reported as skipped.
+//!
+//! Encrypted containers protect each value with AES-CBC (XML Encryption,
+//! `CipherValue = iv ‖ ciphertext`) under a pre-shared key or a key derived
+//! from a password with PBKDF2. A `ValueMAC` authenticates each
+//! `CipherValue` with an HMAC keyed by the container's `MACKey`, itself
+//! encrypted under the same key. Asymmetric key transport is not supported.
+
+use std::fmt;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use keyforge_crypto::cbc_hmac::{self, IV_SIZE};
+use keyforge_crypto::kdf;
+use keyforge_crypto::secure_mem::LockedBuffer;
+use roxmltree::{Document, Node};
+use serde::Serialize;
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use zeroize::{Zeroize, Zeroizing};
+
+use super::{invalid, xml_child, ParsedTokens, PortableToken, SkippedEntry};
+use crate::constants::{
+    DEFAULT_ALGORITHM, DEFAULT_DIGITS, DEFAULT_ISSUER, DEFAULT_PERIOD,
+    PSKC_EXPORT_PBKDF2_ITERATIONS, PSKC_MAX_PBKDF2_ITERATIONS, TOKEN_TYPE_HOTP, TOKEN_TYPE_TOTP,
+};
+use crate::error::VaultError;
+use crate::import::{Confidence, ImportCandidate, ImportSecret, Importer, SniffedFile};
+use crate::token::NewToken;
+
+#[cfg(feature = "sqlite")]
+use crate::db::Vault;
+
+const NAME: &str = "PSKC";
+
+const PSKC_NS: &str = "urn:ietf:params:xml:ns:keyprov:pskc";
+const XENC_NS: &str = "http://www.w3.org/2001/04/xmlenc#";
+const XENC11_NS: &str = "http://www.w3.org/2009/xmlenc11#";
+const PKCS5_NS: &str = "http://www.rsasecurity.com/rsalabs/pkcs/schemes/pkcs-5v2-0#";
+const PBKDF2: &str = "http://www.rsasecurity.com/rsalabs/pkcs/schemes/pkcs-5v2-0#pbkdf2";
+
+/// XML Encryption block ciphers and their key sizes.
+const AES_CBC: [(&str, usize); 3] = [
+    ("http://www.w3.org/2001/04/xmlenc#aes128-cbc", 16),
+    ("http://www.w3.org/2001/04/xmlenc#aes192-cbc", 24),
+    ("http://www.w3.org/2001/04/xmlenc#aes256-cbc", 32),
+];
+const HMAC_SHA1: &str = "http://www.w3.org/2000/09/xmldsig#hmac-sha1";
+const HMAC_SHA256: &str = "http://www.w3.org/2001/04/xmldsig-more#hmac-sha256";
+const HMAC_SHA512: &str = "http://www.w3.org/2001/04/xmldsig-more#hmac-sha512";
+
+/// Algorithm URIs of RFC 6030 and of the drafts before it.
+const HOTP_ALGORITHMS: [&str; 3] = [
+    "urn:ietf:params:xml:ns:keyprov:pskc:hotp",
+    "urn:ietf:params:xml:ns:keyprov:pskc#hotp",
+    "http://www.ietf.org/keyprov/pskc#hotp",
+];
+const TOTP_ALGORITHMS: [&str; 3] = [
+    "urn:ietf:params:xml:ns:keyprov:pskc:totp",
+    "urn:ietf:params:xml:ns:keyprov:pskc#totp",
+    "http://www.ietf.org/keyprov/pskc#totp",
+];
+
+/// Cipher, key size and MAC of password-protected exports: the RFC 6030
+/// example's choices, which every PSKC consumer reads.
+const EXPORT_CIPHER: (&str, usize) = AES_CBC[0];
+const EXPORT_MAC_KEY_SIZE: usize = 20;
+const EXPORT_SALT_SIZE: usize = 16;
+
+/// How a key container protects its secrets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PskcEncryption {
+    None,
+    Password,
+    PreSharedKey,
+}
+
+/// What opens an encrypted key container.
+#[derive(Clone, Copy)]
+pub enum PskcKey<'a> {
+    Password(&'a [u8]),
+    /// Raw AES key bytes, as agreed with whoever sent the file.
+    PreShared(&'a [u8]),
+}
+
+/// How `data` protects its secrets, so callers know what to ask for.
+pub fn encryption(data: &[u8]) -> Result<PskcEncryption, String> {
+    let doc = parse(data)?;
+    protection(doc.root_element())
+}
+
+/// Decode a PSKC key container. `key` is only needed when values are
+/// encrypted; a wrong one fails with
+/// [`VaultError::WrongImportPassword`].
+///
+/// Skipped entries keep their index among the `KeyPackage`s.
+pub fn read_pskc(data: &[u8], key: Option<PskcKey<'_>>) -> Result<ParsedTokens, String> {
+    let doc = parse(data)?;
+    read_container(doc.root_element(), key)
+}
+
+fn read_container(root: Node<'_, '_>, key: Option<PskcKey<'_>>) -> Result<ParsedTokens, String> {
+    let keys = Keys::open(root, key)?;
+
+    let mut parsed = ParsedTokens::default();
+    let packages = root.children().filter(|n| n.has_tag_name("KeyPackage"));
+    for (index, package) in packages.enumerate() {
+        let Some(key) = xml_child(package, "Key") else {
+            parsed.skip(index, String::new(), "no key");
+            continue;
+        };
+        match map_key(package, key, keys.as_ref()) {
+            Ok(token) => parsed.add(index, token),
+            Err(KeyError::Skip(reason)) => {
+                let (issuer, account) = names(package, key);
+                parsed.skip(index, format!("{issuer}:{account}"), reason);
+            }
+            Err(KeyError::Abort(e)) => return Err(e),
+        }
+    }
+    Ok(parsed)
+}
+
+fn parse(data: &[u8]) -> Result<Document<'_>, String> {
+    let text = std::str::from_utf8(data).map_err(|_| invalid(NAME, "the file is not UTF-8"))?;
+    let doc = Document::parse(text).map_err(|e| invalid(NAME, &e.to_string()))?;
+    let root = doc.root_element();
+    if !root.has_tag_name("KeyContainer") {
+        return Err(invalid(NAME, "not a KeyContainer"));
+    }
+    match root.attribute("Version") {
+        None | Some("1.0") => Ok(doc),
+        Some(version) => {
+            Err(VaultError::UnsupportedFormat(format!("PSKC version {version}")).into())
+        }
+    }
+}
+
+/// The error for a container opened without the secret `encryption` needs.
+fn secret_required(encryption: PskcEncryption) -> VaultError {
+    match encryption {
+        PskcEncryption::PreSharedKey => {
+            VaultError::ImportKeyRequired(NAME.into(), "The pre-shared key")
+        }
+        _ => VaultError::ImportPasswordRequired(NAME.into()),
+    }
+}
+
+fn protection(root: Node<'_, '_>) -> Result<PskcEncryption, String> {
+    let Some(key) = xml_child(root, "EncryptionKey") else {
+        return Ok(PskcEncryption::None);
+    };
+    if xml_child(key, "DerivedKey").is_some() {
+        Ok(PskcEncryption::Password)
+    } else if xml_child(key, "KeyName").is_some() {
+        Ok(PskcEncryption::PreSharedKey)
+    } else {
+        Err(VaultError::UnsupportedFormat(
+            "PSKC key transport (only passwords and pre-shared keys are supported)".into(),
+        )
+        .into())
+    }
+}
+
+/// Why a key was not imported: most problems only skip the entry, but a
+/// wrong password or an unsupported cipher fails the whole file.
+enum KeyError {
+    Skip(String),
+    Abort(String),
+}
+
+impl From<&str> for KeyError {
+    fn from(reason: &str) -> Self {
+        Self::Skip(reason.to_string())
+    }
+}
+
+fn map_key(
+    package: Node<'_, '_>,
+    key: Node<'_, '_>,
+    keys: Option<&Keys>,
+) -> Result<PortableToken, KeyError> {
+    let algorithm = key.attribute("Algorithm").unwrap_or("");
+    let token_type = if HOTP_ALGORITHMS.contains(&algorithm) {
+        TOKEN_TYPE_HOTP
+    } else if TOTP_ALGORITHMS.contains(&algorithm) {
+        TOKEN_TYPE_TOTP
+    } else {
+        return Err(KeyError::Skip(format!(
+            "unsupported algorithm {algorithm:?}"
+        )));
+    };
+
+    let params = xml_child(key, "AlgorithmParameters");
+    let hash = params
+        .and_then(|p| text(p, "Suite"))
+        .map(hash_name)
+        .unwrap_or_else(|| DEFAULT_ALGORITHM.to_string());
+    let format = params.and_then(|p| xml_child(p, "ResponseFormat"));
+    if let Some(encoding) = format.and_then(|f| f.attribute("Encoding")) {
+        if !encoding.eq_ignore_ascii_case("DECIMAL") {
+            return Err(KeyError::Skip(format!(
+                "{encoding} responses are not supported"
+            )));
+        }
+    }
+    let digits = match format.and_then(|f| f.attribute("Length")) {
+        Some(length) => length
+            .trim()
+            .parse()
+            .map_err(|_| "invalid response length")?,
+        None => DEFAULT_DIGITS,
+    };
+
+    let data = xml_child(key, "Data").ok_or("no key data")?;
+    let mut secret = match value(data, "Secret", keys)? {
+        Some(Value::Plain(text)) => {
+            Zeroizing::new(decode_base64(&text).ok_or("secret is not base64")?)
+        }
+        Some(Value::Encrypted(secret)) => Zeroizing::new(secret.as_slice().to_vec()),
+        None => return Err("no secret".into()),
+    };
+    if secret.is_empty() {
+        return Err("empty secret".into());
+    }
+    let counter = match token_type {
+        TOKEN_TYPE_HOTP => integer(value(data, "Counter", keys)?)?.unwrap_or(0),
+        _ => 0,
+    };
+    if integer(value(data, "Time", keys)?)?.is_some_and(|t0| t0 != 0) {
+        return Err("a TOTP start time other than 0 is not supported".into());
+    }
+    let period = match integer(value(data, "TimeInterval", keys)?)? {
+        Some(period) => u32::try_from(period).map_err(|_| "invalid time interval")?,
+        None => DEFAULT_PERIOD,
+    };
+
+    let (issuer, account) = names(package, key);
+    PortableToken::checked(NewToken {
+        issuer,
+        account,
+        secret: std::mem::take(&mut *secret),
+        algorithm: hash,
+        digits,
+        token_type: token_type.to_string(),
+        period,
+        counter,
+        icon: None,
+    })
+    .map_err(KeyError::Skip)
+}
+
+/// `HMAC-SHA256`, `SHA-256` and the like as KeyForge names the hash.
+fn hash_name(suite: &str) -> String {
+    let suite = suite.to_ascii_uppercase();
+    suite
+        .strip_prefix("HMAC-")
+        .unwrap_or(&suite)
+        .replace('-', "")
+}
+
+/// Issuer and account: the key's own over its device's, falling back to
+/// the serial number so hardware tokens stay apart.
+fn names(package: Node<'_, '_>, key: Node<'_, '_>) -> (String, String) {
+    let device = xml_child(package, "DeviceInfo");
+    let from_device = |name| device.and_then(|d| text(d, name));
+    let issuer = text(key, "Issuer")
+        .or_else(|| from_device("Manufacturer"))
+        .unwrap_or(DEFAULT_ISSUER);
+    let account = text(key, "UserId")
+        .or_else(|| from_device("UserId"))
+        .or_else(|| text(key, "FriendlyName"))
+        .or_else(|| from_device("SerialNo"))
+        .or_else(|| key.attribute("Id"))
+        .unwrap_or("");
+    (issuer.to_string(), account.to_string())
+}
+
+/// Trimmed, non-empty text of the child element `name`.
+fn text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    xml_child(node, name)
+        .and_then(|n| n.text())
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+}
+
+/// A data element's value as the file carries it.
+enum Value {
+    /// `PlainValue` text: base64 for the secret, decimal for integers.
+    Plain(Zeroizing<String>),
+    /// Decrypted `EncryptedValue` bytes: raw for the secret, big-endian
+    /// for integers.
+    Encrypted(LockedBuffer),
+}
+
+fn value(data: Node<'_, '_>, name: &str, keys: Option<&Keys>) -> Result<Option<Value>, KeyError> {
+    let Some(element) = xml_child(data, name) else {
+        return Ok(None);
+    };
+    if let Some(plain) = xml_child(element, "PlainValue") {
+        return Ok(Some(Value::Plain(Zeroizing::new(
+            plain.text().unwrap_or("").to_string(),
+        ))));
+    }
+    let Some(encrypted) = xml_child(element, "EncryptedValue") else {
+        return Err(KeyError::Skip(format!("{name} has no value")));
+    };
+    let keys =
+        keys.ok_or_else(|| KeyError::Abort(invalid(NAME, "the key container is encrypted")))?;
+    let mac = xml_child(element, "ValueMAC").and_then(|m| m.text());
+    keys.open_value(encrypted, mac)
+        .map(|v| Some(Value::Encrypted(v)))
+        .map_err(KeyError::Abort)
+}
+
+fn integer(value: Option<Value>) -> Result<Option<u64>, KeyError> {
+    match value {
+        None => Ok(None),
+        Some(Value::Plain(text)) => text
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| KeyError::Skip(format!("{:?} is not a number", text.trim()))),
+        Some(Value::Encrypted(bytes)) if bytes.len() <= 8 => {
+            let bytes = bytes.as_slice();
+            Ok(Some(bytes.iter().fold(0, |n, b| n << 8 | u64::from(*b))))
+        }
+        Some(Value::Encrypted(_)) => Err("an encrypted number is too long".into()),
+    }
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let compact: Zeroizing<String> =
+        Zeroizing::new(text.chars().filter(|c| !c.is_whitespace()).collect());
+    BASE64.decode(compact.as_bytes()).ok()
+}
+
+/// Keys that open a container's encrypted values.
+struct Keys {
+    /// The pre-shared key, or the PBKDF2 output cut to each cipher's key
+    /// size when the file does not fix its length.
+    encryption: LockedBuffer,
+    length: Option<usize>,
+    mac: Option<(MacAlgorithm, LockedBuffer)>,
+}
+
+impl Keys {
+    /// Set up decryption, or `None` when nothing in the file is encrypted.
+    fn open(root: Node<'_, '_>, key: Option<PskcKey<'_>>) -> Result<Option<Self>, String> {
+        if !root.descendants().any(|n| n.has_tag_name("EncryptedValue")) {
+            return Ok(None);
+        }
+        let mut keys = match (protection(root)?, key) {
+            (_, None) => return Err(invalid(NAME, "the key container is encrypted")),
+            (PskcEncryption::Password, Some(PskcKey::Password(password))) => {
+                let derived = xml_child(root, "EncryptionKey")
+                    .and_then(|k| xml_child(k, "DerivedKey"))
+                    .expect("protection checked the derived key");
+                derive_key(derived, password)?
+            }
+            (
+                PskcEncryption::None | PskcEncryption::PreSharedKey,
+                Some(PskcKey::PreShared(key)),
+            ) => Self {
+                encryption: LockedBuffer::from_slice(key),
+                length: Some(key.len()),
+                mac: None,
+            },
+            _ => return Err(VaultError::WrongImportPassword.into()),
+        };
+
+        if let Some(method) = xml_child(root, "MACMethod") {
+            let algorithm = MacAlgorithm::from_uri(method.attribute("Algorithm").unwrap_or(""))?;
+            let mac_key = match xml_child(method, "MACKey") {
+                Some(encrypted) => keys.decrypt(encrypted, &cipher_value(encrypted)?, false)?,
+                None => LockedBuffer::from_slice(keys.encryption.as_slice()),
+            };
+            keys.mac = Some((algorithm, mac_key));
+        }
+        Ok(Some(keys))
+    }
+
+    /// Check a value's MAC, then decrypt it. A container declaring a
+    /// `MACMethod` must MAC every value. Values without one (pre-shared
+    /// keys from drafts before RFC 6030) are not authenticated, so a wrong
+    /// key only shows as bad padding.
+    fn open_value(
+        &self,
+        encrypted: Node<'_, '_>,
+        mac: Option<&str>,
+    ) -> Result<LockedBuffer, String> {
+        let ciphertext = cipher_value(encrypted)?;
+        if mac.is_none() && self.mac.is_some() {
+            return Err(invalid(NAME, "an encrypted value has no ValueMAC"));
+        }
+        if let Some(mac) = mac {
+            let expected =
+                decode_base64(mac).ok_or_else(|| invalid(NAME, "a ValueMAC is not base64"))?;
+            // Drafts before RFC 6030 had no MACMethod and keyed the MAC
+            // with the encryption key.
+            let (algorithm, key) = match &self.mac {
+                Some((algorithm, key)) => (*algorithm, key.as_slice()),
+                None => (MacAlgorithm::Sha1, self.encryption.as_slice()),
+            };
+            if !algorithm.verify(key, &ciphertext, &expected) {
+                return Err(VaultError::WrongImportPassword.into());
+            }
+        }
+        self.decrypt(encrypted, &ciphertext, mac.is_some())
+    }
+
+    /// Decrypt a `CipherValue`. Once a MAC has vouched for the key,
+    /// bad padding means a damaged file rather than a wrong key.
+    fn decrypt(
+        &self,
+        encrypted: Node<'_, '_>,
+        ciphertext: &[u8],
+        authenticated: bool,
+    ) -> Result<LockedBuffer, String> {
+        let algorithm = xml_child(encrypted, "EncryptionMethod")
+            .and_then(|m| m.attribute("Algorithm"))
+            .ok_or_else(|| invalid(NAME, "an encryption method is missing"))?;
+        let (_, size) = AES_CBC
+            .iter()
+            .find(|(uri, _)| *uri == algorithm)
+            .ok_or_else(|| VaultError::UnsupportedFormat(format!("PSKC cipher {algorithm}")))?;
+        if self.length.is_some_and(|length| length != *size) {
+            return Err(VaultError::WrongImportPassword.into());
+        }
+        if ciphertext.len() < 2 * IV_SIZE {
+            return Err(invalid(NAME, "an encrypted value is too short"));
+        }
+        let (iv, ciphertext) = ciphertext.split_at(IV_SIZE);
+        let key = &self.encryption.as_slice()[..*size];
+        cbc_hmac::decrypt_unauthenticated_locked(ciphertext, key, iv).map_err(|_| {
+            if authenticated {
+                invalid(NAME, "an encrypted value has bad padding")
+            } else {
+                VaultError::WrongImportPassword.into()
+            }
+        })
+    }
+}
+
+/// Derive the encryption key from `password` as `DerivedKey` describes.
+fn derive_key(derived: Node<'_, '_>, password: &[u8]) -> Result<Keys, String> {
+    let method = xml_child(derived, "KeyDerivationMethod")
+        .ok_or_else(|| invalid(NAME, "the key derivation method is missing"))?;
+    let algorithm = method.attribute("Algorithm").unwrap_or("");
+    if algorithm != PBKDF2 {
+        return Err(
+            VaultError::UnsupportedFormat(format!("PSKC key derivation {algorithm}")).into(),
+        );
+    }
+    let params = xml_child(method, "PBKDF2-params")
+        .ok_or_else(|| invalid(NAME, "the PBKDF2 parameters are missing"))?;
+    let salt = xml_child(params, "Salt")
+        .and_then(|s| text(s, "Specified"))
+        .and_then(decode_base64)
+        .ok_or_else(|| invalid(NAME, "the PBKDF2 salt is missing"))?;
+    let iterations: u32 = text(params, "IterationCount")
+        .and_then(|i| i.parse().ok())
+        .ok_or_else(|| invalid(NAME, "the PBKDF2 iteration count is missing"))?;
+    if iterations > PSKC_MAX_PBKDF2_ITERATIONS {
+        return Err(VaultError::UnsupportedFormat(format!(
+            "PSKC key derivation cost ({iterations} iterations)"
+        ))
+        .into());
+    }
+    let length = match text(params, "KeyLength") {
+        Some(length) => match length.parse() {
+            Ok(length @ (16 | 24 | 32)) => Some(length),
+            _ => return Err(invalid(NAME, "unsupported PBKDF2 key length")),
+        },
+        None => None,
+    };
+
+    // PBKDF2 output is prefix-stable, so a 256-bit derivation covers every
+    // AES key size.
+    let prf = xml_child(params, "PRF").and_then(|p| p.attribute("Algorithm"));
+    let key = match prf {
+        None | Some(HMAC_SHA1) => kdf::derive_key_pbkdf2_sha1_locked(password, &salt, iterations)?,
+        Some(HMAC_SHA256) => kdf::derive_key_pbkdf2_locked(password, &salt, iterations)?,
+        Some(prf) => {
+            return Err(VaultError::UnsupportedFormat(format!("PSKC PBKDF2 PRF {prf}")).into())
+        }
+    };
+    Ok(Keys {
+        encryption: LockedBuffer::from_slice(&key.expose()[..length.unwrap_or(32)]),
+        length,
+        mac: None,
+    })
+}
+
+fn cipher_value(encrypted: Node<'_, '_>) -> Result<Vec<u8>, String> {
+    xml_child(encrypted, "CipherData")
+        .and_then(|d| xml_child(d, "CipherValue"))
+        .and_then(|v| v.text())
+        .and_then(decode_base64)
+        .ok_or_else(|| invalid(NAME, "an encrypted value has no CipherValue"))
+}
+
+#[derive(Clone, Copy)]
+enum MacAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl MacAlgorithm {
+    fn from_uri(uri: &str) -> Result<Self, String> {
+        match uri {
+            HMAC_SHA1 => Ok(Self::Sha1),
+            HMAC_SHA256 => Ok(Self::Sha256),
+            HMAC_SHA512 => Ok(Self::Sha512),
+            _ => Err(VaultError::UnsupportedFormat(format!("PSKC MAC {uri}")).into()),
+        }
+    }
+
+    fn verify(self, key: &[u8], data: &[u8], expected: &[u8]) -> bool {
+        fn check<M: Mac + hmac::digest::KeyInit>(key: &[u8], data: &[u8], expected: &[u8]) -> bool {
+            let mut mac = <M as Mac>::new_from_slice(key).expect("HMAC takes keys of any length");
+            mac.update(data);
+            mac.verify_slice(expected).is_ok()
+        }
+        match self {
+            Self::Sha1 => check::<Hmac<Sha1>>(key, data, expected),
+            Self::Sha256 => check::<Hmac<Sha256>>(key, data, expected),
+            Self::Sha512 => check::<Hmac<Sha512>>(key, data, expected),
+        }
+    }
+}
+
+/// A PSKC key container for a set of tokens. The XML is zeroized on drop,
+/// since a plain container holds every secret in the clear.
+#[derive(Serialize)]
+pub struct PskcExport {
+    /// The `KeyContainer` XML.
+    pub data: Vec<u8>,
+    /// Tokens PSKC cannot represent.
+    pub skipped: Vec<SkippedEntry>,
+}
+
+impl Drop for PskcExport {
+    fn drop(&mut self) {
+        self.data.zeroize();
+    }
+}
+
+impl fmt::Debug for PskcExport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PskcExport")
+            .field("data", &"<redacted>")
+            .field("skipped", &self.skipped)
+            .finish()
+    }
+}
+
+/// Encode `tokens` as a PSKC key container, one `KeyPackage` each.
+///
+/// With a password, every secret is encrypted with AES-128-CBC under a
+/// PBKDF2-HMAC-SHA1 key and authenticated with HMAC-SHA1, as in RFC 6030
+/// §6.2; without one, secrets are written in the clear.
+pub fn write_pskc(tokens: &[NewToken], password: Option<&[u8]>) -> Result<PskcExport, String> {
+    let keys = password.map(ExportKeys::derive).transpose()?;
+    let mut xml = Zeroizing::new(String::with_capacity(export_size(tokens, keys.is_some())));
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    match &keys {
+        Some(keys) => {
+            line(
+                &mut xml,
+                0,
+                &format!(
+                    "<KeyContainer Version=\"1.0\" xmlns=\"{PSKC_NS}\" xmlns:xenc=\"{XENC_NS}\" \
+                     xmlns:xenc11=\"{XENC11_NS}\" xmlns:pkcs5=\"{PKCS5_NS}\">"
+                ),
+            );
+            keys.write_header(&mut xml)?;
+        }
+        None => line(
+            &mut xml,
+            0,
+            &format!("<KeyContainer Version=\"1.0\" xmlns=\"{PSKC_NS}\">"),
+        ),
+    }
+
+    let mut skipped = Vec::new();
+    for (index, token) in tokens.iter().enumerate() {
+        let algorithm = match token.token_type.as_str() {
+            TOKEN_TYPE_HOTP => HOTP_ALGORITHMS[0],
+            TOKEN_TYPE_TOTP => TOTP_ALGORITHMS[0],
+            other => {
+                skipped.push(SkippedEntry {
+                    index,
+                    label: format!("{}:{}", token.issuer, token.account),
+                    reason: format!("{other} tokens cannot be written to PSKC"),
+                });
+                continue;
+            }
+        };
+        line(&mut xml, 1, "<KeyPackage>");
+        line(
+            &mut xml,
+            2,
+            &format!("<Key Id=\"{}\" Algorithm=\"{algorithm}\">", index + 1),
+        );
+        line(
+            &mut xml,
+            3,
+            &format!("<Issuer>{}</Issuer>", escape(&token.issuer)),
+        );
+        line(&mut xml, 3, "<AlgorithmParameters>");
+        line(
+            &mut xml,
+            4,
+            &format!("<Suite>HMAC-{}</Suite>", escape(&token.algorithm)),
+        );
+        line(
+            &mut xml,
+            4,
+            &format!(
+                "<ResponseFormat Length=\"{}\" Encoding=\"DECIMAL\"/>",
+                token.digits
+            ),
+        );
+        line(&mut xml, 3, "</AlgorithmParameters>");
+        line(&mut xml, 3, "<Data>");
+        line(&mut xml, 4, "<Secret>");
+        match &keys {
+            Some(keys) => keys.write_value(&mut xml, &token.secret)?,
+            None => {
+                let secret = Zeroizing::new(BASE64.encode(&token.secret));
+                line_of(&mut xml, 5, &["<PlainValue>", &secret, "</PlainValue>"]);
+            }
+        }
+        line(&mut xml, 4, "</Secret>");
+        let (name, value) = match token.token_type.as_str() {
+            TOKEN_TYPE_HOTP => ("Counter", token.counter),
+            _ => ("TimeInterval", u64::from(token.period)),
+        };
+        line(&mut xml, 4, &format!("<{name}>"));
+        line(&mut xml, 5, &format!("<PlainValue>{value}</PlainValue>"));
+        line(&mut xml, 4, &format!("</{name}>"));
+        line(&mut xml, 3, "</Data>");
+        line(
+            &mut xml,
+            3,
+            &format!("<UserId>{}</UserId>", escape(&token.account)),
+        );
+        line(&mut xml, 2, "</Key>");
+        line(&mut xml, 1, "</KeyPackage>");
+    }
+    line(&mut xml, 0, "</KeyContainer>");
+    Ok(PskcExport {
+        data: std::mem::take(&mut *xml).into_bytes(),
+        skipped,
+    })
+}
+
+/// Password-derived keys of an export.
+struct ExportKeys {
+    salt: Vec<u8>,
+    encryption: LockedBuffer,
+    mac: LockedBuffer,
+}
+
+impl ExportKeys {
+    fn derive(password: &[u8]) -> Result<Self, String> {
+        let salt = keyforge_crypto::random::generate_bytes(EXPORT_SALT_SIZE);
+        let key =
+            kdf::derive_key_pbkdf2_sha1_locked(password, &salt, PSKC_EXPORT_PBKDF2_ITERATIONS)?;
+        let mut mac = LockedBuffer::new(EXPORT_MAC_KEY_SIZE);
+        keyforge_crypto::random::fill(mac.as_mut_slice());
+        Ok(Self {
+            salt,
+            encryption: LockedBuffer::from_slice(&key.expose()[..EXPORT_CIPHER.1]),
+            mac,
+        })
+    }
+
+    /// `EncryptionKey` and `MACMethod`, as in RFC 6030 figure 7.
+    fn write_header(&self, xml: &mut Zeroizing<String>) -> Result<(), String> {
+        line(xml, 1, "<EncryptionKey>");
+        line(xml, 2, "<xenc11:DerivedKey>");
+        line(
+            xml,
+            3,
+            &format!("<xenc11:KeyDerivationMethod Algorithm=\"{PBKDF2}\">"),
+        );
+        line(xml, 4, "<pkcs5:PBKDF2-params>");
+        line(xml, 5, "<Salt>");
+        line(
+            xml,
+            6,
+            &format!("<Specified>{}</Specified>", BASE64.encode(&self.salt)),
+        );
+        line(xml, 5, "</Salt>");
+        line(
+            xml,
+            5,
+            &format!("<IterationCount>{PSKC_EXPORT_PBKDF2_ITERATIONS}</IterationCount>"),
+        );
+        line(
+            xml,
+            5,
+            &format!("<KeyLength>{}</KeyLength>", EXPORT_CIPHER.1),
+        );
+        line(xml, 4, "</pkcs5:PBKDF2-params>");
+        line(xml, 3, "</xenc11:KeyDerivationMethod>");
+        line(xml, 2, "</xenc11:DerivedKey>");
+        line(xml, 1, "</EncryptionKey>");
+        line(xml, 1, &format!("<MACMethod Algorithm=\"{HMAC_SHA1}\">"));
+        line(xml, 2, "<MACKey>");
+        let mac_key =
+            cbc_hmac::encrypt_unauthenticated(self.mac.as_slice(), self.encryption.as_slice())?;
+        write_cipher(xml, 3, &mac_key);
+        line(xml, 2, "</MACKey>");
+        line(xml, 1, "</MACMethod>");
+        Ok(())
+    }
+
+    /// An `EncryptedValue` and its `ValueMAC`.
+    fn write_value(&self, xml: &mut Zeroizing<String>, plaintext: &[u8]) -> Result<(), String> {
+        let ciphertext = cbc_hmac::encrypt_unauthenticated(plaintext, self.encryption.as_slice())?;
+        let mut mac = <Hmac<Sha1> as Mac>::new_from_slice(self.mac.as_slice())
+            .expect("HMAC takes keys of any length");
+        mac.update(&ciphertext);
+        line(xml, 5, "<EncryptedValue>");
+        write_cipher(xml, 6, &ciphertext);
+        line(xml, 5, "</EncryptedValue>");
+        let mac = BASE64.encode(mac.finalize().into_bytes());
+        line(xml, 5, &format!("<ValueMAC>{mac}</ValueMAC>"));
+        Ok(())
+    }
+}
+
+fn write_cipher(xml: &mut Zeroizing<String>, depth: usize, ciphertext: &[u8]) {
+    let (algorithm, _) = EXPORT_CIPHER;
+    line(
+        xml,
+        depth,
+        &format!("<xenc:EncryptionMethod Algorithm=\"{algorithm}\"/>"),
+    );
+    line(xml, depth, "<xenc:CipherData>");
+    let value = BASE64.encode(ciphertext);
+    line(
+        xml,
+        depth + 1,
+        &format!("<xenc:CipherValue>{value}</xenc:CipherValue>"),
+    );
+    line(xml, depth, "</xenc:CipherData>");
+}
+
+/// Room for a whole export, so the buffer plaintext secrets are written
+/// into should never have to grow.
+fn export_size(tokens: &[NewToken], encrypted: bool) -> usize {
+    let (container, package) = if encrypted { (2048, 1024) } else { (256, 512) };
+    let names = |t: &NewToken| t.issuer.len() + t.account.len() + t.algorithm.len();
+    tokens.iter().fold(container, |size, t| {
+        // Escaping at most sextuples a name; base64 under doubles a secret.
+        size + package + 6 * names(t) + 2 * t.secret.len()
+    })
+}
+
+fn line(xml: &mut Zeroizing<String>, depth: usize, content: &str) {
+    line_of(xml, depth, &[content]);
+}
+
+/// Write a line from `parts`, pushed straight into `xml` so secrets leave
+/// no copies behind. Should `xml` be full after all, it moves to a larger
+/// buffer and the old one is wiped.
+fn line_of(xml: &mut Zeroizing<String>, depth: usize, parts: &[&str]) {
+    let len = 2 * depth + parts.iter().map(|p| p.len()).sum::<usize>() + 1;
+    if xml.capacity() - xml.len() < len {
+        let mut grown = String::with_capacity(2 * (xml.len() + len));
+        grown.push_str(xml);
+        *xml = Zeroizing::new(grown);
+    }
+    for _ in 0..depth {
+        xml.push_str("  ");
+    }
+    for part in parts {
+        xml.push_str(part);
+    }
+    xml.push('\n');
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// PSKC key containers: plain, password-protected or under a pre-shared
+/// key.
+pub struct PskcImporter;
+
+impl Importer for PskcImporter {
+    fn id(&self) -> &'static str {
+        "pskc"
+    }
+
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn sniff(&self, file: &SniffedFile<'_>) -> Confidence {
+        let is_container = std::str::from_utf8(file.data)
+            .ok()
+            .and_then(|text| Document::parse(text).ok())
+            .is_some_and(|doc| doc.root_element().has_tag_name("KeyContainer"));
+        if is_container {
+            Confidence::Likely
+        } else {
+            Confidence::No
+        }
+    }
+
+    fn is_encrypted(&self, data: &[u8]) -> bool {
+        encryption(data).is_ok_and(|e| e != PskcEncryption::None)
+    }
+
+    fn missing_secret(&self, data: &[u8]) -> VaultError {
+        secret_required(encryption(data).unwrap_or(PskcEncryption::Password))
+    }
+
+    fn parse(
+        &self,
+        data: &[u8],
+        secret: Option<ImportSecret<'_>>,
+    ) -> Result<Vec<ImportCandidate>, String> {
+        let doc = parse(data)?;
+        let root = doc.root_element();
+        // A key of the wrong kind would only fail as a wrong one.
+        let key = match (protection(root)?, secret) {
+            (PskcEncryption::None, _) => None,
+            (PskcEncryption::Password, Some(ImportSecret::Password(password))) => {
+                Some(PskcKey::Password(password))
+            }
+            (PskcEncryption::PreSharedKey, Some(ImportSecret::Key(key))) => {
+                Some(PskcKey::PreShared(key))
+            }
+            (encryption, _) => return Err(secret_required(encryption).into()),
+        };
+        Ok(read_container(root, key)?.into_candidates())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl Vault {
+    /// Export tokens as a PSKC key container, encrypted under `password`
+    /// or plain when it is `None`.
+    ///
+    /// `ids` selects a subset (exported in vault order); `None` exports the
+    /// whole vault. Skipped entries are indexed within the selection.
+    pub fn export_pskc(
+        &self,
+        ids: Option<&[String]>,
+        password: Option<&[u8]>,
+    ) -> Result<PskcExport, String> {
+        let mut exported = self.export_selected(ids)?;
+        let result = write_pskc(&exported, password);
+        for token in &mut exported {
+            token.secret.zeroize();
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAIN: &[u8] = include_bytes!("../../tests/fixtures/pskc_plain.xml");
+    /// RFC 6030 figure 6: AES-128-CBC under a pre-shared key.
+    const PRE_SHARED: &[u8] = include_bytes!("../../tests/fixtures/pskc_psk.xml");
+    /// RFC 6030 figure 7: PBKDF2 from the password "qwerty".
+    const PASSWORD: &[u8] = include_bytes!("../../tests/fixtures/pskc_pbe.xml");
+
+    const PRE_SHARED_KEY: [u8; 16] = [
+        0x12, 0x34, 0x56, 0x78, 0x90, 0x12, 0x34, 0x56, 0x78, 0x90, 0x12, 0x34, 0x56, 0x78, 0x90,
+        0x12,
+    ];
+
+    fn token(token_type: &str, issuer: &str, account: &str) -> NewToken {
+        NewToken {
+            issuer: issuer.into(),
+            account: account.into(),
+            secret: b"12345678901234567890".to_vec(),
+            algorithm: "SHA1".into(),
+            digits: 6,
+            token_type: token_type.into(),
+            period: 30,
+            counter: 0,
+            icon: None,
+        }
+    }
+
+    #[test]
+    fn test_plain_container() {
+        assert_eq!(encryption(PLAIN).unwrap(), PskcEncryption::None);
+        let parsed = read_pskc(PLAIN, None).unwrap();
+        let summary = parsed.summary();
+        assert_eq!(
+            summary,
+            [
+                ("GitHub", "alice@example.com", "totp"),
+                ("Acme Tokens", "SN-0042", "hotp"),
+                ("Example", "erin's token", "totp"),
+            ]
+        );
+
+        let github = &parsed.tokens[0].token;
+        assert_eq!(github.secret, b"12345678901234567890");
+        assert_eq!(github.algorithm, "SHA256");
+        assert_eq!((github.digits, github.period), (8, 60));
+        let acme = &parsed.tokens[1].token;
+        assert_eq!(acme.secret, b"Hello!\xde\xad\xbe\xef");
+        assert_eq!((acme.digits, acme.counter), (6, 5));
+
+        assert_eq!(
+            parsed.skipped_labels(),
+            [(2, "Bank:bob"), (3, "Legacy:carol"), (4, "Shifted:dave")]
+        );
+        assert!(parsed.skipped[0]
+            .reason
+            .starts_with("unsupported algorithm"));
+        assert_eq!(
+            parsed.skipped[1].reason,
+            "HEXADECIMAL responses are not supported"
+        );
+    }
+
+    #[test]
+    fn test_rfc6030_pre_shared_key() {
+        assert_eq!(
+            encryption(PRE_SHARED).unwrap(),
+            PskcEncryption::PreSharedKey
+        );
+        let parsed = read_pskc(PRE_SHARED, Some(PskcKey::PreShared(&PRE_SHARED_KEY))).unwrap();
+        let token = &parsed.tokens[0].token;
+        assert_eq!(
+            (token.issuer.as_str(), token.account.as_str()),
+            ("Issuer", "987654321")
+        );
+        assert_eq!(token.secret, b"12345678901234567890");
+        assert_eq!((token.token_type.as_str(), token.digits), ("hotp", 8));
+
+        let wrong = VaultError::WrongImportPassword.to_string();
+        let mut other = PRE_SHARED_KEY;
+        other[0] ^= 1;
+        assert_eq!(
+            read_pskc(PRE_SHARED, Some(PskcKey::PreShared(&other))).unwrap_err(),
+            wrong
+        );
+        assert_eq!(
+            read_pskc(PRE_SHARED, Some(PskcKey::PreShared(&[0; 32]))).unwrap_err(),
+            wrong
+        );
+        assert_eq!(
+            read_pskc(PRE_SHARED, Some(PskcKey::Password(b"qwerty"))).unwrap_err(),
+            wrong
+        );
+        assert!(read_pskc(PRE_SHARED, None)
+            .unwrap_err()
+            .contains("encrypted"));
+    }
+
+    #[test]
+    fn test_rfc6030_password() {
+        assert_eq!(encryption(PASSWORD).unwrap(), PskcEncryption::Password);
+        let parsed = read_pskc(PASSWORD, Some(PskcKey::Password(b"qwerty"))).unwrap();
+        let token = &parsed.tokens[0].token;
+        assert_eq!(
+            (token.issuer.as_str(), token.account.as_str()),
+            ("Example-Issuer", "987654321")
+        );
+        assert_eq!(token.secret, b"12345678901234567890");
+
+        let wrong = VaultError::WrongImportPassword.to_string();
+        assert_eq!(
+            read_pskc(PASSWORD, Some(PskcKey::Password(b"qwertz"))).unwrap_err(),
+            wrong
+        );
+
+        // A MAC that does not match fails the import even with the right key.
+        let text = std::str::from_utf8(PASSWORD).unwrap();
+        let tampered = text.replace(
+            "LP6xMvjtypbfT9PdkJhBZ+D6O4w=",
+            "LP7xMvjtypbfT9PdkJhBZ+D6O4w=",
+        );
+        assert_eq!(
+            read_pskc(tampered.as_bytes(), Some(PskcKey::Password(b"qwerty"))).unwrap_err(),
+            wrong
+        );
+
+        // A container with a MACMethod must MAC every value.
+        let start = text.find("<pskc:ValueMAC>").unwrap();
+        let end = text.find("</pskc:ValueMAC>").unwrap() + "</pskc:ValueMAC>".len();
+        let unmacked = format!("{}{}", &text[..start], &text[end..]);
+        let err = read_pskc(unmacked.as_bytes(), Some(PskcKey::Password(b"qwerty"))).unwrap_err();
+        assert!(err.ends_with("an encrypted value has no ValueMAC"), "{err}");
+
+        // File-supplied costs are bounded.
+        let costly = text.replace(">1000<", ">100000000<");
+        let err = read_pskc(costly.as_bytes(), Some(PskcKey::Password(b"qwerty"))).unwrap_err();
+        assert!(err.starts_with("Unsupported vault format"), "{err}");
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let mut hotp = token(TOKEN_TYPE_HOTP, "Bank & Co", "<bob>");
+        hotp.counter = 7;
+        hotp.digits = 8;
+        let mut totp = token(TOKEN_TYPE_TOTP, "GitHub", "alice@example.com");
+        totp.algorithm = "SHA512".into();
+        totp.period = 60;
+        let steam = token("steam", "Steam", "gabe");
+        let tokens = [hotp, steam, totp];
+
+        for password in [None, Some(&b"hunter2"[..])] {
+            let export = write_pskc(&tokens, password).unwrap();
+            assert_eq!(export.skipped.len(), 1);
+            assert_eq!(
+                (export.skipped[0].index, export.skipped[0].label.as_str()),
+                (1, "Steam:gabe")
+            );
+
+            let key = password.map(PskcKey::Password);
+            let expected = if password.is_some() {
+                PskcEncryption::Password
+            } else {
+                PskcEncryption::None
+            };
+            assert_eq!(encryption(&export.data).unwrap(), expected);
+            let text = std::str::from_utf8(&export.data).unwrap();
+            assert_eq!(
+                text.contains("MTIzNDU2Nzg5MDEyMzQ1Njc4OTA="),
+                password.is_none()
+            );
+
+            let parsed = read_pskc(&export.data, key).unwrap();
+            assert!(parsed.skipped.is_empty());
+            let back: Vec<&NewToken> = parsed.tokens.iter().map(|t| &t.token).collect();
+            assert_eq!(back.len(), 2);
+            for (back, original) in back.iter().zip([&tokens[0], &tokens[2]]) {
+                assert_eq!(back.issuer, original.issuer);
+                assert_eq!(back.account, original.account);
+                assert_eq!(back.secret, original.secret);
+                assert_eq!(back.algorithm, original.algorithm);
+                assert_eq!(back.digits, original.digits);
+                assert_eq!(back.token_type, original.token_type);
+                assert_eq!(back.counter, original.counter);
+                assert_eq!(
+                    back.period,
+                    if original.token_type == TOKEN_TYPE_HOTP {
+                        DEFAULT_PERIOD
+                    } else {
+                        original.period
+                    }
+                );
+            }
+        }
+
+        let export = write_pskc(&tokens, Some(b"hunter2")).unwrap();
+        assert_eq!(
+            read_pskc(&export.data, Some(PskcKey::Password(b"hunter3"))).unwrap_err(),
+            VaultError::WrongImportPassword.to_string()
+        );
+    }
+
+    #[test]
+    fn test_rejects_malformed_files() {
+        assert!(read_pskc(b"not xml", None).is_err());
+        assert!(read_pskc(b"<Container/>", None).is_err());
+        let err = read_pskc(b"<KeyContainer Version=\"2.0\"/>", None).unwrap_err();
+        assert!(err.starts_with("Unsupported vault format"));
+        let parsed = read_pskc(b"<KeyContainer><KeyPackage/></KeyContainer>", None).unwrap();
+        assert_eq!(parsed.skipped[0].reason, "no key");
+    }
+}
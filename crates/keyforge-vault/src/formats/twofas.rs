@@ -0,0 +1,416 @@
+//! 2FAS Authenticator import
+//!
+//! Reads `.2fas` backups. A plain backup lists its tokens under `services`;
+//! an encrypted one leaves `services` empty and carries them in
+//! `servicesEncrypted` as `base64(ciphertext ‖ tag):base64(salt):base64(iv)`,
+//! AES-256-GCM under PBKDF2-HMAC-SHA256(password, salt, 10,000). `reference`
+//! holds a known string sealed with the same key, which tells a wrong
+//! password apart from a damaged file. Groups stay in plaintext either way.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use keyforge_crypto::aead::NONCE_SIZE;
+use keyforge_crypto::kdf;
+use keyforge_crypto::secure_mem::{LockedBuffer, LockedKey};
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use zeroize::Zeroize;
+
+use super::{decode_secret, invalid, ParsedTokens, PortableToken};
+use crate::constants::{
+    DEFAULT_DIGITS, DEFAULT_ISSUER, DEFAULT_PERIOD, TOKEN_TYPE_HOTP, TOKEN_TYPE_STEAM,
+    TOKEN_TYPE_TOTP, TWOFAS_MAX_SCHEMA_VERSION, TWOFAS_PBKDF2_ITERATIONS,
+};
+use crate::error::VaultError;
+use crate::import::{Confidence, ImportCandidate, ImportSecret, Importer, SniffedFile};
+use crate::token::NewToken;
+
+const NAME: &str = "2FAS";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Backup<'a> {
+    #[serde(default)]
+    schema_version: u32,
+    #[serde(default, borrow)]
+    services: Vec<&'a RawValue>,
+    #[serde(default)]
+    services_encrypted: Option<String>,
+    #[serde(default)]
+    reference: Option<String>,
+    #[serde(default)]
+    groups: Vec<Group>,
+}
+
+#[derive(Deserialize)]
+struct Group {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Service {
+    #[serde(default)]
+    name: String,
+    secret: String,
+    #[serde(default)]
+    otp: Otp,
+    #[serde(default)]
+    order: Option<Order>,
+    #[serde(default)]
+    group_id: Option<String>,
+}
+
+impl Drop for Service {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+/// What a malformed service is reported as.
+#[derive(Deserialize)]
+struct ServiceName {
+    #[serde(default)]
+    name: String,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Otp {
+    label: Option<String>,
+    account: Option<String>,
+    issuer: Option<String>,
+    digits: Option<u32>,
+    period: Option<u32>,
+    algorithm: Option<String>,
+    counter: Option<u64>,
+    token_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Order {
+    position: i64,
+}
+
+/// Whether a 2FAS backup is password-protected.
+fn is_encrypted(data: &[u8]) -> Result<bool, String> {
+    Ok(parse_backup(data)?.services_encrypted.is_some())
+}
+
+/// Decode a 2FAS backup. `password` is required for encrypted backups and
+/// ignored for plain ones.
+///
+/// Tokens come back in 2FAS's display order (`order.position`), so
+/// importing them in sequence reproduces it. Skipped entries keep their
+/// index in the backup's `services` list.
+pub fn read_twofas(data: &[u8], password: Option<&[u8]>) -> Result<ParsedTokens, String> {
+    let backup = parse_backup(data)?;
+    let decrypted;
+    let services: Vec<&RawValue> = match &backup.services_encrypted {
+        Some(sealed) => {
+            let password = password.ok_or_else(|| invalid(NAME, "the backup is encrypted"))?;
+            decrypted = decrypt_services(sealed, backup.reference.as_deref(), password)?;
+            serde_json::from_slice(decrypted.as_slice())
+                .map_err(|e| invalid(NAME, &e.to_string()))?
+        }
+        None => backup.services,
+    };
+
+    let mut ordered = Vec::new();
+    let mut parsed = ParsedTokens::default();
+    for (index, raw) in services.iter().enumerate() {
+        let service: Service = match serde_json::from_str(raw.get()) {
+            Ok(service) => service,
+            Err(e) => {
+                let name = serde_json::from_str::<ServiceName>(raw.get())
+                    .map(|s| s.name)
+                    .unwrap_or_default();
+                parsed.skip(index, name, format!("malformed entry: {e}"));
+                continue;
+            }
+        };
+        match map_service(&service, &backup.groups) {
+            Ok(mut token) => {
+                token.index = index;
+                let position = service.order.as_ref().map_or(i64::MAX, |o| o.position);
+                ordered.push((position, token));
+            }
+            Err(reason) => {
+                let (issuer, account) = names(&service);
+                parsed.skip(index, format!("{issuer}:{account}"), reason);
+            }
+        }
+    }
+    ordered.sort_by_key(|(position, _)| *position);
+    parsed.tokens = ordered.into_iter().map(|(_, token)| token).collect();
+    Ok(parsed)
+}
+
+fn parse_backup(data: &[u8]) -> Result<Backup<'_>, String> {
+    let backup: Backup = serde_json::from_slice(data).map_err(|e| invalid(NAME, &e.to_string()))?;
+    if backup.schema_version > TWOFAS_MAX_SCHEMA_VERSION {
+        return Err(VaultError::UnsupportedFormat(format!(
+            "2FAS schema version {}",
+            backup.schema_version
+        ))
+        .into());
+    }
+    Ok(backup)
+}
+
+/// One `base64(ciphertext ‖ tag):base64(salt):base64(iv)` field.
+struct Sealed {
+    ciphertext: Vec<u8>,
+    salt: Vec<u8>,
+    iv: Vec<u8>,
+}
+
+impl Sealed {
+    fn parse(value: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = value.split(':').collect();
+        let [ciphertext, salt, iv] = parts[..] else {
+            return Err(invalid(NAME, "encrypted field is not ciphertext:salt:iv"));
+        };
+        let decode = |part: &str| {
+            BASE64
+                .decode(part.trim())
+                .map_err(|_| invalid(NAME, "encrypted field is not valid base64"))
+        };
+        Ok(Self {
+            ciphertext: decode(ciphertext)?,
+            salt: decode(salt)?,
+            iv: decode(iv)?,
+        })
+    }
+
+    fn open(&self, key: &LockedKey) -> Result<LockedBuffer, String> {
+        if self.iv.len() != NONCE_SIZE {
+            return Err(invalid(NAME, "bad IV length"));
+        }
+        let sealed = [self.iv.as_slice(), &self.ciphertext].concat();
+        keyforge_crypto::aead::decrypt_locked(&sealed, key.expose())
+    }
+}
+
+/// Decrypt `servicesEncrypted`, using `reference` (when present) to report
+/// a wrong password rather than a corrupt file.
+fn decrypt_services(
+    services: &str,
+    reference: Option<&str>,
+    password: &[u8],
+) -> Result<LockedBuffer, String> {
+    let services = Sealed::parse(services)?;
+    let key = kdf::derive_key_pbkdf2_locked(password, &services.salt, TWOFAS_PBKDF2_ITERATIONS)?;
+
+    if let Some(reference) = reference {
+        let reference = Sealed::parse(reference)?;
+        reference
+            .open(&key)
+            .map_err(|_| VaultError::WrongImportPassword)?;
+        services
+            .open(&key)
+            .map_err(|_| invalid(NAME, "services failed authentication"))
+    } else {
+        services
+            .open(&key)
+            .map_err(|_| VaultError::WrongImportPassword.into())
+    }
+}
+
+fn map_service(service: &Service, groups: &[Group]) -> Result<PortableToken, String> {
+    let otp = &service.otp;
+    let token_type = otp.token_type.as_deref().unwrap_or("TOTP");
+    let (token_type, counter) = match token_type.to_ascii_uppercase().as_str() {
+        "TOTP" => (TOKEN_TYPE_TOTP, 0),
+        "HOTP" => (TOKEN_TYPE_HOTP, otp.counter.unwrap_or(0)),
+        "STEAM" => (TOKEN_TYPE_STEAM, 0),
+        _ => return Err(format!("{token_type} tokens are not supported")),
+    };
+    let digits = match (token_type, otp.digits) {
+        (TOKEN_TYPE_STEAM, _) => keyforge_crypto::otp::STEAM_DIGITS,
+        (_, Some(digits)) => digits,
+        (_, None) => DEFAULT_DIGITS,
+    };
+
+    let (issuer, account) = names(service);
+    let mut token = PortableToken::checked(NewToken {
+        issuer,
+        account,
+        secret: decode_secret(&service.secret)?,
+        algorithm: otp
+            .algorithm
+            .as_deref()
+            .unwrap_or("SHA1")
+            .to_ascii_uppercase(),
+        digits,
+        token_type: token_type.to_string(),
+        period: otp.period.filter(|p| *p > 0).unwrap_or(DEFAULT_PERIOD),
+        counter,
+        icon: None,
+    })?;
+
+    token.groups = service
+        .group_id
+        .as_ref()
+        .and_then(|id| groups.iter().find(|g| &g.id == id))
+        .map(|g| vec![g.name.clone()])
+        .unwrap_or_default();
+    Ok(token)
+}
+
+/// Issuer and account: `otp.issuer` or the service name, and `otp.account`
+/// or whatever follows the issuer in `otp.label`.
+fn names(service: &Service) -> (String, String) {
+    let otp = &service.otp;
+    let issuer = [otp.issuer.as_deref(), Some(service.name.as_str())]
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .find(|s| !s.is_empty())
+        .unwrap_or(DEFAULT_ISSUER)
+        .to_string();
+    let account = match (&otp.account, &otp.label) {
+        (Some(account), _) if !account.trim().is_empty() => account.trim(),
+        (_, Some(label)) => label
+            .split_once(':')
+            .map_or(label.as_str(), |(_, a)| a)
+            .trim(),
+        _ => "",
+    };
+    (issuer, account.to_string())
+}
+
+/// 2FAS `.2fas` backups, plain or encrypted.
+pub struct TwofasImporter;
+
+impl Importer for TwofasImporter {
+    fn id(&self) -> &'static str {
+        "twofas"
+    }
+
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn sniff(&self, file: &SniffedFile<'_>) -> Confidence {
+        match &file.json {
+            Some(json) if json.get("services").is_some() && json.get("schemaVersion").is_some() => {
+                Confidence::Likely
+            }
+            _ => Confidence::No,
+        }
+    }
+
+    fn is_encrypted(&self, data: &[u8]) -> bool {
+        is_encrypted(data).unwrap_or(false)
+    }
+
+    fn parse(
+        &self,
+        data: &[u8],
+        secret: Option<ImportSecret<'_>>,
+    ) -> Result<Vec<ImportCandidate>, String> {
+        let password = ImportSecret::password_only(secret, NAME)?;
+        Ok(read_twofas(data, password)?.into_candidates())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAIN: &str = include_str!("../../tests/fixtures/twofas_plain.2fas");
+    const ENCRYPTED: &str = include_str!("../../tests/fixtures/twofas_encrypted.2fas");
+
+    #[test]
+    fn test_plain_backup() {
+        assert!(!is_encrypted(PLAIN.as_bytes()).unwrap());
+        let parsed = read_twofas(PLAIN.as_bytes(), None).unwrap();
+        let summary = parsed.summary();
+        // Display order, not file order.
+        assert_eq!(
+            summary,
+            [
+                ("Steam", "gamer", "steam"),
+                ("GitHub", "alice@example.com", "totp"),
+                ("Bank", "bob", "hotp"),
+            ]
+        );
+
+        let steam = &parsed.tokens[0].token;
+        assert_eq!((steam.digits, steam.period), (5, 30));
+        let github = &parsed.tokens[1];
+        assert_eq!(github.token.secret, b"12345678901234567890");
+        assert_eq!(github.token.algorithm, "SHA256");
+        assert_eq!((github.token.digits, github.token.period), (8, 60));
+        assert_eq!(github.groups, ["Work"]);
+        let bank = &parsed.tokens[2];
+        assert_eq!(bank.token.counter, 5);
+        assert!(bank.groups.is_empty());
+
+        assert_eq!(
+            parsed.skipped_labels(),
+            [(3, "Odd:carol"), (4, "Broken:dave")]
+        );
+    }
+
+    #[test]
+    fn test_encrypted_backup_holds_the_plain_one() {
+        assert!(is_encrypted(ENCRYPTED.as_bytes()).unwrap());
+        let parsed = read_twofas(ENCRYPTED.as_bytes(), Some(b"test")).unwrap();
+        let plain = read_twofas(PLAIN.as_bytes(), None).unwrap();
+        assert_eq!(parsed.summary(), plain.summary());
+        assert_eq!(parsed.skipped, plain.skipped);
+        assert_eq!(parsed.tokens[1].groups, plain.tokens[1].groups);
+    }
+
+    #[test]
+    fn test_wrong_password_and_damage() {
+        let err = read_twofas(ENCRYPTED.as_bytes(), Some(b"nope"))
+            .err()
+            .unwrap();
+        assert_eq!(err, VaultError::WrongImportPassword.to_string());
+        assert!(read_twofas(ENCRYPTED.as_bytes(), None).is_err());
+
+        let mut backup: serde_json::Value = serde_json::from_str(ENCRYPTED).unwrap();
+        let sealed = backup["servicesEncrypted"].as_str().unwrap().to_string();
+        let (ciphertext, rest) = sealed.split_once(':').unwrap();
+        let mut bytes = BASE64.decode(ciphertext).unwrap();
+        bytes[0] ^= 1;
+        backup["servicesEncrypted"] = format!("{}:{rest}", BASE64.encode(bytes)).into();
+        let data = serde_json::to_vec(&backup).unwrap();
+        let err = read_twofas(&data, Some(b"test")).err().unwrap();
+        assert!(err.contains("authentication"));
+
+        // Without a reference a failed decryption can only mean the password.
+        backup.as_object_mut().unwrap().remove("reference");
+        let data = serde_json::to_vec(&backup).unwrap();
+        let err = read_twofas(&data, Some(b"test")).err().unwrap();
+        assert_eq!(err, VaultError::WrongImportPassword.to_string());
+    }
+
+    #[test]
+    fn test_names() {
+        let service = |name: &str, otp: serde_json::Value| {
+            Service::deserialize(serde_json::json!({
+                "name": name, "secret": "GEZDGNBV", "otp": otp
+            }))
+            .unwrap()
+        };
+        let s = service("GitHub", serde_json::json!({"label": "GitHub:alice"}));
+        assert_eq!(names(&s), ("GitHub".into(), "alice".into()));
+        let s = service("", serde_json::json!({"issuer": "Acme", "account": "bob"}));
+        assert_eq!(names(&s), ("Acme".into(), "bob".into()));
+        let s = service(" ", serde_json::json!({}));
+        assert_eq!(names(&s), ("Unknown".into(), "".into()));
+    }
+
+    #[test]
+    fn test_rejects_newer_schema_and_malformed_files() {
+        assert!(read_twofas(br#"{"schemaVersion": 99, "services": []}"#, None).is_err());
+        assert!(read_twofas(b"not json", None).is_err());
+        let data = br#"{"schemaVersion": 4, "services": [], "servicesEncrypted": "AAAA"}"#;
+        assert!(read_twofas(data, Some(b"pw")).is_err());
+    }
+}
@@ -1,187 +1,955 @@
 //! Token import
+//!
+//! Besides the KeyForge-specific entry points, every importable format is
+//! an [`Importer`]: it sniffs whether a file is its own and decodes it into
+//! [`ImportCandidate`]s. An [`ImporterRegistry`] holds the importers and
+//! picks the most confident one, so callers can hand over a file without
+//! knowing what wrote it.
+//!
+//! [`Vault::preview_import`] decodes a file without writing anything, so
+//! the user can pick which tokens to keep; [`Vault::commit_import`] then
+//! stores the chosen ones.
+//!
+//! Every import returns an [`ImportReport`]. Imports run in one
+//! transaction and are atomic by default, and the report names each entry
+//! that failed and why.
 
-use crate::constants::{
-    DEFAULT_ALGORITHM, DEFAULT_COUNTER, DEFAULT_DIGITS, DEFAULT_ISSUER, DEFAULT_PERIOD,
-    EXPORT_SALT_SIZE, OTPAUTH_SCHEME, OTPAUTH_SCHEME_LEN, TOKEN_TYPE_HOTP, TOKEN_TYPE_TOTP,
-};
+#[cfg(feature = "sqlite")]
+use std::io::Read;
+
+use zeroize::Zeroizing;
+
+use crate::backup;
+use crate::constants::EXPORT_STREAM_MAGIC;
+#[cfg(feature = "sqlite")]
 use crate::db::Vault;
 use crate::error::VaultError;
+use crate::formats::{self, JsonShape, ParsedTokens, PortableToken, SkippedEntry};
+use crate::otpauth::{OtpAuthUri, ParseMode};
 use crate::token::NewToken;
-use zeroize::Zeroize;
 
-impl Vault {
-    /// Import tokens from `otpauth://` URIs.
-    pub fn import_uris(&self, uris: &[String]) -> Result<usize, String> {
-        let mut count = 0;
-        for uri in uris {
-            if let Some(token) = parse_otpauth_uri(uri)? {
-                self.add_token(token)?;
-                count += 1;
+#[cfg(feature = "sqlite")]
+use {
+    crate::error::UriError,
+    crate::metadata::replace_token_groups,
+    crate::token::Token,
+    hmac::{Hmac, Mac},
+    keyforge_crypto::secure_mem::LockedKey,
+    rusqlite::Connection,
+    serde::{Deserialize, Serialize},
+    sha2::Sha256,
+    std::collections::BTreeSet,
+    std::fmt,
+};
+
+/// How sure an importer is that a file is in its format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    /// Not this format.
+    No,
+    /// Plausible, but nothing specific to the format was seen (header-less
+    /// encrypted blobs).
+    Weak,
+    /// The structure matches: JSON keys, XML root element, URI scheme.
+    Likely,
+    /// A magic number or format marker matched.
+    Certain,
+}
+
+/// One entry an importer found in a file.
+#[derive(Debug)]
+pub enum ImportCandidate {
+    /// A token ready to store, with its note and groups.
+    Token(PortableToken),
+    /// An entry the vault cannot hold, and why.
+    Skipped(SkippedEntry),
+}
+
+impl ParsedTokens {
+    /// The decoded tokens in order, followed by the skipped entries.
+    pub fn into_candidates(mut self) -> Vec<ImportCandidate> {
+        let mut candidates: Vec<ImportCandidate> = std::mem::take(&mut self.tokens)
+            .into_iter()
+            .map(ImportCandidate::Token)
+            .collect();
+        candidates.extend(
+            std::mem::take(&mut self.skipped)
+                .into_iter()
+                .map(ImportCandidate::Skipped),
+        );
+        candidates
+    }
+
+    /// Split candidates back into tokens and skipped entries.
+    pub fn from_candidates(candidates: Vec<ImportCandidate>) -> Self {
+        let mut parsed = Self::default();
+        for candidate in candidates {
+            match candidate {
+                ImportCandidate::Token(token) => parsed.tokens.push(token),
+                ImportCandidate::Skipped(skipped) => parsed.skipped.push(skipped),
             }
         }
-        Ok(count)
+        parsed
+    }
+}
+
+/// What unlocks an encrypted import file.
+#[derive(Clone, Copy)]
+pub enum ImportSecret<'a> {
+    /// The password the file was exported with.
+    Password(&'a [u8]),
+    /// A KeePass keyfile, with the database password if it has one too.
+    Keyfile {
+        keyfile: &'a [u8],
+        password: Option<&'a [u8]>,
+    },
+    /// Raw key bytes, such as a PSKC container's pre-shared key.
+    Key(&'a [u8]),
+}
+
+impl<'a> ImportSecret<'a> {
+    /// The password in `secret`, for formats nothing else opens. Any other
+    /// kind of secret fails with [`VaultError::ImportPasswordRequired`].
+    pub fn password_only(secret: Option<Self>, format: &str) -> Result<Option<&'a [u8]>, String> {
+        match secret {
+            None => Ok(None),
+            Some(Self::Password(password)) => Ok(Some(password)),
+            Some(_) => Err(VaultError::ImportPasswordRequired(format.into()).into()),
+        }
     }
+}
 
-    /// Import from an encrypted KeyForge export.
-    pub fn import_encrypted(&self, data: &[u8], password: &[u8]) -> Result<usize, String> {
-        if data.len() < EXPORT_SALT_SIZE {
-            return Err(VaultError::InvalidExportFile.to_string());
+/// A file as importers sniff it: its bytes, and its outline when it is
+/// JSON, read once for every importer.
+pub struct SniffedFile<'a> {
+    pub data: &'a [u8],
+    pub json: Option<JsonShape>,
+}
+
+impl<'a> SniffedFile<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            json: JsonShape::sniff(data),
         }
-        let (salt_bytes, encrypted) = data.split_at(EXPORT_SALT_SIZE);
-        let mut salt = [0u8; EXPORT_SALT_SIZE];
-        salt.copy_from_slice(salt_bytes);
+    }
+}
 
-        let params = keyforge_crypto::kdf::KdfParams::default();
-        let mut key = keyforge_crypto::kdf::derive_key(password, &salt, &params)?;
-        let result = keyforge_crypto::aead::decrypt(encrypted, &key);
-        key.zeroize();
-        let json = result?;
+/// A file format tokens can be imported from.
+pub trait Importer: Send + Sync {
+    /// Stable identifier reported to callers, e.g. `"aegis"`.
+    fn id(&self) -> &'static str;
 
-        let uris: Vec<String> =
-            serde_json::from_slice(&json).map_err(|e| VaultError::Serialization(e.to_string()))?;
+    /// Display name, e.g. `"Aegis"`.
+    fn name(&self) -> &'static str;
+
+    /// How likely `file` is in this format. Cheap and infallible: a file
+    /// that cannot be read at all is [`Confidence::No`].
+    fn sniff(&self, file: &SniffedFile<'_>) -> Confidence;
+
+    /// Whether `data` needs a secret to be read.
+    fn is_encrypted(&self, _data: &[u8]) -> bool {
+        false
+    }
 
-        self.import_uris(&uris)
+    /// The error for encrypted `data` given no secret, naming what it
+    /// needs: a password unless overridden.
+    fn missing_secret(&self, _data: &[u8]) -> VaultError {
+        VaultError::ImportPasswordRequired(self.name().into())
     }
+
+    /// Decode `data`. `secret` is only used for encrypted files.
+    fn parse(
+        &self,
+        data: &[u8],
+        secret: Option<ImportSecret<'_>>,
+    ) -> Result<Vec<ImportCandidate>, String>;
+}
+
+/// The importers auto-detection chooses from.
+pub struct ImporterRegistry {
+    importers: Vec<Box<dyn Importer>>,
 }
 
-/// Parse an `otpauth://` URI into a NewToken.
-pub fn parse_otpauth_uri(uri: &str) -> Result<Option<NewToken>, String> {
-    if !uri.starts_with(OTPAUTH_SCHEME) {
-        return Err(VaultError::InvalidUri(uri.to_string()).to_string());
+impl ImporterRegistry {
+    /// A registry with no importers.
+    pub fn empty() -> Self {
+        Self {
+            importers: Vec::new(),
+        }
     }
 
-    let without_scheme = &uri[OTPAUTH_SCHEME_LEN..];
-    let (token_type, rest) = without_scheme
-        .split_once('/')
-        .ok_or_else(|| VaultError::InvalidUri("missing token type".to_string()))?;
+    /// Add an importer. On equal confidence, earlier importers win.
+    pub fn register(&mut self, importer: Box<dyn Importer>) {
+        self.importers.push(importer);
+    }
 
-    let token_type = match token_type {
-        t if t == TOKEN_TYPE_TOTP => TOKEN_TYPE_TOTP.to_string(),
-        t if t == TOKEN_TYPE_HOTP => TOKEN_TYPE_HOTP.to_string(),
-        _ => return Err(VaultError::UnknownTokenType(token_type.to_string()).to_string()),
-    };
+    pub fn importers(&self) -> impl Iterator<Item = &dyn Importer> {
+        self.importers.iter().map(|i| i.as_ref())
+    }
 
-    let (label, query) = rest
-        .split_once('?')
-        .ok_or_else(|| VaultError::InvalidUri("missing query parameters".to_string()))?;
+    /// The importer registered under `id`.
+    pub fn get(&self, id: &str) -> Option<&dyn Importer> {
+        self.importers().find(|i| i.id() == id)
+    }
 
-    // label = "issuer:account" or just "account"
-    let label = urlencoding_decode(label);
-    let (issuer_from_label, account) = if let Some((issuer, account)) = label.split_once(':') {
-        (Some(issuer.to_string()), account.to_string())
-    } else {
-        (None, label.to_string())
-    };
+    /// The importer most confident it can read `data`, if any is.
+    pub fn detect(&self, data: &[u8]) -> Option<&dyn Importer> {
+        let file = SniffedFile::new(data);
+        let mut best: Option<(Confidence, &dyn Importer)> = None;
+        for importer in self.importers() {
+            let confidence = importer.sniff(&file);
+            if confidence > best.map_or(Confidence::No, |(c, _)| c) {
+                best = Some((confidence, importer));
+            }
+        }
+        best.map(|(_, importer)| importer)
+    }
 
-    let params: std::collections::HashMap<String, String> = query
-        .split('&')
-        .filter_map(|p| {
-            let (k, v) = p.split_once('=')?;
-            Some((k.to_lowercase(), urlencoding_decode(v)))
-        })
-        .collect();
-
-    let secret_b32 = params
-        .get("secret")
-        .ok_or(VaultError::MissingUriParam("secret"))?;
-
-    let secret = base32::decode(
-        base32::Alphabet::Rfc4648 { padding: false },
-        &secret_b32.to_uppercase(),
-    )
-    .ok_or(VaultError::InvalidBase32Secret)?;
-
-    let issuer = params
-        .get("issuer")
-        .map(|s| s.to_string())
-        .or(issuer_from_label)
-        .unwrap_or_else(|| DEFAULT_ISSUER.to_string());
-
-    let algorithm = params
-        .get("algorithm")
-        .map(|s| s.to_uppercase())
-        .unwrap_or_else(|| DEFAULT_ALGORITHM.to_string());
-
-    // Validate algorithm
-    match algorithm.as_str() {
-        "SHA1" | "SHA256" | "SHA512" => {}
-        other => {
-            return Err(VaultError::InvalidUri(format!("unsupported algorithm: {other}")).into())
-        }
-    }
-
-    let digits: u32 = params
-        .get("digits")
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(DEFAULT_DIGITS);
-
-    // Validate digits (only 6 or 8 per RFC 4226 / HOTP spec)
-    if digits != 6 && digits != 8 {
-        return Err(VaultError::InvalidUri(format!("unsupported digits: {digits}")).into());
-    }
-
-    let period: u32 = params
-        .get("period")
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(DEFAULT_PERIOD);
-
-    // Validate period is positive
-    if period == 0 {
-        return Err(VaultError::InvalidUri("period must be > 0".to_string()).into());
-    }
-
-    let counter: u64 = params
-        .get("counter")
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(DEFAULT_COUNTER);
-
-    Ok(Some(NewToken {
-        issuer,
-        account,
-        secret,
-        algorithm,
-        digits,
-        token_type,
-        period,
-        counter,
-        icon: None,
-    }))
-}
-
-fn urlencoding_decode(s: &str) -> String {
-    let bytes_in = s.as_bytes();
-    let mut bytes_out = Vec::with_capacity(bytes_in.len());
-    let mut i = 0;
-
-    while i < bytes_in.len() {
-        let b = bytes_in[i];
-        if b == b'%' {
-            if i + 2 < bytes_in.len() {
-                let h1 = bytes_in[i + 1] as char;
-                let h2 = bytes_in[i + 2] as char;
-                if h1.is_ascii_hexdigit() && h2.is_ascii_hexdigit() {
-                    let hex_str = &s[i + 1..=i + 2];
-                    if let Ok(byte) = u8::from_str_radix(hex_str, 16) {
-                        bytes_out.push(byte);
-                        i += 3;
-                        continue;
+    /// Detect the format of `data` and decode it.
+    ///
+    /// Fails with [`VaultError::UnrecognizedImport`] when no importer
+    /// claims the file, and with [`Importer::missing_secret`] when it is
+    /// encrypted and `secret` is `None`.
+    pub fn parse(
+        &self,
+        data: &[u8],
+        secret: Option<ImportSecret<'_>>,
+    ) -> Result<(&dyn Importer, Vec<ImportCandidate>), String> {
+        let importer = self
+            .detect(data)
+            .ok_or_else(|| VaultError::UnrecognizedImport.to_string())?;
+        if secret.is_none() && importer.is_encrypted(data) {
+            return Err(importer.missing_secret(data).into());
+        }
+        Ok((importer, importer.parse(data, secret)?))
+    }
+}
+
+impl Default for ImporterRegistry {
+    /// Every format the vault can read. Formats without a marker come last
+    /// so they only win when nothing else matches: URI lists and andOTP's
+    /// encrypted backups, told apart by their leading iteration count.
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(Box::new(crate::extension::ExtensionImporter));
+        registry.register(Box::new(formats::google::GoogleMigrationImporter));
+        registry.register(Box::new(formats::aegis::AegisImporter));
+        registry.register(Box::new(formats::twofas::TwofasImporter));
+        registry.register(Box::new(formats::bitwarden::BitwardenImporter));
+        registry.register(Box::new(formats::freeotp::FreeOtpImporter));
+        registry.register(Box::new(
+            formats::authenticator_pro::AuthenticatorProImporter,
+        ));
+        registry.register(Box::new(formats::ente::EnteImporter));
+        registry.register(Box::new(formats::keepass::KeePassImporter));
+        registry.register(Box::new(formats::pskc::PskcImporter));
+        registry.register(Box::new(UriListImporter));
+        registry.register(Box::new(formats::andotp::AndOtpImporter));
+        registry.register(Box::new(KeyForgeExportImporter));
+        registry
+    }
+}
+
+/// Plain text with one `otpauth://` URI per line.
+pub struct UriListImporter;
+
+impl Importer for UriListImporter {
+    fn id(&self) -> &'static str {
+        "otpauth"
+    }
+
+    fn name(&self) -> &'static str {
+        "otpauth:// URI list"
+    }
+
+    fn sniff(&self, file: &SniffedFile<'_>) -> Confidence {
+        let first = std::str::from_utf8(file.data)
+            .ok()
+            .and_then(|text| uri_lines(text).next());
+        match first {
+            Some((_, line)) if starts_with_ignore_case(line, "otpauth://") => Confidence::Likely,
+            _ => Confidence::No,
+        }
+    }
+
+    fn parse(
+        &self,
+        data: &[u8],
+        _secret: Option<ImportSecret<'_>>,
+    ) -> Result<Vec<ImportCandidate>, String> {
+        let text = std::str::from_utf8(data)
+            .map_err(|_| VaultError::InvalidImport("the file is not UTF-8 text".into()))?;
+        Ok(uri_candidates(uri_lines(text)))
+    }
+}
+
+/// An encrypted KeyForge export, streamed or legacy. Only streamed exports
+/// are detected: the legacy form has no marker to tell it from any other
+/// binary file.
+pub struct KeyForgeExportImporter;
+
+impl Importer for KeyForgeExportImporter {
+    fn id(&self) -> &'static str {
+        "keyforge"
+    }
+
+    fn name(&self) -> &'static str {
+        "KeyForge encrypted export"
+    }
+
+    fn sniff(&self, file: &SniffedFile<'_>) -> Confidence {
+        if file.data.starts_with(EXPORT_STREAM_MAGIC) {
+            Confidence::Certain
+        } else {
+            Confidence::No
+        }
+    }
+
+    fn is_encrypted(&self, _data: &[u8]) -> bool {
+        true
+    }
+
+    fn parse(
+        &self,
+        data: &[u8],
+        secret: Option<ImportSecret<'_>>,
+    ) -> Result<Vec<ImportCandidate>, String> {
+        let password = ImportSecret::password_only(secret, self.name())?
+            .ok_or(VaultError::ImportPasswordRequired(self.name().into()))?;
+        let uris = Zeroizing::new(backup::read_export_bytes(data, password)?);
+        Ok(uri_candidates(
+            uris.iter().enumerate().map(|(i, uri)| (i, uri.as_str())),
+        ))
+    }
+}
+
+/// The lines of a URI list worth parsing, trimmed, with their 0-based
+/// line numbers: blank lines and `#` comments are left out.
+pub fn uri_lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines()
+        .enumerate()
+        .map(|(index, line)| (index, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+}
+
+/// Parse `(index, uri)` pairs leniently, skipping the ones that fail.
+fn uri_candidates<'a>(uris: impl Iterator<Item = (usize, &'a str)>) -> Vec<ImportCandidate> {
+    uris.map(|(index, uri)| match parse_otpauth_uri(uri) {
+        Ok(token) => {
+            let mut token = PortableToken::from(token);
+            token.index = index;
+            ImportCandidate::Token(token)
+        }
+        Err(reason) => ImportCandidate::Skipped(SkippedEntry {
+            index,
+            label: uri_label(uri),
+            reason,
+        }),
+    })
+    .collect()
+}
+
+/// The label part of an `otpauth://` URI, which never holds the secret.
+fn uri_label(uri: &str) -> String {
+    let path = uri.split('?').next().unwrap_or("");
+    path.splitn(4, '/').nth(3).unwrap_or("").to_string()
+}
+
+pub(crate) fn starts_with_ignore_case(text: &str, prefix: &str) -> bool {
+    text.get(..prefix.len())
+        .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+}
+
+#[cfg(feature = "sqlite")]
+impl Vault {
+    /// Import tokens from `otpauth://` URIs, skipping those whose secret
+    /// the vault already holds. Entries in the report are numbered by
+    /// their place in `uris`, from 1.
+    pub fn import_uris(&self, uris: &[String], mode: ImportMode) -> Result<ImportReport, String> {
+        self.import_uri_lines(
+            uris.iter().enumerate().map(|(i, uri)| (i, uri.as_str())),
+            mode,
+        )
+    }
+
+    /// Import a text file of `otpauth://` URIs, one per line, skipping
+    /// blank lines and `#` comments. Entries in the report are numbered by
+    /// line.
+    pub fn import_uri_text(&self, text: &str, mode: ImportMode) -> Result<ImportReport, String> {
+        self.import_uri_lines(uri_lines(text), mode)
+    }
+
+    fn import_uri_lines<'a>(
+        &self,
+        uris: impl Iterator<Item = (usize, &'a str)>,
+        mode: ImportMode,
+    ) -> Result<ImportReport, String> {
+        let mut report = ImportReport::default();
+        let mut parsed = ParsedTokens::default();
+        for (index, uri) in uris {
+            match OtpAuthUri::parse(uri, ParseMode::Lenient).and_then(|u| u.to_new_token()) {
+                Ok(token) => parsed.add(index, PortableToken::from(token)),
+                Err(e) => report.fail(index, uri_label(uri), ImportFailure::from(e)),
+            }
+        }
+        let existing = ExistingTokens::load(self)?;
+        self.store_tokens(&parsed.tokens, Some(existing), mode, report)
+    }
+
+    /// Add every decoded token, in order, with its note and groups. The
+    /// entries the decoder skipped are reported as failed, and the tokens
+    /// the vault already holds as duplicates, as for URI imports.
+    pub fn import_parsed(
+        &self,
+        mut parsed: ParsedTokens,
+        mode: ImportMode,
+    ) -> Result<ImportReport, String> {
+        let existing = ExistingTokens::load(self)?;
+        let mut report = ImportReport::default();
+        report
+            .failed
+            .extend(parsed.skipped.drain(..).map(FailedEntry::from));
+        self.store_tokens(&parsed.tokens, Some(existing), mode, report)
+    }
+
+    /// Add `tokens` in one transaction after the failures already in
+    /// `report`. A token whose secret `existing` holds is reported as a
+    /// duplicate instead; with no `existing`, every token is added.
+    ///
+    /// Tokens are copied for storage, so `tokens` keep their secrets
+    /// whatever the outcome. In [`ImportMode::Atomic`] any failure rolls
+    /// the whole import back.
+    fn store_tokens<'a>(
+        &self,
+        tokens: impl IntoIterator<Item = &'a PortableToken>,
+        mut existing: Option<ExistingTokens>,
+        mode: ImportMode,
+        mut report: ImportReport,
+    ) -> Result<ImportReport, String> {
+        let mut tx = self
+            .conn()
+            .unchecked_transaction()
+            .map_err(|e| VaultError::Query(e.to_string()))?;
+        for candidate in tokens {
+            let token = &candidate.token;
+            let label = format!("{}:{}", token.issuer, token.account);
+            // Only the same secret is the same token; a shared issuer and
+            // account may well be a second one, so it is imported and
+            // reported.
+            let label_match = match existing.as_ref().and_then(|e| e.find(token)) {
+                Some(duplicate) if duplicate.kind == DuplicateKind::Secret => {
+                    report
+                        .duplicates
+                        .push(DuplicateEntry::new(candidate.index, label, duplicate));
+                    continue;
+                }
+                other => other,
+            };
+            // Rolled back on drop, so a failed entry leaves nothing behind.
+            let savepoint = tx
+                .savepoint()
+                .map_err(|e| VaultError::Query(e.to_string()))?;
+            match self.add_candidate(&savepoint, candidate) {
+                Ok(stored) => {
+                    savepoint
+                        .commit()
+                        .map_err(|e| VaultError::Query(e.to_string()))?;
+                    if let Some(existing) = existing.as_mut() {
+                        existing.insert(stored.clone(), &token.secret);
                     }
+                    if let Some(duplicate) = label_match {
+                        report.label_matches.push(DuplicateEntry::new(
+                            candidate.index,
+                            label,
+                            duplicate,
+                        ));
+                    }
+                    report.imported.push(stored);
                 }
+                Err(e) => report.fail(candidate.index, label, ImportFailure::Storage(e)),
             }
-            // Invalid or incomplete percent-escape: keep '%' as-is
-            bytes_out.push(b'%');
-            i += 1;
-        } else if b == b'+' {
-            bytes_out.push(b' ');
-            i += 1;
-        } else {
-            bytes_out.push(b);
-            i += 1;
         }
+        report.failed.sort_by_key(|failed| failed.number);
+
+        if mode == ImportMode::Atomic && !report.failed.is_empty() {
+            // Dropping the transaction rolls it back.
+            drop(tx);
+            report.imported.clear();
+            report.label_matches.clear();
+            return Ok(report);
+        }
+        tx.commit().map_err(|e| VaultError::Query(e.to_string()))?;
+        Ok(report)
+    }
+
+    /// Add a copy of `candidate`'s token with its note and groups.
+    fn add_candidate(&self, conn: &Connection, candidate: &PortableToken) -> Result<Token, String> {
+        let stored = self.add_token(copy_token(&candidate.token))?;
+        if candidate.note.is_some() {
+            self.set_token_note(&stored.id, candidate.note.as_deref())?;
+        }
+        if !candidate.groups.is_empty() {
+            replace_token_groups(conn, &stored.id, &candidate.groups)?;
+        }
+        Ok(stored)
+    }
+
+    /// Import from an encrypted KeyForge export (streamed or legacy).
+    pub fn import_encrypted(&self, data: &[u8], password: &[u8]) -> Result<ImportReport, String> {
+        let uris = Zeroizing::new(backup::read_export_bytes(data, password)?);
+        self.import_uris(&uris, ImportMode::Atomic)
+    }
+
+    /// Import a streamed encrypted export written by
+    /// [`Vault::export_encrypted_to`]. `size_hint` is as for
+    /// [`backup::read_export`].
+    ///
+    /// Nothing is imported unless the whole stream authenticates, so a
+    /// truncated or tampered file never yields a partial import.
+    pub fn import_encrypted_from<R: Read>(
+        &self,
+        reader: R,
+        password: &[u8],
+        size_hint: usize,
+    ) -> Result<ImportReport, String> {
+        let uris = Zeroizing::new(backup::read_export(reader, password, size_hint)?);
+        self.import_uris(&uris, ImportMode::Atomic)
     }
 
-    String::from_utf8(bytes_out)
-        .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
+    /// Import a file in any format the default [`ImporterRegistry`] knows,
+    /// reporting which one it was detected as.
+    ///
+    /// The whole file is decoded before the first insert. In
+    /// [`ImportMode::Atomic`], an entry the file could not provide stops
+    /// the import before anything is written.
+    pub fn import_file(
+        &self,
+        data: &[u8],
+        secret: Option<ImportSecret<'_>>,
+        mode: ImportMode,
+    ) -> Result<FileImportReport, String> {
+        let registry = ImporterRegistry::default();
+        let (importer, candidates) = registry.parse(data, secret)?;
+        let report = self.import_parsed(ParsedTokens::from_candidates(candidates), mode)?;
+        Ok(FileImportReport {
+            format: importer.id(),
+            format_name: importer.name(),
+            report,
+        })
+    }
+
+    /// Decode a file in any format the default [`ImporterRegistry`] knows,
+    /// without importing it. Each candidate is checked against the tokens
+    /// already in the vault.
+    pub fn preview_import(
+        &self,
+        data: &[u8],
+        secret: Option<ImportSecret<'_>>,
+    ) -> Result<PendingImport, String> {
+        let registry = ImporterRegistry::default();
+        let (importer, candidates) = registry.parse(data, secret)?;
+        let mut tokens = ParsedTokens::from_candidates(candidates);
+        let existing = ExistingTokens::load(self)?;
+        let entries = tokens
+            .tokens
+            .iter()
+            .enumerate()
+            .map(|(id, candidate)| {
+                PreviewEntry::new(id, candidate, existing.find(&candidate.token))
+            })
+            .collect();
+        Ok(PendingImport {
+            preview: ImportPreview {
+                format: importer.id(),
+                format_name: importer.name(),
+                entries,
+                skipped: tokens.skipped.drain(..).map(FailedEntry::from).collect(),
+            },
+            tokens,
+        })
+    }
+
+    /// Import the candidates of a preview named in `selected`, in file
+    /// order. The entries the preview skipped are listed as failed again,
+    /// among any the vault could not store.
+    ///
+    /// `pending` is left as it was, so a refused commit can be retried;
+    /// drop it once committed to zeroize the candidates. Selected
+    /// candidates are imported even when the preview flagged them as
+    /// duplicates: the flags are not checked again, so tokens added since
+    /// the preview are not flagged either.
+    pub fn commit_import(
+        &self,
+        pending: &PendingImport,
+        selected: &[usize],
+    ) -> Result<ImportReport, String> {
+        pending.check_selection(selected)?;
+        let selected: BTreeSet<usize> = selected.iter().copied().collect();
+        let chosen = selected.iter().map(|&id| &pending.tokens.tokens[id]);
+        let mut report =
+            self.store_tokens(chosen, None, ImportMode::Atomic, ImportReport::default())?;
+        report
+            .failed
+            .extend(pending.preview.skipped.iter().cloned());
+        report.failed.sort_by_key(|failed| failed.number);
+        Ok(report)
+    }
+}
+
+/// What happens to the rest of an import when an entry fails.
+#[cfg(feature = "sqlite")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// Import everything or nothing.
+    #[default]
+    Atomic,
+    /// Import every entry that can be, and report the rest.
+    BestEffort,
+}
+
+/// Why an entry was not imported.
+#[cfg(feature = "sqlite")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "snake_case")]
+pub enum ImportFailure {
+    /// Not a readable `otpauth://` URI.
+    InvalidUri(String),
+    /// A well-formed URI whose parameters cannot generate codes.
+    Unsupported(String),
+    /// The vault could not store the token.
+    Storage(String),
+    /// An entry of a foreign export that could not be decoded, or that the
+    /// vault cannot hold.
+    Decode(String),
+}
+
+#[cfg(feature = "sqlite")]
+impl From<UriError> for ImportFailure {
+    fn from(e: UriError) -> Self {
+        match e {
+            UriError::Unsupported(_) => Self::Unsupported(e.to_string()),
+            _ => Self::InvalidUri(e.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl fmt::Display for ImportFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidUri(e) | Self::Unsupported(e) | Self::Storage(e) | Self::Decode(e) => {
+                write!(f, "{}", e)
+            }
+        }
+    }
+}
+
+/// The number users see for the entry at 0-based `index` in its source:
+/// its line in a text file, its place in a list or export otherwise.
+/// Report entries are numbered here and nowhere else.
+#[cfg(feature = "sqlite")]
+fn entry_number(index: usize) -> usize {
+    index + 1
+}
+
+/// An entry that could not be imported.
+#[cfg(feature = "sqlite")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FailedEntry {
+    /// 1-based number of the entry in its source: its line for text.
+    pub number: usize,
+    /// Issuer and account as far as they could be read, for display.
+    pub label: String,
+    pub reason: ImportFailure,
+}
+
+#[cfg(feature = "sqlite")]
+impl FailedEntry {
+    fn new(index: usize, label: String, reason: ImportFailure) -> Self {
+        Self {
+            number: entry_number(index),
+            label,
+            reason,
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<SkippedEntry> for FailedEntry {
+    fn from(skipped: SkippedEntry) -> Self {
+        Self::new(
+            skipped.index,
+            skipped.label,
+            ImportFailure::Decode(skipped.reason),
+        )
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl fmt::Display for FailedEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Entry {}: {}", self.number, self.reason)
+    }
+}
+
+/// An entry matching a token the vault held when the import started.
+#[cfg(feature = "sqlite")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DuplicateEntry {
+    /// 1-based number of the entry in its source: its line for text.
+    pub number: usize,
+    pub label: String,
+    #[serde(flatten)]
+    pub duplicate: Duplicate,
+}
+
+#[cfg(feature = "sqlite")]
+impl DuplicateEntry {
+    fn new(index: usize, label: String, duplicate: Duplicate) -> Self {
+        Self {
+            number: entry_number(index),
+            label,
+            duplicate,
+        }
+    }
+}
+
+/// Outcome of every import into the vault.
+///
+/// In [`ImportMode::Atomic`] a single failure rolls the whole import back:
+/// `imported` is then empty and `failed` lists every entry that failed.
+/// [`Vault::commit_import`] leaves `duplicates` and `label_matches` empty;
+/// its candidates were flagged in [`Vault::preview_import`].
+#[cfg(feature = "sqlite")]
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    pub imported: Vec<Token>,
+    /// Entries left out because the vault already holds their secret.
+    pub duplicates: Vec<DuplicateEntry>,
+    /// Imported entries sharing the issuer and account of a token already
+    /// in the vault, for the user to review.
+    pub label_matches: Vec<DuplicateEntry>,
+    pub failed: Vec<FailedEntry>,
+}
+
+#[cfg(feature = "sqlite")]
+impl ImportReport {
+    fn fail(&mut self, index: usize, label: String, reason: ImportFailure) {
+        self.failed.push(FailedEntry::new(index, label, reason));
+    }
+}
+
+/// Why an import candidate looks like a token already in the vault.
+#[cfg(feature = "sqlite")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateKind {
+    /// The same secret.
+    Secret,
+    /// The same issuer and account, ignoring case.
+    Label,
+}
+
+/// The vault token an import candidate duplicates.
+#[cfg(feature = "sqlite")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Duplicate {
+    pub token_id: String,
+    pub kind: DuplicateKind,
+}
+
+/// A decoded token offered for import: everything but its secret.
+#[cfg(feature = "sqlite")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PreviewEntry {
+    /// Candidate ID for [`Vault::commit_import`]: the token's position
+    /// among those decoded, so previewing the same file again gives the
+    /// same IDs.
+    pub id: usize,
+    pub issuer: String,
+    pub account: String,
+    pub algorithm: String,
+    pub digits: u32,
+    pub token_type: String,
+    pub period: u32,
+    pub counter: u64,
+    pub note: Option<String>,
+    pub groups: Vec<String>,
+    /// Set when the vault held this token at preview time.
+    pub duplicate: Option<Duplicate>,
+}
+
+#[cfg(feature = "sqlite")]
+impl PreviewEntry {
+    fn new(id: usize, candidate: &PortableToken, duplicate: Option<Duplicate>) -> Self {
+        let token = &candidate.token;
+        Self {
+            id,
+            issuer: token.issuer.clone(),
+            account: token.account.clone(),
+            algorithm: token.algorithm.clone(),
+            digits: token.digits,
+            token_type: token.token_type.clone(),
+            period: token.period,
+            counter: token.counter,
+            note: candidate.note.clone(),
+            groups: candidate.groups.clone(),
+            duplicate,
+        }
+    }
+}
+
+/// What [`Vault::preview_import`] found in a file.
+#[cfg(feature = "sqlite")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ImportPreview {
+    /// [`Importer::id`] of the detected format.
+    pub format: &'static str,
+    pub format_name: &'static str,
+    pub entries: Vec<PreviewEntry>,
+    /// Entries that cannot be imported whatever is selected.
+    pub skipped: Vec<FailedEntry>,
+}
+
+/// A decoded file waiting for [`Vault::commit_import`]. Holds the
+/// candidates' secrets until then; they are zeroized on drop.
+#[cfg(feature = "sqlite")]
+#[derive(Debug)]
+pub struct PendingImport {
+    preview: ImportPreview,
+    tokens: ParsedTokens,
+}
+
+#[cfg(feature = "sqlite")]
+impl PendingImport {
+    pub fn preview(&self) -> &ImportPreview {
+        &self.preview
+    }
+
+    /// Fail with [`VaultError::UnknownImportCandidate`] unless every ID in
+    /// `selected` names a candidate.
+    pub fn check_selection(&self, selected: &[usize]) -> Result<(), String> {
+        match selected.iter().find(|&&id| id >= self.tokens.tokens.len()) {
+            Some(&id) => Err(VaultError::UnknownImportCandidate(id).into()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The vault's tokens and their secrets, for duplicate checks, followed
+/// by the tokens imported so far.
+///
+/// Secrets are kept only as HMAC-SHA256 tags under a random key drawn per
+/// load, so the plaintext secrets are not held for the whole import.
+#[cfg(feature = "sqlite")]
+struct ExistingTokens {
+    tokens: Vec<Token>,
+    secret_tags: Vec<[u8; 32]>,
+    tag_key: LockedKey,
+    /// How many of `tokens` the vault held before the import.
+    held: usize,
+}
+
+#[cfg(feature = "sqlite")]
+impl ExistingTokens {
+    fn load(vault: &Vault) -> Result<Self, String> {
+        let mut tag_key = LockedKey::zeroed();
+        keyforge_crypto::random::fill(tag_key.expose_mut());
+        let tokens = vault.list_tokens()?;
+        let secret_tags = tokens
+            .iter()
+            .map(|token| {
+                let secret = vault.get_token_secret_locked(&token.id)?;
+                Ok(secret_tag(&tag_key, secret.as_slice()))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self {
+            held: tokens.len(),
+            tokens,
+            secret_tags,
+            tag_key,
+        })
+    }
+
+    /// Count `token`, just imported with `secret`, for the entries after it.
+    fn insert(&mut self, token: Token, secret: &[u8]) {
+        self.tokens.push(token);
+        self.secret_tags.push(secret_tag(&self.tag_key, secret));
+    }
+
+    /// The first token sharing `token`'s secret, or failing that its
+    /// issuer and account. A token imported earlier in the same run only
+    /// shares the secret of an entry with its issuer and account too, so a
+    /// file repeating an entry imports it once but one reusing a secret
+    /// under several names keeps them all.
+    fn find(&self, token: &NewToken) -> Option<Duplicate> {
+        let duplicate = |existing: &Token, kind| Duplicate {
+            token_id: existing.id.clone(),
+            kind,
+        };
+        let same_label = |existing: &Token| {
+            existing.issuer.eq_ignore_ascii_case(&token.issuer)
+                && existing.account.eq_ignore_ascii_case(&token.account)
+        };
+        let tag = secret_tag(&self.tag_key, &token.secret);
+        let by_secret = self.tokens.iter().zip(&self.secret_tags).enumerate().find(
+            |(i, (existing, existing_tag))| {
+                **existing_tag == tag && (*i < self.held || same_label(existing))
+            },
+        );
+        if let Some((_, (existing, _))) = by_secret {
+            return Some(duplicate(existing, DuplicateKind::Secret));
+        }
+        self.tokens
+            .iter()
+            .find(|existing| same_label(existing))
+            .map(|existing| duplicate(existing, DuplicateKind::Label))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn secret_tag(key: &LockedKey, secret: &[u8]) -> [u8; 32] {
+    let mut mac =
+        <Hmac<Sha256> as Mac>::new_from_slice(key.expose()).expect("HMAC accepts any key length");
+    mac.update(secret);
+    mac.finalize().into_bytes().into()
+}
+
+/// Outcome of [`Vault::import_file`].
+#[cfg(feature = "sqlite")]
+#[derive(Debug, Clone, Serialize)]
+pub struct FileImportReport {
+    /// [`Importer::id`] of the detected format.
+    pub format: &'static str,
+    pub format_name: &'static str,
+    #[serde(flatten)]
+    pub report: ImportReport,
+}
+
+/// A copy of `token` for [`Vault::add_token`], which zeroizes the secret
+/// it is given.
+#[cfg(feature = "sqlite")]
+fn copy_token(token: &NewToken) -> NewToken {
+    NewToken {
+        issuer: token.issuer.clone(),
+        account: token.account.clone(),
+        secret: token.secret.clone(),
+        algorithm: token.algorithm.clone(),
+        digits: token.digits,
+        token_type: token.token_type.clone(),
+        period: token.period,
+        counter: token.counter,
+        icon: token.icon.clone(),
+    }
+}
+
+/// Parse an `otpauth://` URI into a NewToken (lenient, as exporters in
+/// the wild are). See [`OtpAuthUri`] for strict parsing and the full model.
+pub fn parse_otpauth_uri(uri: &str) -> Result<NewToken, String> {
+    let uri = OtpAuthUri::parse(uri, ParseMode::Lenient)?;
+    Ok(uri.to_new_token()?)
 }
 
 #[cfg(test)]
@@ -191,7 +959,7 @@ mod tests {
     #[test]
     fn test_parse_totp_uri() {
         let uri = "otpauth://totp/GitHub:user@example.com?secret=JBSWY3DPEHPK3PXP&algorithm=SHA1&digits=6&period=30";
-        let token = parse_otpauth_uri(uri).unwrap().unwrap();
+        let token = parse_otpauth_uri(uri).unwrap();
         assert_eq!(token.issuer, "GitHub");
         assert_eq!(token.account, "user@example.com");
         assert_eq!(token.algorithm, "SHA1");
@@ -203,7 +971,7 @@ mod tests {
     #[test]
     fn test_parse_hotp_uri() {
         let uri = "otpauth://hotp/Test:user?secret=JBSWY3DPEHPK3PXP&counter=42";
-        let token = parse_otpauth_uri(uri).unwrap().unwrap();
+        let token = parse_otpauth_uri(uri).unwrap();
         assert_eq!(token.token_type, "hotp");
         assert_eq!(token.counter, 42);
     }
@@ -211,19 +979,155 @@ mod tests {
     #[test]
     fn test_parse_defaults() {
         let uri = "otpauth://totp/user?secret=JBSWY3DPEHPK3PXP";
-        let token = parse_otpauth_uri(uri).unwrap().unwrap();
+        let token = parse_otpauth_uri(uri).unwrap();
         assert_eq!(token.issuer, "Unknown");
         assert_eq!(token.algorithm, "SHA1");
         assert_eq!(token.digits, 6);
         assert_eq!(token.period, 30);
     }
 
+    #[test]
+    fn test_parse_rejects_bad_parameters() {
+        let base = "otpauth://totp/Test?secret=JBSWY3DPEHPK3PXP";
+        assert!(parse_otpauth_uri(&format!("{base}&algorithm=MD5")).is_err());
+        assert!(parse_otpauth_uri(&format!("{base}&digits=7")).is_err());
+        assert!(parse_otpauth_uri(&format!("{base}&period=0")).is_err());
+    }
+
     #[test]
     fn test_parse_invalid_uri() {
         let result = parse_otpauth_uri("https://example.com");
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_registry_detects_every_format() {
+        let fixtures: [(&[u8], &str); 17] = [
+            (
+                include_bytes!("../tests/fixtures/aegis_plain.json"),
+                "aegis",
+            ),
+            (
+                include_bytes!("../tests/fixtures/aegis_encrypted.json"),
+                "aegis",
+            ),
+            (
+                include_bytes!("../tests/fixtures/andotp_plain.json"),
+                "andotp",
+            ),
+            (
+                include_bytes!("../tests/fixtures/andotp_encrypted.json.aes"),
+                "andotp",
+            ),
+            (
+                include_bytes!("../tests/fixtures/authenticator_pro_plain.json"),
+                "authenticator_pro",
+            ),
+            (
+                include_bytes!("../tests/fixtures/authenticator_pro_encrypted.bin"),
+                "authenticator_pro",
+            ),
+            (
+                include_bytes!("../tests/fixtures/authenticator_pro_legacy.bin"),
+                "authenticator_pro",
+            ),
+            (
+                include_bytes!("../tests/fixtures/bitwarden_plain.json"),
+                "bitwarden",
+            ),
+            (
+                include_bytes!("../tests/fixtures/bitwarden_encrypted.json"),
+                "bitwarden",
+            ),
+            (include_bytes!("../tests/fixtures/ente_plain.txt"), "ente"),
+            (
+                include_bytes!("../tests/fixtures/ente_encrypted.json"),
+                "ente",
+            ),
+            (
+                include_bytes!("../tests/fixtures/freeotp_plus.json"),
+                "freeotp",
+            ),
+            (
+                include_bytes!("../tests/fixtures/keepass_argon2d.kdbx"),
+                "keepass",
+            ),
+            (include_bytes!("../tests/fixtures/pskc_plain.xml"), "pskc"),
+            (include_bytes!("../tests/fixtures/pskc_pbe.xml"), "pskc"),
+            (
+                include_bytes!("../tests/fixtures/twofas_plain.2fas"),
+                "twofas",
+            ),
+            (
+                include_bytes!("../tests/fixtures/twofas_encrypted.2fas"),
+                "twofas",
+            ),
+        ];
+        let registry = ImporterRegistry::default();
+        for (data, format) in fixtures {
+            assert_eq!(registry.detect(data).map(|i| i.id()), Some(format));
+        }
+
+        let text = b"# exported 2026-01-01\n\n otpauth://totp/A:a?secret=GEZDGNBV\n";
+        assert_eq!(registry.detect(text).map(|i| i.id()), Some("otpauth"));
+        let google = b"otpauth-migration://offline?data=AA%3D%3D";
+        assert_eq!(
+            registry.detect(google).map(|i| i.id()),
+            Some("google_migration")
+        );
+        let uris = ["otpauth://totp/A?secret=GEZDGNBV".to_string()];
+        let export = backup::write_export(&uris, b"pw", Vec::new()).unwrap();
+        assert_eq!(registry.detect(&export).map(|i| i.id()), Some("keyforge"));
+        assert!(registry.detect(b"hello").is_none());
+        assert!(registry.detect(b"{\"unrelated\": true}").is_none());
+        // Binary files without a marker are nobody's.
+        let binary: Vec<u8> = (0..=255).collect();
+        assert!(registry.detect(&binary).is_none());
+    }
+
+    #[test]
+    fn test_json_shape_keeps_no_values() {
+        let json =
+            br#"{"tokens": [{"secret": [1, 2], "issuer": "x"}, 3], "a": {"b": {"c": {"d": 1}}}}"#;
+        let shape = JsonShape::sniff(json).unwrap();
+        let token = shape.get("tokens").and_then(JsonShape::first).unwrap();
+        assert_eq!(token.get("issuer"), Some(&JsonShape::Scalar));
+        // Containers past the outlined depth are recorded empty.
+        assert_eq!(token.get("secret"), Some(&JsonShape::Array(None)));
+        assert_eq!(
+            shape.get("a").and_then(|a| a.get("b")?.get("c")),
+            Some(&JsonShape::Object(Vec::new()))
+        );
+
+        assert!(JsonShape::sniff(b"\"otpauth://\"").is_none());
+        assert!(JsonShape::sniff(b"[1] [2]").is_none());
+    }
+
+    #[test]
+    fn test_registry_parse_reports_failures() {
+        let registry = ImporterRegistry::default();
+        let text = b"otpauth://totp/A:a?secret=GEZDGNBV\n\notpauth://totp/B:b?secret=!!\n";
+        let (importer, candidates) = registry.parse(text, None).unwrap();
+        assert_eq!(importer.name(), "otpauth:// URI list");
+        let parsed = ParsedTokens::from_candidates(candidates);
+        assert_eq!(parsed.tokens[0].token.issuer, "A");
+        assert_eq!(parsed.skipped.len(), 1);
+        assert_eq!(
+            (parsed.skipped[0].index, parsed.skipped[0].label.as_str()),
+            (2, "B:b")
+        );
+
+        let encrypted = include_bytes!("../tests/fixtures/aegis_encrypted.json");
+        assert_eq!(
+            registry.parse(encrypted, None).err().unwrap(),
+            VaultError::ImportPasswordRequired("Aegis".into()).to_string()
+        );
+        assert_eq!(
+            registry.parse(b"hello", None).err().unwrap(),
+            VaultError::UnrecognizedImport.to_string()
+        );
+    }
+
     #[test]
     fn test_parse_missing_secret() {
         let result = parse_otpauth_uri("otpauth://totp/Test?algorithm=SHA1");
@@ -1,7 +1,17 @@
+pub mod backup;
 pub mod constants;
+#[cfg(feature = "sqlite")]
 pub mod db;
 pub mod error;
+#[cfg(feature = "sqlite")]
 pub mod export;
+pub mod extension;
+pub mod formats;
 pub mod import;
+#[cfg(feature = "sqlite")]
+pub mod metadata;
+#[cfg(feature = "sqlite")]
 pub mod migrations;
+pub mod otpauth;
+pub mod provisioning;
 pub mod token;
@@ -0,0 +1,104 @@
+//! Token notes and groups
+//!
+//! Optional per-token metadata, mostly carried over from other
+//! authenticators on import. It lives beside the `tokens` row rather than in
+//! [`Token`](crate::token::Token) so listing tokens stays one query.
+
+use chrono::Utc;
+use rusqlite::Connection;
+
+use crate::db::Vault;
+use crate::error::VaultError;
+
+impl Vault {
+    /// The token's free-text note, if any.
+    pub fn token_note(&self, id: &str) -> Result<Option<String>, String> {
+        self.conn()
+            .query_row(
+                "SELECT note FROM tokens WHERE id = ?1",
+                rusqlite::params![id],
+                |row| row.get(0),
+            )
+            .map_err(|_| VaultError::TokenNotFound.into())
+    }
+
+    /// Replace the token's note; `None` or an empty note clears it.
+    pub fn set_token_note(&self, id: &str, note: Option<&str>) -> Result<(), String> {
+        let note = note.filter(|n| !n.is_empty());
+        let rows = self
+            .conn()
+            .execute(
+                "UPDATE tokens SET note = ?1, updated_at = ?2 WHERE id = ?3",
+                rusqlite::params![note, Utc::now().to_rfc3339(), id],
+            )
+            .map_err(|e| VaultError::Query(e.to_string()))?;
+        if rows == 0 {
+            return Err(VaultError::TokenNotFound.into());
+        }
+        Ok(())
+    }
+
+    /// Names of the groups the token belongs to, sorted.
+    pub fn token_groups(&self, id: &str) -> Result<Vec<String>, String> {
+        let mut stmt = self
+            .conn()
+            .prepare("SELECT name FROM token_groups WHERE token_id = ?1 ORDER BY name")
+            .map_err(|e| VaultError::Query(e.to_string()))?;
+        let names = stmt
+            .query_map(rusqlite::params![id], |row| row.get(0))
+            .map_err(|e| VaultError::Query(e.to_string()))?;
+        names
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| -> String { VaultError::Query(e.to_string()).into() })
+    }
+
+    /// Replace the token's groups. Blank and repeated names are dropped.
+    pub fn set_token_groups(&self, id: &str, groups: &[String]) -> Result<(), String> {
+        if self.get_token(id)?.is_none() {
+            return Err(VaultError::TokenNotFound.into());
+        }
+        let tx = self
+            .conn()
+            .unchecked_transaction()
+            .map_err(|e| VaultError::Query(e.to_string()))?;
+        replace_token_groups(&tx, id, groups)?;
+        tx.commit().map_err(|e| VaultError::Query(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Every group name in use, sorted.
+    pub fn list_groups(&self) -> Result<Vec<String>, String> {
+        let mut stmt = self
+            .conn()
+            .prepare("SELECT DISTINCT name FROM token_groups ORDER BY name")
+            .map_err(|e| VaultError::Query(e.to_string()))?;
+        let names = stmt
+            .query_map([], |row| row.get(0))
+            .map_err(|e| VaultError::Query(e.to_string()))?;
+        names
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| -> String { VaultError::Query(e.to_string()).into() })
+    }
+}
+
+/// Replace the groups of token `id` without checking that it exists, for
+/// callers already inside a transaction.
+pub(crate) fn replace_token_groups(
+    conn: &Connection,
+    id: &str,
+    groups: &[String],
+) -> Result<(), String> {
+    conn.execute(
+        "DELETE FROM token_groups WHERE token_id = ?1",
+        rusqlite::params![id],
+    )
+    .map_err(|e| VaultError::Query(e.to_string()))?;
+    for name in groups.iter().map(|g| g.trim()).filter(|g| !g.is_empty()) {
+        conn.execute(
+            "INSERT OR IGNORE INTO token_groups (token_id, name) VALUES (?1, ?2)",
+            rusqlite::params![id, name],
+        )
+        .map_err(|e| VaultError::Query(e.to_string()))?;
+    }
+    Ok(())
+}
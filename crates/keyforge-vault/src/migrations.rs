@@ -16,9 +16,12 @@ pub fn run_migrations(conn: &Connection) -> Result<(), String> {
 
     let current_version = get_current_version(conn)?;
 
-    if current_version < SCHEMA_VERSION {
+    if current_version < 1 {
         migrate_v1(conn)?;
     }
+    if current_version < SCHEMA_VERSION {
+        migrate_v2(conn)?;
+    }
 
     Ok(())
 }
@@ -68,6 +71,31 @@ fn migrate_v1(conn: &Connection) -> Result<(), String> {
     Ok(())
 }
 
+/// v2: per-token notes and groups (see [`crate::metadata`]).
+fn migrate_v2(conn: &Connection) -> Result<(), String> {
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| VaultError::Migration(e.to_string()))?;
+    tx.execute_batch(
+        "ALTER TABLE tokens ADD COLUMN note TEXT;
+
+        CREATE TABLE IF NOT EXISTS token_groups (
+            token_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            PRIMARY KEY (token_id, name)
+        );
+
+        INSERT OR IGNORE INTO migrations (version, applied_at) VALUES (2, datetime('now'));
+        UPDATE vault_meta SET value = '2' WHERE key = 'schema_version';
+        ",
+    )
+    .map_err(|e| VaultError::Migration(e.to_string()))?;
+    tx.commit()
+        .map_err(|e| VaultError::Migration(e.to_string()))?;
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -83,7 +111,7 @@ mod tests {
         run_migrations(&conn).unwrap();
 
         let version = get_current_version(&conn).unwrap();
-        assert_eq!(version, 1);
+        assert_eq!(version, SCHEMA_VERSION);
     }
 
     #[test]
@@ -93,7 +121,7 @@ mod tests {
         run_migrations(&conn).unwrap(); // Should be no-op
 
         let version = get_current_version(&conn).unwrap();
-        assert_eq!(version, 1);
+        assert_eq!(version, SCHEMA_VERSION);
     }
 
     #[test]
@@ -121,4 +149,37 @@ mod tests {
             .unwrap();
         assert_eq!(count, 1);
     }
+
+    #[test]
+    fn test_upgrade_from_v1() {
+        let conn = open_test_db();
+        conn.execute_batch(
+            "CREATE TABLE migrations (version INTEGER PRIMARY KEY, applied_at TEXT NOT NULL);",
+        )
+        .unwrap();
+        migrate_v1(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO tokens (id, issuer, secret_encrypted, created_at, updated_at)
+             VALUES ('t1', 'GitHub', x'00', 'now', 'now')",
+            [],
+        )
+        .unwrap();
+
+        run_migrations(&conn).unwrap();
+        assert_eq!(get_current_version(&conn).unwrap(), 2);
+        let note: Option<String> = conn
+            .query_row("SELECT note FROM tokens WHERE id = 't1'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(note, None);
+        let schema: String = conn
+            .query_row(
+                "SELECT value FROM vault_meta WHERE key = 'schema_version'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(schema, "2");
+    }
 }
@@ -0,0 +1,645 @@
+//! `otpauth://` Key URI model
+//!
+//! [`OtpAuthUri`] is the one parser and formatter for Key URIs: imports,
+//! exports, provisioning and the bindings all go through it. Parsing comes
+//! in two modes. [`ParseMode::Lenient`] accepts what real-world exporters
+//! emit (mixed case, padded secrets, `+` for space, a label issuer that
+//! disagrees with `issuer=`). [`ParseMode::Strict`] enforces the Key URI
+//! format and reports the first violation as a [`UriError`].
+//!
+//! Parameters the vault does not use (`image`, `color`, `lock`, ...) are
+//! kept in [`OtpAuthUri::extra`]. The [`fmt::Display`] output is canonical:
+//! for any URI that passes [`OtpAuthUri::validate`], formatting and parsing
+//! it again gives back the same value, and so the same bytes.
+
+use std::fmt;
+use std::str::FromStr;
+
+use keyforge_crypto::hotp::Algorithm;
+use keyforge_crypto::otp::{TokenSpec, STEAM_DIGITS, TYPE_HOTP, TYPE_STEAM, TYPE_TOTP};
+use zeroize::Zeroize;
+
+use crate::constants::{
+    DEFAULT_COUNTER, DEFAULT_DIGITS, DEFAULT_ISSUER, DEFAULT_PERIOD, OTPAUTH_SCHEME,
+    OTPAUTH_SCHEME_LEN,
+};
+use crate::error::UriError;
+use crate::token::NewToken;
+
+const PARAM_SECRET: &str = "secret";
+const PARAM_ISSUER: &str = "issuer";
+const PARAM_ALGORITHM: &str = "algorithm";
+const PARAM_DIGITS: &str = "digits";
+const PARAM_PERIOD: &str = "period";
+const PARAM_COUNTER: &str = "counter";
+const KNOWN_PARAMS: [&str; 6] = [
+    PARAM_SECRET,
+    PARAM_ISSUER,
+    PARAM_ALGORITHM,
+    PARAM_DIGITS,
+    PARAM_PERIOD,
+    PARAM_COUNTER,
+];
+
+/// How forgiving [`OtpAuthUri::parse`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Reject anything outside the Key URI format.
+    Strict,
+    /// Accept common deviations; used for imports.
+    Lenient,
+}
+
+/// A parsed `otpauth://` URI. Optional fields are `None` when the
+/// parameter was absent; [`OtpAuthUri::to_new_token`] applies defaults.
+#[derive(Clone, PartialEq, Eq)]
+pub struct OtpAuthUri {
+    /// Lower-case token type (`totp`, `hotp` or `steam`).
+    pub token_type: String,
+    /// Issuer prefix of the label (`Issuer:account`), if any.
+    pub label_issuer: Option<String>,
+    pub account: String,
+    pub secret: Vec<u8>,
+    /// The `issuer` parameter.
+    pub issuer: Option<String>,
+    pub algorithm: Option<Algorithm>,
+    pub digits: Option<u32>,
+    pub period: Option<u32>,
+    pub counter: Option<u64>,
+    /// Unrecognised parameters, decoded, in their original order.
+    pub extra: Vec<(String, String)>,
+}
+
+impl fmt::Debug for OtpAuthUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OtpAuthUri")
+            .field("token_type", &self.token_type)
+            .field("label_issuer", &self.label_issuer)
+            .field("account", &self.account)
+            .field("secret", &"<redacted>")
+            .field("issuer", &self.issuer)
+            .field("algorithm", &self.algorithm)
+            .field("digits", &self.digits)
+            .field("period", &self.period)
+            .field("counter", &self.counter)
+            .field("extra", &self.extra)
+            .finish()
+    }
+}
+
+impl Drop for OtpAuthUri {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+impl OtpAuthUri {
+    /// Parse `uri` in the given mode.
+    pub fn parse(uri: &str, mode: ParseMode) -> Result<Self, UriError> {
+        let strict = mode == ParseMode::Strict;
+
+        let scheme = uri.get(..OTPAUTH_SCHEME_LEN).ok_or(UriError::Scheme)?;
+        let scheme_ok = if strict {
+            scheme == OTPAUTH_SCHEME
+        } else {
+            scheme.eq_ignore_ascii_case(OTPAUTH_SCHEME)
+        };
+        if !scheme_ok {
+            return Err(UriError::Scheme);
+        }
+
+        let (token_type, rest) = uri[OTPAUTH_SCHEME_LEN..]
+            .split_once('/')
+            .ok_or(UriError::MissingType)?;
+        let token_type = if strict {
+            token_type.to_string()
+        } else {
+            token_type.to_ascii_lowercase()
+        };
+        if ![TYPE_TOTP, TYPE_HOTP, TYPE_STEAM].contains(&token_type.as_str()) {
+            return Err(UriError::UnknownType(token_type));
+        }
+
+        let (label, query) = rest.split_once('?').ok_or(UriError::MissingQuery)?;
+        let (label_issuer, account) = match split_label(label) {
+            (Some(issuer), account) => (Some(decode(issuer, mode)?), account),
+            (None, account) => (None, account),
+        };
+        let account = decode(account, mode)?;
+        // The Key URI format allows spaces between the separator and the account.
+        let account = match label_issuer {
+            Some(_) => account.trim_start_matches(' ').to_string(),
+            None => account,
+        };
+
+        let mut parsed = Self {
+            token_type,
+            label_issuer,
+            account,
+            secret: Vec::new(),
+            issuer: None,
+            algorithm: None,
+            digits: None,
+            period: None,
+            counter: None,
+            extra: Vec::new(),
+        };
+        let mut has_secret = false;
+
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let Some((key, value)) = pair.split_once('=') else {
+                if strict {
+                    return Err(UriError::MalformedParameter(pair.to_string()));
+                }
+                continue;
+            };
+            let key = decode(key, mode)?;
+            let mut value = decode(value, mode)?;
+
+            let known = KNOWN_PARAMS.iter().copied().find(|k| {
+                if strict {
+                    key == *k
+                } else {
+                    key.eq_ignore_ascii_case(k)
+                }
+            });
+            let already_set = match known {
+                Some(PARAM_SECRET) => has_secret,
+                Some(PARAM_ISSUER) => parsed.issuer.is_some(),
+                Some(PARAM_ALGORITHM) => parsed.algorithm.is_some(),
+                Some(PARAM_DIGITS) => parsed.digits.is_some(),
+                Some(PARAM_PERIOD) => parsed.period.is_some(),
+                Some(PARAM_COUNTER) => parsed.counter.is_some(),
+                _ => false,
+            };
+            if already_set && strict {
+                value.zeroize();
+                return Err(UriError::DuplicateParameter(key));
+            }
+
+            match known {
+                // The first secret wins; a repeat is wiped rather than kept
+                // as an unknown parameter.
+                Some(PARAM_SECRET) => {
+                    let decoded = if already_set {
+                        Ok(())
+                    } else {
+                        decode_secret(&value, mode).map(|secret| {
+                            parsed.secret = secret;
+                            has_secret = true;
+                        })
+                    };
+                    value.zeroize();
+                    decoded?;
+                }
+                Some(PARAM_ISSUER) if !already_set => parsed.issuer = Some(value),
+                Some(PARAM_ALGORITHM) if !already_set => {
+                    parsed.algorithm = Some(parse_algorithm(&value, mode)?)
+                }
+                Some(PARAM_DIGITS) if !already_set => {
+                    parsed.digits = Some(parse_number(PARAM_DIGITS, &value)?)
+                }
+                Some(PARAM_PERIOD) if !already_set => {
+                    parsed.period = Some(parse_number(PARAM_PERIOD, &value)?)
+                }
+                Some(PARAM_COUNTER) if !already_set => {
+                    parsed.counter = Some(parse_number(PARAM_COUNTER, &value)?)
+                }
+                // Unknown parameters, and repeats of known ones in lenient mode.
+                _ => parsed.extra.push((key, value)),
+            }
+        }
+
+        if !has_secret {
+            return Err(UriError::MissingParameter(PARAM_SECRET));
+        }
+        parsed.spec()?;
+        if strict {
+            parsed.validate()?;
+        }
+        Ok(parsed)
+    }
+
+    /// Build the canonical URI for a vault token.
+    ///
+    /// An empty issuer is left out. An account that contains `:` but has no
+    /// issuer cannot be written unambiguously; the part before the first
+    /// colon reads back as the issuer.
+    pub fn from_token(token: &NewToken) -> Self {
+        let issuer = (!token.issuer.is_empty()).then(|| token.issuer.clone());
+        let token_type = token.token_type.to_ascii_lowercase();
+        let (period, counter) = match token_type.as_str() {
+            TYPE_HOTP => (None, Some(token.counter)),
+            _ => (Some(token.period), None),
+        };
+        Self {
+            label_issuer: issuer.clone(),
+            account: token.account.clone(),
+            secret: token.secret.clone(),
+            issuer,
+            algorithm: Algorithm::from_str(&token.algorithm).ok(),
+            digits: Some(token.digits),
+            period,
+            counter,
+            extra: Vec::new(),
+            token_type,
+        }
+    }
+
+    /// Check the invariants strict parsing enforces, which are also what
+    /// makes the formatted output parse back to `self`.
+    pub fn validate(&self) -> Result<(), UriError> {
+        if self.account.is_empty() {
+            return Err(UriError::EmptyAccount);
+        }
+        match &self.label_issuer {
+            Some(_) if self.account.starts_with(' ') => return Err(UriError::AmbiguousLabel),
+            None if self.account.contains(':') => return Err(UriError::AmbiguousLabel),
+            _ => {}
+        }
+        if let (Some(label), Some(param)) = (&self.label_issuer, &self.issuer) {
+            if label != param {
+                return Err(UriError::IssuerMismatch {
+                    label: label.clone(),
+                    param: param.clone(),
+                });
+            }
+        }
+        if self.secret.is_empty() {
+            return Err(UriError::InvalidSecret);
+        }
+        if self.token_type == TYPE_HOTP && self.counter.is_none() {
+            return Err(UriError::MissingParameter(PARAM_COUNTER));
+        }
+        if let Some((key, _)) = self
+            .extra
+            .iter()
+            .find(|(key, _)| KNOWN_PARAMS.iter().any(|k| key.eq_ignore_ascii_case(k)))
+        {
+            return Err(UriError::DuplicateParameter(key.clone()));
+        }
+        self.spec().map(|_| ())
+    }
+
+    /// Issuer to store: the `issuer` parameter, else the label prefix.
+    pub fn resolved_issuer(&self) -> &str {
+        self.issuer
+            .as_deref()
+            .or(self.label_issuer.as_deref())
+            .unwrap_or(DEFAULT_ISSUER)
+    }
+
+    /// Code-generation parameters, with Key URI defaults applied.
+    pub fn spec(&self) -> Result<TokenSpec, UriError> {
+        let default_digits = match self.token_type.as_str() {
+            TYPE_STEAM => STEAM_DIGITS,
+            _ => DEFAULT_DIGITS,
+        };
+        TokenSpec::from_parts(
+            &self.token_type,
+            self.algorithm.unwrap_or(Algorithm::SHA1).as_str(),
+            self.digits.unwrap_or(default_digits),
+            self.period.unwrap_or(DEFAULT_PERIOD) as u64,
+            self.counter.unwrap_or(DEFAULT_COUNTER),
+        )
+        .map_err(UriError::Unsupported)
+    }
+
+    /// Convert to a vault token, applying defaults.
+    pub fn to_new_token(&self) -> Result<NewToken, UriError> {
+        let spec = self.spec()?;
+        Ok(NewToken {
+            issuer: self.resolved_issuer().to_string(),
+            account: self.account.clone(),
+            secret: self.secret.clone(),
+            algorithm: spec.algorithm.as_str().to_string(),
+            digits: spec.digits,
+            token_type: self.token_type.clone(),
+            period: self.period.unwrap_or(DEFAULT_PERIOD),
+            counter: spec.counter,
+            icon: None,
+        })
+    }
+}
+
+impl fmt::Display for OtpAuthUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}/", OTPAUTH_SCHEME, self.token_type)?;
+        if let Some(issuer) = &self.label_issuer {
+            write!(f, "{}:", encode(issuer))?;
+        }
+        write!(f, "{}", encode(&self.account))?;
+
+        let mut secret = base32::encode(base32::Alphabet::Rfc4648 { padding: false }, &self.secret);
+        let written = write!(f, "?{}={}", PARAM_SECRET, secret);
+        secret.zeroize();
+        written?;
+
+        if let Some(issuer) = &self.issuer {
+            write!(f, "&{}={}", PARAM_ISSUER, encode(issuer))?;
+        }
+        if let Some(algorithm) = self.algorithm {
+            write!(f, "&{}={}", PARAM_ALGORITHM, algorithm.as_str())?;
+        }
+        if let Some(digits) = self.digits {
+            write!(f, "&{}={}", PARAM_DIGITS, digits)?;
+        }
+        if let Some(period) = self.period {
+            write!(f, "&{}={}", PARAM_PERIOD, period)?;
+        }
+        if let Some(counter) = self.counter {
+            write!(f, "&{}={}", PARAM_COUNTER, counter)?;
+        }
+        for (key, value) in &self.extra {
+            write!(f, "&{}={}", encode(key), encode(value))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for OtpAuthUri {
+    type Err = UriError;
+
+    /// Strict parse, the inverse of the [`fmt::Display`] output.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s, ParseMode::Strict)
+    }
+}
+
+/// Split a raw label at the issuer separator: the first literal `:`, or
+/// failing that the first `%3A`.
+fn split_label(label: &str) -> (Option<&str>, &str) {
+    if let Some((issuer, account)) = label.split_once(':') {
+        return (Some(issuer), account);
+    }
+    let upper = label.to_ascii_uppercase();
+    match upper.find("%3A") {
+        Some(i) => (Some(&label[..i]), &label[i + 3..]),
+        None => (None, label),
+    }
+}
+
+fn decode_secret(value: &str, mode: ParseMode) -> Result<Vec<u8>, UriError> {
+    let mut normalized = match mode {
+        ParseMode::Strict => value.to_ascii_uppercase(),
+        ParseMode::Lenient => value
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '=')
+            .collect::<String>()
+            .to_ascii_uppercase(),
+    };
+    let secret = base32::decode(base32::Alphabet::Rfc4648 { padding: false }, &normalized);
+    normalized.zeroize();
+    secret.ok_or(UriError::InvalidSecret)
+}
+
+fn parse_algorithm(value: &str, mode: ParseMode) -> Result<Algorithm, UriError> {
+    let algorithm = Algorithm::from_str(value).map_err(UriError::Unsupported)?;
+    if mode == ParseMode::Strict && value != algorithm.as_str() {
+        return Err(UriError::Unsupported(format!(
+            "non-canonical algorithm {}",
+            value
+        )));
+    }
+    Ok(algorithm)
+}
+
+fn parse_number<T: FromStr>(param: &'static str, value: &str) -> Result<T, UriError> {
+    value.parse().map_err(|_| UriError::InvalidNumber {
+        param,
+        value: value.to_string(),
+    })
+}
+
+/// Percent-encode everything outside the RFC 3986 unreserved set.
+fn encode(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                result.push(byte as char)
+            }
+            _ => result.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    result
+}
+
+/// Percent-decode, treating `+` as a space. Strict mode rejects malformed
+/// escapes and invalid UTF-8; lenient mode keeps a stray `%` as-is and
+/// replaces invalid UTF-8.
+fn decode(s: &str, mode: ParseMode) -> Result<String, UriError> {
+    let bytes_in = s.as_bytes();
+    let mut bytes_out = Vec::with_capacity(bytes_in.len());
+    let mut i = 0;
+
+    while i < bytes_in.len() {
+        match bytes_in[i] {
+            b'%' => {
+                let byte = s
+                    .get(i + 1..i + 3)
+                    .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match byte {
+                    Some(byte) => {
+                        bytes_out.push(byte);
+                        i += 3;
+                    }
+                    None if mode == ParseMode::Strict => {
+                        return Err(UriError::InvalidEscape(s.to_string()))
+                    }
+                    None => {
+                        bytes_out.push(b'%');
+                        i += 1;
+                    }
+                }
+            }
+            b'+' => {
+                bytes_out.push(b' ');
+                i += 1;
+            }
+            b => {
+                bytes_out.push(b);
+                i += 1;
+            }
+        }
+    }
+
+    match String::from_utf8(bytes_out) {
+        Ok(decoded) => Ok(decoded),
+        Err(_) if mode == ParseMode::Strict => Err(UriError::InvalidEscape(s.to_string())),
+        Err(e) => Ok(String::from_utf8_lossy(e.as_bytes()).into_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lenient(uri: &str) -> Result<OtpAuthUri, UriError> {
+        OtpAuthUri::parse(uri, ParseMode::Lenient)
+    }
+
+    fn strict(uri: &str) -> Result<OtpAuthUri, UriError> {
+        OtpAuthUri::parse(uri, ParseMode::Strict)
+    }
+
+    #[test]
+    fn test_canonical_roundtrip() {
+        let uri = "otpauth://totp/ACME%20Co:john%3Adoe%40example.com?secret=JBSWY3DPEHPK3PXP\
+                   &issuer=ACME%20Co&algorithm=SHA256&digits=8&period=60\
+                   &image=https%3A%2F%2Fexample.com%2Flogo.png&color=FF0000";
+        let parsed = strict(uri).unwrap();
+        assert_eq!(parsed.label_issuer.as_deref(), Some("ACME Co"));
+        assert_eq!(parsed.account, "john:doe@example.com");
+        assert_eq!(parsed.algorithm, Some(Algorithm::SHA256));
+        assert_eq!(
+            parsed.extra,
+            [
+                (
+                    "image".to_string(),
+                    "https://example.com/logo.png".to_string()
+                ),
+                ("color".to_string(), "FF0000".to_string()),
+            ]
+        );
+        assert_eq!(parsed.to_string(), uri);
+        assert_eq!(lenient(uri).unwrap(), parsed);
+    }
+
+    #[test]
+    fn test_lenient_canonicalises_real_world_uris() {
+        let parsed = lenient(
+            "OTPAUTH://TOTP/Example%3Aalice+smith?Secret=jbsw%20y3dp%3D%3D%3D&ALGORITHM=sha-1&lock=true",
+        )
+        .unwrap();
+        assert_eq!(parsed.label_issuer.as_deref(), Some("Example"));
+        assert_eq!(parsed.account, "alice smith");
+        assert_eq!(parsed.secret, b"Hello");
+        assert_eq!(parsed.algorithm, Some(Algorithm::SHA1));
+        assert_eq!(parsed.extra, [("lock".to_string(), "true".to_string())]);
+        assert_eq!(
+            parsed.to_string(),
+            "otpauth://totp/Example:alice%20smith?secret=JBSWY3DP&algorithm=SHA1&lock=true"
+        );
+    }
+
+    #[test]
+    fn test_issuer_mismatch() {
+        let uri = "otpauth://totp/Google:alice?secret=JBSWY3DP&issuer=Google%20Inc";
+        assert_eq!(
+            strict(uri).unwrap_err(),
+            UriError::IssuerMismatch {
+                label: "Google".to_string(),
+                param: "Google Inc".to_string()
+            }
+        );
+        let parsed = lenient(uri).unwrap();
+        assert_eq!(parsed.resolved_issuer(), "Google Inc");
+        assert_eq!(parsed.to_new_token().unwrap().account, "alice");
+        assert_eq!(lenient(&parsed.to_string()).unwrap(), parsed);
+    }
+
+    #[test]
+    fn test_strict_errors() {
+        let ok = "otpauth://totp/A:a?secret=JBSWY3DP";
+        assert!(strict(ok).is_ok());
+        assert_eq!(
+            strict("https://a?secret=JBSWY3DP").unwrap_err(),
+            UriError::Scheme
+        );
+        assert_eq!(
+            strict("otpauth://TOTP/A:a?secret=JBSWY3DP").unwrap_err(),
+            UriError::UnknownType("TOTP".to_string())
+        );
+        assert_eq!(
+            strict(&format!("{ok}&secret=GEZDGNBV")).unwrap_err(),
+            UriError::DuplicateParameter("secret".to_string())
+        );
+        assert_eq!(
+            strict("otpauth://totp/A:a?secret=JBSWY3DP%3D").unwrap_err(),
+            UriError::InvalidSecret
+        );
+        assert!(matches!(
+            strict("otpauth://totp/A:a%ZZ?secret=JBSWY3DP").unwrap_err(),
+            UriError::InvalidEscape(_)
+        ));
+        assert_eq!(
+            strict(&format!("{ok}&digits=six")).unwrap_err(),
+            UriError::InvalidNumber {
+                param: "digits",
+                value: "six".to_string()
+            }
+        );
+        assert!(matches!(
+            strict(&format!("{ok}&digits=7")).unwrap_err(),
+            UriError::Unsupported(_)
+        ));
+        assert_eq!(
+            strict("otpauth://hotp/A:a?secret=JBSWY3DP").unwrap_err(),
+            UriError::MissingParameter("counter")
+        );
+        assert_eq!(
+            strict("otpauth://totp/A:?secret=JBSWY3DP").unwrap_err(),
+            UriError::EmptyAccount
+        );
+        assert_eq!(
+            strict("otpauth://totp/A:a?issuer=A").unwrap_err(),
+            UriError::MissingParameter("secret")
+        );
+    }
+
+    #[test]
+    fn test_lenient_drops_repeated_secret() {
+        let uri = lenient("otpauth://totp/A:a?secret=JBSWY3DP&Secret=GEZDGNBV").unwrap();
+        assert_eq!(uri.secret, b"Hello");
+        assert!(uri.extra.is_empty());
+        assert!(!uri.to_string().contains("GEZDGNBV"));
+        assert!(!format!("{uri:?}").contains("GEZDGNBV"));
+    }
+
+    #[test]
+    fn test_lenient_still_rejects_unusable_uris() {
+        assert!(lenient("otpauth://totp/a?secret=not*base32").is_err());
+        assert!(lenient("otpauth://totp/a?secret=JBSWY3DP&period=0").is_err());
+        assert!(lenient("otpauth://totp/a?secret=JBSWY3DP&algorithm=MD5").is_err());
+        assert!(lenient("otpauth://foo/a?secret=JBSWY3DP").is_err());
+    }
+
+    #[test]
+    fn test_from_token_roundtrips() {
+        let token = NewToken {
+            issuer: "Acme: Corp".to_string(),
+            account: "a:b@example.com".to_string(),
+            secret: b"12345678901234567890".to_vec(),
+            algorithm: "SHA512".to_string(),
+            digits: 8,
+            token_type: "hotp".to_string(),
+            period: 30,
+            counter: 7,
+            icon: None,
+        };
+        let uri = OtpAuthUri::from_token(&token);
+        let reparsed: OtpAuthUri = uri.to_string().parse().unwrap();
+        assert_eq!(reparsed, uri);
+
+        let back = reparsed.to_new_token().unwrap();
+        assert_eq!(back.issuer, token.issuer);
+        assert_eq!(back.account, token.account);
+        assert_eq!(back.secret, token.secret);
+        assert_eq!(back.counter, 7);
+    }
+
+    #[test]
+    fn test_steam_defaults_to_five_digits() {
+        let parsed = lenient("otpauth://steam/Steam:gaben?secret=JBSWY3DP").unwrap();
+        assert_eq!(parsed.to_new_token().unwrap().digits, STEAM_DIGITS);
+    }
+
+    #[test]
+    fn test_debug_redacts_secret() {
+        let parsed = lenient("otpauth://totp/a?secret=JBSWY3DP").unwrap();
+        assert!(!format!("{parsed:?}").contains("72"));
+        assert!(format!("{parsed:?}").contains("redacted"));
+    }
+}
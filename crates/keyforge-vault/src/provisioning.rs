@@ -0,0 +1,466 @@
+//! OTP provisioning and verification
+//!
+//! Library mode for services that *issue* 2FA rather than store it:
+//! generate a secret, hand the user an [`OtpAuthUri`], then verify the
+//! codes they submit.
+//!
+//! A [`UsedCodeStore`] remembers which TOTP steps were already accepted for
+//! each credential so a code cannot be replayed inside its window.
+//! [`MemoryUsedCodeStore`] suits a single process; [`SqliteUsedCodeStore`]
+//! (feature `sqlite`) survives restarts and can be shared between workers.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use keyforge_crypto::otp::{OtpContext, OtpGenerator, TokenKind, TokenSpec};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::error::VaultError;
+use crate::otpauth::OtpAuthUri;
+use crate::token::NewToken;
+
+#[cfg(feature = "sqlite")]
+use rusqlite::Connection;
+
+/// TOTP steps accepted either side of the current one by default (RFC 6238
+/// §5.2 recommends at most one step of network delay).
+pub const DEFAULT_SKEW_STEPS: u64 = 1;
+/// HOTP counters checked ahead of the expected one by default.
+pub const DEFAULT_HOTP_LOOK_AHEAD: u64 = 10;
+
+/// Size of a generated shared secret.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SecretStrength {
+    /// 80 bits, the RFC 4226 minimum. Only for legacy verifiers.
+    Bits80,
+    /// 160 bits, the RFC 4226 recommendation and the HMAC-SHA1 block match.
+    #[default]
+    Bits160,
+    /// 256 bits, matching HMAC-SHA256.
+    Bits256,
+    /// 512 bits, matching HMAC-SHA512.
+    Bits512,
+}
+
+impl SecretStrength {
+    /// Secret length in bytes.
+    pub fn bytes(self) -> usize {
+        match self {
+            Self::Bits80 => 10,
+            Self::Bits160 => 20,
+            Self::Bits256 => 32,
+            Self::Bits512 => 64,
+        }
+    }
+}
+
+/// Generate a random shared secret of the given strength.
+pub fn generate_secret(strength: SecretStrength) -> Vec<u8> {
+    keyforge_crypto::random::generate_bytes(strength.bytes())
+}
+
+/// A freshly provisioned credential. The secret is zeroized on drop; store
+/// it encrypted on the service side and show `uri` to the user once.
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct Provisioned {
+    pub secret: Vec<u8>,
+    pub uri: String,
+}
+
+/// Generate a secret for `issuer`/`account` and its provisioning URI.
+pub fn provision(
+    issuer: &str,
+    account: &str,
+    spec: &TokenSpec,
+    strength: SecretStrength,
+) -> Result<Provisioned, String> {
+    spec.validate()?;
+    let period = u32::try_from(spec.period)
+        .map_err(|_| VaultError::InvalidToken(format!("period {} too large", spec.period)))?;
+
+    let mut token = NewToken {
+        issuer: issuer.to_string(),
+        account: account.to_string(),
+        secret: generate_secret(strength),
+        algorithm: spec.algorithm.as_str().to_string(),
+        digits: spec.digits,
+        token_type: spec.type_name().to_string(),
+        period,
+        counter: spec.counter,
+        icon: None,
+    };
+    let uri = OtpAuthUri::from_token(&token).to_string();
+    Ok(Provisioned {
+        secret: std::mem::take(&mut token.secret),
+        uri,
+    })
+}
+
+/// Records accepted TOTP steps per credential.
+pub trait UsedCodeStore {
+    /// Record that `step` was accepted for credential `key`, to be kept
+    /// until Unix time `expires_at`. Returns `Ok(false)` if it was already
+    /// recorded, i.e. the code is a replay. Must be atomic.
+    fn mark_used(&self, key: &str, step: u64, expires_at: u64) -> Result<bool, String>;
+
+    /// Forget every entry that expired at or before `now`.
+    fn prune(&self, now: u64) -> Result<(), String>;
+}
+
+/// In-process [`UsedCodeStore`].
+#[derive(Debug, Default)]
+pub struct MemoryUsedCodeStore {
+    used: Mutex<HashMap<(String, u64), u64>>,
+}
+
+impl MemoryUsedCodeStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl UsedCodeStore for MemoryUsedCodeStore {
+    fn mark_used(&self, key: &str, step: u64, expires_at: u64) -> Result<bool, String> {
+        let mut used = self
+            .used
+            .lock()
+            .map_err(|e| VaultError::CodeStore(e.to_string()))?;
+        Ok(used.insert((key.to_string(), step), expires_at).is_none())
+    }
+
+    fn prune(&self, now: u64) -> Result<(), String> {
+        let mut used = self
+            .used
+            .lock()
+            .map_err(|e| VaultError::CodeStore(e.to_string()))?;
+        used.retain(|_, expires_at| *expires_at > now);
+        Ok(())
+    }
+}
+
+/// [`UsedCodeStore`] backed by a plain SQLite database. It holds no
+/// secrets (only credential keys and step numbers), so it is not encrypted.
+#[cfg(feature = "sqlite")]
+pub struct SqliteUsedCodeStore {
+    conn: Mutex<Connection>,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteUsedCodeStore {
+    /// Open (or create) the store at `path`.
+    pub fn open(path: &str) -> Result<Self, String> {
+        let conn = Connection::open(path).map_err(|e| VaultError::DatabaseOpen(e.to_string()))?;
+        Self::init(conn)
+    }
+
+    /// A store that lives only as long as this value.
+    pub fn in_memory() -> Result<Self, String> {
+        let conn =
+            Connection::open_in_memory().map_err(|e| VaultError::DatabaseOpen(e.to_string()))?;
+        Self::init(conn)
+    }
+
+    fn init(conn: Connection) -> Result<Self, String> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS used_codes (
+                key TEXT NOT NULL,
+                step INTEGER NOT NULL,
+                expires_at INTEGER NOT NULL,
+                PRIMARY KEY (key, step)
+            );
+            CREATE INDEX IF NOT EXISTS idx_used_codes_expires_at ON used_codes(expires_at);",
+        )
+        .map_err(|e| VaultError::Migration(e.to_string()))?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn conn(&self) -> Result<std::sync::MutexGuard<'_, Connection>, String> {
+        self.conn
+            .lock()
+            .map_err(|e| VaultError::CodeStore(e.to_string()).into())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl UsedCodeStore for SqliteUsedCodeStore {
+    fn mark_used(&self, key: &str, step: u64, expires_at: u64) -> Result<bool, String> {
+        let inserted = self
+            .conn()?
+            .execute(
+                "INSERT OR IGNORE INTO used_codes (key, step, expires_at) VALUES (?1, ?2, ?3)",
+                rusqlite::params![key, to_sql_int(step), to_sql_int(expires_at)],
+            )
+            .map_err(|e| VaultError::Query(e.to_string()))?;
+        Ok(inserted == 1)
+    }
+
+    fn prune(&self, now: u64) -> Result<(), String> {
+        self.conn()?
+            .execute(
+                "DELETE FROM used_codes WHERE expires_at <= ?1",
+                rusqlite::params![to_sql_int(now)],
+            )
+            .map_err(|e| VaultError::Query(e.to_string()))?;
+        Ok(())
+    }
+}
+
+/// SQLite integers are `i64`; clamp the (never reached) upper half of `u64`.
+#[cfg(feature = "sqlite")]
+fn to_sql_int(value: u64) -> i64 {
+    i64::try_from(value).unwrap_or(i64::MAX)
+}
+
+/// Outcome of [`Verifier::verify`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verification {
+    /// The code matched. For TOTP `factor` is the accepted time step; for
+    /// HOTP it is the matching counter, and the caller must persist
+    /// `factor + 1` as the next expected counter.
+    Accepted { factor: u64 },
+    /// The code matched a TOTP step that was already accepted.
+    Replayed,
+    /// The code matched nothing in the window.
+    Rejected,
+}
+
+/// Verifies submitted codes against a shared secret.
+pub struct Verifier<S: UsedCodeStore> {
+    store: S,
+    skew_steps: u64,
+    hotp_look_ahead: u64,
+}
+
+impl<S: UsedCodeStore> Verifier<S> {
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            skew_steps: DEFAULT_SKEW_STEPS,
+            hotp_look_ahead: DEFAULT_HOTP_LOOK_AHEAD,
+        }
+    }
+
+    /// Accept TOTP codes up to `steps` periods before or after now.
+    pub fn with_skew(mut self, steps: u64) -> Self {
+        self.skew_steps = steps;
+        self
+    }
+
+    /// Check HOTP counters up to `count` past the expected one.
+    pub fn with_hotp_look_ahead(mut self, count: u64) -> Self {
+        self.hotp_look_ahead = count;
+        self
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Verify `code` for credential `key`. For HOTP, `spec.counter` is the
+    /// next expected counter.
+    pub fn verify(
+        &self,
+        key: &str,
+        secret: &[u8],
+        spec: &TokenSpec,
+        code: &str,
+        ctx: &OtpContext,
+    ) -> Result<Verification, String> {
+        spec.validate()?;
+        let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+
+        match spec.kind {
+            TokenKind::Totp => self.verify_totp(key, secret, spec, &code, ctx),
+            TokenKind::Hotp => {
+                for counter in spec.counter..=spec.counter.saturating_add(self.hotp_look_ahead) {
+                    let candidate = TokenSpec {
+                        counter,
+                        ..spec.clone()
+                    };
+                    if codes_equal(&candidate.generate(secret, ctx)?.code, &code) {
+                        return Ok(Verification::Accepted { factor: counter });
+                    }
+                }
+                Ok(Verification::Rejected)
+            }
+        }
+    }
+
+    fn verify_totp(
+        &self,
+        key: &str,
+        secret: &[u8],
+        spec: &TokenSpec,
+        code: &str,
+        ctx: &OtpContext,
+    ) -> Result<Verification, String> {
+        let current = ctx.time / spec.period;
+        let first = current.saturating_sub(self.skew_steps);
+        let last = current.saturating_add(self.skew_steps);
+
+        for step in first..=last {
+            let at = OtpContext::at(step.saturating_mul(spec.period));
+            if !codes_equal(&spec.generate(secret, &at)?.code, code) {
+                continue;
+            }
+            // Once `step` drops out of the skew window it can never match again.
+            let expires_at = step
+                .saturating_add(self.skew_steps + 1)
+                .saturating_mul(spec.period);
+            self.store.prune(ctx.time)?;
+            return Ok(if self.store.mark_used(key, step, expires_at)? {
+                Verification::Accepted { factor: step }
+            } else {
+                Verification::Replayed
+            });
+        }
+        Ok(Verification::Rejected)
+    }
+}
+
+/// Constant-time comparison of a generated and a submitted code.
+fn codes_equal(expected: &str, submitted: &str) -> bool {
+    let (a, b) = (expected.as_bytes(), submitted.as_bytes());
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::parse_otpauth_uri;
+    use keyforge_crypto::hotp::Algorithm;
+
+    const SECRET: &[u8] = b"12345678901234567890";
+
+    fn totp() -> TokenSpec {
+        TokenSpec::totp(Algorithm::SHA1, 8, 30)
+    }
+
+    #[test]
+    fn test_provision_uri_roundtrips() {
+        let provisioned = provision(
+            "Example Corp",
+            "alice@example.com",
+            &TokenSpec::totp(Algorithm::SHA256, 8, 60),
+            SecretStrength::Bits256,
+        )
+        .unwrap();
+        assert_eq!(provisioned.secret.len(), 32);
+
+        let parsed = parse_otpauth_uri(&provisioned.uri).unwrap();
+        assert_eq!(parsed.secret, provisioned.secret);
+        assert_eq!(parsed.issuer, "Example Corp");
+        assert_eq!(parsed.account, "alice@example.com");
+        assert_eq!(parsed.algorithm, "SHA256");
+        assert_eq!(parsed.digits, 8);
+        assert_eq!(parsed.period, 60);
+    }
+
+    #[test]
+    fn test_secret_strengths() {
+        assert_eq!(generate_secret(SecretStrength::default()).len(), 20);
+        assert_eq!(generate_secret(SecretStrength::Bits80).len(), 10);
+        assert_eq!(generate_secret(SecretStrength::Bits512).len(), 64);
+        assert!(provision(
+            "A",
+            "a",
+            &TokenSpec::totp(Algorithm::SHA1, 7, 30),
+            Default::default()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_totp_accepts_once_within_skew() {
+        let verifier = Verifier::new(MemoryUsedCodeStore::new());
+        let ctx = OtpContext::at(59);
+        // RFC 6238 vector for T = 59 is step 1; step 0 (T = 29) is inside skew.
+        assert_eq!(
+            verifier
+                .verify("alice", SECRET, &totp(), "9428 7082", &ctx)
+                .unwrap(),
+            Verification::Accepted { factor: 1 }
+        );
+        assert_eq!(
+            verifier
+                .verify("alice", SECRET, &totp(), "94287082", &ctx)
+                .unwrap(),
+            Verification::Replayed
+        );
+        // Replay tracking is per credential.
+        assert_eq!(
+            verifier
+                .verify("bob", SECRET, &totp(), "94287082", &ctx)
+                .unwrap(),
+            Verification::Accepted { factor: 1 }
+        );
+        assert_eq!(
+            verifier
+                .verify("alice", SECRET, &totp(), "00000000", &ctx)
+                .unwrap(),
+            Verification::Rejected
+        );
+    }
+
+    #[test]
+    fn test_totp_rejects_outside_skew() {
+        let verifier = Verifier::new(MemoryUsedCodeStore::new()).with_skew(0);
+        let code = totp().generate(SECRET, &OtpContext::at(29)).unwrap().code;
+        assert_eq!(
+            verifier
+                .verify("alice", SECRET, &totp(), &code, &OtpContext::at(59))
+                .unwrap(),
+            Verification::Rejected
+        );
+    }
+
+    #[test]
+    fn test_memory_store_prunes_expired() {
+        let store = MemoryUsedCodeStore::new();
+        assert!(store.mark_used("k", 1, 90).unwrap());
+        store.prune(90).unwrap();
+        assert!(store.mark_used("k", 1, 90).unwrap());
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_store_persists() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("used.db");
+        let path = path.to_str().unwrap();
+
+        let verifier = Verifier::new(SqliteUsedCodeStore::open(path).unwrap());
+        let ctx = OtpContext::at(59);
+        assert!(matches!(
+            verifier
+                .verify("alice", SECRET, &totp(), "94287082", &ctx)
+                .unwrap(),
+            Verification::Accepted { .. }
+        ));
+        drop(verifier);
+
+        let store = SqliteUsedCodeStore::open(path).unwrap();
+        assert!(!store.mark_used("alice", 1, 90).unwrap());
+        store.prune(90).unwrap();
+        assert!(store.mark_used("alice", 1, 90).unwrap());
+        assert!(SqliteUsedCodeStore::in_memory().is_ok());
+    }
+
+    #[test]
+    fn test_hotp_look_ahead() {
+        let verifier = Verifier::new(MemoryUsedCodeStore::new()).with_hotp_look_ahead(2);
+        let spec = TokenSpec::hotp(Algorithm::SHA1, 6, 0);
+        let ctx = OtpContext::at(0);
+        // RFC 4226 vectors: counter 2 = 359152, counter 3 = 969429.
+        assert_eq!(
+            verifier.verify("k", SECRET, &spec, "359152", &ctx).unwrap(),
+            Verification::Accepted { factor: 2 }
+        );
+        assert_eq!(
+            verifier.verify("k", SECRET, &spec, "969429", &ctx).unwrap(),
+            Verification::Rejected
+        );
+    }
+}
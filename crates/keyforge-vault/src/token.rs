@@ -1,13 +1,19 @@
 //! Token CRUD operations
 
-use chrono::Utc;
+use keyforge_crypto::otp::TokenSpec;
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
-use zeroize::Zeroize;
 
-use crate::constants::INITIAL_SORT_ORDER;
-use crate::db::Vault;
-use crate::error::VaultError;
+#[cfg(feature = "sqlite")]
+use {
+    crate::constants::INITIAL_SORT_ORDER,
+    crate::db::Vault,
+    crate::error::VaultError,
+    chrono::Utc,
+    keyforge_crypto::otp::{OtpCode, OtpContext, OtpGenerator},
+    keyforge_crypto::secure_mem::LockedBuffer,
+    uuid::Uuid,
+    zeroize::Zeroize,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Token {
@@ -42,8 +48,40 @@ pub struct NewToken {
     pub icon: Option<String>,
 }
 
+impl Token {
+    /// Code-generation parameters for this token.
+    pub fn spec(&self) -> Result<TokenSpec, String> {
+        TokenSpec::from_parts(
+            &self.token_type,
+            &self.algorithm,
+            self.digits,
+            self.period as u64,
+            self.counter,
+        )
+    }
+}
+
+impl NewToken {
+    /// Code-generation parameters for this token.
+    pub fn spec(&self) -> Result<TokenSpec, String> {
+        TokenSpec::from_parts(
+            &self.token_type,
+            &self.algorithm,
+            self.digits,
+            self.period as u64,
+            self.counter,
+        )
+    }
+}
+
+#[cfg(feature = "sqlite")]
 impl Vault {
     pub fn add_token(&self, mut new_token: NewToken) -> Result<Token, String> {
+        if let Err(e) = new_token.spec() {
+            new_token.secret.zeroize();
+            return Err(VaultError::InvalidToken(e).into());
+        }
+
         let id = Uuid::new_v4().to_string();
         let now = Utc::now().to_rfc3339();
 
@@ -185,6 +223,32 @@ impl Vault {
             .map_err(|e| -> String { VaultError::DecryptSecret(e).into() })
     }
 
+    /// Decrypt the secret for a token into locked memory.
+    pub fn get_token_secret_locked(&self, id: &str) -> Result<LockedBuffer, String> {
+        let encrypted: Vec<u8> = self
+            .conn()
+            .query_row(
+                "SELECT secret_encrypted FROM tokens WHERE id = ?1",
+                rusqlite::params![id],
+                |row| row.get(0),
+            )
+            .map_err(|_| VaultError::TokenNotFound)?;
+
+        keyforge_crypto::aead::decrypt_locked(&encrypted, self.secret_key())
+            .map_err(|e| -> String { VaultError::DecryptSecret(e).into() })
+    }
+
+    /// Generate the current code for a token via its [`TokenSpec`].
+    ///
+    /// HOTP codes use the stored counter; advancing it is left to
+    /// [`Vault::increment_counter`].
+    pub fn generate_code(&self, id: &str, ctx: &OtpContext) -> Result<OtpCode, String> {
+        let token = self.get_token(id)?.ok_or(VaultError::TokenNotFound)?;
+        let spec = token.spec()?;
+        let secret = self.get_token_secret_locked(id)?;
+        spec.generate(secret.as_slice(), ctx)
+    }
+
     /// Update token metadata.
     pub fn update_token(&self, id: &str, issuer: &str, account: &str) -> Result<(), String> {
         let now = Utc::now().to_rfc3339();
@@ -202,11 +266,20 @@ impl Vault {
         Ok(())
     }
 
-    /// Delete a token.
+    /// Delete a token and its groups.
     pub fn delete_token(&self, id: &str) -> Result<(), String> {
-        self.conn()
-            .execute("DELETE FROM tokens WHERE id = ?1", rusqlite::params![id])
+        let tx = self
+            .conn()
+            .unchecked_transaction()
+            .map_err(|e| VaultError::Query(e.to_string()))?;
+        tx.execute(
+            "DELETE FROM token_groups WHERE token_id = ?1",
+            rusqlite::params![id],
+        )
+        .map_err(|e| VaultError::Query(e.to_string()))?;
+        tx.execute("DELETE FROM tokens WHERE id = ?1", rusqlite::params![id])
             .map_err(|e| VaultError::Query(e.to_string()))?;
+        tx.commit().map_err(|e| VaultError::Query(e.to_string()))?;
         Ok(())
     }
 
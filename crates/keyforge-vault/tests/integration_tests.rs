@@ -1,4 +1,9 @@
+use keyforge_crypto::otp::OtpContext;
 use keyforge_vault::db::Vault;
+use keyforge_vault::formats::{ParsedTokens, PortableToken};
+use keyforge_vault::import::{
+    DuplicateKind, ImportFailure, ImportMode, ImportReport, ImportSecret,
+};
 use keyforge_vault::token::NewToken;
 use tempfile::TempDir;
 
@@ -7,10 +12,26 @@ fn create_test_vault() -> (Vault, TempDir) {
     let path = dir.path().join("test.vault");
     let sqlcipher_key = [0x42u8; 32];
     let secret_key = [0x43u8; 32];
-    let vault = Vault::create(path.to_str().unwrap(), &sqlcipher_key, secret_key).unwrap();
+    let vault = Vault::create(
+        path.to_str().unwrap(),
+        &sqlcipher_key,
+        &mut secret_key.clone(),
+    )
+    .unwrap();
     (vault, dir)
 }
 
+/// Import `data` in whatever format it is detected as.
+fn import_file(
+    vault: &Vault,
+    data: &[u8],
+    password: Option<&[u8]>,
+    mode: ImportMode,
+) -> Result<ImportReport, String> {
+    let secret = password.map(ImportSecret::Password);
+    Ok(vault.import_file(data, secret, mode)?.report)
+}
+
 fn test_token(issuer: &str) -> NewToken {
     NewToken {
         issuer: issuer.to_string(),
@@ -34,12 +55,22 @@ fn test_create_and_open_vault() {
 
     // Create vault
     {
-        let _vault = Vault::create(path.to_str().unwrap(), &sqlcipher_key, secret_key).unwrap();
+        let _vault = Vault::create(
+            path.to_str().unwrap(),
+            &sqlcipher_key,
+            &mut secret_key.clone(),
+        )
+        .unwrap();
     }
 
     // Re-open vault
     {
-        let _vault = Vault::open(path.to_str().unwrap(), &sqlcipher_key, secret_key).unwrap();
+        let _vault = Vault::open(
+            path.to_str().unwrap(),
+            &sqlcipher_key,
+            &mut secret_key.clone(),
+        )
+        .unwrap();
     }
 }
 
@@ -53,11 +84,16 @@ fn test_wrong_key_fails() {
 
     // Create vault
     {
-        let _vault = Vault::create(path.to_str().unwrap(), &sqlcipher_key, secret_key).unwrap();
+        let _vault = Vault::create(
+            path.to_str().unwrap(),
+            &sqlcipher_key,
+            &mut secret_key.clone(),
+        )
+        .unwrap();
     }
 
     // Try opening with wrong key
-    let result = Vault::open(path.to_str().unwrap(), &wrong_key, secret_key);
+    let result = Vault::open(path.to_str().unwrap(), &wrong_key, &mut secret_key.clone());
     assert!(result.is_err());
 }
 
@@ -112,6 +148,78 @@ fn test_token_secret_roundtrip() {
     assert_eq!(decrypted, secret);
 }
 
+#[test]
+fn test_token_secret_locked_roundtrip() {
+    let (vault, _dir) = create_test_vault();
+    let token = vault.add_token(test_token("GitHub")).unwrap();
+
+    let locked = vault.get_token_secret_locked(&token.id).unwrap();
+    assert_eq!(locked.as_slice(), b"12345678901234567890");
+}
+
+#[test]
+fn test_open_with_locked_keys() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("test.vault");
+    let sqlcipher_key = keyforge_crypto::secure_mem::LockedKey::from(&mut [0x42u8; 32]);
+
+    {
+        let vault = Vault::create(
+            path.to_str().unwrap(),
+            sqlcipher_key.expose(),
+            keyforge_crypto::secure_mem::LockedKey::from(&mut [0x43u8; 32]),
+        )
+        .unwrap();
+        vault.add_token(test_token("GitHub")).unwrap();
+    }
+
+    // A plain array key is interchangeable with the locked one.
+    let vault = Vault::open(path.to_str().unwrap(), &[0x42u8; 32], &mut [0x43u8; 32]).unwrap();
+    let tokens = vault.list_tokens().unwrap();
+    assert_eq!(
+        vault.get_token_secret(&tokens[0].id).unwrap(),
+        b"12345678901234567890"
+    );
+}
+
+#[test]
+fn test_generate_code_dispatches_on_type() {
+    use keyforge_crypto::otp::OtpContext;
+
+    let (vault, _dir) = create_test_vault();
+    let totp = vault.add_token(test_token("TOTP")).unwrap();
+    let hotp = vault
+        .add_token(NewToken {
+            token_type: "hotp".to_string(),
+            counter: 1,
+            ..test_token("HOTP")
+        })
+        .unwrap();
+
+    let code = vault.generate_code(&totp.id, &OtpContext::at(59)).unwrap();
+    assert_eq!(code.code, "287082");
+    assert_eq!(code.valid_until, Some(60));
+
+    let code = vault.generate_code(&hotp.id, &OtpContext::at(59)).unwrap();
+    assert_eq!(code.code, "287082");
+    assert_eq!(code.valid_until, None);
+
+    assert!(vault
+        .generate_code("nonexistent", &OtpContext::at(0))
+        .is_err());
+}
+
+#[test]
+fn test_add_token_rejects_invalid_spec() {
+    let (vault, _dir) = create_test_vault();
+    let result = vault.add_token(NewToken {
+        digits: 7,
+        ..test_token("Bad")
+    });
+    assert!(result.is_err());
+    assert!(vault.list_tokens().unwrap().is_empty());
+}
+
 #[test]
 fn test_update_token() {
     let (vault, _dir) = create_test_vault();
@@ -209,7 +317,12 @@ fn test_full_roundtrip() {
     // Create vault and add tokens
     let token_id;
     {
-        let vault = Vault::create(path.to_str().unwrap(), &sqlcipher_key, secret_key).unwrap();
+        let vault = Vault::create(
+            path.to_str().unwrap(),
+            &sqlcipher_key,
+            &mut secret_key.clone(),
+        )
+        .unwrap();
         let token = vault
             .add_token(NewToken {
                 issuer: "GitHub".to_string(),
@@ -228,7 +341,12 @@ fn test_full_roundtrip() {
 
     // Close and reopen (simulating lock/unlock)
     {
-        let vault = Vault::open(path.to_str().unwrap(), &sqlcipher_key, secret_key).unwrap();
+        let vault = Vault::open(
+            path.to_str().unwrap(),
+            &sqlcipher_key,
+            &mut secret_key.clone(),
+        )
+        .unwrap();
         let tokens = vault.list_tokens().unwrap();
         assert_eq!(tokens.len(), 1);
         assert_eq!(tokens[0].issuer, "GitHub");
@@ -247,3 +365,750 @@ fn test_full_roundtrip() {
         assert_eq!(code, "287082");
     }
 }
+
+#[test]
+fn test_encrypted_export_streams_through_file() {
+    let (vault, dir) = create_test_vault();
+    vault.add_token(test_token("GitHub")).unwrap();
+    vault.add_token(test_token("Google")).unwrap();
+
+    let path = dir.path().join("backup.kfx");
+    let file = std::fs::File::create(&path).unwrap();
+    vault.export_encrypted_to(b"backup-pw", file).unwrap();
+
+    let (vault2, _dir2) = create_test_vault();
+    let file = std::fs::File::open(&path).unwrap();
+    let len = file.metadata().unwrap().len() as usize;
+    let report = vault2
+        .import_encrypted_from(file, b"backup-pw", len)
+        .unwrap();
+    assert_eq!(report.imported.len(), 2);
+    assert_eq!(vault2.list_tokens().unwrap().len(), 2);
+}
+
+#[test]
+fn test_truncated_encrypted_export_imports_nothing() {
+    let (vault, _dir) = create_test_vault();
+    vault.add_token(test_token("GitHub")).unwrap();
+    let data = vault.export_encrypted(b"backup-pw").unwrap();
+
+    let (vault2, _dir2) = create_test_vault();
+    assert!(vault2
+        .import_encrypted(&data[..data.len() - 1], b"backup-pw")
+        .is_err());
+    assert!(vault2.import_encrypted(&data, b"wrong-pw").is_err());
+    assert!(vault2.list_tokens().unwrap().is_empty());
+}
+
+#[test]
+fn test_legacy_encrypted_export_still_imports() {
+    let uris = vec![
+        "otpauth://totp/GitHub:user?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=GitHub"
+            .to_string(),
+    ];
+    let json = serde_json::to_vec(&uris).unwrap();
+    let salt = keyforge_crypto::random::generate_salt();
+    let params = keyforge_crypto::kdf::KdfParams::default();
+    let key = keyforge_crypto::kdf::derive_key(b"old-pw", &salt, &params).unwrap();
+    let mut legacy = salt.to_vec();
+    legacy.extend_from_slice(&keyforge_crypto::aead::encrypt(&json, &key).unwrap());
+
+    let (vault, _dir) = create_test_vault();
+    let report = vault.import_encrypted(&legacy, b"old-pw").unwrap();
+    assert_eq!(report.imported.len(), 1);
+}
+
+#[test]
+fn test_extension_vault_roundtrip_preserves_order() {
+    let (vault, _dir) = create_test_vault();
+    vault.add_token(test_token("GitHub")).unwrap();
+    vault.add_token(test_token("Google")).unwrap();
+    let ids: Vec<String> = vault
+        .list_tokens()
+        .unwrap()
+        .into_iter()
+        .rev()
+        .map(|t| t.id)
+        .collect();
+    vault.reorder_tokens(&ids).unwrap();
+
+    let data = vault.export_extension_vault(b"extension-pw").unwrap();
+    let doc: serde_json::Value = serde_json::from_slice(&data).unwrap();
+    assert_eq!(doc["vault"]["kdf_params"]["iterations"], 600_000);
+
+    let (vault2, _dir2) = create_test_vault();
+    assert_eq!(
+        vault2
+            .import_extension_vault(&data, b"wrong-pw", ImportMode::Atomic)
+            .err()
+            .as_deref(),
+        Some("Wrong password for the import file")
+    );
+    let report = vault2
+        .import_extension_vault(&data, b"extension-pw", ImportMode::Atomic)
+        .unwrap();
+    assert_eq!(report.imported.len(), 2);
+    let issuers: Vec<String> = vault2
+        .list_tokens()
+        .unwrap()
+        .into_iter()
+        .map(|t| t.issuer)
+        .collect();
+    assert_eq!(issuers, ["Google", "GitHub"]);
+    let token = &vault2.list_tokens().unwrap()[0];
+    assert_eq!(
+        vault2.get_token_secret(&token.id).unwrap(),
+        b"12345678901234567890"
+    );
+}
+
+#[test]
+fn test_google_migration_import_ignores_rescanned_batches() {
+    let single = "otpauth-migration://offline?data=CjUKCkhlbGxvId6tvu8SGEV4YW1wbGU6YWxpY2VAZ29vZ2xlLmNvbRoHRXhhbXBsZSABKAEwAhABGAEgACiJyYH9Aw%3D%3D";
+    let mixed = "otpauth-migration://offline?data=CikKFDEyMzQ1Njc4OTAxMjM0NTY3ODkwEgNib2IaBEFjbWUgAygCMAE4KgouChQxMjM0NTY3ODkwMTIzNDU2Nzg5MBIMTGVnYWN5OmNhcm9sGgAgBCgBMAI4AAosChQxMjM0NTY3ODkwMTIzNDU2Nzg5MBIEZGF2ZRoGRnV0dXJlIAEoATAHOAAKJgoUMTIzNDU2Nzg5MDEyMzQ1Njc4OTASBGVyaW4aACACKAAwADgAEAEYAiABKPn%2F%2F%2F%2F%2F%2F%2F%2F%2F%2FwE%3D";
+    let first = "otpauth-migration://offline?data=CiwKFDEyMzQ1Njc4OTAxMjM0NTY3ODkwEghBY21lOnpvZRoEQWNtZSABKAEwAhABGAIgACj5%2F%2F%2F%2F%2F%2F%2F%2F%2F%2F8B";
+    let (vault, _dir) = create_test_vault();
+
+    let bad = format!("{single}\notpauth-migration://offline?data=%%");
+    assert!(import_file(&vault, bad.as_bytes(), None, ImportMode::Atomic).is_err());
+    assert!(vault.list_tokens().unwrap().is_empty());
+
+    let incomplete = format!("{single}\n{mixed}");
+    assert!(import_file(&vault, incomplete.as_bytes(), None, ImportMode::BestEffort).is_err());
+
+    let uris = [single, first, mixed, single].join("\n");
+    let report = import_file(&vault, uris.as_bytes(), None, ImportMode::Atomic).unwrap();
+    assert!(report.imported.is_empty());
+    assert_eq!(report.failed.len(), 2);
+    assert!(vault.list_tokens().unwrap().is_empty());
+    let report = import_file(&vault, uris.as_bytes(), None, ImportMode::BestEffort).unwrap();
+    assert_eq!(report.imported.len(), 4);
+    let numbers: Vec<usize> = report.failed.iter().map(|f| f.number).collect();
+    assert_eq!(numbers, [4, 5]);
+    let issuers: Vec<String> = vault
+        .list_tokens()
+        .unwrap()
+        .into_iter()
+        .map(|t| t.issuer)
+        .collect();
+    assert_eq!(issuers, ["Example", "Acme", "Acme", "Unknown"]);
+}
+
+#[test]
+fn test_google_migration_export_subset_round_trips() {
+    let (vault, _dir) = create_test_vault();
+    vault.add_token(test_token("GitHub")).unwrap();
+    let google = vault.add_token(test_token("Google")).unwrap();
+    let mut slow = test_token("Slow");
+    slow.period = 60;
+    let slow = vault.add_token(slow).unwrap();
+
+    let export = vault.export_google_migration(None).unwrap();
+    assert_eq!(export.uris.len(), 1);
+    assert_eq!(export.skipped.len(), 1);
+    assert_eq!(export.skipped[0].label, "Slow:test@example.com");
+
+    let subset = vault
+        .export_google_migration(Some(&[google.id.clone(), slow.id.clone()]))
+        .unwrap();
+    assert_eq!(subset.skipped[0].index, 1);
+    assert!(vault
+        .export_google_migration(Some(&["missing".to_string()]))
+        .is_err());
+
+    let (vault2, _dir2) = create_test_vault();
+    let report = import_file(
+        &vault2,
+        subset.uris.join("\n").as_bytes(),
+        None,
+        ImportMode::Atomic,
+    )
+    .unwrap();
+    assert_eq!(report.imported.len(), 1);
+    let token = &vault2.list_tokens().unwrap()[0];
+    assert_eq!(token.issuer, "Google");
+    assert_eq!(
+        vault2.get_token_secret(&token.id).unwrap(),
+        b"12345678901234567890"
+    );
+}
+
+#[test]
+fn test_wrong_import_password_imports_nothing() {
+    let (vault, _dir) = create_test_vault();
+    let attempts: [(&str, &[u8], &[u8]); 8] = [
+        (
+            "aegis",
+            include_bytes!("fixtures/aegis_encrypted.json"),
+            b"wrong",
+        ),
+        (
+            "twofas",
+            include_bytes!("fixtures/twofas_encrypted.2fas"),
+            b"wrong",
+        ),
+        (
+            "ente",
+            include_bytes!("fixtures/ente_encrypted.json"),
+            b"wrong",
+        ),
+        (
+            "bitwarden",
+            include_bytes!("fixtures/bitwarden_encrypted.json"),
+            b"wrong",
+        ),
+        (
+            "andotp",
+            include_bytes!("fixtures/andotp_encrypted.json.aes"),
+            b"wrong",
+        ),
+        (
+            "authenticator_pro",
+            include_bytes!("fixtures/authenticator_pro_encrypted.bin"),
+            b"wrong",
+        ),
+        // The database also needs its keyfile.
+        (
+            "keepass",
+            include_bytes!("fixtures/keepass_aes.kdbx"),
+            b"test",
+        ),
+        ("pskc", include_bytes!("fixtures/pskc_pbe.xml"), b"wrong"),
+    ];
+    for (format, data, password) in attempts {
+        let err = vault
+            .import_file(
+                data,
+                Some(ImportSecret::Password(password)),
+                ImportMode::Atomic,
+            )
+            .err();
+        assert_eq!(
+            err.as_deref(),
+            Some("Wrong password for the import file"),
+            "{format}"
+        );
+    }
+    assert!(vault.list_tokens().unwrap().is_empty());
+}
+
+#[test]
+fn test_aegis_import_keeps_notes_groups_and_icons() {
+    let data = include_bytes!("fixtures/aegis_encrypted.json");
+    let (vault, _dir) = create_test_vault();
+
+    let report = import_file(&vault, data, Some(b"test"), ImportMode::BestEffort).unwrap();
+    assert_eq!(report.imported.len(), 3);
+    assert_eq!(report.failed.len(), 2);
+
+    let tokens = vault.list_tokens().unwrap();
+    let github = &tokens[0];
+    assert_eq!(github.issuer, "GitHub");
+    assert_eq!(
+        github.icon.as_deref(),
+        Some("data:image/svg+xml;base64,PHN2Zy8+")
+    );
+    assert_eq!(
+        vault.token_note(&github.id).unwrap().as_deref(),
+        Some("Recovery codes in the safe")
+    );
+    assert_eq!(
+        vault.token_groups(&tokens[1].id).unwrap(),
+        ["Personal", "Work"]
+    );
+    assert_eq!(vault.list_groups().unwrap(), ["Personal", "Work"]);
+    assert_eq!(tokens[2].token_type, "steam");
+    assert_eq!(
+        vault
+            .generate_code(&tokens[2].id, &OtpContext::at(59))
+            .unwrap()
+            .code
+            .len(),
+        5
+    );
+
+    vault.delete_token(&tokens[1].id).unwrap();
+    assert_eq!(vault.list_groups().unwrap(), ["Work"]);
+    vault.set_token_note(&github.id, None).unwrap();
+    assert_eq!(vault.token_note(&github.id).unwrap(), None);
+    assert!(vault.set_token_groups("missing", &[]).is_err());
+}
+
+#[test]
+fn test_aegis_export_round_trips_between_vaults() {
+    let (vault, _dir) = create_test_vault();
+    let github = vault.add_token(test_token("GitHub")).unwrap();
+    let mut hotp = test_token("Bank");
+    hotp.token_type = "hotp".to_string();
+    hotp.counter = 12;
+    vault.add_token(hotp).unwrap();
+    vault
+        .set_token_note(&github.id, Some("backup codes"))
+        .unwrap();
+    vault
+        .set_token_groups(&github.id, &["Work".to_string()])
+        .unwrap();
+
+    let data = vault.export_aegis(Some(b"aegis-pw")).unwrap();
+
+    let (vault2, _dir2) = create_test_vault();
+    let report = import_file(&vault2, &data, Some(b"aegis-pw"), ImportMode::Atomic).unwrap();
+    assert_eq!(report.imported.len(), 2);
+    let tokens = vault2.list_tokens().unwrap();
+    assert_eq!(
+        vault2.token_note(&tokens[0].id).unwrap().as_deref(),
+        Some("backup codes")
+    );
+    assert_eq!(vault2.token_groups(&tokens[0].id).unwrap(), ["Work"]);
+    assert_eq!(
+        (tokens[1].token_type.as_str(), tokens[1].counter),
+        ("hotp", 12)
+    );
+    assert_eq!(
+        vault2.get_token_secret(&tokens[1].id).unwrap(),
+        b"12345678901234567890"
+    );
+}
+
+#[test]
+fn test_twofas_import_keeps_order_and_groups() {
+    let data = include_bytes!("fixtures/twofas_encrypted.2fas");
+    let (vault, _dir) = create_test_vault();
+    let mut existing = test_token("Existing");
+    existing.secret = b"another secret".to_vec();
+    vault.add_token(existing).unwrap();
+
+    let report = import_file(&vault, data, Some(b"test"), ImportMode::BestEffort).unwrap();
+    assert_eq!(report.imported.len(), 3);
+    assert_eq!(report.failed.len(), 2);
+
+    let tokens = vault.list_tokens().unwrap();
+    let issuers: Vec<&str> = tokens.iter().map(|t| t.issuer.as_str()).collect();
+    assert_eq!(issuers, ["Existing", "Steam", "GitHub", "Bank"]);
+    assert_eq!(vault.token_groups(&tokens[2].id).unwrap(), ["Work"]);
+    assert_eq!(
+        (tokens[3].token_type.as_str(), tokens[3].counter),
+        ("hotp", 5)
+    );
+}
+
+#[test]
+fn test_ente_import_keeps_notes_tags_and_pins() {
+    let data = include_bytes!("fixtures/ente_encrypted.json");
+    let (vault, _dir) = create_test_vault();
+
+    let report = import_file(&vault, data, Some(b"test"), ImportMode::BestEffort).unwrap();
+    assert_eq!(report.imported.len(), 3);
+    assert_eq!(report.failed.len(), 2);
+
+    let tokens = vault.list_tokens().unwrap();
+    let issuers: Vec<&str> = tokens.iter().map(|t| t.issuer.as_str()).collect();
+    assert_eq!(issuers, ["Bank", "GitHub", "Steam"]);
+    assert_eq!(
+        vault.token_note(&tokens[1].id).unwrap().as_deref(),
+        Some("Recovery codes in the safe")
+    );
+    assert_eq!(vault.list_groups().unwrap(), ["Games", "Personal", "Work"]);
+}
+
+#[test]
+fn test_bitwarden_import_reads_totp_fields() {
+    let data = include_bytes!("fixtures/bitwarden_encrypted.json");
+    let (vault, _dir) = create_test_vault();
+
+    let report = import_file(&vault, data, Some(b"test"), ImportMode::BestEffort).unwrap();
+    assert_eq!(report.imported.len(), 4);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].label, "Broken:dave");
+
+    let tokens = vault.list_tokens().unwrap();
+    assert_eq!(
+        (tokens[0].issuer.as_str(), tokens[0].account.as_str()),
+        ("GitHub", "alice@example.com")
+    );
+    assert_eq!(vault.token_groups(&tokens[0].id).unwrap(), ["Work"]);
+    assert_eq!(tokens[2].token_type, "steam");
+}
+
+#[test]
+fn test_andotp_import_reports_motp() {
+    let data = include_bytes!("fixtures/andotp_encrypted.json.aes");
+    let (vault, _dir) = create_test_vault();
+    let report = import_file(&vault, data, Some(b"test"), ImportMode::BestEffort).unwrap();
+    assert_eq!(report.imported.len(), 4);
+    assert_eq!(
+        report.failed[0].reason.to_string(),
+        "MOTP tokens are not supported"
+    );
+
+    let tokens = vault.list_tokens().unwrap();
+    assert_eq!(tokens[0].icon.as_deref(), Some("Github"));
+    assert_eq!(vault.list_groups().unwrap(), ["Games", "Personal", "Work"]);
+}
+
+#[test]
+fn test_authenticator_pro_and_freeotp_import() {
+    let (vault, _dir) = create_test_vault();
+
+    let data = include_bytes!("fixtures/authenticator_pro_legacy.bin");
+    let report = import_file(&vault, data, Some(b"test"), ImportMode::BestEffort).unwrap();
+    assert_eq!(report.imported.len(), 3);
+    assert_eq!(report.failed.len(), 2);
+    // The FreeOTP+ fixture reuses the Authenticator Pro secrets.
+    let freeotp = include_bytes!("fixtures/freeotp_plus.json");
+    let report = import_file(&vault, freeotp, None, ImportMode::BestEffort).unwrap();
+    assert!(report.imported.is_empty());
+    assert_eq!(report.duplicates.len(), 4);
+    let (vault2, _dir2) = create_test_vault();
+    let report = import_file(&vault2, freeotp, None, ImportMode::BestEffort).unwrap();
+    assert_eq!(report.imported.len(), 4);
+
+    let tokens = vault.list_tokens().unwrap();
+    assert_eq!(tokens.len(), 3);
+    assert!(tokens[0]
+        .icon
+        .as_deref()
+        .unwrap()
+        .starts_with("data:image/png"));
+    assert_eq!(
+        vault.token_groups(&tokens[2].id).unwrap(),
+        ["Games", "Work"]
+    );
+}
+
+#[test]
+fn test_keepass_import_tags_group_paths() {
+    let (vault, _dir) = create_test_vault();
+    let data = include_bytes!("fixtures/keepass_aes.kdbx");
+    let keyfile = include_bytes!("fixtures/keepass_aes.keyx");
+    let secret = ImportSecret::Keyfile {
+        keyfile,
+        password: Some(b"test"),
+    };
+    let report = vault
+        .import_file(data, Some(secret), ImportMode::BestEffort)
+        .unwrap()
+        .report;
+    assert_eq!(report.imported.len(), 4);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(vault.list_groups().unwrap(), ["Work", "Work/Servers"]);
+}
+
+#[test]
+fn test_pskc_export_subset_round_trips_encrypted() {
+    let (vault, _dir) = create_test_vault();
+    vault.add_token(test_token("GitHub")).unwrap();
+    let google = vault.add_token(test_token("Google")).unwrap();
+
+    let export = vault
+        .export_pskc(Some(std::slice::from_ref(&google.id)), Some(b"hunter2"))
+        .unwrap();
+    assert!(export.skipped.is_empty());
+    assert!(vault
+        .export_pskc(Some(&["missing".to_string()]), None)
+        .is_err());
+
+    let (vault2, _dir2) = create_test_vault();
+    let report = import_file(&vault2, &export.data, Some(b"hunter2"), ImportMode::Atomic).unwrap();
+    assert_eq!(report.imported.len(), 1);
+    let token = &vault2.list_tokens().unwrap()[0];
+    assert_eq!(token.issuer, "Google");
+    assert_eq!(
+        vault2.get_token_secret(&token.id).unwrap(),
+        b"12345678901234567890"
+    );
+}
+
+#[test]
+fn test_import_file_detects_format() {
+    let (vault, _dir) = create_test_vault();
+
+    let aegis = include_bytes!("fixtures/aegis_encrypted.json");
+    assert_eq!(
+        vault
+            .import_file(aegis, None, ImportMode::Atomic)
+            .err()
+            .unwrap(),
+        "A password is required for this Aegis file"
+    );
+    // Two of its entries cannot be imported, which stops an atomic import.
+    let password = Some(ImportSecret::Password(b"test"));
+    let outcome = vault
+        .import_file(aegis, password, ImportMode::Atomic)
+        .unwrap();
+    assert_eq!(outcome.format, "aegis");
+    assert!(outcome.report.imported.is_empty());
+    assert_eq!(outcome.report.failed.len(), 2);
+    assert!(vault.list_tokens().unwrap().is_empty());
+    let outcome = vault
+        .import_file(aegis, password, ImportMode::BestEffort)
+        .unwrap();
+    assert_eq!(outcome.report.imported.len(), 3);
+    assert_eq!(outcome.report.failed.len(), 2);
+
+    let uris = b"otpauth://totp/Example:alice?secret=JBSWY3DPEHPK3PXP\n";
+    let outcome = vault.import_file(uris, None, ImportMode::Atomic).unwrap();
+    assert_eq!(outcome.format, "otpauth");
+    assert_eq!(outcome.report.imported.len(), 1);
+
+    assert_eq!(
+        vault
+            .import_file(b"not a backup", None, ImportMode::Atomic)
+            .err()
+            .unwrap(),
+        "Unrecognized import file format"
+    );
+}
+
+#[test]
+fn test_import_file_takes_keyfiles_and_keys() {
+    let (vault, _dir) = create_test_vault();
+
+    let keepass = include_bytes!("fixtures/keepass_aes.kdbx");
+    let keyfile = include_bytes!("fixtures/keepass_aes.keyx");
+    assert_eq!(
+        vault
+            .import_file(keepass, None, ImportMode::Atomic)
+            .err()
+            .unwrap(),
+        "A password or keyfile is required for this KeePass file"
+    );
+    let outcome = vault
+        .import_file(
+            keepass,
+            Some(ImportSecret::Keyfile {
+                keyfile,
+                password: Some(b"test"),
+            }),
+            ImportMode::BestEffort,
+        )
+        .unwrap();
+    assert_eq!(outcome.format, "keepass");
+    assert_eq!(outcome.report.imported.len(), 4);
+
+    let pskc = include_bytes!("fixtures/pskc_psk.xml");
+    let key = [
+        0x12, 0x34, 0x56, 0x78, 0x90, 0x12, 0x34, 0x56, 0x78, 0x90, 0x12, 0x34, 0x56, 0x78, 0x90,
+        0x12,
+    ];
+    assert_eq!(
+        vault
+            .import_file(pskc, None, ImportMode::Atomic)
+            .err()
+            .unwrap(),
+        "The pre-shared key is required for this PSKC file"
+    );
+    assert_eq!(
+        vault
+            .import_file(
+                pskc,
+                Some(ImportSecret::Password(b"qwerty")),
+                ImportMode::Atomic
+            )
+            .err()
+            .unwrap(),
+        "The pre-shared key is required for this PSKC file"
+    );
+    let outcome = vault
+        .import_file(pskc, Some(ImportSecret::Key(&key)), ImportMode::Atomic)
+        .unwrap();
+    assert_eq!(outcome.format, "pskc");
+    // Its one key came with the KeePass database.
+    assert!(outcome.report.imported.is_empty());
+    assert_eq!(outcome.report.duplicates.len(), 1);
+    assert_eq!(outcome.report.duplicates[0].number, 1);
+}
+
+#[test]
+fn test_import_preview_flags_duplicates_and_commits_selection() {
+    let (vault, _dir) = create_test_vault();
+    let github = vault.add_token(test_token("GitHub")).unwrap();
+    let mut gitlab = test_token("GitLab");
+    gitlab.account = "alice".to_string();
+    let gitlab = vault.add_token(gitlab).unwrap();
+
+    // Secret "12345678901234567890" in base32, as test_token uses.
+    let file = b"otpauth://totp/Other:bob?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ\n\
+otpauth://totp/GITLAB:Alice?secret=JBSWY3DPEHPK3PXP\n\
+otpauth://totp/New:carol?secret=KRSXG5A\n\
+otpauth://totp/Bad:dave?secret=!!\n";
+    let pending = vault.preview_import(file, None).unwrap();
+    let preview = pending.preview();
+    assert_eq!(preview.format, "otpauth");
+    assert_eq!(preview.entries.len(), 3);
+    assert_eq!(preview.skipped.len(), 1);
+    let duplicates: Vec<_> = preview
+        .entries
+        .iter()
+        .map(|e| e.duplicate.as_ref().map(|d| (d.token_id.as_str(), d.kind)))
+        .collect();
+    assert_eq!(
+        duplicates,
+        [
+            Some((github.id.as_str(), DuplicateKind::Secret)),
+            Some((gitlab.id.as_str(), DuplicateKind::Label)),
+            None,
+        ]
+    );
+    assert_eq!(vault.list_tokens().unwrap().len(), 2);
+
+    let again = vault.preview_import(file, None).unwrap();
+    assert_eq!(again.preview(), preview);
+    assert_eq!(
+        vault.commit_import(&again, &[0, 3]).err().unwrap(),
+        "No import candidate with ID 3"
+    );
+    assert_eq!(vault.list_tokens().unwrap().len(), 2);
+    // A refused commit leaves the preview to retry.
+    assert_eq!(again.preview(), preview);
+    assert_eq!(
+        pending.check_selection(&[3]).err().unwrap(),
+        "No import candidate with ID 3"
+    );
+    assert!(pending.check_selection(&[2]).is_ok());
+
+    let report = vault.commit_import(&pending, &[2, 2]).unwrap();
+    assert_eq!(report.imported.len(), 1);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].number, 4);
+    let tokens = vault.list_tokens().unwrap();
+    assert_eq!(tokens.len(), 3);
+    assert_eq!(tokens[2].issuer, "New");
+}
+
+#[test]
+fn test_uri_import_is_atomic_unless_best_effort() {
+    let (vault, _dir) = create_test_vault();
+    let github = vault.add_token(test_token("GitHub")).unwrap();
+
+    // Secret "12345678901234567890" in base32, as test_token uses.
+    let text = "# KeyForge URI list\n\
+\n\
+otpauth://totp/New:alice?secret=JBSWY3DPEHPK3PXP\n\
+otpauth://totp/Other:bob?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ\n\
+otpauth://totp/Bad:carol?secret=!!\n\
+otpauth://totp/Odd:dave?secret=KRSXG5A&digits=3\n";
+
+    let report = vault.import_uri_text(text, ImportMode::Atomic).unwrap();
+    assert!(report.imported.is_empty());
+    assert_eq!(vault.list_tokens().unwrap().len(), 1);
+    let failed: Vec<_> = report
+        .failed
+        .iter()
+        .map(|f| {
+            (
+                f.number,
+                f.label.as_str(),
+                std::mem::discriminant(&f.reason),
+            )
+        })
+        .collect();
+    assert_eq!(
+        failed,
+        [
+            (
+                5,
+                "Bad:carol",
+                std::mem::discriminant(&ImportFailure::InvalidUri(String::new()))
+            ),
+            (
+                6,
+                "Odd:dave",
+                std::mem::discriminant(&ImportFailure::Unsupported(String::new()))
+            ),
+        ]
+    );
+    assert_eq!(report.duplicates.len(), 1);
+    assert!(report.failed[0].to_string().starts_with("Entry 5: "));
+    assert_eq!(report.duplicates[0].number, 4);
+    assert_eq!(report.duplicates[0].duplicate.token_id, github.id);
+    assert_eq!(report.duplicates[0].duplicate.kind, DuplicateKind::Secret);
+
+    let report = vault.import_uri_text(text, ImportMode::BestEffort).unwrap();
+    assert_eq!(report.imported.len(), 1);
+    assert_eq!(report.imported[0].issuer, "New");
+    assert_eq!(report.failed.len(), 2);
+    let tokens = vault.list_tokens().unwrap();
+    assert_eq!(tokens.len(), 2);
+    assert_eq!(tokens[1].id, report.imported[0].id);
+
+    let uris = ["otpauth://totp/Clean:erin?secret=KRSXG5A".to_string()];
+    let report = vault.import_uris(&uris, ImportMode::Atomic).unwrap();
+    assert_eq!(report.imported.len(), 1);
+    assert!(report.failed.is_empty() && report.duplicates.is_empty());
+}
+
+#[test]
+fn test_uri_import_reports_repeated_lines() {
+    let (vault, _dir) = create_test_vault();
+    let text = "otpauth://totp/New:alice?secret=JBSWY3DPEHPK3PXP\n\
+otpauth://totp/New:alice?secret=JBSWY3DPEHPK3PXP\n";
+    let report = vault.import_uri_text(text, ImportMode::Atomic).unwrap();
+    assert_eq!(report.imported.len(), 1);
+    assert_eq!(report.duplicates.len(), 1);
+    assert_eq!(report.duplicates[0].number, 2);
+    assert_eq!(
+        report.duplicates[0].duplicate.token_id,
+        report.imported[0].id
+    );
+    assert_eq!(vault.list_tokens().unwrap().len(), 1);
+}
+
+#[test]
+fn test_parsed_import_reports_duplicates_and_storage_failures() {
+    let (vault, _dir) = create_test_vault();
+    let github = vault.add_token(test_token("GitHub")).unwrap();
+    let parsed = || {
+        let mut parsed = ParsedTokens::default();
+        let mut tokens = [test_token("Copy"), test_token("GitHub"), test_token("Odd")];
+        tokens[1].secret = b"another secret".to_vec();
+        tokens[2].secret = b"a third secret".to_vec();
+        tokens[2].digits = 3;
+        for (index, token) in tokens.into_iter().enumerate() {
+            let mut token = PortableToken::from(token);
+            token.index = index;
+            parsed.tokens.push(token);
+        }
+        parsed
+    };
+
+    let report = vault.import_parsed(parsed(), ImportMode::Atomic).unwrap();
+    assert!(report.imported.is_empty());
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(vault.list_tokens().unwrap().len(), 1);
+
+    let report = vault
+        .import_parsed(parsed(), ImportMode::BestEffort)
+        .unwrap();
+    assert_eq!(report.duplicates.len(), 1);
+    assert_eq!(report.duplicates[0].number, 1);
+    assert_eq!(report.duplicates[0].duplicate.token_id, github.id);
+    assert_eq!(report.imported.len(), 1);
+    assert_eq!(report.label_matches.len(), 1);
+    assert_eq!(report.label_matches[0].duplicate.kind, DuplicateKind::Label);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].number, 3);
+    assert!(matches!(report.failed[0].reason, ImportFailure::Storage(_)));
+    assert_eq!(vault.list_tokens().unwrap().len(), 2);
+}
+
+#[test]
+fn test_uri_import_keeps_label_matches() {
+    let (vault, _dir) = create_test_vault();
+    let github = vault.add_token(test_token("GitHub")).unwrap();
+
+    // Same issuer and account as the vault's token, another secret.
+    let uris = ["otpauth://totp/github:TEST@example.com?secret=JBSWY3DPEHPK3PXP".to_string()];
+    let report = vault.import_uris(&uris, ImportMode::Atomic).unwrap();
+    assert_eq!(report.imported.len(), 1);
+    assert!(report.duplicates.is_empty());
+    assert_eq!(report.label_matches.len(), 1);
+    assert_eq!(report.label_matches[0].number, 1);
+    assert_eq!(report.label_matches[0].duplicate.token_id, github.id);
+    assert_eq!(report.label_matches[0].duplicate.kind, DuplicateKind::Label);
+    assert_eq!(vault.list_tokens().unwrap().len(), 2);
+    assert_eq!(
+        vault.get_token_secret(&report.imported[0].id).unwrap(),
+        b"Hello!\xde\xad\xbe\xef"
+    );
+}
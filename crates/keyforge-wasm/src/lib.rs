@@ -0,0 +1,161 @@
+//! WebAssembly bindings
+//!
+//! Exposes the Rust OTP, AEAD, `otpauth://` and encrypted-export code to the
+//! web app and the browser extension through `wasm-bindgen`, so they share
+//! one implementation with the desktop app instead of a TypeScript port.
+//!
+//! Errors are thrown to JS as strings, matching the Tauri commands. `u64`
+//! arguments (times, counters) are `BigInt` on the JS side.
+
+use keyforge_crypto::aead;
+use keyforge_crypto::error::CryptoError;
+use keyforge_crypto::hotp::Algorithm;
+use keyforge_crypto::kdf::KEY_LENGTH;
+use keyforge_crypto::otp::{OtpContext, OtpGenerator, TokenSpec};
+use keyforge_vault::backup;
+use wasm_bindgen::prelude::*;
+
+fn key_array(key: &[u8]) -> Result<[u8; KEY_LENGTH], String> {
+    key.try_into().map_err(|_| {
+        CryptoError::InvalidKeySize {
+            expected: KEY_LENGTH,
+            got: key.len(),
+        }
+        .into()
+    })
+}
+
+// ── OTP ──────────────────────────────────────────────────────────────
+
+/// Generate an RFC 4226 HOTP code.
+#[wasm_bindgen]
+pub fn hotp(secret: &[u8], counter: u64, digits: u32, algorithm: &str) -> Result<String, String> {
+    let algorithm: Algorithm = algorithm.parse()?;
+    let spec = TokenSpec::hotp(algorithm, digits, counter);
+    Ok(spec.generate(secret, &OtpContext::at(0))?.code)
+}
+
+/// Generate an RFC 6238 TOTP code for Unix time `time` (seconds).
+#[wasm_bindgen]
+pub fn totp(
+    secret: &[u8],
+    time: u64,
+    period: u64,
+    digits: u32,
+    algorithm: &str,
+) -> Result<String, String> {
+    let algorithm: Algorithm = algorithm.parse()?;
+    let spec = TokenSpec::totp(algorithm, digits, period);
+    Ok(spec.generate(secret, &OtpContext::at(time))?.code)
+}
+
+// ── AEAD ─────────────────────────────────────────────────────────────
+
+/// AES-256-GCM encrypt; output is `[nonce][ciphertext][tag]`.
+#[wasm_bindgen(js_name = aeadEncrypt)]
+pub fn aead_encrypt(plaintext: &[u8], key: &[u8]) -> Result<Vec<u8>, String> {
+    aead::encrypt(plaintext, &key_array(key)?)
+}
+
+/// AES-256-GCM decrypt of `[nonce][ciphertext][tag]`.
+#[wasm_bindgen(js_name = aeadDecrypt)]
+pub fn aead_decrypt(encrypted: &[u8], key: &[u8]) -> Result<Vec<u8>, String> {
+    aead::decrypt(encrypted, &key_array(key)?)
+}
+
+// ── otpauth:// ───────────────────────────────────────────────────────
+
+/// A token parsed from an `otpauth://` URI.
+#[wasm_bindgen(getter_with_clone)]
+pub struct OtpAuthToken {
+    pub issuer: String,
+    pub account: String,
+    pub secret: Vec<u8>,
+    pub algorithm: String,
+    pub digits: u32,
+    #[wasm_bindgen(js_name = tokenType)]
+    pub token_type: String,
+    pub period: u32,
+    pub counter: u64,
+    pub icon: Option<String>,
+}
+
+/// Parse an `otpauth://` URI, failing with the reason the vault would
+/// skip it on import.
+#[wasm_bindgen(js_name = parseOtpauthUri)]
+pub fn parse_otpauth_uri(uri: &str) -> Result<OtpAuthToken, String> {
+    let token = keyforge_vault::import::parse_otpauth_uri(uri)?;
+    Ok(OtpAuthToken {
+        issuer: token.issuer,
+        account: token.account,
+        secret: token.secret,
+        algorithm: token.algorithm,
+        digits: token.digits,
+        token_type: token.token_type,
+        period: token.period,
+        counter: token.counter,
+        icon: token.icon,
+    })
+}
+
+// ── Encrypted export ─────────────────────────────────────────────────
+
+/// Build a KeyForge encrypted export from `otpauth://` URIs.
+#[wasm_bindgen(js_name = writeEncryptedExport)]
+pub fn write_encrypted_export(uris: Vec<String>, password: &str) -> Result<Vec<u8>, String> {
+    backup::write_export(&uris, password.as_bytes(), Vec::new())
+}
+
+/// Read the `otpauth://` URIs from a KeyForge encrypted export
+/// (streamed or legacy format).
+#[wasm_bindgen(js_name = readEncryptedExport)]
+pub fn read_encrypted_export(data: &[u8], password: &str) -> Result<Vec<String>, String> {
+    backup::read_export_bytes(data, password.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn test_hotp_rfc4226() {
+        assert_eq!(hotp(SECRET, 0, 6, "SHA1").unwrap(), "755224");
+        assert_eq!(hotp(SECRET, 9, 6, "sha1").unwrap(), "520489");
+    }
+
+    #[test]
+    fn test_totp_rfc6238() {
+        assert_eq!(totp(SECRET, 59, 30, 8, "SHA1").unwrap(), "94287082");
+        assert!(totp(SECRET, 59, 0, 6, "SHA1").is_err());
+        assert!(totp(SECRET, 59, 30, 6, "MD5").is_err());
+    }
+
+    #[test]
+    fn test_aead_roundtrip_and_key_check() {
+        let key = [7u8; KEY_LENGTH];
+        let sealed = aead_encrypt(b"hello", &key).unwrap();
+        assert_eq!(aead_decrypt(&sealed, &key).unwrap(), b"hello");
+        assert!(aead_encrypt(b"hello", &key[..16]).is_err());
+    }
+
+    #[test]
+    fn test_parse_otpauth_uri() {
+        let token =
+            parse_otpauth_uri("otpauth://totp/GitHub:user?secret=GEZDGNBVGY3TQOJQ&issuer=GitHub")
+                .unwrap();
+        assert_eq!(token.issuer, "GitHub");
+        assert_eq!(token.account, "user");
+        assert_eq!(token.secret, b"1234567890");
+        assert!(parse_otpauth_uri("https://example.com").is_err());
+    }
+
+    #[test]
+    fn test_encrypted_export_roundtrip() {
+        let uris = vec!["otpauth://totp/A:a?secret=GEZDGNBV".to_string()];
+        let data = write_encrypted_export(uris.clone(), "pw").unwrap();
+        assert_eq!(read_encrypted_export(&data, "pw").unwrap(), uris);
+        assert!(read_encrypted_export(&data, "nope").is_err());
+    }
+}